@@ -0,0 +1,22 @@
+//! Shared by the benchmarks.
+//!
+//! Everything is measured on the CPU under the `cpu/` groups, with no GPU kernel at all,
+//! so the baselines saved with `--save-baseline` are comparable between the machines with
+//! and without a GPU. The same work on the GPU is under the `gpu/` groups, which only run
+//! with `BELLMAN_BENCH_GPU=1`.
+
+#![allow(dead_code)]
+
+use rand::{Rand, SeedableRng, XorShiftRng};
+use std::env;
+
+pub fn gpu_enabled() -> bool {
+    env::var("BELLMAN_BENCH_GPU").map(|v| v == "1").unwrap_or(false)
+}
+
+/// Same values on every run.
+pub fn random_elements<T: Rand>(n: usize) -> Vec<T> {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    (0..n).map(|_| T::rand(rng)).collect()
+}
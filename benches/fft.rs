@@ -0,0 +1,118 @@
+//! `cargo bench --features bench --bench fft`
+
+mod common;
+
+use bellman_gpu::bench::{best_mixed_radix_fft, LockedMultiFFTKernel};
+use bellman_gpu::domain::best_fft_multiple_gpu;
+use bellman_gpu::pairing::bn256::{Bn256, Fr};
+use bellman_gpu::plonk::domains::Domain;
+use bellman_gpu::plonk::fft::cooley_tukey_ntt::BitReversedOmegas;
+use bellman_gpu::plonk::fft::strategy::{
+    best_bitreversed_ntt_with_strategy, best_ntt_with_strategy, NttStrategy,
+};
+use bellman_gpu::worker::Worker;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const LOG_SIZES: [u32; 3] = [12, 16, 20];
+
+const STRATEGIES: [NttStrategy; 3] = [
+    NttStrategy::Radix2,
+    NttStrategy::Radix4,
+    NttStrategy::SplitRadix,
+];
+
+/// Number of polynomials in one `fft_multiple` batch, the LDE factor of the prover.
+const BATCH_SIZE: usize = 4;
+
+fn bench_fft(c: &mut Criterion) {
+    let worker = Worker::new();
+
+    let mut group = c.benchmark_group("cpu/fft");
+    for &log_n in LOG_SIZES.iter() {
+        let values = common::random_elements::<Fr>(1 << log_n);
+        let domain = Domain::<Fr>::new_for_size(1 << log_n).unwrap();
+        let omegas = BitReversedOmegas::<Fr>::new_for_domain(&domain, &worker);
+
+        for strategy in STRATEGIES.iter() {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", strategy), log_n),
+                &values,
+                |b, values| {
+                    b.iter_batched_ref(
+                        || values.clone(),
+                        |a| best_ntt_with_strategy(a, &worker, &domain.generator, log_n, *strategy),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}_bitreversed", strategy), log_n),
+                &values,
+                |b, values| {
+                    b.iter_batched_ref(
+                        || values.clone(),
+                        |a| best_bitreversed_ntt_with_strategy(a, &worker, log_n, &omegas, *strategy),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+
+        // 3/4 of the power of two size
+        let mixed_size = 3 << (log_n - 2);
+        let mixed_domain = Domain::<Fr>::new_for_size_mixed_radix(mixed_size).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("MixedRadix", log_n),
+            &values[..mixed_size as usize],
+            |b, values| {
+                b.iter_batched_ref(
+                    || values.to_vec(),
+                    |a| best_mixed_radix_fft(a, &worker, &mixed_domain.generator, log_n - 2, 1),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("cpu/fft_multiple");
+    bench_fft_multiple(&mut group, &worker, |_| None);
+    group.finish();
+
+    if common::gpu_enabled() {
+        let mut group = c.benchmark_group("gpu/fft_multiple");
+        bench_fft_multiple(&mut group, &worker, |log_n| {
+            Some(LockedMultiFFTKernel::<Bn256>::new(log_n as usize, false))
+        });
+        group.finish();
+    }
+}
+
+fn bench_fft_multiple<M: criterion::measurement::Measurement>(
+    group: &mut criterion::BenchmarkGroup<M>,
+    worker: &Worker,
+    make_kernel: impl Fn(u32) -> Option<LockedMultiFFTKernel<Bn256>>,
+) {
+    for &log_n in LOG_SIZES.iter() {
+        let polys: Vec<Vec<Fr>> = (0..BATCH_SIZE)
+            .map(|_| common::random_elements::<Fr>(1 << log_n))
+            .collect();
+        let domain = Domain::<Fr>::new_for_size(1 << log_n).unwrap();
+        let mut kern = make_kernel(log_n);
+
+        group.bench_with_input(BenchmarkId::from_parameter(log_n), &polys, |b, polys| {
+            b.iter_batched_ref(
+                || polys.clone(),
+                |polys| {
+                    let mut slices: Vec<&mut [Fr]> = polys.iter_mut().map(|p| &mut p[..]).collect();
+                    best_fft_multiple_gpu(&mut kern, &mut slices, worker, &domain.generator, log_n)
+                        .unwrap();
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, bench_fft);
+criterion_main!(benches);
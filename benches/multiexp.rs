@@ -0,0 +1,55 @@
+//! `cargo bench --features bench --bench multiexp`
+
+mod common;
+
+use bellman_gpu::bench::{dense_multiexp, LockedMultiexpKernel};
+use bellman_gpu::kate_commitment::{Crs, CrsForMonomialForm};
+use bellman_gpu::pairing::bn256::{Bn256, Fr};
+use bellman_gpu::pairing::ff::PrimeField;
+use bellman_gpu::worker::Worker;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::sync::Arc;
+
+const LOG_SIZES: [u32; 3] = [12, 16, 20];
+
+fn bench_dense_multiexp(c: &mut Criterion) {
+    let worker = Worker::new();
+    let max_size = 1 << LOG_SIZES[LOG_SIZES.len() - 1];
+    let crs = Crs::<Bn256, CrsForMonomialForm>::crs_42(max_size, &worker);
+    let scalars: Vec<_> = common::random_elements::<Fr>(max_size)
+        .into_iter()
+        .map(|s| s.into_repr())
+        .collect();
+
+    let mut groups = vec![("cpu/dense_multiexp", false)];
+    if common::gpu_enabled() {
+        groups.push(("gpu/dense_multiexp", true));
+    }
+
+    for (name, use_gpu) in groups.into_iter() {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+        for &log_n in LOG_SIZES.iter() {
+            let size = 1 << log_n;
+            let bases = Arc::new(crs.g1_bases[..size].to_vec());
+            let exponents = Arc::new(scalars[..size].to_vec());
+            let mut kern = if use_gpu {
+                Some(LockedMultiexpKernel::<Bn256>::new(log_n as usize, false))
+            } else {
+                None
+            };
+
+            group.bench_function(BenchmarkId::from_parameter(log_n), |b| {
+                b.iter(|| {
+                    dense_multiexp(&worker, bases.clone(), exponents.clone(), &mut kern)
+                        .wait()
+                        .unwrap()
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_dense_multiexp);
+criterion_main!(benches);
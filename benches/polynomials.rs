@@ -0,0 +1,136 @@
+//! `cargo bench --features bench --bench polynomials`
+
+mod common;
+
+use bellman_gpu::bench::LockedMultiFFTKernel;
+use bellman_gpu::pairing::bn256::{Bn256, Fr};
+use bellman_gpu::pairing::ff::PrimeField;
+use bellman_gpu::plonk::domains::Domain;
+use bellman_gpu::plonk::fft::cooley_tukey_ntt::BitReversedOmegas;
+use bellman_gpu::plonk::polynomials::pool::PolynomialPool;
+use bellman_gpu::plonk::polynomials::{Coefficients, Polynomial, Values};
+use bellman_gpu::worker::Worker;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const LOG_SIZES: [u32; 3] = [12, 16, 18];
+
+const LDE_FACTOR: usize = 4;
+
+/// Number of polynomials in one batched LDE, the wires of the prover.
+const BATCH_SIZE: usize = 4;
+
+fn random_poly(log_n: u32) -> Polynomial<Bn256, Coefficients> {
+    Polynomial::from_coeffs(common::random_elements::<Fr>(1 << log_n)).unwrap()
+}
+
+fn bench_lde(c: &mut Criterion) {
+    let worker = Worker::new();
+    let coset_factor = Fr::multiplicative_generator();
+
+    let mut group = c.benchmark_group("cpu/lde");
+    group.sample_size(10);
+    for &log_n in LOG_SIZES.iter() {
+        let poly = random_poly(log_n);
+        let domain = Domain::<Fr>::new_for_size(1 << log_n).unwrap();
+        let omegas = BitReversedOmegas::<Fr>::new_for_domain(&domain, &worker);
+
+        group.bench_with_input(BenchmarkId::new("lde", log_n), &poly, |b, poly| {
+            b.iter_batched(
+                || poly.clone(),
+                |p| p.lde(&worker, LDE_FACTOR).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("coset_lde", log_n), &poly, |b, poly| {
+            b.iter_batched(
+                || poly.clone(),
+                |p| p.coset_lde(&worker, LDE_FACTOR).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(
+            BenchmarkId::new("lde_using_bitreversed_ntt", log_n),
+            &poly,
+            |b, poly| {
+                b.iter_batched(
+                    || poly.clone(),
+                    |p| p.lde_using_bitreversed_ntt(&worker, LDE_FACTOR, &omegas).unwrap(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+
+    let mut groups = vec![("cpu/bitreversed_lde", false)];
+    if common::gpu_enabled() {
+        groups.push(("gpu/bitreversed_lde", true));
+    }
+
+    for (name, use_gpu) in groups.into_iter() {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+        for &log_n in LOG_SIZES.iter() {
+            let polys: Vec<_> = (0..BATCH_SIZE).map(|_| random_poly(log_n)).collect();
+            let refs: Vec<_> = polys.iter().collect();
+            let pool = PolynomialPool::<Fr>::new(None);
+            let mut kern = if use_gpu {
+                Some(LockedMultiFFTKernel::<Bn256>::new(log_n as usize, false))
+            } else {
+                None
+            };
+
+            // one polynomial after another, as the prover did before the batched version
+            group.bench_function(BenchmarkId::new("single", log_n), |b| {
+                b.iter(|| {
+                    for p in polys.iter() {
+                        p.bitreversed_lde_using_bitreversed_ntt_in(
+                            &pool,
+                            &worker,
+                            LDE_FACTOR,
+                            &coset_factor,
+                            &mut kern,
+                        )
+                        .unwrap()
+                        .into_pool(&pool);
+                    }
+                })
+            });
+            group.bench_function(BenchmarkId::new("batch", log_n), |b| {
+                b.iter(|| {
+                    let ldes = Polynomial::bitreversed_lde_using_bitreversed_ntt_batch_in(
+                        &refs,
+                        &pool,
+                        &worker,
+                        LDE_FACTOR,
+                        &coset_factor,
+                        &mut kern,
+                    )
+                    .unwrap();
+                    for lde in ldes.into_iter() {
+                        lde.into_pool(&pool);
+                    }
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_grand_product(c: &mut Criterion) {
+    let worker = Worker::new();
+
+    let mut group = c.benchmark_group("cpu/calculate_grand_product");
+    for &log_n in LOG_SIZES.iter() {
+        let values: Polynomial<Bn256, Values> =
+            Polynomial::from_values(common::random_elements::<Fr>(1 << log_n)).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(log_n), &values, |b, values| {
+            b.iter(|| values.calculate_grand_product(&worker).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lde, bench_grand_product);
+criterion_main!(benches);
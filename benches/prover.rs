@@ -0,0 +1,144 @@
+//! `cargo bench --features bench --bench prover`
+//!
+//! Every step is measured on its own out of the timings of whole proofs, see
+//! `prove_native_by_steps_with_timings`. A proof records all five steps, so a step only
+//! runs new proofs once the timings left by the other steps are used up.
+
+mod common;
+
+use bellman_gpu::kate_commitment::{Crs, CrsForMonomialForm};
+use bellman_gpu::pairing::bn256::{Bn256, Fr};
+use bellman_gpu::pairing::ff::{Field, PrimeField};
+use bellman_gpu::pairing::Engine;
+use bellman_gpu::plonk::better_cs::cs::{Circuit, ConstraintSystem, PlonkCsWidth4WithNextStepParams};
+use bellman_gpu::plonk::better_cs::generator::GeneratorAssembly4WithNextStep;
+use bellman_gpu::plonk::commitments::transcript::keccak_transcript::RollingKeccakTranscript;
+use bellman_gpu::plonk::{prove_native_by_steps_with_timings, ProverConfig};
+use bellman_gpu::worker::Worker;
+use bellman_gpu::SynthesisError;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Logarithms of the domain sizes.
+const LOG_SIZES: [u32; 3] = [10, 14, 16];
+
+const STEPS: [&str; 5] = [
+    "first_step_witness_commitment",
+    "second_step_grand_product",
+    "third_step_quotient",
+    "fourth_step_openings",
+    "fifth_step_opening_proofs",
+];
+
+/// Chain of multiplications `x_{i+2} = x_i * x_{i+1}` starting from the public input.
+#[derive(Clone)]
+struct MultiplicationChain {
+    num_gates: usize,
+}
+
+impl<E: Engine> Circuit<E, PlonkCsWidth4WithNextStepParams> for MultiplicationChain {
+    fn synthesize<CS: ConstraintSystem<E, PlonkCsWidth4WithNextStepParams>>(
+        &self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError> {
+        let zero = E::Fr::zero();
+        let one = E::Fr::one();
+        let mut negative_one = one;
+        negative_one.negate();
+        let dummy = cs.get_dummy_variable();
+
+        let mut a_value = E::Fr::from_str("3").unwrap();
+        let mut a = cs.alloc_input(|| Ok(a_value))?;
+        let mut b_value = a_value;
+        let mut b = a;
+
+        for _ in 0..self.num_gates {
+            let mut c_value = a_value;
+            c_value.mul_assign(&b_value);
+            let c = cs.alloc(|| Ok(c_value))?;
+
+            // a * b - c == 0
+            cs.new_gate(
+                [a, b, c, dummy],
+                [zero, zero, negative_one, zero, one, zero],
+                [zero],
+            )?;
+
+            a = b;
+            a_value = b_value;
+            b = c;
+            b_value = c_value;
+        }
+
+        Ok(())
+    }
+}
+
+fn bench_prover_steps(c: &mut Criterion) {
+    let worker = Worker::new();
+
+    let mut configs = vec![("cpu/prover", ProverConfig::cpu_only())];
+    if common::gpu_enabled() {
+        configs.push(("gpu/prover", ProverConfig::from_env()));
+    }
+
+    for (name, config) in configs.into_iter() {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+
+        for &log_n in LOG_SIZES.iter() {
+            // with the input gate 2^log_n - 1 gates, so the domain is of size 2^log_n
+            let circuit = MultiplicationChain {
+                num_gates: (1 << log_n) - 2,
+            };
+
+            let mut assembly = GeneratorAssembly4WithNextStep::<Bn256>::new();
+            Circuit::<Bn256, PlonkCsWidth4WithNextStepParams>::synthesize(&circuit, &mut assembly)
+                .unwrap();
+            assembly.finalize();
+            let setup = assembly.setup(&worker).unwrap();
+            let crs_mons = Crs::<Bn256, CrsForMonomialForm>::crs_42(
+                setup.permutation_polynomials[0].size(),
+                &worker,
+            );
+
+            // step timings of the proofs made so far and not yet used by their step
+            let recorded: RefCell<[VecDeque<Duration>; 5]> = RefCell::new(Default::default());
+
+            for (step, step_name) in STEPS.iter().enumerate() {
+                group.bench_function(BenchmarkId::new(*step_name, log_n), |b| {
+                    b.iter_custom(|iters| {
+                        let mut total = Duration::new(0, 0);
+                        for _ in 0..iters {
+                            if recorded.borrow()[step].is_empty() {
+                                let (_, timings) = prove_native_by_steps_with_timings::<
+                                    _,
+                                    _,
+                                    RollingKeccakTranscript<Fr>,
+                                >(
+                                    &circuit, &setup, None, &crs_mons, None, &config
+                                )
+                                .unwrap();
+                                let mut recorded = recorded.borrow_mut();
+                                for (queue, &duration) in
+                                    recorded.iter_mut().zip(timings.steps.iter())
+                                {
+                                    queue.push_back(duration);
+                                }
+                            }
+                            total += recorded.borrow_mut()[step].pop_front().unwrap();
+                        }
+
+                        total
+                    })
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_prover_steps);
+criterion_main!(benches);
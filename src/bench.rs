@@ -0,0 +1,19 @@
+//! Internals measured by the benchmarks in `benches/`. Only compiled with the `bench`
+//! feature and not a stable API.
+
+use crate::pairing::ff::PrimeField;
+use crate::worker::Worker;
+
+pub use crate::locks::{LockedMultiFFTKernel, LockedMultiexpKernel};
+pub use crate::multiexp::dense_multiexp;
+
+/// Transform of the `2^log_2 * 3^log_3` values of `a`, `omega` is a generator of that order.
+pub fn best_mixed_radix_fft<F: PrimeField>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    log_2: u32,
+    log_3: u32,
+) {
+    crate::plonk::fft::mixed_radix::best_mixed_radix_fft(a, worker, omega, log_2, log_3)
+}
@@ -0,0 +1,253 @@
+//! Per-prover configuration of the GPU/CPU split.
+//!
+//! Everything here used to be read from process-wide environment variables deep
+//! inside the kernel wrappers. The variables are still honoured, but only by
+//! [`ProverConfig::from_env`], and the kernels get their devices from the configuration
+//! (see `gpu::KernelOnDevices`), so two provers in one process can run with different
+//! device assignments.
+
+use crate::plonk::fft::strategy::{NttSelection, DEFAULT_CACHE_SIZE};
+use crate::worker::Worker;
+use log::warn;
+use std::env;
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProverConfig {
+    /// Never touch the GPU (`BELLMAN_USE_CPU=1`).
+    pub use_cpu: bool,
+    /// Split the GPUs into groups of this size (`BELLMAN_NUM_GPUS_PER_GROUP`).
+    /// `None` means a single group containing every device.
+    pub num_gpus_per_group: Option<usize>,
+    /// Which of the groups this prover uses (`BELLMAN_GPU_GROUP_INDEX`).
+    pub gpu_group_index: usize,
+    /// Core counts of GPUs unknown to the kernels, as `"name:cores,..."` (`BELLMAN_CUSTOM_GPU`).
+    pub custom_gpu: Option<String>,
+    /// Fraction of multiexp and FFT work given to the CPU while the GPU is busy
+    /// (`BELLMAN_CPU_UTILIZATION`).
+    pub cpu_utilization: f64,
+    /// Bytes of coset values the quotient computation may keep in memory
+    /// (`BELLMAN_MEMORY_LIMIT`). With a limit the coset values are streamed through
+    /// disk-backed polynomials instead of keeping the whole LDEs in memory. The monomials,
+    /// the precomputed setup LDEs and the quotient parts are not counted and stay resident.
+    pub memory_limit: Option<usize>,
+    /// Bytes of the quotient and grand product buffers the second and third steps may take
+    /// from their polynomial pool (`BELLMAN_MEMORY_BUDGET`), nothing else is counted. A step
+    /// that needs more fails with `SynthesisError::MemoryBudgetExceeded`. With a
+    /// `memory_limit` the quotient does not use the pool, so only the grand product is left.
+    pub memory_budget: Option<usize>,
+    /// Thread pool of the prover. `None` uses the pool shared by every `Worker::new()`.
+    pub worker: Option<Worker>,
+    /// CPU transforms of the prover (`BELLMAN_NTT_STRATEGY` and `BELLMAN_CACHE_SIZE`),
+    /// radix-2 by default.
+    pub ntt: NttSelection,
+}
+
+/// All GPUs and no CPU share, the environment is not consulted.
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self::cpu_and_gpu()
+    }
+}
+
+impl ProverConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self::cpu_and_gpu();
+
+        if let Ok(flag) = env::var("BELLMAN_USE_CPU") {
+            config.use_cpu = flag == "1";
+        }
+
+        // the group is only taken into account if both variables are valid
+        let num = env::var("BELLMAN_NUM_GPUS_PER_GROUP")
+            .ok()
+            .and_then(|v| v.parse().ok());
+        let index = env::var("BELLMAN_GPU_GROUP_INDEX")
+            .ok()
+            .and_then(|v| v.parse().ok());
+        if let (Some(num), Some(index)) = (num, index) {
+            config.num_gpus_per_group = Some(num);
+            config.gpu_group_index = index;
+        }
+
+        config.custom_gpu = env::var("BELLMAN_CUSTOM_GPU").ok();
+
+        if let Some(utilization) = env::var("BELLMAN_CPU_UTILIZATION")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            config.cpu_utilization = utilization;
+        }
+
+        config.memory_limit = env::var("BELLMAN_MEMORY_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok());
+        config.memory_budget = env::var("BELLMAN_MEMORY_BUDGET")
+            .ok()
+            .and_then(|v| v.parse().ok());
+
+        if let Ok(name) = env::var("BELLMAN_NTT_STRATEGY") {
+            let cache_size = env::var("BELLMAN_CACHE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_CACHE_SIZE);
+            match NttSelection::from_name(&name, cache_size) {
+                Some(ntt) => config.ntt = ntt,
+                None => warn!("Unknown BELLMAN_NTT_STRATEGY {:?}, using radix-2", name),
+            }
+        }
+
+        config.sanitized()
+    }
+
+    /// All GPUs, no CPU share, regardless of the environment.
+    pub fn cpu_and_gpu() -> Self {
+        ProverConfig {
+            use_cpu: false,
+            num_gpus_per_group: None,
+            gpu_group_index: 0,
+            custom_gpu: None,
+            cpu_utilization: 0f64,
+            memory_limit: None,
+            memory_budget: None,
+            worker: None,
+            ntt: NttSelection::default(),
+        }
+    }
+
+    /// CPU only, regardless of the environment.
+    pub fn cpu_only() -> Self {
+        ProverConfig {
+            use_cpu: true,
+            ..Self::cpu_and_gpu()
+        }
+    }
+
+    pub fn with_gpu_group(mut self, num_gpus_per_group: usize, group_index: usize) -> Self {
+        self.num_gpus_per_group = Some(num_gpus_per_group);
+        self.gpu_group_index = group_index;
+
+        self
+    }
+
+    pub fn with_cpu_utilization(mut self, cpu_utilization: f64) -> Self {
+        self.cpu_utilization = cpu_utilization;
+
+        self.sanitized()
+    }
+
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = Some(memory_limit);
+
+        self
+    }
+
+    pub fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.memory_budget = Some(memory_budget);
+
+        self
+    }
+
+    pub fn with_worker(mut self, worker: Worker) -> Self {
+        self.worker = Some(worker);
+
+        self
+    }
+
+    pub fn with_ntt(mut self, ntt: NttSelection) -> Self {
+        self.ntt = ntt;
+
+        self
+    }
+
+    /// The configured thread pool, or the shared one, with the configured transforms.
+    pub fn worker(&self) -> Worker {
+        self.worker
+            .clone()
+            .unwrap_or_else(Worker::new)
+            .with_ntt(self.ntt)
+    }
+
+    fn sanitized(mut self) -> Self {
+        self.cpu_utilization = self.cpu_utilization.max(0f64).min(1f64);
+
+        self
+    }
+
+    /// Name suffix of the GPU lock file and the range of devices this prover owns,
+    /// out of `all_gpus` available ones.
+    pub fn lock_name_and_gpu_range(&self, all_gpus: usize) -> (String, Range<usize>) {
+        let num_gpus_per_group = self.num_gpus_per_group.unwrap_or(all_gpus);
+        let group_index = if self.num_gpus_per_group.is_some() {
+            self.gpu_group_index
+        } else {
+            0
+        };
+
+        assert!(
+            (group_index + 1) * num_gpus_per_group <= all_gpus,
+            "gpu group index and number of gpus per group do not fit into {} devices",
+            all_gpus
+        );
+
+        (
+            group_index.to_string(),
+            Range {
+                start: group_index * num_gpus_per_group,
+                end: (group_index + 1) * num_gpus_per_group,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_ranges_of_groups() {
+        let config = ProverConfig::cpu_and_gpu();
+        assert_eq!(config.lock_name_and_gpu_range(4), ("0".to_string(), 0..4));
+
+        let config = ProverConfig::cpu_and_gpu().with_gpu_group(2, 1);
+        assert_eq!(config.lock_name_and_gpu_range(4), ("1".to_string(), 2..4));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_gpu_group_out_of_range() {
+        ProverConfig::cpu_and_gpu()
+            .with_gpu_group(2, 2)
+            .lock_name_and_gpu_range(4);
+    }
+
+    #[test]
+    fn test_default_ignores_environment() {
+        assert_eq!(ProverConfig::default(), ProverConfig::cpu_and_gpu());
+    }
+
+    #[test]
+    fn test_configured_worker_is_used() {
+        let worker = Worker::builder().num_threads(3).build().unwrap();
+        let config = ProverConfig::cpu_only().with_worker(worker.clone());
+        assert_eq!(config.worker(), worker);
+        assert_eq!(config.worker().get_num_cpus(), 3);
+
+        assert_eq!(ProverConfig::cpu_only().worker(), Worker::new());
+    }
+
+    #[test]
+    fn test_configured_ntt_reaches_worker() {
+        let ntt = NttSelection::BySize { cache_size: 1 << 16 };
+        let config = ProverConfig::cpu_only().with_ntt(ntt);
+        assert_eq!(config.worker().ntt(), ntt);
+
+        assert_eq!(ProverConfig::cpu_only().worker().ntt(), NttSelection::default());
+    }
+
+    #[test]
+    fn test_cpu_utilization_is_clamped() {
+        let config = ProverConfig::cpu_and_gpu().with_cpu_utilization(1.5);
+        assert_eq!(config.cpu_utilization, 1f64);
+    }
+}
@@ -174,8 +174,12 @@ pub enum SynthesisError {
     IoError(io::Error),
     /// During verification, our verifying key was malformed.
     MalformedVerifyingKey,
+    /// During verification, the proof was malformed, e.g. it opened an index outside the domain
+    MalformedProof,
     /// During CRS generation, we observed an unconstrained auxillary variable
-    UnconstrainedVariable
+    UnconstrainedVariable,
+    /// During proof generation, a polynomial did not fit into the memory budget
+    MemoryBudgetExceeded
 }
 
 impl From<io::Error> for SynthesisError {
@@ -195,7 +199,9 @@ impl SynthesisError {
             SynthesisError::UnexpectedIdentity => "encountered an identity element in the CRS",
             SynthesisError::IoError(_) => "encountered an I/O error",
             SynthesisError::MalformedVerifyingKey => "malformed verifying key",
-            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained"
+            SynthesisError::MalformedProof => "malformed proof",
+            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained",
+            SynthesisError::MemoryBudgetExceeded => "polynomial memory budget exceeded"
         }
     }
 }
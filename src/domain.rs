@@ -19,14 +19,20 @@ use pairing::{
 };
 
 
+use crate::config::ProverConfig;
+use crate::gpu::{GPUError, GPUResult, KernelOnDevices};
 use crate::locks::LockedMultiFFTKernel;
 use crate::locks::LockedMultiexpKernel;
-use ec_gpu_gen::EcError;
-use ec_gpu_gen::EcResult;
+use crate::locks::MultiFFTKernel;
 
 
 use crate::plonk::domains::Domain;
+use crate::plonk::fft::strategy::best_ntt;
+use lazy_static::lazy_static;
 use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 pub struct EvaluationDomain<E: Engine, G: Group<E>> {
     coeffs: Vec<G>,
@@ -90,12 +96,52 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         })
     }
 
+    /// Same as `from_coeffs`, but pads to the smallest domain of size `2^a * 3^b`.
+    pub fn from_coeffs_mixed_radix(
+        mut coeffs: Vec<G>,
+    ) -> Result<EvaluationDomain<E, G>, SynthesisError> {
+        let domain = Domain::<E::Fr>::new_for_size_mixed_radix(coeffs.len() as u64)?;
+        let m = domain.size as usize;
+        let omega = domain.generator;
+
+        coeffs.resize(m, G::group_zero());
+
+        Ok(EvaluationDomain {
+            coeffs,
+            exp: domain.power_of_two as u32,
+            omega,
+            omegainv: omega.inverse().unwrap(),
+            geninv: E::Fr::multiplicative_generator().inverse().unwrap(),
+            minv: E::Fr::from_str(&format!("{}", m))
+                .unwrap()
+                .inverse()
+                .unwrap(),
+        })
+    }
+
+    /// Power of three in the size of the domain, `exp` is the power of two.
+    fn power_of_three(&self) -> u32 {
+        let mut rest = self.coeffs.len() >> self.exp;
+        let mut power_of_three = 0;
+        while rest > 1 && rest % 3 == 0 {
+            rest /= 3;
+            power_of_three += 1;
+        }
+
+        power_of_three
+    }
+
     pub fn fft(
         &mut self,
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
-    ) -> EcResult<()> {
-        best_fft(kern, &mut self.coeffs, worker, &self.omega, self.exp)?;
+    ) -> GPUResult<()> {
+        let power_of_three = self.power_of_three();
+        if power_of_three != 0 {
+            mixed_radix_fft(kern, &mut self.coeffs, worker, &self.omega, self.exp, power_of_three)?;
+        } else {
+            best_fft(kern, &mut self.coeffs, worker, &self.omega, self.exp)?;
+        }
         Ok(())
     }
 
@@ -103,8 +149,13 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         &mut self,
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
-    ) -> EcResult<()> {
-        best_fft(kern, &mut self.coeffs, worker, &self.omegainv, self.exp)?;
+    ) -> GPUResult<()> {
+        let power_of_three = self.power_of_three();
+        if power_of_three != 0 {
+            mixed_radix_fft(kern, &mut self.coeffs, worker, &self.omegainv, self.exp, power_of_three)?;
+        } else {
+            best_fft(kern, &mut self.coeffs, worker, &self.omegainv, self.exp)?;
+        }
 
         worker.scope(self.coeffs.len(), |scope, chunk| {
             let minv = self.minv;
@@ -139,7 +190,7 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         &mut self,
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
-    ) -> EcResult<()> {
+    ) -> GPUResult<()> {
         self.distribute_powers(worker, E::Fr::multiplicative_generator());
         self.fft(worker, kern)?;
         Ok(())
@@ -149,7 +200,7 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         &mut self,
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
-    ) -> EcResult<()> {
+    ) -> GPUResult<()> {
         let geninv = self.geninv;
         self.ifft(worker, kern)?;
         self.distribute_powers(worker, geninv);
@@ -170,7 +221,7 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
     }
 
     /// This evaluates t(tau) for this domain, which is
-    /// tau^m - 1 for these radix-2 domains.
+    /// tau^m - 1 for these radix-2 and mixed radix domains.
     pub fn z(&self, tau: &E::Fr) -> E::Fr {
         let mut tmp = tau.pow(&[self.coeffs.len() as u64]);
         tmp.sub_assign(&E::Fr::one());
@@ -243,7 +294,7 @@ fn best_fft<E: Engine, T: Group<E>>(
     worker: &Worker,
     omega: &E::Fr,
     log_n: u32,
-) -> EcResult<()> {
+) -> GPUResult<()> {
     if let Some(ref mut kern) = kern {
         if kern
             .with(|k: &mut MultiFFTKernel<E>| gpu_fft(k, a, omega, log_n))
@@ -268,7 +319,7 @@ pub fn gpu_fft<E: Engine, T: Group<E>>(
     a: &mut [T],
     omega: &E::Fr,
     log_n: u32,
-) -> EcResult<()> {
+) -> GPUResult<()> {
     // EvaluationDomain module is supposed to work only with E::Fr elements, and not CurveProjective
     // points. The Bellman authors have implemented an unnecessarry abstraction called Group<E>
     // which is implemented for both PrimeField and CurveProjective elements. As nowhere in the code
@@ -327,6 +378,46 @@ pub fn serial_fft<E: Engine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_n: u32
     }
 }
 
+/// Group arithmetic of the evaluation domains for the shared mixed radix FFT.
+struct GroupOps<E: Engine>(std::marker::PhantomData<E>);
+
+impl<E: Engine, T: Group<E>> crate::plonk::fft::mixed_radix::MixedRadixOps<E::Fr, T> for GroupOps<E> {
+    fn zero() -> T {
+        T::group_zero()
+    }
+
+    fn add_assign(a: &mut T, b: &T) {
+        a.group_add_assign(b);
+    }
+
+    fn sub_assign(a: &mut T, b: &T) {
+        a.group_sub_assign(b);
+    }
+
+    fn mul_assign(a: &mut T, by: &E::Fr) {
+        a.group_mul_assign(by);
+    }
+}
+
+/// FFT of size `2^log_2 * 3^log_3` with the rows on `best_fft`, see `plonk::fft::mixed_radix`.
+fn mixed_radix_fft<E: Engine, T: Group<E>>(
+    kern: &mut Option<LockedMultiFFTKernel<E>>,
+    a: &mut [T],
+    worker: &Worker,
+    omega: &E::Fr,
+    log_2: u32,
+    log_3: u32,
+) -> GPUResult<()> {
+    crate::plonk::fft::mixed_radix::mixed_radix_fft::<E::Fr, T, GroupOps<E>, _, _>(
+        a,
+        worker,
+        omega,
+        log_2,
+        log_3,
+        |row, row_omega| best_fft(kern, row, worker, row_omega, log_2),
+    )
+}
+
 fn parallel_fft<E: Engine, T: Group<E>>(
     a: &mut [T],
     worker: &Worker,
@@ -398,11 +489,15 @@ pub fn distribute_powers<E: Engine>(coeffs: &mut [E::Fr], worker: &Worker, g: E:
     });
 }
 
-pub fn create_fft_kernel<E>(_log_d: usize, priority: bool) -> Option<MultiFFTKernel<E>>
+pub fn create_fft_kernel<E>(
+    _log_d: usize,
+    priority: bool,
+    config: &ProverConfig,
+) -> Option<MultiFFTKernel<E>>
 where
     E: Engine,
 {
-    match MultiFFTKernel::create(priority) {
+    match MultiFFTKernel::<E>::create_on_devices(priority, config) {
         Ok(k) => {
             info!("GPU FFT kernel instantiated!");
             Some(k)
@@ -420,28 +515,244 @@ pub fn best_fft_multiple_gpu<E: Engine>(
     worker: &Worker,
     omega: &E::Fr,
     log_n: u32,
-) -> EcResult<()> {
-    if let Some(ref mut kern) = kern {
-        if kern
-            .with(|k: &mut MultiFFTKernel<E>| gpu_fft_multiple(k, polys, omega, log_n))
-            .is_ok()
-        {
-            return Ok(());
+) -> GPUResult<()> {
+    FFT_SCHEDULER.fft_multiple(kern, polys, worker, omega, log_n)
+}
+
+fn cpu_fft_multiple<E: Engine>(
+    polys: &mut [&mut [E::Fr]],
+    worker: &Worker,
+    omega: &E::Fr,
+    log_n: u32,
+) {
+    for poly in polys.iter_mut() {
+        best_ntt(poly, worker, omega, log_n);
+    }
+}
+
+/// Anything that can run a batch of same-size FFTs off the CPU.
+pub trait FftBatchKernel<E: Engine> {
+    fn fft_batch(
+        &mut self,
+        polys: &mut [&mut [E::Fr]],
+        omega: &E::Fr,
+        log_n: u32,
+    ) -> GPUResult<()>;
+
+    /// Share of the batch to give to the CPU until throughput has been measured.
+    fn cpu_utilization(&self) -> Option<f64> {
+        None
+    }
+
+    /// A disabled kernel gets no work at all.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    /// GPU group the kernel runs on, throughput is measured separately per group.
+    fn device_group(&self) -> DeviceGroup {
+        None
+    }
+}
+
+impl<E: Engine> FftBatchKernel<E> for LockedMultiFFTKernel<E> {
+    fn fft_batch(
+        &mut self,
+        polys: &mut [&mut [E::Fr]],
+        omega: &E::Fr,
+        log_n: u32,
+    ) -> GPUResult<()> {
+        self.with(|k: &mut MultiFFTKernel<E>| gpu_fft_multiple(k, polys, omega, log_n))
+    }
+
+    fn cpu_utilization(&self) -> Option<f64> {
+        Some(self.config().cpu_utilization)
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.config().use_cpu
+    }
+
+    fn device_group(&self) -> DeviceGroup {
+        let config = self.config();
+        config
+            .num_gpus_per_group
+            .map(|num| (num, config.gpu_group_index))
+    }
+}
+
+lazy_static! {
+    static ref FFT_SCHEDULER: FftScheduler = FftScheduler::new();
+}
+
+/// Measured FFT throughput of both sides, in butterflies per second.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FftThroughput {
+    pub gpu: Option<f64>,
+    pub cpu: Option<f64>,
+}
+
+/// GPU group (`num_gpus_per_group`, `gpu_group_index`) of a kernel, `None` for all devices.
+pub type DeviceGroup = Option<(usize, usize)>;
+
+/// Share of a batch given to the CPU while its throughput has not been measured yet,
+/// so that a default share of 0 still measures the CPU once.
+const EXPLORATION_CPU_SHARE: f64 = 0.1;
+
+/// Splits a batch of same-size FFTs between a GPU kernel and the CPU (`strategy::best_ntt`).
+///
+/// Each side gets a share of the batch proportional to its measured throughput.
+/// Throughput is kept per domain size and GPU group, since neither side scales
+/// linearly with the size and groups may have different devices. Provers sharing
+/// a group share the measurements, as they compete for the same devices anyway.
+/// Until both sides have been measured the CPU share is the kernel's configured
+/// `cpu_utilization`, but at least one polynomial of the batch while the CPU is unmeasured.
+pub struct FftScheduler {
+    default_cpu_share: f64,
+    smoothing: f64,
+    throughput: Mutex<HashMap<(u32, DeviceGroup), FftThroughput>>,
+}
+
+impl FftScheduler {
+    pub fn new() -> Self {
+        Self::with_cpu_share(ProverConfig::from_env().cpu_utilization)
+    }
+
+    pub fn with_cpu_share(share: f64) -> Self {
+        FftScheduler {
+            default_cpu_share: share.max(0f64).min(1f64),
+            smoothing: 0.5,
+            throughput: Mutex::new(HashMap::new()),
         }
     }
 
-    let log_cpus = worker.log_num_cpus();
-    if log_n <= log_cpus {
-        for poly in polys.iter_mut() {
-            serial_fft_fr::<E>(poly, omega, log_n);
+    pub fn throughput(&self, log_n: u32, device_group: DeviceGroup) -> FftThroughput {
+        self.throughput
+            .lock()
+            .unwrap()
+            .get(&(log_n, device_group))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Number of polynomials out of a batch of `batch_size` FFTs of size `2^log_n`
+    /// that should go to the CPU when using all devices.
+    pub fn num_for_cpu(&self, batch_size: usize, log_n: u32) -> usize {
+        self.num_for_cpu_with_default(batch_size, log_n, None, self.default_cpu_share)
+    }
+
+    fn num_for_cpu_with_default(
+        &self,
+        batch_size: usize,
+        log_n: u32,
+        device_group: DeviceGroup,
+        default_cpu_share: f64,
+    ) -> usize {
+        let throughput = self.throughput(log_n, device_group);
+        let share = match (throughput.gpu, throughput.cpu) {
+            (Some(gpu), Some(cpu)) if gpu + cpu > 0f64 => cpu / (gpu + cpu),
+            (_, None) => default_cpu_share.max(EXPLORATION_CPU_SHARE),
+            _ => default_cpu_share,
+        };
+
+        let mut num = (batch_size as f64 * share).round() as usize;
+        if throughput.cpu.is_none() && batch_size > 1 {
+            num = std::cmp::max(num, 1);
         }
-    } else {
-        for poly in polys.iter_mut() {
-            parallel_fft_fr::<E>(poly, worker, omega, log_n, log_cpus);
+
+        std::cmp::min(num, batch_size)
+    }
+
+    fn record(
+        &self,
+        on_gpu: bool,
+        num_polys: usize,
+        log_n: u32,
+        device_group: DeviceGroup,
+        elapsed: Duration,
+    ) {
+        let secs = elapsed.as_secs_f64();
+        if num_polys == 0 || secs == 0f64 {
+            return;
         }
+        let butterflies = (num_polys << log_n) as f64 * f64::from(log_n.max(1));
+        let rate = butterflies / secs;
+
+        let mut measurements = self.throughput.lock().unwrap();
+        let throughput = measurements.entry((log_n, device_group)).or_default();
+        let slot = if on_gpu {
+            &mut throughput.gpu
+        } else {
+            &mut throughput.cpu
+        };
+        *slot = Some(match *slot {
+            Some(prev) => prev * (1f64 - self.smoothing) + rate * self.smoothing,
+            None => rate,
+        });
     }
 
-    Ok(())
+    /// Runs FFTs of all `polys`, the GPU part on the calling thread and the CPU part
+    /// on the worker pool at the same time. Whatever the GPU fails to process is
+    /// redone on the CPU.
+    pub fn fft_multiple<E: Engine, K: FftBatchKernel<E>>(
+        &self,
+        kern: &mut Option<K>,
+        polys: &mut [&mut [E::Fr]],
+        worker: &Worker,
+        omega: &E::Fr,
+        log_n: u32,
+    ) -> GPUResult<()> {
+        let device_group = kern.as_ref().and_then(|k| k.device_group());
+        let num_for_cpu = match kern {
+            Some(ref kern) if kern.is_enabled() => {
+                let default_cpu_share = kern.cpu_utilization().unwrap_or(self.default_cpu_share);
+                self.num_for_cpu_with_default(polys.len(), log_n, device_group, default_cpu_share)
+            }
+            _ => polys.len(),
+        };
+        let num_for_gpu = polys.len() - num_for_cpu;
+
+        let (gpu_polys, cpu_polys) = polys.split_at_mut(num_for_gpu);
+
+        let mut gpu_elapsed = None;
+        let mut cpu_elapsed = None;
+
+        worker.in_place_scope(0, |scope, _| {
+            if !cpu_polys.is_empty() {
+                let cpu_elapsed = &mut cpu_elapsed;
+                scope.spawn(move |_| {
+                    let now = Instant::now();
+                    cpu_fft_multiple::<E>(cpu_polys, worker, omega, log_n);
+                    *cpu_elapsed = Some(now.elapsed());
+                });
+            }
+
+            if let Some(ref mut kern) = kern {
+                if !gpu_polys.is_empty() {
+                    let now = Instant::now();
+                    if kern.fft_batch(gpu_polys, omega, log_n).is_ok() {
+                        gpu_elapsed = Some(now.elapsed());
+                    }
+                }
+            }
+        });
+
+        if let Some(elapsed) = cpu_elapsed {
+            self.record(false, num_for_cpu, log_n, device_group, elapsed);
+        }
+
+        match gpu_elapsed {
+            Some(elapsed) => self.record(true, num_for_gpu, log_n, device_group, elapsed),
+            None if !gpu_polys.is_empty() => {
+                let now = Instant::now();
+                cpu_fft_multiple::<E>(gpu_polys, worker, omega, log_n);
+                self.record(false, num_for_gpu, log_n, device_group, now.elapsed());
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
 }
 
 pub fn gpu_fft_multiple<E: Engine>(
@@ -449,7 +760,7 @@ pub fn gpu_fft_multiple<E: Engine>(
     polys: &mut [&mut [E::Fr]],
     omega: &E::Fr,
     log_n: u32,
-) -> EcResult<()> {
+) -> GPUResult<()> {
     kern.fft_multiple(polys, omega, log_n)?;
 
     Ok(())
@@ -562,8 +873,8 @@ pub fn best_fft_recursive_gpu<E: Engine>(
     worker: &Worker,
     omega: &E::Fr,
     log_n: u32,
-) -> EcResult<()> {
-    if let Some(ref mut kern) = kern {
+) -> GPUResult<()> {
+    if kern.is_some() {
         let size = a.len();
         let mut copy_a: Vec<E::Fr> = Vec::with_capacity(size);
         unsafe { copy_a.set_len(size) };
@@ -597,15 +908,18 @@ pub fn best_fft_recursive_gpu<E: Engine>(
         });
         println!("extract vector taken {:?}", now.elapsed());
 
-        if kern
-            .with(|k: &mut MultiFFTKernel<E>| {
-                let domain = Domain::<E::Fr>::new_for_size(half_size as u64).unwrap();
-                let omega = domain.generator.inverse().unwrap();
-                gpu_fft_multiple(k, &mut [left, right], &omega, domain.power_of_two as u32)
-            })
+        let domain = Domain::<E::Fr>::new_for_size(half_size as u64).unwrap();
+        let half_omega = domain.generator.inverse().unwrap();
+        if FFT_SCHEDULER
+            .fft_multiple(
+                kern,
+                &mut [&mut *left, &mut *right],
+                worker,
+                &half_omega,
+                domain.power_of_two as u32,
+            )
             .is_ok()
         {
-            println!("use ONE GPU");
             // distribute powers of in the second half of copy_a
 
             let now = Instant::now();
@@ -683,7 +997,7 @@ pub fn fft_parallel<E: Engine>(
     minv: &E::Fr,
     log_n: u32,
     direction: bool,
-) -> EcResult<()> {
+) -> GPUResult<()> {
     let size = 1 << log_n;
 
     use std::time::Instant;
@@ -748,3 +1062,151 @@ fn test_best_fft_recursive_gpu_consistency() {
     .unwrap();
     best_fft_recursive_gpu(&mut fft_kern, &mut coeff2, &worker, &omegainv, log_n as u32).unwrap();
 }
+
+#[cfg(test)]
+struct CpuMockFftKernel {
+    delay: Duration,
+    fail: bool,
+}
+
+#[cfg(test)]
+impl<E: Engine> FftBatchKernel<E> for CpuMockFftKernel {
+    fn fft_batch(
+        &mut self,
+        polys: &mut [&mut [E::Fr]],
+        omega: &E::Fr,
+        log_n: u32,
+    ) -> GPUResult<()> {
+        if self.fail {
+            return Err(GPUError::Simple("mock kernel failure"));
+        }
+        std::thread::sleep(self.delay);
+        for poly in polys.iter_mut() {
+            serial_fft_fr::<E>(poly, omega, log_n);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn run_scheduled_fft_and_compare(scheduler: &FftScheduler, kern: &mut Option<CpuMockFftKernel>) {
+    use crate::pairing::bn256::{Bn256, Fr};
+
+    let log_n = 10;
+    let worker = Worker::new();
+    let domain = Domain::<Fr>::new_for_size(1 << log_n).unwrap();
+
+    let polys: Vec<Vec<Fr>> = (0..4)
+        .map(|_| crate::kate_commitment::test::make_random_field_elements::<Fr>(&worker, 1 << log_n))
+        .collect();
+
+    let mut expected = polys.clone();
+    for p in expected.iter_mut() {
+        serial_fft_fr::<Bn256>(p, &domain.generator, log_n);
+    }
+
+    let mut actual = polys;
+    {
+        let mut refs: Vec<&mut [Fr]> = actual.iter_mut().map(|p| &mut p[..]).collect();
+        scheduler
+            .fft_multiple::<Bn256, _>(kern, &mut refs, &worker, &domain.generator, log_n)
+            .unwrap();
+    }
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn test_fft_scheduler_split_matches_serial() {
+    let scheduler = FftScheduler::with_cpu_share(0.5);
+    assert_eq!(scheduler.num_for_cpu(4, 10), 2);
+
+    let mut kern = Some(CpuMockFftKernel {
+        delay: Duration::from_millis(0),
+        fail: false,
+    });
+    run_scheduled_fft_and_compare(&scheduler, &mut kern);
+
+    let throughput = scheduler.throughput(10, None);
+    assert!(throughput.gpu.is_some());
+    assert!(throughput.cpu.is_some());
+
+    // other sizes are measured on their own
+    let throughput = scheduler.throughput(11, None);
+    assert!(throughput.gpu.is_none());
+    assert!(throughput.cpu.is_none());
+    assert_eq!(scheduler.num_for_cpu(4, 11), 2);
+}
+
+#[test]
+fn test_fft_scheduler_moves_work_away_from_slow_kernel() {
+    let scheduler = FftScheduler::with_cpu_share(0.5);
+    let mut kern = Some(CpuMockFftKernel {
+        delay: Duration::from_millis(200),
+        fail: false,
+    });
+    run_scheduled_fft_and_compare(&scheduler, &mut kern);
+
+    assert!(scheduler.num_for_cpu(4, 10) >= 3);
+}
+
+#[test]
+fn test_fft_scheduler_explores_cpu() {
+    // without a measurement the CPU still gets one polynomial
+    let scheduler = FftScheduler::with_cpu_share(0.0);
+    assert_eq!(scheduler.num_for_cpu(4, 10), 1);
+    assert_eq!(scheduler.num_for_cpu(1, 10), 0);
+
+    let mut kern = Some(CpuMockFftKernel {
+        delay: Duration::from_millis(0),
+        fail: false,
+    });
+    run_scheduled_fft_and_compare(&scheduler, &mut kern);
+    assert!(scheduler.throughput(10, None).cpu.is_some());
+}
+
+#[test]
+fn test_fft_scheduler_falls_back_to_cpu() {
+    let scheduler = FftScheduler::with_cpu_share(0.0);
+
+    let mut kern = Some(CpuMockFftKernel {
+        delay: Duration::from_millis(0),
+        fail: true,
+    });
+    run_scheduled_fft_and_compare(&scheduler, &mut kern);
+
+    // the redone part is measured as CPU work
+    let throughput = scheduler.throughput(10, None);
+    assert!(throughput.gpu.is_none());
+    assert!(throughput.cpu.is_some());
+}
+
+#[test]
+fn test_mixed_radix_evaluation_domain() {
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::plonk::polynomials::{Coefficients, Polynomial};
+
+    let worker = Worker::new();
+    // 2^7 + 1 coefficients need 3^2 * 2^4 values instead of 2^8
+    let scalars = crate::kate_commitment::test::make_random_field_elements::<Fr>(&worker, 129);
+
+    let mut domain = EvaluationDomain::<Bn256, _>::from_coeffs_mixed_radix(
+        scalars.iter().map(|s| Scalar::<Bn256>(*s)).collect(),
+    )
+    .unwrap();
+    assert_eq!(domain.as_ref().len(), 144);
+
+    let expected = Polynomial::<Bn256, Coefficients>::from_coeffs_mixed_radix(scalars)
+        .unwrap()
+        .coset_fft(&worker, &mut None);
+    let original: Vec<Fr> = domain.as_ref().iter().map(|s| s.0).collect();
+
+    domain.coset_fft(&worker, &mut None).unwrap();
+    let values: Vec<Fr> = domain.as_ref().iter().map(|s| s.0).collect();
+    assert_eq!(&values[..], expected.as_ref());
+
+    domain.icoset_fft(&worker, &mut None).unwrap();
+    let back: Vec<Fr> = domain.as_ref().iter().map(|s| s.0).collect();
+    assert_eq!(back, original);
+}
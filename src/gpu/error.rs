@@ -0,0 +1,19 @@
+#[cfg(feature = "gpu")]
+use ec_gpu_gen::EcError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum GPUError {
+    #[error("GPUError: {0}")]
+    Simple(&'static str),
+    #[cfg(feature = "gpu")]
+    #[error("Kernel error: {0}")]
+    Kernel(#[from] EcError),
+    #[error("GPU taken by a high priority process!")]
+    GPUTaken,
+    #[error("No kernel is initialized!")]
+    KernelUninitialized,
+    #[error("GPU accelerator is disabled!")]
+    GPUDisabled,
+}
+
+pub type GPUResult<T> = std::result::Result<T, GPUError>;
@@ -0,0 +1,210 @@
+//! Software implementation of the FFT and multiexp kernel interfaces.
+//!
+//! With the `gpu-mock` feature the locked kernel wrappers in `locks.rs` are built on top
+//! of these types instead of the OpenCL kernels, so the retry, preemption and fallback
+//! logic can be exercised on machines without a GPU. Misbehaviour is injected through
+//! [`MockGpu`]. Its state is kept per thread, because kernels are always created and
+//! driven from the thread that calls `with`.
+
+use crate::domain::serial_fft_fr;
+use crate::locks::PriorityLock;
+use crate::multiexp::signed_digit_multiexp_inner;
+use crate::pairing::ff::{PrimeField, ScalarEngine};
+use crate::pairing::{CurveAffine, Engine};
+use crate::worker::Worker;
+
+use super::{GPUError, GPUResult, KernelOnDevices};
+use crate::config::ProverConfig;
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockGpuStats {
+    /// Number of kernels successfully created.
+    pub kernels_created: usize,
+    /// Number of kernel calls that did the work.
+    pub calls: usize,
+    /// Number of kernel calls that failed with an injected error.
+    pub failures: usize,
+    /// Number of kernel calls that reported `GPUTaken`.
+    pub taken: usize,
+    /// Lock name and devices the last kernel was created on.
+    pub last_devices: Option<(String, Range<usize>)>,
+}
+
+struct MockGpuState {
+    available: bool,
+    num_devices: usize,
+    fail_next: usize,
+    take_next: usize,
+    delay: Duration,
+    stats: MockGpuStats,
+}
+
+impl Default for MockGpuState {
+    fn default() -> Self {
+        MockGpuState {
+            available: true,
+            num_devices: 4,
+            fail_next: 0,
+            take_next: 0,
+            delay: Duration::from_millis(0),
+            stats: MockGpuStats::default(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<MockGpuState> = RefCell::new(MockGpuState::default());
+}
+
+/// Controls the behaviour of the mock kernels created on the current thread.
+pub struct MockGpu;
+
+impl MockGpu {
+    /// Restores a healthy device and clears the statistics.
+    pub fn reset() {
+        STATE.with(|s| *s.borrow_mut() = MockGpuState::default());
+    }
+
+    /// When unavailable, kernel creation fails as if no device was found.
+    pub fn set_available(available: bool) {
+        STATE.with(|s| s.borrow_mut().available = available);
+    }
+
+    /// Number of emulated devices the GPU groups are taken from, 4 by default.
+    pub fn set_num_devices(num_devices: usize) {
+        STATE.with(|s| s.borrow_mut().num_devices = num_devices);
+    }
+
+    /// Makes the next `n` kernel calls fail with an error.
+    pub fn fail_next_calls(n: usize) {
+        STATE.with(|s| s.borrow_mut().fail_next = n);
+    }
+
+    /// Makes the next `n` kernel calls report that the GPU was taken by a
+    /// high priority process.
+    pub fn take_next_calls(n: usize) {
+        STATE.with(|s| s.borrow_mut().take_next = n);
+    }
+
+    /// Delays every kernel call, emulating a slow device.
+    pub fn set_delay(delay: Duration) {
+        STATE.with(|s| s.borrow_mut().delay = delay);
+    }
+
+    pub fn stats() -> MockGpuStats {
+        STATE.with(|s| s.borrow().stats.clone())
+    }
+
+    /// Like the `ec-gpu-gen` constructors the mock picks the devices of the group of `config`.
+    fn on_create(config: &ProverConfig) -> GPUResult<()> {
+        STATE.with(|s| {
+            let mut s = s.borrow_mut();
+            if !s.available {
+                return Err(GPUError::Simple("mock GPU is unavailable"));
+            }
+            let devices = config.lock_name_and_gpu_range(s.num_devices);
+            s.stats.kernels_created += 1;
+            s.stats.last_devices = Some(devices);
+
+            Ok(())
+        })
+    }
+
+    fn on_call(priority: bool) -> GPUResult<()> {
+        let delay = STATE.with(|s| s.borrow().delay);
+        if delay > Duration::from_millis(0) {
+            std::thread::sleep(delay);
+        }
+
+        let should_break = PriorityLock::should_break(priority);
+
+        STATE.with(|s| {
+            let mut s = s.borrow_mut();
+            if s.take_next > 0 || should_break {
+                s.take_next = s.take_next.saturating_sub(1);
+                s.stats.taken += 1;
+                return Err(GPUError::GPUTaken);
+            }
+            if s.fail_next > 0 {
+                s.fail_next -= 1;
+                s.stats.failures += 1;
+                return Err(GPUError::Simple("mock GPU failure"));
+            }
+            s.stats.calls += 1;
+
+            Ok(())
+        })
+    }
+}
+
+pub struct MockFftKernel<E: Engine> {
+    priority: bool,
+    _marker: std::marker::PhantomData<E>,
+}
+
+impl<E: Engine> KernelOnDevices for MockFftKernel<E> {
+    fn create_on_devices(priority: bool, config: &ProverConfig) -> GPUResult<Self> {
+        MockGpu::on_create(config)?;
+
+        Ok(MockFftKernel {
+            priority,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<E: Engine> MockFftKernel<E> {
+
+    pub fn fft_multiple(
+        &mut self,
+        polys: &mut [&mut [E::Fr]],
+        omega: &E::Fr,
+        log_n: u32,
+    ) -> GPUResult<()> {
+        MockGpu::on_call(self.priority)?;
+
+        for poly in polys.iter_mut() {
+            serial_fft_fr::<E>(poly, omega, log_n);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct MockMultiexpKernel<E: Engine> {
+    priority: bool,
+    _marker: std::marker::PhantomData<E>,
+}
+
+impl<E: Engine> KernelOnDevices for MockMultiexpKernel<E> {
+    fn create_on_devices(priority: bool, config: &ProverConfig) -> GPUResult<Self> {
+        MockGpu::on_create(config)?;
+
+        Ok(MockMultiexpKernel {
+            priority,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<E: Engine> MockMultiexpKernel<E> {
+
+    pub fn dense_multiexp<G: CurveAffine>(
+        &mut self,
+        _pool: &Worker,
+        bases: Arc<Vec<G>>,
+        exps: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+        n: usize,
+    ) -> GPUResult<G::Projective> {
+        MockGpu::on_call(self.priority)?;
+
+        // like the OpenCL kernel only the first `n` bases are used
+        signed_digit_multiexp_inner(&bases[..n], &exps[..n], 8, 1)
+            .map_err(|_| GPUError::Simple("mock multiexp failed"))
+    }
+}
@@ -0,0 +1,21 @@
+//! Error type shared by the GPU kernel wrappers and, behind the `gpu-mock`
+//! feature, a CPU-emulated stand-in for the `ec-gpu-gen` kernels.
+
+mod error;
+pub use self::error::*;
+
+#[cfg(feature = "gpu-mock")]
+pub mod mock;
+
+#[cfg(all(feature = "gpu", not(feature = "gpu-mock")))]
+mod opencl;
+
+use crate::config::ProverConfig;
+
+/// Kernel constructors that take their devices from a [`ProverConfig`], the
+/// `BELLMAN_*` variables are only read by [`ProverConfig::from_env`].
+pub trait KernelOnDevices: Sized {
+    /// Opens the devices of the GPU group of `config`, see
+    /// [`ProverConfig::lock_name_and_gpu_range`], with the core counts of its `custom_gpu`.
+    fn create_on_devices(priority: bool, config: &ProverConfig) -> GPUResult<Self>;
+}
@@ -0,0 +1,36 @@
+//! Device selection of the `ec-gpu-gen` kernels.
+//!
+//! The kernels enumerate the OpenCL devices themselves, so the group is handed over as a
+//! function of the device count and the kernel locks and opens only the devices in the
+//! returned range. Nothing goes through the process environment.
+
+use super::{GPUResult, KernelOnDevices};
+use crate::config::ProverConfig;
+use crate::pairing::Engine;
+
+use ec_gpu_gen::fft::FftKernel;
+use ec_gpu_gen::multiexp::MultiexpKernel;
+
+impl<E: Engine> KernelOnDevices for FftKernel<E> {
+    fn create_on_devices(priority: bool, config: &ProverConfig) -> GPUResult<Self> {
+        let kernel = FftKernel::<E>::create_on_devices(
+            priority,
+            |all_gpus| config.lock_name_and_gpu_range(all_gpus),
+            config.custom_gpu.as_ref().map(|s| s.as_str()),
+        )?;
+
+        Ok(kernel)
+    }
+}
+
+impl<E: Engine> KernelOnDevices for MultiexpKernel<E> {
+    fn create_on_devices(priority: bool, config: &ProverConfig) -> GPUResult<Self> {
+        let kernel = MultiexpKernel::<E>::create_on_devices(
+            priority,
+            |all_gpus| config.lock_name_and_gpu_range(all_gpus),
+            config.custom_gpu.as_ref().map(|s| s.as_str()),
+        )?;
+
+        Ok(kernel)
+    }
+}
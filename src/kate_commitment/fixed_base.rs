@@ -0,0 +1,434 @@
+//! Fixed-base precomputation for the CRS commitments.
+//!
+//! The CRS bases never change between commitments, so for every base `B` we can store its
+//! windowed multiples `B * 2^(j * c)` for all windows `j` of `c` bits. A multiexp then
+//! runs the bucket method over all `(base, window)` pairs at once with a single set of
+//! buckets: there are no doublings between the windows and the buckets are summed only
+//! once instead of once per window, at the cost of `num_windows` stored points per base.
+
+use crate::byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use crate::pairing::ff::{PrimeField, PrimeFieldRepr, ScalarEngine};
+use crate::multiexp::get_window_bits;
+use crate::pairing::{CurveAffine, CurveProjective, EncodedPoint};
+use crate::worker::Worker;
+use blake2s_simd::State;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use std::io::{Read, Write};
+
+/// Largest supported window, every thread keeps `2^c - 1` buckets in memory.
+pub const MAX_FIXED_BASE_WINDOW_SIZE: u32 = 16;
+
+/// Number of `(base, window)` entries that are recomputed when the tables are matched
+/// against the bases, picked by an RNG seeded with the tables digest.
+const NUM_SPOT_CHECKS: usize = 16;
+
+/// The tables of a file are read without reserving more points than this up front, so a
+/// header with a huge number of bases fails on the missing data instead of the allocator.
+const MAX_PREALLOCATED_POINTS: usize = 1 << 16;
+
+pub struct FixedBaseTables<G: CurveAffine> {
+    window_size: u32,
+    num_windows: usize,
+    /// `bases[i] * 2^(j * window_size)` at the index `i * num_windows + j`
+    shifted_bases: Vec<G>,
+    /// `tables_digest` of the tables.
+    digest: [u8; 32],
+}
+
+type ScalarRepr<G> = <<<G as CurveAffine>::Engine as ScalarEngine>::Fr as PrimeField>::Repr;
+
+fn num_windows_for<G: CurveAffine>(window_size: u32) -> usize {
+    let num_bits = <<G::Engine as ScalarEngine>::Fr as PrimeField>::NUM_BITS;
+
+    ((num_bits + window_size - 1) / window_size) as usize
+}
+
+/// Blake2s of the header and the encoded points, it is stored in the serialized header.
+fn tables_digest<G: CurveAffine>(window_size: u32, num_windows: usize, shifted_bases: &[G]) -> [u8; 32] {
+    let mut h = State::new();
+    h.update(&window_size.to_be_bytes());
+    h.update(&(num_windows as u64).to_be_bytes());
+    h.update(&((shifted_bases.len() / num_windows) as u64).to_be_bytes());
+    for p in shifted_bases.iter() {
+        h.update(p.into_uncompressed().as_ref());
+    }
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(h.finalize().as_bytes());
+
+    digest
+}
+
+impl<G: CurveAffine> FixedBaseTables<G> {
+    /// Bytes needed to store the tables for `num_bases` bases with the window `window_size`
+    /// plus the `2^c - 1` buckets that every thread of `worker` allocates in a multiexp.
+    pub fn memory_required(num_bases: usize, window_size: u32, worker: &Worker) -> usize {
+        let tables = num_bases * num_windows_for::<G>(window_size) * std::mem::size_of::<G>();
+        let buckets = worker.get_num_cpus() * ((1usize << window_size) - 1) * std::mem::size_of::<G::Projective>();
+
+        tables + buckets
+    }
+
+    /// Picks the window with the smallest number of group operations for the `num_bases`
+    /// bases such that the tables fit into `memory_budget` bytes. Larger windows make
+    /// smaller tables, so `None` means that even the largest window does not fit.
+    pub fn optimal_window_size(num_bases: usize, memory_budget: usize, worker: &Worker) -> Option<u32> {
+        let num_threads = worker.get_num_cpus();
+
+        (1..=MAX_FIXED_BASE_WINDOW_SIZE)
+            .filter(|c| Self::memory_required(num_bases, *c, worker) <= memory_budget)
+            .min_by_key(|c| {
+                // bucket additions plus summation of the buckets by every thread
+                num_bases * num_windows_for::<G>(*c) + num_threads * (1usize << (c + 1))
+            })
+    }
+
+    /// Builds the tables within `memory_budget` bytes, returns `None` if they do not fit.
+    pub fn new(bases: &[G], memory_budget: usize, worker: &Worker) -> Option<Self> {
+        Self::optimal_window_size(bases.len(), memory_budget, worker)
+            .map(|window_size| Self::new_with_window_size(bases, window_size, worker))
+    }
+
+    pub fn new_with_window_size(bases: &[G], window_size: u32, worker: &Worker) -> Self {
+        assert!(window_size > 0 && window_size <= MAX_FIXED_BASE_WINDOW_SIZE);
+        assert!(!bases.is_empty());
+
+        let num_windows = num_windows_for::<G>(window_size);
+        let mut shifted_bases = vec![G::zero(); bases.len() * num_windows];
+
+        worker.scope(bases.len(), |scope, chunk| {
+            for (b, s) in bases.chunks(chunk).zip(shifted_bases.chunks_mut(chunk * num_windows)) {
+                scope.spawn(move |_| {
+                    let mut tmp = Vec::with_capacity(s.len());
+                    for base in b.iter() {
+                        let mut current = base.into_projective();
+                        for _ in 0..num_windows {
+                            tmp.push(current);
+                            for _ in 0..window_size {
+                                current.double();
+                            }
+                        }
+                    }
+
+                    G::Projective::batch_normalization(&mut tmp);
+
+                    for (s, t) in s.iter_mut().zip(tmp.into_iter()) {
+                        *s = t.into_affine();
+                    }
+                });
+            }
+        });
+
+        let digest = tables_digest(window_size, num_windows, &shifted_bases);
+
+        Self {
+            window_size,
+            num_windows,
+            shifted_bases,
+            digest,
+        }
+    }
+
+    pub fn num_bases(&self) -> usize {
+        self.shifted_bases.len() / self.num_windows
+    }
+
+    pub fn window_size(&self) -> u32 {
+        self.window_size
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.shifted_bases.len() * std::mem::size_of::<G>()
+    }
+
+    /// Checks that the tables were made for the first bases of `bases`. The first window of
+    /// every base is compared as is, the shifted ones are recomputed for a few entries. The
+    /// entries are derived from the tables digest, so the same tables always pass or fail.
+    pub fn is_for_bases(&self, bases: &[G]) -> bool {
+        if self.num_bases() > bases.len()
+            || !self
+                .shifted_bases
+                .chunks(self.num_windows)
+                .zip(bases.iter())
+                .all(|(s, b)| s[0] == *b)
+        {
+            return false;
+        }
+
+        if self.num_windows == 1 {
+            return true;
+        }
+
+        let mut seed = [0u32; 4];
+        for (s, bytes) in seed.iter_mut().zip(self.digest.chunks(4)) {
+            *s = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        // the generator does not accept an all zero seed
+        seed[0] |= 1;
+        let rng = &mut XorShiftRng::from_seed(seed);
+        (0..NUM_SPOT_CHECKS).all(|_| {
+            let i = rng.gen_range(0, self.num_bases());
+            let j = rng.gen_range(1, self.num_windows);
+
+            let mut expected = bases[i].into_projective();
+            for _ in 0..(j * self.window_size as usize) {
+                expected.double();
+            }
+
+            self.shifted_bases[i * self.num_windows + j] == expected.into_affine()
+        })
+    }
+
+    /// Multiexp of the first `scalars.len()` bases.
+    pub fn multiexp(&self, scalars: &[ScalarRepr<G>], worker: &Worker) -> G::Projective {
+        assert!(scalars.len() <= self.num_bases(), "tables are too short for the scalars");
+
+        if scalars.is_empty() {
+            return G::Projective::zero();
+        }
+
+        let window_size = self.window_size;
+        let num_windows = self.num_windows;
+        let mut partial_results = vec![G::Projective::zero(); worker.get_num_spawned_threads(scalars.len())];
+
+        worker.scope(scalars.len(), |scope, chunk| {
+            for ((s, b), r) in scalars
+                .chunks(chunk)
+                .zip(self.shifted_bases.chunks(chunk * num_windows))
+                .zip(partial_results.iter_mut())
+            {
+                scope.spawn(move |_| {
+                    let mut buckets = vec![G::Projective::zero(); (1 << window_size) - 1];
+
+                    for (scalar, shifted) in s.iter().zip(b.chunks(num_windows)) {
+                        let limbs = scalar.as_ref();
+                        for (j, base) in shifted.iter().enumerate() {
+                            let digit = get_window_bits(limbs, j * (window_size as usize), window_size);
+                            if digit != 0 {
+                                buckets[(digit - 1) as usize].add_assign_mixed(base);
+                            }
+                        }
+                    }
+
+                    // Summation by parts
+                    let mut running_sum = G::Projective::zero();
+                    let mut acc = G::Projective::zero();
+                    for bucket in buckets.into_iter().rev() {
+                        running_sum.add_assign(&bucket);
+                        acc.add_assign(&running_sum);
+                    }
+
+                    *r = acc;
+                });
+            }
+        });
+
+        let mut result = G::Projective::zero();
+        for r in partial_results.iter() {
+            result.add_assign(r);
+        }
+
+        result
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_u32::<BigEndian>(self.window_size)?;
+        writer.write_u64::<BigEndian>(self.num_windows as u64)?;
+        writer.write_u64::<BigEndian>(self.num_bases() as u64)?;
+        writer.write_all(&self.digest)?;
+        for p in self.shifted_bases.iter() {
+            writer.write_all(p.into_uncompressed().as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let window_size = reader.read_u32::<BigEndian>()?;
+        let num_windows = reader.read_u64::<BigEndian>()? as usize;
+        let num_bases = reader.read_u64::<BigEndian>()? as usize;
+        let mut digest = [0u8; 32];
+        reader.read_exact(&mut digest)?;
+
+        if window_size == 0
+            || window_size > MAX_FIXED_BASE_WINDOW_SIZE
+            || num_windows != num_windows_for::<G>(window_size)
+            || num_bases == 0
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid fixed base tables header",
+            ));
+        }
+
+        let num_points = num_bases.checked_mul(num_windows).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid fixed base tables header",
+            )
+        })?;
+
+        let mut repr = G::Uncompressed::empty();
+        let mut shifted_bases =
+            Vec::with_capacity(std::cmp::min(num_points, MAX_PREALLOCATED_POINTS));
+        for _ in 0..num_points {
+            reader.read_exact(repr.as_mut())?;
+            let p = repr
+                .into_affine()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            shifted_bases.push(p);
+        }
+
+        if tables_digest(window_size, num_windows, &shifted_bases) != digest {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "fixed base tables digest mismatch",
+            ));
+        }
+
+        Ok(Self {
+            window_size,
+            num_windows,
+            shifted_bases,
+            digest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::{Bn256, Fr, G1Affine};
+    use crate::pairing::ff::Field;
+    use crate::pairing::Engine;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    fn naive_multiexp(bases: &[G1Affine], scalars: &[<Fr as PrimeField>::Repr]) -> <Bn256 as Engine>::G1 {
+        let mut acc = <Bn256 as Engine>::G1::zero();
+        for (b, s) in bases.iter().zip(scalars.iter()) {
+            acc.add_assign(&b.mul(*s));
+        }
+
+        acc
+    }
+
+    #[test]
+    fn test_fixed_base_multiexp() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let bases: Vec<G1Affine> = (0..100).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect();
+        let mut scalars: Vec<_> = (0..100).map(|_| Fr::rand(rng).into_repr()).collect();
+        // edge cases of the digits
+        scalars[0] = Fr::zero().into_repr();
+        scalars[1] = Fr::one().into_repr();
+        scalars[2] = {
+            let mut minus_one = Fr::one();
+            minus_one.negate();
+            minus_one.into_repr()
+        };
+
+        let expected = naive_multiexp(&bases, &scalars);
+
+        for window_size in [1, 3, 7, 8, 13, MAX_FIXED_BASE_WINDOW_SIZE].iter() {
+            let tables = FixedBaseTables::new_with_window_size(&bases, *window_size, &worker);
+            assert!(tables.is_for_bases(&bases));
+            assert_eq!(tables.multiexp(&scalars, &worker), expected);
+
+            // shorter inputs use the first bases
+            assert_eq!(tables.multiexp(&scalars[..10], &worker), naive_multiexp(&bases[..10], &scalars[..10]));
+        }
+    }
+
+    #[test]
+    fn test_fixed_base_memory_budget() {
+        let worker = Worker::new();
+        let num_bases = 1 << 10;
+
+        assert!(FixedBaseTables::<G1Affine>::optimal_window_size(num_bases, 0, &worker).is_none());
+
+        let budget = 1 << 24;
+        let window_size = FixedBaseTables::<G1Affine>::optimal_window_size(num_bases, budget, &worker).unwrap();
+        assert!(FixedBaseTables::<G1Affine>::memory_required(num_bases, window_size, &worker) <= budget);
+
+        // the buckets of every thread are counted on top of the tables
+        let tables_only = num_bases * num_windows_for::<G1Affine>(window_size) * std::mem::size_of::<G1Affine>();
+        assert!(FixedBaseTables::<G1Affine>::memory_required(num_bases, window_size, &worker) > tables_only);
+
+        // more memory never gives a worse window
+        let unlimited = FixedBaseTables::<G1Affine>::optimal_window_size(num_bases, usize::max_value(), &worker).unwrap();
+        assert!(unlimited <= window_size);
+    }
+
+    #[test]
+    fn test_fixed_base_tables_serialization() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let bases: Vec<G1Affine> = (0..16).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect();
+        let tables = FixedBaseTables::new_with_window_size(&bases, 8, &worker);
+
+        let mut buffer = vec![];
+        tables.write(&mut buffer).unwrap();
+        let read = FixedBaseTables::<G1Affine>::read(&buffer[..]).unwrap();
+
+        assert_eq!(read.window_size(), 8);
+        assert!(read.shifted_bases == tables.shifted_bases);
+        assert!(read.is_for_bases(&bases));
+        assert!(!read.is_for_bases(&bases[1..]));
+
+        assert!(FixedBaseTables::<G1Affine>::read(&buffer[..100]).is_err());
+
+        // the digest follows the 20 bytes of the window size and the counts
+        let mut corrupted = buffer.clone();
+        corrupted[20] ^= 1;
+        assert!(FixedBaseTables::<G1Affine>::read(&corrupted[..]).is_err());
+
+        // a number of bases that overflows with the windows is rejected before allocating
+        let mut corrupted = buffer.clone();
+        (&mut corrupted[12..20]).write_u64::<BigEndian>(u64::max_value()).unwrap();
+        let err = FixedBaseTables::<G1Affine>::read(&corrupted[..]).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // a huge but valid number of bases fails on the missing points
+        let mut corrupted = buffer.clone();
+        (&mut corrupted[12..20]).write_u64::<BigEndian>(1 << 40).unwrap();
+        assert!(FixedBaseTables::<G1Affine>::read(&corrupted[..]).is_err());
+    }
+
+    #[test]
+    fn test_fixed_base_tables_check_is_deterministic() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let bases: Vec<G1Affine> = (0..64).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect();
+        let mut tables = FixedBaseTables::new_with_window_size(&bases, 8, &worker);
+        // a single broken entry is found by some seeds and missed by the others
+        tables.shifted_bases[17 * tables.num_windows + 5] = bases[0];
+
+        let first = tables.is_for_bases(&bases);
+        for _ in 0..8 {
+            assert_eq!(tables.is_for_bases(&bases), first);
+        }
+    }
+
+    #[test]
+    fn test_fixed_base_tables_shifted_bases_are_checked() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let bases: Vec<G1Affine> = (0..16).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect();
+        let mut tables = FixedBaseTables::new_with_window_size(&bases, 8, &worker);
+
+        // keep the first window of every base and break all the shifted ones
+        let num_windows = tables.num_windows;
+        for s in tables.shifted_bases.chunks_mut(num_windows) {
+            let base = s[0];
+            for shifted in s[1..].iter_mut() {
+                *shifted = base;
+            }
+        }
+
+        assert!(!tables.is_for_bases(&bases));
+    }
+}
@@ -0,0 +1,330 @@
+//! GLV decomposition for the BN254 G1 commitments.
+//!
+//! BN254 G1 has the endomorphism `phi(x, y) = (beta * x, y)` with `phi(P) = lambda * P`,
+//! where `beta` and `lambda` are cube roots of unity in Fq and Fr. Every scalar is split
+//! as `k = k1 + k2 * lambda` with `|k1|, |k2| < 2^128`, so a multiexp of `n` full scalars
+//! becomes a multiexp of `2n` half length scalars over the bases and their images. The
+//! bucket method then needs half the windows and half the doublings.
+
+use crate::pairing::bn256::{Fq, Fr, FrRepr, G1Affine, G1};
+use crate::pairing::ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+use crate::pairing::{CurveAffine, CurveProjective, Engine};
+use crate::multiexp::{signed_digit_multiexp_with_bits, signed_digit_window_size_for_bits};
+use crate::worker::Worker;
+use std::any::Any;
+use std::sync::Arc;
+
+/// Bit length of the halves of the decomposed scalars.
+pub const GLV_SCALAR_BITS: u32 = 128;
+
+const BETA: &str = "2203960485148121921418603742825762020974279258880205651966";
+const LAMBDA: &str = "4407920970296243842393367215006156084916469457145843978461";
+
+// Short basis of the lattice `{(a, b) : a + b * lambda = 0 mod r}` is
+// `(a1, -b1)` and `(a2, b2)` with `a1 = b2`, the rounding constants are
+// `g1 = floor(2^256 * b2 / r)` and `g2 = floor(2^256 * b1 / r)`.
+const B1: &str = "147946756881789319000765030803803410728";
+const B2: &str = "9931322734385697763";
+const G1_ROUNDING: [u64; 3] = [0xd91d232ec7e0b3d7, 0x2, 0x0];
+const G2_ROUNDING: [u64; 3] = [0x7a7bd9d4391eb18d, 0x4ccef014a773d2cf, 0x2];
+
+struct GlvConstants {
+    beta: Fq,
+    lambda: Fr,
+    b1: Fr,
+    b2: Fr,
+    half_modulus: FrRepr,
+}
+
+impl GlvConstants {
+    fn new() -> Self {
+        let mut half_modulus = Fr::char();
+        half_modulus.div2();
+
+        Self {
+            beta: Fq::from_str(BETA).unwrap(),
+            lambda: Fr::from_str(LAMBDA).unwrap(),
+            b1: Fr::from_str(B1).unwrap(),
+            b2: Fr::from_str(B2).unwrap(),
+            half_modulus,
+        }
+    }
+}
+
+lazy_static! {
+    static ref CONSTANTS: GlvConstants = GlvConstants::new();
+}
+
+/// `floor(k * g / 2^256)`
+fn mul_shift_256(k: &FrRepr, g: &[u64; 3]) -> FrRepr {
+    let k = k.as_ref();
+    let mut product = [0u64; 7];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..3 {
+            let t = (k[i] as u128) * (g[j] as u128) + (product[i + j] as u128) + carry;
+            product[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        product[i + 3] = carry as u64;
+    }
+
+    let mut result = FrRepr::default();
+    result.as_mut()[..3].copy_from_slice(&product[4..]);
+
+    result
+}
+
+/// Splits an element into the sign and the absolute value of its representative in
+/// `(-r/2, r/2]`.
+fn into_signed(value: Fr, constants: &GlvConstants) -> (bool, FrRepr) {
+    let repr = value.into_repr();
+    if repr > constants.half_modulus {
+        let mut negated = value;
+        negated.negate();
+        (true, negated.into_repr())
+    } else {
+        (false, repr)
+    }
+}
+
+/// `k = (-1)^s1 * k1 + (-1)^s2 * k2 * lambda` with `k1, k2 < 2^128`.
+fn decompose(k: &FrRepr, constants: &GlvConstants) -> ((bool, FrRepr), (bool, FrRepr)) {
+    let c1 = Fr::from_repr(mul_shift_256(k, &G1_ROUNDING)).expect("is below the modulus");
+    let c2 = Fr::from_repr(mul_shift_256(k, &G2_ROUNDING)).expect("is below the modulus");
+
+    // k2 = c1 * b1 - c2 * b2
+    let mut k2 = c1;
+    k2.mul_assign(&constants.b1);
+    let mut tmp = c2;
+    tmp.mul_assign(&constants.b2);
+    k2.sub_assign(&tmp);
+
+    // k1 = k - k2 * lambda
+    let mut k1 = Fr::from_repr(*k).expect("is a valid scalar");
+    let mut tmp = k2;
+    tmp.mul_assign(&constants.lambda);
+    k1.sub_assign(&tmp);
+
+    let k1 = into_signed(k1, constants);
+    let k2 = into_signed(k2, constants);
+    debug_assert!(k1.1.num_bits() <= GLV_SCALAR_BITS && k2.1.num_bits() <= GLV_SCALAR_BITS);
+
+    (k1, k2)
+}
+
+/// `phi(P) = lambda * P`
+pub fn endomorphism(point: &G1Affine) -> G1Affine {
+    if point.is_zero() {
+        return *point;
+    }
+
+    let (mut x, y) = point.into_xy_unchecked();
+    x.mul_assign(&CONSTANTS.beta);
+
+    G1Affine::from_xy_unchecked(x, y)
+}
+
+/// Endomorphism images of all the bases.
+pub fn endomorphism_bases(bases: &[G1Affine], worker: &Worker) -> Vec<G1Affine> {
+    let mut images = vec![G1Affine::zero(); bases.len()];
+    worker.scope(bases.len(), |scope, chunk| {
+        for (b, i) in bases.chunks(chunk).zip(images.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                for (b, i) in b.iter().zip(i.iter_mut()) {
+                    *i = endomorphism(b);
+                }
+            });
+        }
+    });
+
+    images
+}
+
+/// Multiexp of the first `scalars.len()` bases through the GLV decomposition. `images`
+/// are the endomorphism images of at least as many bases.
+pub fn glv_multiexp(bases: &[G1Affine], images: &[G1Affine], scalars: &[FrRepr], worker: &Worker) -> G1 {
+    let n = scalars.len();
+    assert!(bases.len() >= n && images.len() >= n, "not enough bases for scalars");
+
+    if n == 0 {
+        return G1::zero();
+    }
+
+    let constants: &GlvConstants = &CONSTANTS;
+    let mut split_bases = vec![G1Affine::zero(); 2 * n];
+    let mut split_scalars = vec![FrRepr::default(); 2 * n];
+    worker.scope(n, |scope, chunk| {
+        for ((((b, i), k), sb), ss) in bases[..n]
+            .chunks(chunk)
+            .zip(images[..n].chunks(chunk))
+            .zip(scalars.chunks(chunk))
+            .zip(split_bases.chunks_mut(2 * chunk))
+            .zip(split_scalars.chunks_mut(2 * chunk))
+        {
+            scope.spawn(move |_| {
+                for ((((b, i), k), sb), ss) in b
+                    .iter()
+                    .zip(i.iter())
+                    .zip(k.iter())
+                    .zip(sb.chunks_mut(2))
+                    .zip(ss.chunks_mut(2))
+                {
+                    let ((s1, k1), (s2, k2)) = decompose(k, constants);
+
+                    sb[0] = *b;
+                    if s1 {
+                        sb[0].negate();
+                    }
+                    sb[1] = *i;
+                    if s2 {
+                        sb[1].negate();
+                    }
+                    ss[0] = k1;
+                    ss[1] = k2;
+                }
+            });
+        }
+    });
+
+    let (c, num_chunks) = signed_digit_window_size_for_bits(GLV_SCALAR_BITS, 2 * n, worker.get_num_cpus());
+    worker
+        .install(|| signed_digit_multiexp_with_bits(&split_bases, &split_scalars, GLV_SCALAR_BITS, c, num_chunks))
+        .expect("signed digit multiexp does not fail")
+}
+
+/// Endomorphism images of the bases if the engine has GLV support.
+pub(crate) fn endomorphism_bases_for_engine<E: Engine>(
+    bases: &Arc<Vec<E::G1Affine>>,
+    worker: &Worker,
+) -> Option<Vec<E::G1Affine>> {
+    let bases = (bases as &dyn Any).downcast_ref::<Arc<Vec<G1Affine>>>()?;
+    let images: Box<dyn Any> = Box::new(endomorphism_bases(&bases[..], worker));
+
+    images.downcast::<Vec<E::G1Affine>>().ok().map(|images| *images)
+}
+
+/// GLV multiexp of the first `scalars.len()` bases if the engine has GLV support. The
+/// images are computed on the fly if they are not cached.
+pub(crate) fn glv_multiexp_for_engine<E: Engine>(
+    bases: &Arc<Vec<E::G1Affine>>,
+    images: Option<&Arc<Vec<E::G1Affine>>>,
+    scalars: &Vec<<<E as ScalarEngine>::Fr as PrimeField>::Repr>,
+    worker: &Worker,
+) -> Option<E::G1> {
+    let bases = (bases as &dyn Any).downcast_ref::<Arc<Vec<G1Affine>>>()?;
+    let scalars = (scalars as &dyn Any).downcast_ref::<Vec<FrRepr>>()?;
+    if bases.len() < scalars.len() {
+        return None;
+    }
+
+    let result = match images.and_then(|i| (i as &dyn Any).downcast_ref::<Arc<Vec<G1Affine>>>()) {
+        Some(images) if images.len() >= scalars.len() => glv_multiexp(&bases[..], &images[..], scalars, worker),
+        _ => {
+            let images = endomorphism_bases(&bases[..scalars.len()], worker);
+            glv_multiexp(&bases[..], &images, scalars, worker)
+        }
+    };
+    let result: Box<dyn Any> = Box::new(result);
+
+    result.downcast::<E::G1>().ok().map(|result| *result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Bn256;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    fn edge_scalars() -> Vec<FrRepr> {
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        let mut half = Fr::char();
+        half.div2();
+        let mut lambda_minus_one = CONSTANTS.lambda;
+        lambda_minus_one.sub_assign(&Fr::one());
+
+        vec![
+            Fr::zero().into_repr(),
+            Fr::one().into_repr(),
+            minus_one.into_repr(),
+            half,
+            CONSTANTS.lambda.into_repr(),
+            lambda_minus_one.into_repr(),
+        ]
+    }
+
+    #[test]
+    fn test_endomorphism() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let p = G1::rand(rng).into_affine();
+            assert_eq!(endomorphism(&p), p.mul(CONSTANTS.lambda.into_repr()).into_affine());
+        }
+
+        assert!(endomorphism(&G1Affine::zero()).is_zero());
+    }
+
+    #[test]
+    fn test_decomposition() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let constants = GlvConstants::new();
+
+        let scalars = edge_scalars()
+            .into_iter()
+            .chain((0..1000).map(|_| Fr::rand(rng).into_repr()));
+        for k in scalars {
+            let ((s1, k1), (s2, k2)) = decompose(&k, &constants);
+            assert!(k1.num_bits() <= GLV_SCALAR_BITS);
+            assert!(k2.num_bits() <= GLV_SCALAR_BITS);
+
+            let mut k1 = Fr::from_repr(k1).unwrap();
+            if s1 {
+                k1.negate();
+            }
+            let mut k2 = Fr::from_repr(k2).unwrap();
+            if s2 {
+                k2.negate();
+            }
+            k2.mul_assign(&constants.lambda);
+            k1.add_assign(&k2);
+
+            assert_eq!(k1, Fr::from_repr(k).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_glv_multiexp() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        for n in [1, 7, 100, 1 << 10].iter() {
+            let mut bases: Vec<G1Affine> = (0..*n).map(|_| G1::rand(rng).into_affine()).collect();
+            let mut scalars: Vec<FrRepr> = (0..*n).map(|_| Fr::rand(rng).into_repr()).collect();
+            for (s, e) in scalars.iter_mut().zip(edge_scalars().into_iter()) {
+                *s = e;
+            }
+            if *n > 10 {
+                bases[9] = G1Affine::zero();
+                bases[10] = bases[8];
+            }
+
+            let mut expected = G1::zero();
+            for (b, s) in bases.iter().zip(scalars.iter()) {
+                expected.add_assign(&b.mul(*s));
+            }
+
+            let images = endomorphism_bases(&bases, &worker);
+            assert_eq!(glv_multiexp(&bases, &images, &scalars, &worker), expected);
+
+            // generic entry points find the engine and compute missing images
+            let bases = Arc::new(bases);
+            let result = glv_multiexp_for_engine::<Bn256>(&bases, None, &scalars, &worker).unwrap();
+            assert_eq!(result, expected);
+            let cached = Arc::new(endomorphism_bases_for_engine::<Bn256>(&bases, &worker).unwrap());
+            let result = glv_multiexp_for_engine::<Bn256>(&bases, Some(&cached), &scalars, &worker).unwrap();
+            assert_eq!(result, expected);
+        }
+    }
+}
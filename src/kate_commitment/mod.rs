@@ -5,8 +5,15 @@ use crate::pairing::{CurveAffine, CurveProjective, Engine};
 use crate::plonk::polynomials::*;
 use crate::worker::Worker;
 use crate::SynthesisError;
+use log::debug;
 use std::sync::Arc;
 
+mod fixed_base;
+pub mod glv;
+
+pub use self::fixed_base::{FixedBaseTables, MAX_FIXED_BASE_WINDOW_SIZE};
+pub use crate::multiexp::MultiexpStats;
+
 pub trait CrsType {}
 
 pub struct CrsForMonomialForm;
@@ -19,8 +26,15 @@ impl CrsType for CrsForLagrangeFormOnCoset {}
 
 pub struct Crs<E: Engine, T: CrsType> {
     pub g1_bases: Arc<Vec<E::G1Affine>>,
+    /// Powers of tau in G2, at least two of them. Longer bases are only needed for the
+    /// commitments in G2.
     pub g2_monomial_bases: Arc<Vec<E::G2Affine>>,
 
+    /// Optional precomputation for the CPU commitments, see `FixedBaseTables`.
+    fixed_base_tables: Option<Arc<FixedBaseTables<E::G1Affine>>>,
+    /// Optional cache of the endomorphism images of the G1 bases, see `glv`.
+    glv_endomorphism_bases: Option<Arc<Vec<E::G1Affine>>>,
+
     _marker: std::marker::PhantomData<T>,
 }
 
@@ -71,7 +85,13 @@ impl<E: Engine, T: CrsType> Crs<E, T> {
         }
 
         let num_g2 = reader.read_u64::<BigEndian>()?;
-        assert!(num_g2 == 2u64);
+        // the verification keys take the first two powers
+        if num_g2 < 2 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "expected at least two G2 bases",
+            ));
+        }
 
         let mut g2_bases = Vec::with_capacity(num_g2 as usize);
 
@@ -87,11 +107,180 @@ impl<E: Engine, T: CrsType> Crs<E, T> {
             g1_bases: Arc::new(g1_bases),
             g2_monomial_bases: Arc::new(g2_bases),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         };
 
         Ok(new)
     }
+
+    /// Precomputes the fixed-base tables for all the G1 bases, using at most
+    /// `memory_budget` bytes. Returns `false` and leaves the CRS as is if the tables do
+    /// not fit, then commitments keep using the generic multiexp.
+    pub fn precompute_fixed_base_tables(&mut self, memory_budget: usize, worker: &Worker) -> bool {
+        match FixedBaseTables::new(&self.g1_bases[..], memory_budget, worker) {
+            Some(tables) => {
+                self.fixed_base_tables = Some(Arc::new(tables));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn fixed_base_tables(&self) -> Option<&Arc<FixedBaseTables<E::G1Affine>>> {
+        self.fixed_base_tables.as_ref()
+    }
+
+    /// Attaches the tables made earlier, e.g. for another CRS of the same setup.
+    pub fn set_fixed_base_tables(
+        &mut self,
+        tables: Arc<FixedBaseTables<E::G1Affine>>,
+    ) -> std::io::Result<()> {
+        if !tables.is_for_bases(&self.g1_bases[..]) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "fixed base tables do not match the CRS bases",
+            ));
+        }
+
+        self.fixed_base_tables = Some(tables);
+
+        Ok(())
+    }
+
+    pub fn clear_fixed_base_tables(&mut self) {
+        self.fixed_base_tables = None;
+    }
+
+    pub fn write_fixed_base_tables<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        match self.fixed_base_tables.as_ref() {
+            Some(tables) => tables.write(writer),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "fixed base tables are not precomputed",
+            )),
+        }
+    }
+
+    pub fn read_fixed_base_tables<R: Read>(&mut self, reader: R) -> std::io::Result<()> {
+        let tables = FixedBaseTables::read(reader)?;
+
+        self.set_fixed_base_tables(Arc::new(tables))
+    }
+
+    /// Caches the endomorphism images of the G1 bases for the GLV commitments. Returns
+    /// `false` if the engine has no GLV support, then the images are never used.
+    pub fn precompute_glv_bases(&mut self, worker: &Worker) -> bool {
+        match glv::endomorphism_bases_for_engine::<E>(&self.g1_bases, worker) {
+            Some(images) => {
+                self.glv_endomorphism_bases = Some(Arc::new(images));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn glv_bases(&self) -> Option<&Arc<Vec<E::G1Affine>>> {
+        self.glv_endomorphism_bases.as_ref()
+    }
+
+    pub fn clear_glv_bases(&mut self) {
+        self.glv_endomorphism_bases = None;
+    }
+
+    /// Multiexp over the first `exps.len()` G1 bases. On the CPU uses the fixed-base
+    /// tables if they are present and long enough, then the GLV decomposition if the
+    /// engine supports it, and the generic multiexp otherwise.
+    pub(crate) fn multiexp_g1(
+        &self,
+        exps: Vec<<E::Fr as PrimeField>::Repr>,
+        worker: &Worker,
+        kern: &mut Option<LockedMultiexpKernel<E>>,
+    ) -> Result<E::G1, SynthesisError> {
+        if kern.is_none() {
+            if let Some(tables) = self.fixed_base_tables.as_ref() {
+                if exps.len() <= tables.num_bases() {
+                    return Ok(tables.multiexp(&exps, worker));
+                }
+            }
+
+            let images = self.glv_endomorphism_bases.as_ref();
+            if let Some(result) = glv::glv_multiexp_for_engine::<E>(&self.g1_bases, images, &exps, worker) {
+                return Ok(result);
+            }
+        }
+
+        let bases = Arc::new(self.g1_bases[..exps.len()].to_vec());
+        let exps = Arc::new(exps);
+
+        multiexp::dense_multiexp::<E::G1Affine>(&worker, bases, exps, kern).wait()
+    }
+
+    /// Density aware multiexp over the first `exps.len()` G1 bases. The fixed-base tables
+    /// skip zero digits by themselves and take all the scalars, otherwise only the
+    /// non-trivial scalars go to GLV or to the kernel.
+    pub(crate) fn sparse_multiexp_g1(
+        &self,
+        exps: Vec<<E::Fr as PrimeField>::Repr>,
+        worker: &Worker,
+        kern: &mut Option<LockedMultiexpKernel<E>>,
+    ) -> Result<(E::G1, MultiexpStats), SynthesisError> {
+        if kern.is_none() {
+            if let Some(tables) = self.fixed_base_tables.as_ref() {
+                if exps.len() <= tables.num_bases() {
+                    let stats = multiexp::exponents_stats::<E::Fr>(worker, &exps);
+
+                    return Ok((tables.multiexp(&exps, worker), stats));
+                }
+            }
+        }
+
+        let split = multiexp::split_sparse_exponents(worker, &self.g1_bases[..], &exps);
+        drop(exps);
+
+        let mut acc = split.ones_sum;
+        if split.indexes.is_empty() {
+            return Ok((acc, split.stats));
+        }
+
+        let bases = Arc::new(multiexp::gather(worker, &self.g1_bases[..], &split.indexes));
+
+        let glv = if kern.is_none() {
+            let images = self
+                .glv_endomorphism_bases
+                .as_ref()
+                .map(|images| Arc::new(multiexp::gather(worker, &images[..], &split.indexes)));
+            glv::glv_multiexp_for_engine::<E>(&bases, images.as_ref(), &split.exponents, worker)
+        } else {
+            None
+        };
+
+        let dense = match glv {
+            Some(dense) => dense,
+            None => multiexp::dense_multiexp::<E::G1Affine>(&worker, bases, Arc::new(split.exponents), kern)
+                .wait()?,
+        };
+        acc.add_assign(&dense);
+
+        Ok((acc, split.stats))
+    }
+
+    /// Multiexp over the first `exps.len()` G2 bases, on the GPU if the kernel is given.
+    pub(crate) fn multiexp_g2(
+        &self,
+        exps: Vec<<E::Fr as PrimeField>::Repr>,
+        worker: &Worker,
+        kern: &mut Option<LockedMultiexpKernel<E>>,
+    ) -> Result<E::G2, SynthesisError> {
+        assert!(exps.len() <= self.g2_monomial_bases.len(), "not enough G2 bases");
+
+        let bases = Arc::new(self.g2_monomial_bases[..exps.len()].to_vec());
+        let exps = Arc::new(exps);
+
+        multiexp::dense_multiexp::<E::G2Affine>(&worker, bases, exps, kern).wait()
+    }
 }
 
 impl<E: Engine> Crs<E, CrsForMonomialForm> {
@@ -105,27 +294,32 @@ impl<E: Engine> Crs<E, CrsForMonomialForm> {
             g1_bases: Arc::new(g1),
             g2_monomial_bases: Arc::new(g2),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         }
     }
 
     pub fn crs_42(size: usize, worker: &Worker) -> Self {
+        Self::crs_42_with_g2(size, 2, worker)
+    }
+
+    /// Same as `crs_42` with `g2_size` powers of tau in G2 for the commitments in G2.
+    pub fn crs_42_with_g2(size: usize, g2_size: usize, worker: &Worker) -> Self {
         // kind of how ceremony would work
         assert!(size.is_power_of_two());
-
-        let mut g2 = vec![E::G2Affine::one(); 2];
+        assert!(g2_size >= 2);
 
         use crate::domain::EvaluationDomain;
         use crate::group::Scalar;
         use crate::pairing::Wnaf;
 
-        let mut coeffs = vec![Scalar::<E>(E::Fr::one()); size];
+        let mut coeffs = vec![Scalar::<E>(E::Fr::one()); std::cmp::max(size, g2_size)];
 
         {
             let gen = E::Fr::from_str("42").unwrap();
 
-            g2[1] = g2[1].mul(gen.into_repr()).into_affine();
-
             worker.scope(coeffs.len(), |scope, chunk| {
                 for (i, p) in coeffs.chunks_mut(chunk).enumerate() {
                     scope.spawn(move |_| {
@@ -165,10 +359,24 @@ impl<E: Engine> Crs<E, CrsForMonomialForm> {
 
         let g1: Vec<_> = g1.into_iter().map(|el| el.into_affine()).collect();
 
+        let mut g2 = vec![E::G2Affine::zero(); g2_size];
+        worker.scope(g2.len(), |scope, chunk| {
+            for (g2, p) in g2.chunks_mut(chunk).zip(coeffs.chunks(chunk)) {
+                scope.spawn(move |_| {
+                    for (g2, p) in g2.iter_mut().zip(p.iter()) {
+                        *g2 = E::G2Affine::one().mul(p.0.into_repr()).into_affine();
+                    }
+                });
+            }
+        });
+
         Self {
             g1_bases: Arc::new(g1),
             g2_monomial_bases: Arc::new(g2),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         }
     }
@@ -186,6 +394,9 @@ impl<E: Engine> Crs<E, CrsForLagrangeForm> {
             g1_bases: Arc::new(g1),
             g2_monomial_bases: Arc::new(g2),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         }
     }
@@ -230,6 +441,9 @@ impl<E: Engine> Crs<E, CrsForLagrangeForm> {
             g1_bases: Arc::new(g1),
             g2_monomial_bases: Arc::new(g2),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         }
     }
@@ -247,6 +461,9 @@ impl<E: Engine> Crs<E, CrsForLagrangeFormOnCoset> {
             g1_bases: Arc::new(g1),
             g2_monomial_bases: Arc::new(g2),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         }
     }
@@ -292,6 +509,9 @@ impl<E: Engine> Crs<E, CrsForLagrangeFormOnCoset> {
             g1_bases: Arc::new(g1),
             g2_monomial_bases: Arc::new(g2),
 
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
             _marker: std::marker::PhantomData,
         }
     }
@@ -335,16 +555,12 @@ pub fn commit_using_monomials<E: Engine>(
     let subtime = Instant::now();
 
     let scalars_repr = elements_into_representations::<E>(&worker, &poly.as_ref())?;
-    let length = scalars_repr.len();
-    let exps = Arc::new(scalars_repr);
 
     println!("Scalars conversion taken {:?}", subtime.elapsed());
 
     let subtime = Instant::now();
-    let bases = Arc::new(crs.g1_bases[..length].to_vec());
 
-    let res = multiexp::dense_multiexp::<E::G1Affine>(&worker, bases, exps, kern);
-    let res = res.wait()?;
+    let res = crs.multiexp_g1(scalars_repr, worker, kern)?;
 
     println!("Multiexp taken {:?}", subtime.elapsed());
 
@@ -353,6 +569,24 @@ pub fn commit_using_monomials<E: Engine>(
     Ok(res.into_affine())
 }
 
+/// Commitment in G2 to the polynomial in the monomial form, needs at least `poly.size()`
+/// powers of tau in G2.
+pub fn commit_g2_using_monomials<E: Engine>(
+    poly: &Polynomial<E, Coefficients>,
+    crs: &Crs<E, CrsForMonomialForm>,
+    worker: &Worker,
+    kern: &mut Option<LockedMultiexpKernel<E>>,
+) -> Result<E::G2Affine, SynthesisError> {
+    debug!("Committing coefficients in G2");
+    assert!(poly.size() <= crs.g2_monomial_bases.len());
+
+    let scalars_repr = elements_into_representations::<E>(&worker, &poly.as_ref())?;
+
+    let res = crs.multiexp_g2(scalars_repr, worker, kern)?;
+
+    Ok(res.into_affine())
+}
+
 pub fn commit_using_values<E: Engine>(
     poly: &Polynomial<E, Values>,
     crs: &Crs<E, CrsForLagrangeForm>,
@@ -369,15 +603,12 @@ pub fn commit_using_values<E: Engine>(
     let subtime = Instant::now();
 
     let scalars_repr = elements_into_representations::<E>(&worker, &poly.as_ref())?;
-    let exps = Arc::new(scalars_repr);
 
     println!("Scalars conversion taken {:?}", subtime.elapsed());
 
     let subtime = Instant::now();
 
-    let bases = Arc::new(crs.g1_bases[..].to_vec());
-    let res = multiexp::dense_multiexp::<E::G1Affine>(&worker, bases, exps, kern);
-    let res = res.wait()?;
+    let res = crs.multiexp_g1(scalars_repr, worker, kern)?;
 
     println!("Multiexp taken {:?}", subtime.elapsed());
 
@@ -395,15 +626,30 @@ pub fn commit_using_raw_values<E: Engine>(
     assert_eq!(values.len().next_power_of_two(), crs.g1_bases.len());
     println!("Committing raw values over domain");
     let scalars_repr = elements_into_representations::<E>(&worker, &values)?;
-    let exps = Arc::new(scalars_repr);
-    let bases = Arc::new(crs.g1_bases[..values.len()].to_vec());
 
-    let res = multiexp::dense_multiexp::<E::G1Affine>(&worker, bases, exps, kern);
-    let res = res.wait()?;
+    let res = crs.multiexp_g1(scalars_repr, worker, kern)?;
 
     Ok(res.into_affine())
 }
 
+/// Same as `commit_using_raw_values`, but the bases of zero and one values skip the bucket
+/// method. Meant for mostly boolean wires, goes through the fixed-base tables or GLV on the
+/// CPU like the other commitments.
+pub fn commit_using_sparse_raw_values<E: Engine>(
+    values: &[E::Fr],
+    crs: &Crs<E, CrsForLagrangeForm>,
+    worker: &Worker,
+    kern: &mut Option<LockedMultiexpKernel<E>>,
+) -> Result<(E::G1Affine, MultiexpStats), SynthesisError> {
+    assert_eq!(values.len().next_power_of_two(), crs.g1_bases.len());
+    debug!("Committing sparse raw values over domain");
+    let scalars_repr = elements_into_representations::<E>(&worker, &values)?;
+
+    let (res, stats) = crs.sparse_multiexp_g1(scalars_repr, worker, kern)?;
+
+    Ok((res.into_affine(), stats))
+}
+
 use crate::source::QueryDensity;
 
 pub fn commit_using_values_on_coset<E: Engine>(
@@ -416,11 +662,7 @@ pub fn commit_using_values_on_coset<E: Engine>(
     assert_eq!(poly.size(), crs.g1_bases.len());
     let scalars_repr = elements_into_representations::<E>(&worker, &poly.as_ref())?;
 
-    let exps = Arc::new(scalars_repr);
-    let bases = Arc::new(crs.g1_bases[..].to_vec());
-
-    let res = multiexp::dense_multiexp::<E::G1Affine>(&worker, bases, exps, kern);
-    let res = res.wait()?;
+    let res = crs.multiexp_g1(scalars_repr, worker, kern)?;
 
     Ok(res.into_affine())
 }
@@ -885,6 +1127,9 @@ pub fn make_crs_from_ignition_transcript<S: AsRef<std::ffi::OsStr> + ?Sized>(
         g1_bases: Arc::new(g1_bases),
         g2_monomial_bases: Arc::new(g2_bases),
 
+        fixed_base_tables: None,
+        glv_endomorphism_bases: None,
+
         _marker: std::marker::PhantomData,
     };
 
@@ -989,6 +1234,182 @@ pub(crate) mod test {
         assert!(commitment == commitment_values_on_coset);
     }
 
+    #[test]
+    fn test_commitments_with_fixed_base_tables() {
+        use rand::{Rand, SeedableRng, XorShiftRng};
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let size = 64;
+        let mut monomial = Crs::<Bn256, CrsForMonomialForm>::crs_42(size, &worker);
+        let mut lagrange = Crs::<Bn256, CrsForLagrangeForm>::crs_42(size, &worker);
+
+        let poly = Polynomial::<Bn256, Coefficients>::from_coeffs((0..size).map(|_| Fr::rand(rng)).collect()).unwrap();
+        let short_poly = Polynomial::<Bn256, Coefficients>::from_coeffs(poly.as_ref()[..16].to_vec()).unwrap();
+        let values = poly.clone().fft(&worker, &mut None);
+
+        let expected = commit_using_monomials(&poly, &monomial, &worker, &mut None).unwrap();
+        let expected_short = commit_using_monomials(&short_poly, &monomial, &worker, &mut None).unwrap();
+
+        assert!(!monomial.precompute_fixed_base_tables(0, &worker));
+        assert!(monomial.fixed_base_tables().is_none());
+
+        assert!(monomial.precompute_fixed_base_tables(1 << 24, &worker));
+        assert!(lagrange.precompute_fixed_base_tables(1 << 24, &worker));
+
+        assert_eq!(commit_using_monomials(&poly, &monomial, &worker, &mut None).unwrap(), expected);
+        assert_eq!(commit_using_monomials(&short_poly, &monomial, &worker, &mut None).unwrap(), expected_short);
+        assert_eq!(commit_using_values(&values, &lagrange, &worker, &mut None).unwrap(), expected);
+
+        // persisted tables are accepted only by the CRS they were made for
+        let mut buffer = vec![];
+        monomial.write_fixed_base_tables(&mut buffer).unwrap();
+        assert!(lagrange.read_fixed_base_tables(&buffer[..]).is_err());
+
+        let mut restored = Crs::<Bn256, CrsForMonomialForm>::crs_42(size, &worker);
+        restored.read_fixed_base_tables(&buffer[..]).unwrap();
+        assert_eq!(commit_using_monomials(&poly, &restored, &worker, &mut None).unwrap(), expected);
+
+        // tables for a prefix of the bases fall back to the generic multiexp for longer inputs
+        let mut prefix = Crs::<Bn256, CrsForMonomialForm>::crs_42(size, &worker);
+        let tables = FixedBaseTables::new_with_window_size(&prefix.g1_bases[..16], 8, &worker);
+        prefix.set_fixed_base_tables(Arc::new(tables)).unwrap();
+        assert_eq!(commit_using_monomials(&short_poly, &prefix, &worker, &mut None).unwrap(), expected_short);
+        assert_eq!(commit_using_monomials(&poly, &prefix, &worker, &mut None).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_commitments_in_g2() {
+        use rand::{Rand, SeedableRng, XorShiftRng};
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let size = 16;
+        let crs = Crs::<Bn256, CrsForMonomialForm>::crs_42_with_g2(size, size, &worker);
+        assert_eq!(crs.g2_monomial_bases.len(), size);
+        // the first powers are the same as for the usual setup
+        let usual = Crs::<Bn256, CrsForMonomialForm>::crs_42(size, &worker);
+        assert_eq!(&crs.g2_monomial_bases[..2], &usual.g2_monomial_bases[..]);
+        assert!(crs.g1_bases == usual.g1_bases);
+
+        // long G2 bases survive the serialization
+        let mut buffer = vec![];
+        crs.write(&mut buffer).unwrap();
+        assert!(Crs::<Bn256, CrsForMonomialForm>::read(&buffer[..]).unwrap() == crs);
+
+        let poly = Polynomial::<Bn256, Coefficients>::from_coeffs((0..size).map(|_| Fr::rand(rng)).collect()).unwrap();
+        let in_g1 = commit_using_monomials(&poly, &crs, &worker, &mut None).unwrap();
+        let in_g2 = commit_g2_using_monomials(&poly, &crs, &worker, &mut None).unwrap();
+
+        // both commit to p(tau): e([p(tau)]_1, g2) = e(g1, [p(tau)]_2)
+        let lhs = Bn256::pairing(in_g1, crs.g2_monomial_bases[0]);
+        let rhs = Bn256::pairing(crs.g1_bases[0], in_g2);
+        assert_eq!(lhs, rhs);
+
+        let at_42 = poly.evaluate_at(&worker, Fr::from_str("42").unwrap());
+        assert_eq!(in_g2, <Bn256 as Engine>::G2Affine::one().mul(at_42.into_repr()).into_affine());
+
+        // a CRS with a single G2 base is rejected
+        let mut short = vec![];
+        let truncated = Crs::<Bn256, CrsForMonomialForm> {
+            g1_bases: crs.g1_bases.clone(),
+            g2_monomial_bases: Arc::new(crs.g2_monomial_bases[..1].to_vec()),
+
+            fixed_base_tables: None,
+            glv_endomorphism_bases: None,
+
+            _marker: std::marker::PhantomData,
+        };
+        truncated.write(&mut short).unwrap();
+        assert!(Crs::<Bn256, CrsForMonomialForm>::read(&short[..]).is_err());
+    }
+
+    #[test]
+    fn test_glv_commitments() {
+        use rand::{Rand, SeedableRng, XorShiftRng};
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let size = 64;
+        let mut monomial = Crs::<Bn256, CrsForMonomialForm>::crs_42(size, &worker);
+        let lagrange = Crs::<Bn256, CrsForLagrangeForm>::crs_42(size, &worker);
+
+        let poly = Polynomial::<Bn256, Coefficients>::from_coeffs((0..size).map(|_| Fr::rand(rng)).collect()).unwrap();
+        let values = poly.clone().fft(&worker, &mut None);
+
+        let exps = poly.as_ref().iter().map(|el| el.into_repr()).collect::<Vec<_>>();
+        let expected = multiexp::dense_multiexp(&worker, monomial.g1_bases.clone(), Arc::new(exps), &mut None)
+            .wait()
+            .unwrap()
+            .into_affine();
+
+        // images are computed on the fly without the cache
+        assert!(monomial.glv_bases().is_none());
+        assert_eq!(commit_using_monomials(&poly, &monomial, &worker, &mut None).unwrap(), expected);
+        assert_eq!(commit_using_values(&values, &lagrange, &worker, &mut None).unwrap(), expected);
+
+        assert!(monomial.precompute_glv_bases(&worker));
+        assert_eq!(monomial.glv_bases().unwrap().len(), size);
+        assert_eq!(commit_using_monomials(&poly, &monomial, &worker, &mut None).unwrap(), expected);
+
+        // engines without the endomorphism keep the generic multiexp
+        use crate::pairing::bls12_381::Bls12;
+        let mut other = Crs::<Bls12, CrsForMonomialForm>::dummy_crs(4);
+        assert!(!other.precompute_glv_bases(&worker));
+        assert!(other.glv_bases().is_none());
+    }
+
+    #[test]
+    fn test_sparse_commitment() {
+        use rand::{Rand, SeedableRng, XorShiftRng};
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let size = 64;
+        let mut crs = Crs::<Bn256, CrsForLagrangeForm>::crs_42(size, &worker);
+
+        let mut values = vec![Fr::zero(); size - 1];
+        for i in 0..20 {
+            values[i] = Fr::one();
+        }
+        for i in 40..50 {
+            values[i] = Fr::rand(rng);
+        }
+
+        let expected = commit_using_raw_values(&values, &crs, &worker, &mut None).unwrap();
+        let (commitment, stats) = commit_using_sparse_raw_values(&values, &crs, &worker, &mut None).unwrap();
+        assert_eq!(commitment, expected);
+        assert_eq!(
+            stats,
+            MultiexpStats {
+                num_bases: size - 1,
+                num_zeros: size - 1 - 30,
+                num_ones: 20,
+            }
+        );
+
+        // all zero wire commits to the point at infinity
+        let zeros = vec![Fr::zero(); size];
+        let (commitment, stats) = commit_using_sparse_raw_values(&zeros, &crs, &worker, &mut None).unwrap();
+        assert!(commitment.is_zero());
+        assert_eq!(stats.num_skipped(), size);
+
+        // the dispatcher takes the cached GLV images and the fixed-base tables
+        assert!(crs.precompute_glv_bases(&worker));
+        let (commitment, glv_stats) = commit_using_sparse_raw_values(&values, &crs, &worker, &mut None).unwrap();
+        assert_eq!(commitment, expected);
+
+        assert!(crs.precompute_fixed_base_tables(usize::max_value(), &worker));
+        let (commitment, tables_stats) = commit_using_sparse_raw_values(&values, &crs, &worker, &mut None).unwrap();
+        assert_eq!(commitment, expected);
+        assert_eq!(glv_stats, tables_stats);
+        assert_eq!(tables_stats.num_ones, 20);
+    }
+
     #[test]
     fn test_transformations_of_crs_large() {
         let worker = Worker::new();
@@ -1101,6 +1522,9 @@ pub(crate) mod test {
                 g1_bases: Arc::new(large_setup.g1_bases[..size].to_vec()),
                 g2_monomial_bases: large_setup.g2_monomial_bases.clone(),
 
+                fixed_base_tables: None,
+                glv_endomorphism_bases: None,
+
                 _marker: std::marker::PhantomData,
             };
 
@@ -18,37 +18,63 @@ extern crate rand;
 use crate::pairing::ff;
 pub use ff::*;
 pub use pairing::*;
-pub mod domain;
 pub mod plonk;
 
 #[macro_use]
 extern crate lazy_static;
 
-pub mod kate_commitment;
 pub mod constants;
-mod group;
-mod multiexp;
-mod prefetch;
-mod source;
-
-#[cfg(test)]
-mod tests;
-
 mod cs;
-mod multicore;
-pub mod worker {
-    pub use super::multicore::*;
-}
 
 pub use self::cs::*;
 
+// Everything below needs threads (and possibly a GPU), so none of it is part of
+// the `verifier-only` build.
+cfg_if! {
+    if #[cfg(not(feature = "verifier-only"))] {
+        pub mod domain;
+        pub mod kate_commitment;
+        mod group;
+        mod multiexp;
+        mod prefetch;
+        mod source;
+
+        #[cfg(test)]
+        mod tests;
+
+        mod multicore;
+        pub mod worker {
+            pub use super::multicore::*;
+        }
+
+        #[cfg(feature = "bench")]
+        #[doc(hidden)]
+        pub mod bench;
+    }
+}
+
 use std::env;
 use std::str::FromStr;
 
 
 #[macro_use]
 mod log;
-mod locks;
+#[cfg(not(feature = "verifier-only"))]
+pub mod config;
+#[cfg(not(feature = "verifier-only"))]
+pub mod gpu;
+
+cfg_if! {
+    if #[cfg(feature = "verifier-only")] {
+    } else if #[cfg(any(feature = "gpu", feature = "gpu-mock"))] {
+        mod locks;
+    } else {
+        mod nogpu;
+        mod locks {
+            pub use super::nogpu::*;
+        }
+    }
+}
 
 
 
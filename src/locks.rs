@@ -11,36 +11,6 @@ fn tmp_path(filename: &str) -> PathBuf {
     p
 }
 
-pub fn get_lock_name_and_gpu_range(all_gpus: usize) -> (String, Range<usize>) {
-    let mut num_gpus_per_group = all_gpus;
-    let mut group_index = 0usize;
-
-    let maybe_num = env::var("BELLMAN_NUM_GPUS_PER_GROUP");
-    let maybe_index = env::var("BELLMAN_GPU_GROUP_INDEX");
-
-    if maybe_num.is_ok() && maybe_index.is_ok() {
-        let maybe_num = maybe_num.unwrap().parse();
-        let maybe_index = maybe_index.unwrap().parse();
-        if maybe_num.is_ok() && maybe_index.is_ok() {
-            num_gpus_per_group = maybe_num.unwrap();
-            group_index = maybe_index.unwrap();
-        }
-    }
-
-    assert!(
-        (group_index + 1) * num_gpus_per_group <= all_gpus,
-        "BELLMAN_GPU_GROUP_INDEX and BELLMAN_NUM_GPUS_PER_GROUP error"
-    );
-
-    (
-        group_index.to_string(),
-        Range {
-            start: group_index * num_gpus_per_group,
-            end: (group_index + 1) * num_gpus_per_group,
-        },
-    )
-}
-
 /// `GPULock` prevents two kernel objects to be instantiated simultaneously.
 #[derive(Debug)]
 pub struct GPULock(File);
@@ -107,15 +77,20 @@ impl Drop for PriorityLock {
 }
 
 
-use ec_gpu_gen::{EcError, EcResult};
-use ec_gpu_gen::multiexp::MultiexpKernel;
-use ec_gpu_gen::fft::FftKernel;
+use crate::gpu::{GPUError, GPUResult};
+
+#[cfg(not(feature = "gpu-mock"))]
+pub use ec_gpu_gen::fft::FftKernel as MultiFFTKernel;
+#[cfg(not(feature = "gpu-mock"))]
+pub use ec_gpu_gen::multiexp::MultiexpKernel;
 
+#[cfg(feature = "gpu-mock")]
+pub use crate::gpu::mock::{MockFftKernel as MultiFFTKernel, MockMultiexpKernel as MultiexpKernel};
+
+use crate::config::ProverConfig;
 use crate::domain::create_fft_kernel;
 use crate::multiexp::create_multiexp_kernel;
 use crate::pairing::Engine;
-use std::env;
-use std::ops::Range;
 
 macro_rules! locked_kernel {
     ($class:ident, $kern:ident, $func:ident, $name:expr) => {
@@ -125,6 +100,7 @@ macro_rules! locked_kernel {
         {
             log_d: usize,
             priority: bool,
+            config: ProverConfig,
             kernel: Option<$kern<E>>,
         }
 
@@ -132,19 +108,33 @@ macro_rules! locked_kernel {
         where
             E: Engine,
         {
+            /// Uses the configuration given by the `BELLMAN_*` environment variables.
             pub fn new(log_d: usize, priority: bool) -> $class<E> {
+                Self::new_with_config(log_d, priority, ProverConfig::from_env())
+            }
+
+            pub fn new_with_config(
+                log_d: usize,
+                priority: bool,
+                config: ProverConfig,
+            ) -> $class<E> {
                 $class::<E> {
                     log_d,
                     priority,
+                    config,
                     kernel: None,
                 }
             }
 
+            pub fn config(&self) -> &ProverConfig {
+                &self.config
+            }
+
             fn init(&mut self) {
                 if self.kernel.is_none() {
                     PriorityLock::wait(self.priority);
                     info!("GPU is available for {}!", $name);
-                    self.kernel = $func::<E>(self.log_d, self.priority);
+                    self.kernel = $func::<E>(self.log_d, self.priority, &self.config);
                 }
             }
 
@@ -161,10 +151,8 @@ macro_rules! locked_kernel {
             where
                 F: FnMut(&mut $kern<E>) -> GPUResult<R>,
             {
-                if let Ok(flag) = std::env::var("BELLMAN_USE_CPU") {
-                    if flag == "1" {
-                        return Err(GPUError::GPUDisabled);
-                    }
+                if self.config.use_cpu {
+                    return Err(GPUError::GPUDisabled);
                 }
 
                 self.init();
@@ -193,7 +181,7 @@ macro_rules! locked_kernel {
 
 locked_kernel!(
     LockedMultiFFTKernel,
-    FftKernel,
+    MultiFFTKernel,
     create_fft_kernel,
     "FFT"
 );
@@ -203,3 +191,527 @@ locked_kernel!(
     create_multiexp_kernel,
     "Multiexp"
 );
+
+#[cfg(all(test, feature = "gpu-mock"))]
+mod tests {
+    use super::*;
+    use crate::domain::{serial_fft_fr, FftScheduler};
+    use crate::gpu::mock::MockGpu;
+    use crate::multiexp::dense_multiexp;
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::PrimeField;
+    use crate::pairing::{CurveAffine, CurveProjective};
+    use crate::plonk::domains::Domain;
+    use crate::worker::Worker;
+
+    use std::env;
+    use std::io::{self, BufRead, BufReader, Read};
+    use std::process::{Child, ChildStdout, Command, Stdio};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::thread;
+    use std::time::Duration;
+
+    // Lock files are shared by everything in the process, so these tests must not overlap.
+    lazy_static! {
+        static ref SERIAL: Mutex<()> = Mutex::new(());
+    }
+
+    fn serial() -> MutexGuard<'static, ()> {
+        SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    const LOG_N: usize = 8;
+
+    fn random_polys(num: usize) -> Vec<Vec<Fr>> {
+        let worker = Worker::new();
+        (0..num)
+            .map(|_| {
+                crate::kate_commitment::test::make_random_field_elements::<Fr>(&worker, 1 << LOG_N)
+            })
+            .collect()
+    }
+
+    fn fft_with(kern: &mut LockedMultiFFTKernel<Bn256>, polys: &mut [Vec<Fr>]) -> GPUResult<()> {
+        let omega = Domain::<Fr>::new_for_size(1 << LOG_N).unwrap().generator;
+        let mut refs: Vec<&mut [Fr]> = polys.iter_mut().map(|p| &mut p[..]).collect();
+
+        kern.with(|k: &mut MultiFFTKernel<Bn256>| {
+            k.fft_multiple(&mut refs, &omega, LOG_N as u32)
+        })
+    }
+
+    fn serial_ffts(polys: &[Vec<Fr>]) -> Vec<Vec<Fr>> {
+        let omega = Domain::<Fr>::new_for_size(1 << LOG_N).unwrap().generator;
+        let mut result = polys.to_vec();
+        for p in result.iter_mut() {
+            serial_fft_fr::<Bn256>(p, &omega, LOG_N as u32);
+        }
+
+        result
+    }
+
+    #[test]
+    fn test_locked_fft_kernel_with() {
+        let _guard = serial();
+        MockGpu::reset();
+
+        let mut polys = random_polys(2);
+        let expected = serial_ffts(&polys);
+
+        let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, false);
+        fft_with(&mut kern, &mut polys).unwrap();
+
+        assert_eq!(polys, expected);
+        let stats = MockGpu::stats();
+        assert_eq!(stats.kernels_created, 1);
+        assert_eq!(stats.calls, 1);
+    }
+
+    #[test]
+    fn test_locked_fft_kernel_retries_when_taken() {
+        let _guard = serial();
+        MockGpu::reset();
+        MockGpu::take_next_calls(2);
+
+        let mut polys = random_polys(1);
+        let expected = serial_ffts(&polys);
+
+        let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, false);
+        fft_with(&mut kern, &mut polys).unwrap();
+
+        assert_eq!(polys, expected);
+        let stats = MockGpu::stats();
+        assert_eq!(stats.taken, 2);
+        assert_eq!(stats.kernels_created, 3);
+        assert_eq!(stats.calls, 1);
+    }
+
+    #[test]
+    fn test_locked_fft_kernel_reports_failures() {
+        let _guard = serial();
+        MockGpu::reset();
+        MockGpu::fail_next_calls(1);
+
+        let mut polys = random_polys(1);
+        let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, false);
+        assert!(fft_with(&mut kern, &mut polys).is_err());
+        assert_eq!(MockGpu::stats().failures, 1);
+
+        MockGpu::reset();
+        MockGpu::set_available(false);
+        let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, false);
+        match fft_with(&mut kern, &mut polys) {
+            Err(GPUError::KernelUninitialized) => {}
+            other => panic!("expected uninitialized kernel, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_fft_falls_back_to_cpu() {
+        let _guard = serial();
+        MockGpu::reset();
+        MockGpu::fail_next_calls(1);
+
+        let worker = Worker::new();
+        let omega = Domain::<Fr>::new_for_size(1 << LOG_N).unwrap().generator;
+        let mut polys = random_polys(4);
+        let expected = serial_ffts(&polys);
+
+        // a scheduler of its own, the global one is shared with every other test
+        let scheduler = FftScheduler::with_cpu_share(0.0);
+        let mut kern = Some(LockedMultiFFTKernel::<Bn256>::new(LOG_N, false));
+        {
+            let mut refs: Vec<&mut [Fr]> = polys.iter_mut().map(|p| &mut p[..]).collect();
+            scheduler
+                .fft_multiple::<Bn256, _>(&mut kern, &mut refs, &worker, &omega, LOG_N as u32)
+                .unwrap();
+        }
+
+        assert_eq!(polys, expected);
+        assert_eq!(MockGpu::stats().failures, 1);
+    }
+
+    #[test]
+    fn test_kernels_use_their_own_config() {
+        let _guard = serial();
+        MockGpu::reset();
+
+        let mut polys = random_polys(1);
+
+        let config = ProverConfig::cpu_and_gpu().with_gpu_group(1, 0);
+        let mut first = LockedMultiFFTKernel::<Bn256>::new_with_config(LOG_N, false, config);
+        fft_with(&mut first, &mut polys).unwrap();
+        assert_eq!(
+            MockGpu::stats().last_devices,
+            Some(("0".to_string(), 0..1))
+        );
+
+        let config = ProverConfig::cpu_and_gpu().with_gpu_group(2, 1);
+        let mut second = LockedMultiFFTKernel::<Bn256>::new_with_config(LOG_N, false, config);
+        fft_with(&mut second, &mut polys).unwrap();
+        assert_eq!(
+            MockGpu::stats().last_devices,
+            Some(("1".to_string(), 2..4))
+        );
+
+        let mut disabled =
+            LockedMultiFFTKernel::<Bn256>::new_with_config(LOG_N, false, ProverConfig::cpu_only());
+        assert!(matches!(
+            fft_with(&mut disabled, &mut polys),
+            Err(GPUError::GPUDisabled)
+        ));
+        assert_eq!(MockGpu::stats().kernels_created, 2);
+    }
+
+    #[test]
+    fn test_multiexp_split_between_cpu_and_gpu() {
+        use rand::Rand;
+
+        let _guard = serial();
+        MockGpu::reset();
+
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+        let bases = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exps = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| Fr::rand(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+
+        let cpu = dense_multiexp(&worker, bases.clone(), exps.clone(), &mut None)
+            .wait()
+            .unwrap();
+
+        let config = ProverConfig::cpu_and_gpu().with_cpu_utilization(0.25);
+        let mut kern = Some(LockedMultiexpKernel::<Bn256>::new_with_config(LOG_N, false, config));
+        let split = dense_multiexp(&worker, bases.clone(), exps.clone(), &mut kern)
+            .wait()
+            .unwrap();
+        assert_eq!(cpu, split);
+        assert_eq!(MockGpu::stats().calls, 1);
+
+        // the GPU share is recomputed on the CPU if the kernel fails
+        MockGpu::fail_next_calls(1);
+        let fallback = dense_multiexp(&worker, bases, exps, &mut kern)
+            .wait()
+            .unwrap();
+        assert_eq!(cpu, fallback);
+        assert_eq!(MockGpu::stats().failures, 1);
+    }
+
+    #[test]
+    fn test_locked_multiexp_kernel_with() {
+        use rand::Rand;
+
+        let _guard = serial();
+        MockGpu::reset();
+
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+        let bases = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exps = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| Fr::rand(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+
+        let cpu = dense_multiexp(&worker, bases.clone(), exps.clone(), &mut None)
+            .wait()
+            .unwrap();
+
+        let mut kern = LockedMultiexpKernel::<Bn256>::new(LOG_N, false);
+        let gpu = kern
+            .with(|k: &mut MultiexpKernel<Bn256>| {
+                k.dense_multiexp(&worker, bases.clone(), exps.clone(), 1 << LOG_N)
+            })
+            .unwrap();
+        assert_eq!(cpu, gpu);
+
+        MockGpu::fail_next_calls(1);
+        let mut kern = Some(kern);
+        let fallback = dense_multiexp(&worker, bases, exps, &mut kern)
+            .wait()
+            .unwrap();
+        assert_eq!(cpu, fallback);
+
+        let stats = MockGpu::stats();
+        assert_eq!(stats.calls, 1);
+        assert_eq!(stats.failures, 1);
+    }
+
+    #[test]
+    fn test_g2_multiexp_on_gpu() {
+        use rand::Rand;
+
+        let _guard = serial();
+        MockGpu::reset();
+
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+        let bases = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| <Bn256 as Engine>::G2::rand(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exps = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| Fr::rand(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+
+        let cpu = dense_multiexp(&worker, bases.clone(), exps.clone(), &mut None)
+            .wait()
+            .unwrap();
+
+        let mut kern = Some(LockedMultiexpKernel::<Bn256>::new(LOG_N, false));
+        let gpu = dense_multiexp(&worker, bases, exps, &mut kern)
+            .wait()
+            .unwrap();
+        assert_eq!(cpu, gpu);
+        assert_eq!(MockGpu::stats().calls, 1);
+    }
+
+    #[test]
+    fn test_sparse_multiexp_on_gpu() {
+        use crate::multiexp::sparse_multiexp;
+        use crate::pairing::ff::Field;
+        use rand::Rand;
+
+        let _guard = serial();
+        MockGpu::reset();
+
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+        let bases = (0..(1 << LOG_N))
+            .map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine())
+            .collect::<Vec<_>>();
+        // boolean wire with a few dense values
+        let exps = (0..(1 << LOG_N))
+            .map(|i| match i % 4 {
+                0 | 1 => Fr::zero().into_repr(),
+                2 => Fr::one().into_repr(),
+                _ => Fr::rand(rng).into_repr(),
+            })
+            .collect::<Vec<_>>();
+
+        let cpu = dense_multiexp(&worker, Arc::new(bases.clone()), Arc::new(exps.clone()), &mut None)
+            .wait()
+            .unwrap();
+
+        let mut kern = Some(LockedMultiexpKernel::<Bn256>::new(LOG_N, false));
+        let (gpu, stats) = sparse_multiexp(&worker, &bases, &exps, &mut kern).unwrap();
+        assert_eq!(cpu, gpu);
+        assert_eq!(stats.num_zeros, 1 << (LOG_N - 1));
+        assert_eq!(stats.num_ones, 1 << (LOG_N - 2));
+        assert_eq!(MockGpu::stats().calls, 1);
+
+        // nothing is left for the GPU if all the values are trivial
+        let trivial = exps
+            .iter()
+            .map(|e| if *e == Fr::zero().into_repr() { *e } else { Fr::one().into_repr() })
+            .collect::<Vec<_>>();
+        let (_, stats) = sparse_multiexp(&worker, &bases, &trivial, &mut kern).unwrap();
+        assert_eq!(stats.num_skipped(), stats.num_bases);
+        assert_eq!(MockGpu::stats().calls, 1);
+    }
+
+    const HOLD_LOCK_VAR: &str = "BELLMAN_TEST_HOLD_LOCK";
+    const LOCK_HELD: &str = "lock held by the child process";
+
+    /// A lock held by another process: this test binary run again with only `lock_holder`.
+    struct ChildLock {
+        child: Child,
+        stdout: BufReader<ChildStdout>,
+    }
+
+    impl ChildLock {
+        /// `lock` is `priority` or `gpu:<index>`, returns once the child holds it.
+        fn hold(lock: &str) -> ChildLock {
+            let mut child = Command::new(env::current_exe().unwrap())
+                .args(&["locks::tests::lock_holder", "--exact", "--nocapture"])
+                .env(HOLD_LOCK_VAR, lock)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+            let mut line = String::new();
+            while stdout.read_line(&mut line).unwrap() > 0 {
+                if line.contains(LOCK_HELD) {
+                    return ChildLock { child, stdout };
+                }
+                line.clear();
+            }
+
+            panic!("child process exited without taking the {} lock", lock);
+        }
+    }
+
+    /// Closing its stdin makes the child release the lock and exit.
+    impl Drop for ChildLock {
+        fn drop(&mut self) {
+            drop(self.child.stdin.take());
+            let _ = io::copy(&mut self.stdout, &mut io::sink());
+            let _ = self.child.wait();
+        }
+    }
+
+    /// Holds the lock of `BELLMAN_TEST_HOLD_LOCK` until stdin is closed, does nothing
+    /// without it. Only the child processes of `ChildLock::hold` set it.
+    #[test]
+    fn lock_holder() {
+        let lock = match env::var(HOLD_LOCK_VAR) {
+            Ok(lock) => lock,
+            Err(_) => return,
+        };
+
+        let _priority = if lock == "priority" {
+            Some(PriorityLock::lock())
+        } else {
+            None
+        };
+        let _gpu = if lock.starts_with("gpu:") {
+            Some(GPULock::lock(lock["gpu:".len()..].to_string()))
+        } else {
+            None
+        };
+        assert!(_priority.is_some() || _gpu.is_some(), "unknown lock {}", lock);
+
+        println!("{}", LOCK_HELD);
+        io::stdin().read_to_end(&mut Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn test_priority_lock_should_break() {
+        let _guard = serial();
+
+        assert!(!PriorityLock::should_break(false));
+        assert!(!PriorityLock::should_break(true));
+
+        let holder = ChildLock::hold("priority");
+        assert!(PriorityLock::should_break(false));
+        assert!(!PriorityLock::should_break(true));
+
+        drop(holder);
+        assert!(!PriorityLock::should_break(false));
+    }
+
+    #[test]
+    fn test_low_priority_kernel_waits_for_priority_lock() {
+        let _guard = serial();
+
+        let holder = ChildLock::hold("priority");
+        let released = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let released = released.clone();
+            thread::spawn(move || {
+                MockGpu::reset();
+                let mut polys = random_polys(1);
+                let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, false);
+                fft_with(&mut kern, &mut polys).unwrap();
+
+                (released.load(Ordering::SeqCst), MockGpu::stats())
+            })
+        };
+
+        thread::sleep(Duration::from_millis(200));
+        released.store(true, Ordering::SeqCst);
+        drop(holder);
+
+        let (ran_after_release, stats) = handle.join().unwrap();
+        assert!(ran_after_release);
+        assert_eq!(stats.kernels_created, 1);
+        assert_eq!(stats.calls, 1);
+    }
+
+    #[test]
+    fn test_low_priority_kernel_is_preempted() {
+        let _guard = serial();
+
+        let (created_tx, created_rx) = channel();
+        let (locked_tx, locked_rx) = channel();
+        let released = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let released = released.clone();
+            thread::spawn(move || {
+                MockGpu::reset();
+                let mut polys = random_polys(1);
+                let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, false);
+                fft_with(&mut kern, &mut polys).unwrap();
+
+                created_tx.send(()).unwrap();
+                locked_rx.recv().unwrap();
+
+                fft_with(&mut kern, &mut polys).unwrap();
+
+                (released.load(Ordering::SeqCst), MockGpu::stats())
+            })
+        };
+
+        created_rx.recv().unwrap();
+        let holder = ChildLock::hold("priority");
+        locked_tx.send(()).unwrap();
+
+        thread::sleep(Duration::from_millis(200));
+        released.store(true, Ordering::SeqCst);
+        drop(holder);
+
+        let (ran_after_release, stats) = handle.join().unwrap();
+        assert!(ran_after_release);
+        assert_eq!(stats.taken, 1);
+        assert_eq!(stats.kernels_created, 2);
+        assert_eq!(stats.calls, 2);
+    }
+
+    #[test]
+    fn test_priority_kernel_is_not_preempted() {
+        let _guard = serial();
+        MockGpu::reset();
+
+        let holder = ChildLock::hold("priority");
+        let mut polys = random_polys(1);
+        let mut kern = LockedMultiFFTKernel::<Bn256>::new(LOG_N, true);
+        fft_with(&mut kern, &mut polys).unwrap();
+        drop(holder);
+
+        let stats = MockGpu::stats();
+        assert_eq!(stats.taken, 0);
+        assert_eq!(stats.calls, 1);
+    }
+
+    #[test]
+    fn test_gpu_lock_contention() {
+        let _guard = serial();
+
+        let holder = ChildLock::hold("gpu:mock-test");
+        let released = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let released = released.clone();
+            thread::spawn(move || {
+                let _lock = GPULock::lock("mock-test".to_string());
+                released.load(Ordering::SeqCst)
+            })
+        };
+
+        thread::sleep(Duration::from_millis(200));
+        released.store(true, Ordering::SeqCst);
+        drop(holder);
+
+        assert!(handle.join().unwrap());
+    }
+}
@@ -10,9 +10,10 @@ use lazy_static::lazy_static;
 use log::{error, trace};
 use std::env;
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::plonk::fft::strategy::NttSelection;
 
-static WORKER_SPAWN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 lazy_static! {
     static ref NUM_CPUS: usize = if let Ok(num) = env::var("BELLMAN_NUM_CPUS") {
@@ -24,28 +25,143 @@ lazy_static! {
     } else {
         num_cpus::get()
     };
-    // See Worker::compute below for a description of this.
-    static ref WORKER_SPAWN_MAX_COUNT: usize = *NUM_CPUS * 4;
-    pub static ref THREAD_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
-        .num_threads(*NUM_CPUS)
-        .build()
-        .unwrap();
+    static ref GLOBAL_WORKER: Worker = Worker {
+        pool: Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(*NUM_CPUS)
+                .build()
+                .unwrap()
+        ),
+        num_cpus: *NUM_CPUS,
+        spawn_counter: Arc::new(AtomicUsize::new(0)),
+        ntt: NttSelection::default(),
+    };
 }
 
+/// A handle to a thread pool. `Worker::new` shares one process-wide pool sized
+/// by `BELLMAN_NUM_CPUS`. A pool created with [`WorkerBuilder`] belongs to the
+/// worker and its clones only, so concurrent provers don't compete for threads.
 #[derive(Clone)]
-pub struct Worker {}
+pub struct Worker {
+    pool: Arc<rayon::ThreadPool>,
+    num_cpus: usize,
+    // See Worker::compute below for a description of this.
+    spawn_counter: Arc<AtomicUsize>,
+    // How `plonk::fft::strategy` picks the CPU transforms, not part of the pool.
+    ntt: NttSelection,
+}
+
+/// Configures the thread pool owned by a [`Worker`].
+#[derive(Clone, Debug, Default)]
+pub struct WorkerBuilder {
+    num_threads: Option<usize>,
+    core_ids: Option<Vec<usize>>,
+    thread_name_prefix: Option<String>,
+    stack_size: Option<usize>,
+}
+
+impl WorkerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defaults to the number of cores, or to the number of pinned cores.
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = Some(num_threads);
+        self
+    }
+
+    /// Pins thread `i` of the pool to `core_ids[i % core_ids.len()]`.
+    pub fn pin_to_cores(mut self, core_ids: Vec<usize>) -> Self {
+        assert!(!core_ids.is_empty(), "no cores to pin the threads to");
+        self.core_ids = Some(core_ids);
+        self
+    }
+
+    /// Threads are named `"{prefix}-{index}"`.
+    pub fn thread_name(mut self, prefix: &str) -> Self {
+        self.thread_name_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn stack_size(mut self, bytes: usize) -> Self {
+        self.stack_size = Some(bytes);
+        self
+    }
+
+    pub fn build(self) -> Result<Worker, rayon::ThreadPoolBuildError> {
+        let num_threads = self
+            .num_threads
+            .or_else(|| self.core_ids.as_ref().map(|ids| ids.len()))
+            .unwrap_or_else(num_cpus::get);
+        assert!(num_threads > 0, "worker must have at least one thread");
+
+        let mut builder = rayon::ThreadPoolBuilder::new().num_threads(num_threads);
+        if let Some(prefix) = self.thread_name_prefix {
+            builder = builder.thread_name(move |i| format!("{}-{}", prefix, i));
+        }
+        if let Some(stack_size) = self.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        if let Some(core_ids) = self.core_ids {
+            builder = builder.start_handler(move |i| {
+                let id = core_ids[i % core_ids.len()];
+                if !core_affinity::set_for_current(core_affinity::CoreId { id }) {
+                    error!("could not pin worker thread {} to core {}", i, id);
+                }
+            });
+        }
+
+        Ok(Worker {
+            pool: Arc::new(builder.build()?),
+            num_cpus: num_threads,
+            spawn_counter: Arc::new(AtomicUsize::new(0)),
+            ntt: NttSelection::default(),
+        })
+    }
+}
+
+/// Workers are equal if they share the same thread pool.
+impl PartialEq for Worker {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pool, &other.pool)
+    }
+}
+
+impl std::fmt::Debug for Worker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Worker")
+            .field("num_cpus", &self.num_cpus)
+            .field("ntt", &self.ntt)
+            .finish()
+    }
+}
 
 impl Worker {
     pub fn new() -> Worker {
-        Worker {}
+        GLOBAL_WORKER.clone()
+    }
+
+    pub fn builder() -> WorkerBuilder {
+        WorkerBuilder::new()
     }
 
     pub fn get_num_cpus(&self) -> usize {
-        *NUM_CPUS
+        self.num_cpus
+    }
+
+    /// Same pool, the CPU transforms are chosen by `ntt`.
+    pub fn with_ntt(mut self, ntt: NttSelection) -> Self {
+        self.ntt = ntt;
+        self
+    }
+
+    pub fn ntt(&self) -> NttSelection {
+        self.ntt
     }
 
     pub fn log_num_cpus(&self) -> u32 {
-        log2_floor(*NUM_CPUS)
+        log2_floor(self.num_cpus)
     }
 
     pub fn compute<F, R>(&self, f: F) -> Waiter<R>
@@ -55,8 +171,8 @@ impl Worker {
     {
         let (sender, receiver) = bounded(1);
 
-        let thread_index = if THREAD_POOL.current_thread_index().is_some() {
-            THREAD_POOL.current_thread_index().unwrap()
+        let thread_index = if self.pool.current_thread_index().is_some() {
+            self.pool.current_thread_index().unwrap()
         } else {
             0
         };
@@ -68,7 +184,8 @@ impl Worker {
         // limits, we run the risk of memory exhaustion due to limited
         // stack space consumed by all of the pending closures to be
         // executed.
-        let previous_count = WORKER_SPAWN_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let spawn_counter = self.spawn_counter.clone();
+        let previous_count = spawn_counter.fetch_add(1, Ordering::SeqCst);
 
         // If the number of spawns requested has exceeded the number
         // of cores available for processing by some factor (the
@@ -76,21 +193,22 @@ impl Worker {
         // thread, we instead execute the closure in the context of an
         // install call to help clear the growing work queue and
         // minimize the chances of memory exhaustion.
-        if previous_count > *WORKER_SPAWN_MAX_COUNT {
-            THREAD_POOL.install(move || {
+        if previous_count > self.num_cpus * 4 {
+            let pool = self.pool.clone();
+            self.pool.install(move || {
                 trace!("[{}] switching to install to help clear backlog[current threads {}, threads requested {}]",
                        thread_index,
-                       THREAD_POOL.current_num_threads(),
-                       WORKER_SPAWN_COUNTER.load(Ordering::SeqCst));
+                       pool.current_num_threads(),
+                       spawn_counter.load(Ordering::SeqCst));
                 let res = f();
                 sender.send(res).unwrap();
-                WORKER_SPAWN_COUNTER.fetch_sub(1, Ordering::SeqCst);
+                spawn_counter.fetch_sub(1, Ordering::SeqCst);
             });
         } else {
-            THREAD_POOL.spawn(move || {
+            self.pool.spawn(move || {
                 let res = f();
                 sender.send(res).unwrap();
-                WORKER_SPAWN_COUNTER.fetch_sub(1, Ordering::SeqCst);
+                spawn_counter.fetch_sub(1, Ordering::SeqCst);
             });
         }
 
@@ -104,7 +222,7 @@ impl Worker {
     {
         let chunk_size = self.get_chunk_size(elements);
 
-        THREAD_POOL.scope(|scope| f(scope, chunk_size))
+        self.pool.scope(|scope| f(scope, chunk_size))
     }
 
     pub fn in_place_scope<'a, F, R>(&self, elements: usize, f: F) -> R
@@ -113,14 +231,24 @@ impl Worker {
     {
         let chunk_size = self.get_chunk_size(elements);
 
-        THREAD_POOL.in_place_scope(|scope| f(scope, chunk_size))
+        self.pool.in_place_scope(|scope| f(scope, chunk_size))
+    }
+
+    /// Runs `f` inside the owned pool, so rayon parallel iterators in it use
+    /// this worker's threads.
+    pub fn install<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        self.pool.install(f)
     }
 
     pub fn get_chunk_size(&self, elements: usize) -> usize {
-        let chunk_size = if elements <= *NUM_CPUS {
+        let chunk_size = if elements <= self.num_cpus {
             1
         } else {
-            Self::chunk_size_for_num_spawned_threads(elements, *NUM_CPUS)
+            Self::chunk_size_for_num_spawned_threads(elements, self.num_cpus)
         };
 
         chunk_size
@@ -141,7 +269,7 @@ impl Worker {
     }
 
     pub fn get_num_spawned_threads(&self, elements: usize) -> usize {
-        let num_spawned = if elements <= *NUM_CPUS {
+        let num_spawned = if elements <= self.num_cpus {
             elements
         } else {
             let chunk = self.get_chunk_size(elements);
@@ -149,7 +277,7 @@ impl Worker {
             if spawned * chunk < elements {
                 spawned += 1;
             }
-            assert!(spawned <= 2 * *NUM_CPUS);
+            assert!(spawned <= 2 * self.num_cpus);
 
             spawned
         };
@@ -165,7 +293,7 @@ pub struct Waiter<T> {
 impl<T> Waiter<T> {
     /// Wait for the result.
     pub fn wait(&self) -> T {
-        if THREAD_POOL.current_thread_index().is_some() {
+        if rayon::current_thread_index().is_some() {
             // Calling `wait()` from within the worker thread pool can lead to dead logs
             error!("The wait call should never be done inside the worker thread pool");
             debug_assert!(false);
@@ -207,4 +335,45 @@ mod tests {
         assert_eq!(log2_floor(7), 2);
         assert_eq!(log2_floor(8), 3);
     }
+
+    #[test]
+    fn test_owned_pool() {
+        let worker = Worker::builder()
+            .num_threads(3)
+            .thread_name("test-prover")
+            .stack_size(4 << 20)
+            .build()
+            .unwrap();
+        assert_eq!(worker.get_num_cpus(), 3);
+
+        let names = std::sync::Mutex::new(vec![]);
+        worker.scope(6, |scope, chunk| {
+            assert_eq!(chunk, 2);
+            for _ in 0..3 {
+                scope.spawn(|_| {
+                    let name = std::thread::current().name().map(|n| n.to_string());
+                    names.lock().unwrap().push(name);
+                });
+            }
+        });
+        for name in names.into_inner().unwrap() {
+            assert!(name.unwrap().starts_with("test-prover-"));
+        }
+
+        let name = worker
+            .compute(|| std::thread::current().name().map(|n| n.to_string()))
+            .wait();
+        assert!(name.unwrap().starts_with("test-prover-"));
+
+        // clones share the pool
+        let other = worker.clone();
+        assert_eq!(other.install(|| rayon::current_num_threads()), 3);
+    }
+
+    #[test]
+    fn test_pinned_pool() {
+        let worker = Worker::builder().pin_to_cores(vec![0]).build().unwrap();
+        assert_eq!(worker.get_num_cpus(), 1);
+        assert_eq!(worker.compute(|| 42).wait(), 42);
+    }
 }
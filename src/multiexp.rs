@@ -1,5 +1,5 @@
 use crate::pairing::{
-    ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine},
+    ff::{Field, PrimeField, ScalarEngine},
     CurveAffine, CurveProjective, Engine,
 };
 use bit_vec::{self, BitVec};
@@ -11,12 +11,13 @@ use std::sync::Arc;
 
 use super::worker::{Waiter, Worker};
 use super::SynthesisError;
+use crate::config::ProverConfig;
 use crate::gpu;
+use crate::gpu::KernelOnDevices;
 
 use crate::locks::LockedMultiFFTKernel;
 use crate::locks::LockedMultiexpKernel;
-use ec_gpu_gen::EcError;
-use ec_gpu_gen::EcResult;
+use crate::locks::MultiexpKernel;
 
 /// An object that builds a source of bases.
 pub trait SourceBuilder<G: CurveAffine>: Send + Sync + 'static + Clone {
@@ -199,12 +200,17 @@ impl DensityTracker {
     }
 }
 
-fn dense_multiexp_inner<G: CurveAffine>(
+/// Unsigned bucket method with projective buckets. The CPU path uses the signed digit
+/// variant, this one is kept as a reference in the tests.
+#[cfg(test)]
+pub(crate) fn dense_multiexp_inner<G: CurveAffine>(
     bases: Arc<Vec<G>>,
     exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
     c: u32,
     handle_trivial: bool,
 ) -> Result<<G as CurveAffine>::Projective, SynthesisError> {
+    use crate::pairing::ff::PrimeFieldRepr;
+
     // Perform this region of the multiexp
     let this = move |bases: Arc<Vec<G>>,
                      exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
@@ -268,6 +274,312 @@ fn dense_multiexp_inner<G: CurveAffine>(
         })
 }
 
+/// Window of `width` bits of the scalar starting from the bit `start`.
+#[inline(always)]
+pub(crate) fn get_window_bits(limbs: &[u64], start: usize, width: u32) -> u64 {
+    let limb = start / 64;
+    let shift = start % 64;
+    if limb >= limbs.len() {
+        return 0;
+    }
+
+    let mut value = limbs[limb] >> shift;
+    if shift + (width as usize) > 64 && limb + 1 < limbs.len() {
+        value |= limbs[limb + 1] << (64 - shift);
+    }
+
+    value & ((1u64 << width) - 1)
+}
+
+/// Largest window of the signed digit multiexp, every task keeps `2^(c - 1)` buckets.
+const MAX_SIGNED_DIGIT_WINDOW_SIZE: u32 = 16;
+
+/// Number of bucket additions that share a single inversion.
+const AFFINE_ADDITION_BATCH_SIZE: usize = 256;
+
+/// Relative costs of a batched affine bucket addition (about 6 multiplications including
+/// the share of the inversion) and of a projective addition in the bucket summation
+/// (about 12 multiplications).
+const AFFINE_ADDITION_COST: usize = 1;
+const PROJECTIVE_ADDITION_COST: usize = 2;
+
+fn num_signed_digit_windows(num_bits: u32, c: u32) -> usize {
+    // the top window takes the carry of the one below
+    (num_bits / c + 1) as usize
+}
+
+/// Digit of the `window` in `[-2^(c-1), 2^(c-1)]` such that the scalar is the sum of
+/// `digit_j * 2^(j * c)` over all windows. The carry into the window is computed from the
+/// windows below: a window above the half always carries, a window below the half never
+/// does and a window equal to the half passes the carry it received.
+#[inline(always)]
+fn signed_digit(limbs: &[u64], window: usize, c: u32) -> i64 {
+    let half = 1u64 << (c - 1);
+
+    let mut carry = 0;
+    for j in (0..window).rev() {
+        let w = get_window_bits(limbs, j * (c as usize), c);
+        if w != half {
+            carry = (w > half) as u64;
+            break;
+        }
+    }
+
+    let digit = get_window_bits(limbs, window * (c as usize), c) + carry;
+    if digit > half {
+        (digit as i64) - (1i64 << c)
+    } else {
+        digit as i64
+    }
+}
+
+/// Buckets in affine form. Additions into the buckets are delayed till a batch is full,
+/// so that all of them share one inversion. A point that hits a bucket which already has
+/// a pending addition in the batch goes to a projective overflow bucket instead.
+struct AffineBuckets<G: CurveAffine> {
+    buckets: Vec<G>,
+    overflow: Vec<G::Projective>,
+    pending: Vec<(usize, G)>,
+    in_batch: Vec<bool>,
+    batch_size: usize,
+    denominators: Vec<G::Base>,
+    prefix_products: Vec<G::Base>,
+}
+
+impl<G: CurveAffine> AffineBuckets<G> {
+    fn new(num_buckets: usize) -> Self {
+        let batch_size = std::cmp::min(AFFINE_ADDITION_BATCH_SIZE, num_buckets);
+
+        Self {
+            buckets: vec![G::zero(); num_buckets],
+            overflow: vec![G::Projective::zero(); num_buckets],
+            pending: Vec::with_capacity(batch_size),
+            in_batch: vec![false; num_buckets],
+            batch_size,
+            denominators: Vec::with_capacity(batch_size),
+            prefix_products: Vec::with_capacity(batch_size),
+        }
+    }
+
+    fn add(&mut self, bucket: usize, point: G) {
+        if self.in_batch[bucket] {
+            self.overflow[bucket].add_assign_mixed(&point);
+            return;
+        }
+
+        if self.buckets[bucket].is_zero() {
+            self.buckets[bucket] = point;
+            return;
+        }
+
+        self.in_batch[bucket] = true;
+        self.pending.push((bucket, point));
+        if self.pending.len() == self.batch_size {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        self.denominators.clear();
+        self.prefix_products.clear();
+
+        // x2 - x1 for every addition, doublings and additions of the inverse point are
+        // rare and use the projective formulas, so they get a dummy denominator
+        let mut acc = G::Base::one();
+        for (bucket, point) in self.pending.iter() {
+            let (x1, _) = self.buckets[*bucket].as_xy();
+            let (x2, _) = point.as_xy();
+            let mut denominator = *x2;
+            denominator.sub_assign(x1);
+            if denominator.is_zero() {
+                denominator = G::Base::one();
+            }
+
+            self.prefix_products.push(acc);
+            acc.mul_assign(&denominator);
+            self.denominators.push(denominator);
+        }
+
+        // Montgomery's batch inversion
+        let mut inverse = acc.inverse().expect("denominators are not zero");
+        for (denominator, prefix) in self
+            .denominators
+            .iter_mut()
+            .zip(self.prefix_products.iter())
+            .rev()
+        {
+            let mut tmp = inverse;
+            tmp.mul_assign(prefix);
+            inverse.mul_assign(denominator);
+            *denominator = tmp;
+        }
+
+        for ((bucket, point), inverse) in self.pending.drain(..).zip(self.denominators.iter()) {
+            let current = self.buckets[bucket];
+            let (x1, y1) = current.as_xy();
+            let (x2, y2) = point.as_xy();
+
+            self.buckets[bucket] = if x1 == x2 {
+                let mut tmp = current.into_projective();
+                tmp.add_assign_mixed(&point);
+                tmp.into_affine()
+            } else {
+                // lambda = (y2 - y1) / (x2 - x1)
+                // x3 = lambda^2 - x1 - x2
+                // y3 = lambda * (x1 - x3) - y1
+                let mut lambda = *y2;
+                lambda.sub_assign(y1);
+                lambda.mul_assign(inverse);
+
+                let mut x3 = lambda;
+                x3.square();
+                x3.sub_assign(x1);
+                x3.sub_assign(x2);
+
+                let mut y3 = *x1;
+                y3.sub_assign(&x3);
+                y3.mul_assign(&lambda);
+                y3.sub_assign(y1);
+
+                G::from_xy_unchecked(x3, y3)
+            };
+            self.in_batch[bucket] = false;
+        }
+    }
+
+    /// Sum of `(i + 1) * bucket_i`.
+    fn sum(mut self) -> G::Projective {
+        self.flush();
+
+        // Summation by parts
+        let mut running_sum = G::Projective::zero();
+        let mut acc = G::Projective::zero();
+        for (bucket, overflow) in self.buckets.iter().zip(self.overflow.iter()).rev() {
+            running_sum.add_assign_mixed(bucket);
+            running_sum.add_assign(overflow);
+            acc.add_assign(&running_sum);
+        }
+
+        acc
+    }
+}
+
+/// Pippenger's bucket method with signed digits: a window of `c` bits only needs
+/// `2^(c - 1)` buckets as the negative digits add the negated bases. The bucket additions
+/// are done in affine coordinates in batches sharing one inversion, so the curve must
+/// have real affine coordinates. The bases are split into `num_chunks` chunks to give
+/// every thread a task when there are fewer windows than threads.
+pub(crate) fn signed_digit_multiexp_inner<G: CurveAffine>(
+    bases: &[G],
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    c: u32,
+    num_chunks: usize,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError> {
+    let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS;
+
+    signed_digit_multiexp_with_bits(bases, exponents, num_bits, c, num_chunks)
+}
+
+/// Same as `signed_digit_multiexp_inner` for scalars shorter than `num_bits` bits, such
+/// as the halves of the GLV decomposition.
+pub(crate) fn signed_digit_multiexp_with_bits<G: CurveAffine>(
+    bases: &[G],
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    num_bits: u32,
+    c: u32,
+    num_chunks: usize,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError> {
+    assert!(c >= 2 && c <= MAX_SIGNED_DIGIT_WINDOW_SIZE);
+    assert!(num_chunks > 0);
+    assert_eq!(bases.len(), exponents.len(), "bases must be equal to exps.");
+
+    let n = bases.len();
+    if n == 0 {
+        return Ok(G::Projective::zero());
+    }
+
+    let num_windows = num_signed_digit_windows(num_bits, c);
+    let chunk_size = (n + num_chunks - 1) / num_chunks;
+    let num_chunks = (n + chunk_size - 1) / chunk_size;
+
+    let parts = (0..(num_windows * num_chunks))
+        .into_par_iter()
+        .map(|task| {
+            let window = task / num_chunks;
+            let start = (task % num_chunks) * chunk_size;
+            let end = std::cmp::min(start + chunk_size, n);
+
+            let mut buckets = AffineBuckets::<G>::new(1 << (c - 1));
+            for (base, exp) in bases[start..end].iter().zip(exponents[start..end].iter()) {
+                if base.is_zero() {
+                    continue;
+                }
+
+                let digit = signed_digit(exp.as_ref(), window, c);
+                if digit > 0 {
+                    buckets.add((digit - 1) as usize, *base);
+                } else if digit < 0 {
+                    let mut base = *base;
+                    base.negate();
+                    buckets.add((-digit - 1) as usize, base);
+                }
+            }
+
+            buckets.sum()
+        })
+        .collect::<Vec<_>>();
+
+    let mut acc = G::Projective::zero();
+    for window_parts in parts.chunks(num_chunks).rev() {
+        for _ in 0..c {
+            acc.double();
+        }
+
+        for part in window_parts.iter() {
+            acc.add_assign(part);
+        }
+    }
+
+    Ok(acc)
+}
+
+/// Window size and number of chunks of the bases for the signed digit multiexp, chosen
+/// by the cost of the slowest thread. Larger scalar fields have more windows, so the
+/// choice depends on the curve as well as on the number of threads.
+pub(crate) fn signed_digit_window_size<G: CurveAffine>(num_exponents: usize, num_threads: usize) -> (u32, usize) {
+    let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS;
+
+    signed_digit_window_size_for_bits(num_bits, num_exponents, num_threads)
+}
+
+pub(crate) fn signed_digit_window_size_for_bits(
+    num_bits: u32,
+    num_exponents: usize,
+    num_threads: usize,
+) -> (u32, usize) {
+    let num_threads = std::cmp::max(num_threads, 1);
+    let num_exponents = std::cmp::max(num_exponents, 1);
+
+    (2..=MAX_SIGNED_DIGIT_WINDOW_SIZE)
+        .map(|c| {
+            let num_windows = num_signed_digit_windows(num_bits, c);
+            let num_chunks = std::cmp::min((num_threads + num_windows - 1) / num_windows, num_exponents);
+            let num_rounds = (num_windows * num_chunks + num_threads - 1) / num_threads;
+
+            let per_task = ((num_exponents + num_chunks - 1) / num_chunks) * AFFINE_ADDITION_COST
+                + (1usize << c) * PROJECTIVE_ADDITION_COST;
+
+            (num_rounds * per_task, c, num_chunks)
+        })
+        .min_by_key(|(cost, c, _)| (*cost, *c))
+        .map(|(_, c, num_chunks)| (c, num_chunks))
+        .expect("range of windows is not empty")
+}
+
 /// Perform multi-exponentiation. The caller is responsible for ensuring the
 /// query size is the same as the number of exponents.
 pub fn dense_multiexp<G: CurveAffine>(
@@ -279,22 +591,72 @@ pub fn dense_multiexp<G: CurveAffine>(
     assert_eq!(bases.len(), exponents.len(), "bases must be equal to exps.");
     let n = bases.len();
     if let Some(ref mut kern) = kern {
-        if let Ok(p) = kern.with(|k: &mut MultiexpKernel<G::Engine>| {
-            let bss = bases.clone();
-            let exps = exponents.clone();
-            k.dense_multiexp(pool, bss, exps, n)
-        }) {
-            return Waiter::done(Ok(p));
+        // the configured share of the bases is processed by the CPU while the GPU
+        // handles the rest
+        let cpu_n = if kern.config().use_cpu {
+            n
+        } else {
+            ((n as f64) * kern.config().cpu_utilization) as usize
+        };
+
+        if cpu_n < n {
+            // the GPU multiplies the first `gpu_n` bases of the shared vectors and the
+            // CPU the rest, so neither part copies the CRS
+            let gpu_n = n - cpu_n;
+            let cpu_part = if cpu_n > 0 {
+                let cpu_bases = bases.clone();
+                let cpu_exps = exponents.clone();
+                let (c, num_chunks) = signed_digit_window_size::<G>(cpu_n, pool.get_num_cpus());
+
+                Some(pool.compute(move || {
+                    signed_digit_multiexp_inner(&cpu_bases[gpu_n..], &cpu_exps[gpu_n..], c, num_chunks)
+                }))
+            } else {
+                None
+            };
+
+            let gpu_result = kern.with(|k: &mut MultiexpKernel<G::Engine>| {
+                let bss = bases.clone();
+                let exps = exponents.clone();
+                Ok(k.dense_multiexp(pool, bss, exps, gpu_n)?)
+            });
+
+            match (gpu_result, cpu_part) {
+                (Ok(p), None) => return Waiter::done(Ok(p)),
+                (Ok(p), Some(cpu_part)) => {
+                    let result = cpu_part.wait().map(|mut acc| {
+                        acc.add_assign(&p);
+                        acc
+                    });
+                    return Waiter::done(result);
+                }
+                (Err(e), Some(cpu_part)) => {
+                    warn!("GPU multiexp failed, its {} bases go to the CPU: {}", gpu_n, e);
+                    // on the threads of `pool`, like the CPU part
+                    let (c, num_chunks) = signed_digit_window_size::<G>(gpu_n, pool.get_num_cpus());
+                    let gpu_bases = bases.clone();
+                    let gpu_exps = exponents.clone();
+                    let rest = pool
+                        .compute(move || {
+                            signed_digit_multiexp_inner(&gpu_bases[..gpu_n], &gpu_exps[..gpu_n], c, num_chunks)
+                        })
+                        .wait();
+                    let result = cpu_part.wait().and_then(|mut acc| {
+                        acc.add_assign(&rest?);
+                        Ok(acc)
+                    });
+                    return Waiter::done(result);
+                }
+                (Err(e), None) => {
+                    warn!("GPU multiexp failed, all {} bases go to the CPU: {}", n, e);
+                }
+            }
         }
     }
 
-    let c = if exponents.len() < 32 {
-        3u32
-    } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
-    };
+    let (c, num_chunks) = signed_digit_window_size::<G>(exponents.len(), pool.get_num_cpus());
 
-    let result = pool.compute(move || dense_multiexp_inner(bases, exponents, c, true));
+    let result = pool.compute(move || signed_digit_multiexp_inner(&bases, &exponents, c, num_chunks));
     #[cfg(feature = "gpu")]
     {
         // Do not give the control back to the caller till the
@@ -307,6 +669,177 @@ pub fn dense_multiexp<G: CurveAffine>(
     result
 }
 
+const SCALAR_ZERO: u8 = 0;
+const SCALAR_ONE: u8 = 1;
+const SCALAR_OTHER: u8 = 2;
+
+/// How many bases a density aware multiexp did not pass to the bucket method.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MultiexpStats {
+    pub num_bases: usize,
+    /// Zero scalars, their bases are skipped.
+    pub num_zeros: usize,
+    /// Scalars equal to one, their bases are added to the result directly.
+    pub num_ones: usize,
+}
+
+impl MultiexpStats {
+    pub fn num_skipped(&self) -> usize {
+        self.num_zeros + self.num_ones
+    }
+}
+
+/// Densities of the scalars that need the bucket method and of the scalars equal to one.
+/// Zero scalars are in neither of them.
+pub fn exponents_density<F: PrimeField>(
+    pool: &Worker,
+    exponents: &[F::Repr],
+) -> (DensityTracker, DensityTracker) {
+    let zero = F::zero().into_repr();
+    let one = F::one().into_repr();
+
+    let mut classes = vec![SCALAR_ZERO; exponents.len()];
+    pool.scope(exponents.len(), |scope, chunk| {
+        for (e, c) in exponents.chunks(chunk).zip(classes.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                for (e, c) in e.iter().zip(c.iter_mut()) {
+                    *c = if *e == zero {
+                        SCALAR_ZERO
+                    } else if *e == one {
+                        SCALAR_ONE
+                    } else {
+                        SCALAR_OTHER
+                    };
+                }
+            });
+        }
+    });
+
+    let tracker = |class: u8| DensityTracker {
+        bv: BitVec::from_fn(classes.len(), |i| classes[i] == class),
+        total_density: classes.iter().filter(|c| **c == class).count(),
+    };
+
+    (tracker(SCALAR_OTHER), tracker(SCALAR_ONE))
+}
+
+fn stats_for_density(n: usize, density: &DensityTracker, ones: &DensityTracker) -> MultiexpStats {
+    MultiexpStats {
+        num_bases: n,
+        num_zeros: n - density.get_total_density() - ones.get_total_density(),
+        num_ones: ones.get_total_density(),
+    }
+}
+
+/// Counts the trivial scalars without splitting them off, for the multiexps that skip the
+/// zero digits anyway.
+pub fn exponents_stats<F: PrimeField>(pool: &Worker, exponents: &[F::Repr]) -> MultiexpStats {
+    let (density, ones) = exponents_density::<F>(pool, exponents);
+
+    stats_for_density(exponents.len(), &density, &ones)
+}
+
+/// Scalars of a density aware multiexp that still need the bucket method, the bases of the
+/// scalars equal to one are already summed up.
+pub struct SparseExponents<G: CurveAffine> {
+    /// Indexes of the bases of the non-trivial scalars.
+    pub indexes: Vec<usize>,
+    pub exponents: Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+    pub ones_sum: G::Projective,
+    pub stats: MultiexpStats,
+}
+
+/// Skips the zero scalars and sums the bases of the scalars equal to one. `bases` may be
+/// longer than `exponents`.
+pub fn split_sparse_exponents<G: CurveAffine>(
+    pool: &Worker,
+    bases: &[G],
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+) -> SparseExponents<G> {
+    assert!(bases.len() >= exponents.len(), "not enough bases for exps.");
+    let n = exponents.len();
+
+    let (density, ones) = exponents_density::<<G::Engine as ScalarEngine>::Fr>(pool, exponents);
+    let stats = stats_for_density(n, &density, &ones);
+
+    // gather the dense part and sum the bases of the ones chunk by chunk
+    let mut parts = vec![(vec![], vec![], G::Projective::zero()); pool.get_num_spawned_threads(n)];
+    pool.scope(n, |scope, chunk| {
+        for (i, part) in parts.iter_mut().enumerate() {
+            let density = &density;
+            let ones = &ones;
+            scope.spawn(move |_| {
+                let start = i * chunk;
+                let end = std::cmp::min(start + chunk, n);
+                for idx in start..end {
+                    if density.bv[idx] {
+                        part.0.push(idx);
+                        part.1.push(exponents[idx]);
+                    } else if ones.bv[idx] {
+                        part.2.add_assign_mixed(&bases[idx]);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut indexes = Vec::with_capacity(density.get_total_density());
+    let mut dense_exps = Vec::with_capacity(density.get_total_density());
+    let mut ones_sum = G::Projective::zero();
+    for (i, e, s) in parts.into_iter() {
+        indexes.extend(i);
+        dense_exps.extend(e);
+        ones_sum.add_assign(&s);
+    }
+
+    SparseExponents {
+        indexes,
+        exponents: dense_exps,
+        ones_sum,
+        stats,
+    }
+}
+
+/// Copies `values[i]` for all the `indexes`.
+pub fn gather<T: Copy + Send + Sync>(pool: &Worker, values: &[T], indexes: &[usize]) -> Vec<T> {
+    let mut result = Vec::with_capacity(indexes.len());
+    unsafe {
+        result.set_len(indexes.len());
+    }
+    pool.scope(indexes.len(), |scope, chunk| {
+        for (i, r) in indexes.chunks(chunk).zip(result.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                for (i, r) in i.iter().zip(r.iter_mut()) {
+                    *r = values[*i];
+                }
+            });
+        }
+    });
+
+    result
+}
+
+/// Multiexp that skips the bases of zero scalars and adds the bases of scalars equal to
+/// one directly, only the rest goes through `dense_multiexp` on the CPU or the GPU. Pays
+/// off for mostly boolean polynomials. `bases` may be longer than `exponents`.
+pub fn sparse_multiexp<G: CurveAffine>(
+    pool: &Worker,
+    bases: &[G],
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    kern: &mut Option<LockedMultiexpKernel<G::Engine>>,
+) -> Result<(<G as CurveAffine>::Projective, MultiexpStats), SynthesisError> {
+    let split = split_sparse_exponents(pool, bases, exponents);
+
+    let mut acc = split.ones_sum;
+    if !split.indexes.is_empty() {
+        let dense_bases = gather(pool, bases, &split.indexes);
+        let dense = dense_multiexp(pool, Arc::new(dense_bases), Arc::new(split.exponents), kern).wait()?;
+        acc.add_assign(&dense);
+    }
+
+    Ok((acc, split.stats))
+}
+
 #[cfg(any(feature = "pairing", feature = "blst"))]
 #[test]
 fn test_with_bls12() {
@@ -359,11 +892,126 @@ fn test_with_bls12() {
     assert_eq!(naive, fast);
 }
 
-pub fn create_multiexp_kernel<E>(_log_d: usize, priority: bool) -> Option<MultiexpKernel<E>>
+#[test]
+fn test_signed_digit_multiexp() {
+    use crate::pairing::bn256::{Bn256, Fr, G1Affine, G2Affine};
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    fn naive_multiexp<G: CurveAffine>(
+        bases: &[G],
+        exponents: &[<G::Scalar as PrimeField>::Repr],
+    ) -> G::Projective {
+        let mut acc = G::Projective::zero();
+        for (base, exp) in bases.iter().zip(exponents.iter()) {
+            acc.add_assign(&base.mul(*exp));
+        }
+
+        acc
+    }
+
+    fn check<G: CurveAffine>(bases: Vec<G>, exponents: Vec<<G::Scalar as PrimeField>::Repr>) {
+        let expected = naive_multiexp(&bases, &exponents);
+        let bases = Arc::new(bases);
+        let exponents = Arc::new(exponents);
+
+        let unsigned = dense_multiexp_inner(bases.clone(), exponents.clone(), 8, true).unwrap();
+        assert_eq!(unsigned, expected);
+
+        for c in [2, 3, 4, 7, 8, 11, MAX_SIGNED_DIGIT_WINDOW_SIZE].iter() {
+            for num_chunks in [1, 3].iter() {
+                let signed = signed_digit_multiexp_inner(&bases, &exponents, *c, *num_chunks).unwrap();
+                assert_eq!(signed, expected, "c = {}, chunks = {}", c, num_chunks);
+            }
+        }
+
+        let pool = Worker::new();
+        let fast = dense_multiexp(&pool, bases, exponents, &mut None).wait().unwrap();
+        assert_eq!(fast, expected);
+    }
+
+    fn edge_scalars() -> Vec<<Fr as PrimeField>::Repr> {
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+
+        let mut result = vec![Fr::zero().into_repr(), Fr::one().into_repr(), minus_one.into_repr()];
+        // windows equal to the half pass the carry through: 0xaa.. for c = 2, 0x88.. for
+        // c = 4 and 0x80.. for c = 8
+        for pattern in [0xaaaaaaaaaaaaaaaa, 0x5555555555555555, 0x8888888888888888, 0x8080808080808080].iter() {
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            for limb in repr.as_mut().iter_mut() {
+                *limb = *pattern;
+            }
+            repr.as_mut()[3] >>= 3;
+            result.push(repr);
+        }
+
+        result
+    }
+
+    let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    for n in [1, 2, 100, 1 << 12].iter() {
+        let mut bases: Vec<G1Affine> = (0..*n).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect();
+        let mut exponents: Vec<_> = (0..*n).map(|_| Fr::rand(rng).into_repr()).collect();
+        for (e, s) in exponents.iter_mut().zip(edge_scalars().into_iter()) {
+            *e = s;
+        }
+
+        if *n >= 100 {
+            // equal and opposite points in the same bucket hit the doubling and the
+            // point at infinity in the batched additions
+            let mut negated = bases[10];
+            negated.negate();
+            bases[11] = bases[10];
+            bases[12] = negated;
+            bases[13] = G1Affine::zero();
+            for i in 10..14 {
+                exponents[i] = exponents[20];
+            }
+            // many equal scalars overflow into the projective buckets
+            for i in 30..60 {
+                exponents[i] = exponents[29];
+            }
+        }
+
+        check(bases, exponents);
+    }
+
+    let bases: Vec<G2Affine> = (0..100).map(|_| <Bn256 as Engine>::G2::rand(rng).into_affine()).collect();
+    let exponents: Vec<_> = (0..100).map(|_| Fr::rand(rng).into_repr()).collect();
+    check(bases, exponents);
+}
+
+#[test]
+fn test_signed_digit_window_size() {
+    use crate::pairing::bn256::G1Affine;
+
+    let (c, num_chunks) = signed_digit_window_size::<G1Affine>(1, 8);
+    assert_eq!((c, num_chunks), (2, 1));
+
+    let mut previous = 0;
+    for log_n in 4..24 {
+        let (c, num_chunks) = signed_digit_window_size::<G1Affine>(1 << log_n, 16);
+        assert!(c >= 2 && c <= MAX_SIGNED_DIGIT_WINDOW_SIZE);
+        assert!(num_chunks >= 1);
+        // larger inputs never get smaller windows
+        assert!(c >= previous);
+        previous = c;
+    }
+
+    // a single thread has nothing to gain from splitting the bases
+    assert_eq!(signed_digit_window_size::<G1Affine>(1 << 16, 1).1, 1);
+}
+
+pub fn create_multiexp_kernel<E>(
+    _log_d: usize,
+    priority: bool,
+    config: &ProverConfig,
+) -> Option<MultiexpKernel<E>>
 where
     E: crate::pairing::Engine,
 {
-    match MultiexpKernel::<E>::create(priority) {
+    match MultiexpKernel::<E>::create_on_devices(priority, config) {
         Ok(k) => {
             info!("GPU Multiexp kernel instantiated!");
             Some(k)
@@ -0,0 +1,165 @@
+//! Stand-ins for the locked GPU kernels, used when the crate is built without the
+//! `gpu` feature.
+//!
+//! They keep the signatures of `locks.rs`, so the FFT and multiexp code paths do not
+//! need to be duplicated, but every call reports `GPUDisabled` and the callers fall
+//! back to the CPU implementations.
+
+use crate::config::ProverConfig;
+use crate::gpu::{GPUError, GPUResult, KernelOnDevices};
+use crate::pairing::ff::{PrimeField, ScalarEngine};
+use crate::pairing::{CurveAffine, Engine};
+use crate::worker::Worker;
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+pub struct MultiFFTKernel<E: Engine>(PhantomData<E>);
+
+impl<E: Engine> KernelOnDevices for MultiFFTKernel<E> {
+    fn create_on_devices(_priority: bool, _config: &ProverConfig) -> GPUResult<Self> {
+        Err(GPUError::GPUDisabled)
+    }
+}
+
+impl<E: Engine> MultiFFTKernel<E> {
+
+    pub fn fft_multiple(
+        &mut self,
+        _polys: &mut [&mut [E::Fr]],
+        _omega: &E::Fr,
+        _log_n: u32,
+    ) -> GPUResult<()> {
+        Err(GPUError::GPUDisabled)
+    }
+}
+
+pub struct MultiexpKernel<E: Engine>(PhantomData<E>);
+
+impl<E: Engine> KernelOnDevices for MultiexpKernel<E> {
+    fn create_on_devices(_priority: bool, _config: &ProverConfig) -> GPUResult<Self> {
+        Err(GPUError::GPUDisabled)
+    }
+}
+
+impl<E: Engine> MultiexpKernel<E> {
+
+    pub fn dense_multiexp<G: CurveAffine>(
+        &mut self,
+        _pool: &Worker,
+        _bases: Arc<Vec<G>>,
+        _exps: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+        _n: usize,
+    ) -> GPUResult<G::Projective> {
+        Err(GPUError::GPUDisabled)
+    }
+}
+
+macro_rules! locked_kernel {
+    ($class:ident, $kern:ident) => {
+        pub struct $class<E>
+        where
+            E: Engine,
+        {
+            config: ProverConfig,
+            _marker: PhantomData<E>,
+        }
+
+        impl<E> $class<E>
+        where
+            E: Engine,
+        {
+            pub fn new(log_d: usize, priority: bool) -> $class<E> {
+                Self::new_with_config(log_d, priority, ProverConfig::from_env())
+            }
+
+            /// The configuration is forced to CPU only, there is no device to use.
+            pub fn new_with_config(
+                _log_d: usize,
+                _priority: bool,
+                config: ProverConfig,
+            ) -> $class<E> {
+                $class::<E> {
+                    config: ProverConfig {
+                        use_cpu: true,
+                        ..config
+                    },
+                    _marker: PhantomData,
+                }
+            }
+
+            pub fn config(&self) -> &ProverConfig {
+                &self.config
+            }
+
+            pub fn with<F, R>(&mut self, _f: F) -> GPUResult<R>
+            where
+                F: FnMut(&mut $kern<E>) -> GPUResult<R>,
+            {
+                Err(GPUError::GPUDisabled)
+            }
+        }
+    };
+}
+
+locked_kernel!(LockedMultiFFTKernel, MultiFFTKernel);
+locked_kernel!(LockedMultiexpKernel, MultiexpKernel);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{best_fft_multiple_gpu, serial_fft_fr};
+    use crate::multiexp::dense_multiexp;
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::CurveProjective;
+    use crate::plonk::domains::Domain;
+    use rand::Rand;
+
+    #[test]
+    fn test_cpu_only_kernels_fall_back() {
+        const LOG_N: u32 = 8;
+
+        let worker = Worker::new();
+        let rng = &mut rand::thread_rng();
+
+        let config = ProverConfig::cpu_and_gpu().with_cpu_utilization(0.5);
+        let mut fft_kern = Some(LockedMultiFFTKernel::<Bn256>::new_with_config(
+            LOG_N as usize,
+            false,
+            config.clone(),
+        ));
+        assert!(fft_kern.as_ref().unwrap().config().use_cpu);
+
+        let omega = Domain::<Fr>::new_for_size(1 << LOG_N).unwrap().generator;
+        let poly = (0..(1 << LOG_N)).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        let mut expected = poly.clone();
+        serial_fft_fr::<Bn256>(&mut expected, &omega, LOG_N);
+        let mut actual = poly;
+        best_fft_multiple_gpu(&mut fft_kern, &mut [&mut actual[..]], &worker, &omega, LOG_N)
+            .unwrap();
+        assert_eq!(expected, actual);
+
+        let bases = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exps = Arc::new(
+            (0..(1 << LOG_N))
+                .map(|_| Fr::rand(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<Bn256>::new_with_config(
+            LOG_N as usize,
+            false,
+            config,
+        ));
+        let cpu = dense_multiexp(&worker, bases.clone(), exps.clone(), &mut None)
+            .wait()
+            .unwrap();
+        let fallback = dense_multiexp(&worker, bases, exps, &mut multiexp_kern)
+            .wait()
+            .unwrap();
+        assert_eq!(cpu, fallback);
+    }
+}
@@ -11,6 +11,7 @@ use std::marker::PhantomData;
 use super::cs::*;
 use super::keys::SetupPolynomials;
 pub use super::utils::make_non_residues;
+use crate::config::ProverConfig;
 use crate::locks::LockedMultiFFTKernel;
 
 #[derive(Debug, Clone)]
@@ -385,6 +386,14 @@ impl<E: Engine> GeneratorAssembly4WithNextStep<E> {
     pub fn setup(
         self,
         worker: &Worker,
+    ) -> Result<SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
+        self.setup_with_config(worker, &ProverConfig::from_env())
+    }
+
+    pub fn setup_with_config(
+        self,
+        worker: &Worker,
+        config: &ProverConfig,
     ) -> Result<SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
         assert!(self.is_finalized);
 
@@ -400,7 +409,11 @@ impl<E: Engine> GeneratorAssembly4WithNextStep<E> {
 
         //fft
         let log_d = sigma_1.exp as usize;
-        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new(log_d, false));
+        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         let mut polys = ifft_multiple(
             vec![
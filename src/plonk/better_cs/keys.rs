@@ -4,17 +4,22 @@ use crate::pairing::ff::{Field, PrimeField};
 use crate::pairing::{CurveAffine, EncodedPoint, Engine};
 
 use crate::plonk::domains::*;
-use crate::plonk::polynomials::*;
-use crate::worker::Worker;
 use crate::SynthesisError;
 
+#[cfg(not(feature = "verifier-only"))]
 use crate::kate_commitment::*;
+#[cfg(not(feature = "verifier-only"))]
+use crate::plonk::polynomials::*;
+#[cfg(not(feature = "verifier-only"))]
+use crate::worker::Worker;
 
 use std::marker::PhantomData;
 
 use super::utils::*;
+#[cfg(not(feature = "verifier-only"))]
 use super::LDE_FACTOR;
 
+#[cfg(not(feature = "verifier-only"))]
 pub struct SetupPolynomials<E: Engine, P: PlonkConstraintSystemParams<E>> {
     pub n: usize,
     pub num_inputs: usize,
@@ -170,6 +175,29 @@ pub fn read_fr_vec<F: PrimeField, R: Read>(mut reader: R) -> std::io::Result<Vec
     Ok(elements)
 }
 
+/// Same as `read_fr_vec`, but fails before reading any element if the encoded length is
+/// over `max_len`.
+pub fn read_fr_vec_bounded<F: PrimeField, R: Read>(
+    max_len: usize,
+    mut reader: R,
+) -> std::io::Result<Vec<F>> {
+    let num_elements = reader.read_u64::<BigEndian>()?;
+    if num_elements > max_len as u64 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "too many field elements",
+        ));
+    }
+
+    let mut elements = Vec::with_capacity(num_elements as usize);
+    for _ in 0..num_elements {
+        let el = read_fr(&mut reader)?;
+        elements.push(el);
+    }
+
+    Ok(elements)
+}
+
 pub fn read_fr_raw<F: PrimeField, R: Read>(mut reader: R) -> std::io::Result<F> {
     use crate::ff::PrimeFieldRepr;
     let mut repr = F::Repr::default();
@@ -199,6 +227,7 @@ pub fn write_optional_flag<W: Write>(is_some: bool, mut writer: W) -> std::io::R
     Ok(())
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn read_optional_polynomial_coeffs<E: Engine, R: Read>(
     mut reader: R,
 ) -> std::io::Result<Option<Polynomial<E, Coefficients>>> {
@@ -212,6 +241,7 @@ pub fn read_optional_polynomial_coeffs<E: Engine, R: Read>(
     }
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn read_optional_polynomial_values_unpadded<E: Engine, R: Read>(
     mut reader: R,
 ) -> std::io::Result<Option<Polynomial<E, Values>>> {
@@ -225,6 +255,7 @@ pub fn read_optional_polynomial_values_unpadded<E: Engine, R: Read>(
     }
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn write_optional_polynomial<E: Engine, P: PolynomialForm, W: Write>(
     p: &Option<Polynomial<E, P>>,
     mut writer: W,
@@ -236,6 +267,7 @@ pub fn write_optional_polynomial<E: Engine, P: PolynomialForm, W: Write>(
     Ok(())
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn read_polynomials_coeffs_vec<E: Engine, R: Read>(
     mut reader: R,
 ) -> std::io::Result<Vec<Polynomial<E, Coefficients>>> {
@@ -249,6 +281,7 @@ pub fn read_polynomials_coeffs_vec<E: Engine, R: Read>(
     Ok(polys)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn read_polynomials_values_unpadded_vec<E: Engine, R: Read>(
     mut reader: R,
 ) -> std::io::Result<Vec<Polynomial<E, Values>>> {
@@ -262,6 +295,7 @@ pub fn read_polynomials_values_unpadded_vec<E: Engine, R: Read>(
     Ok(polys)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn write_polynomials_vec<E: Engine, P: PolynomialForm, W: Write>(
     p: &[Polynomial<E, P>],
     mut writer: W,
@@ -273,6 +307,7 @@ pub fn write_polynomials_vec<E: Engine, P: PolynomialForm, W: Write>(
     Ok(())
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn write_polynomial<E: Engine, P: PolynomialForm, W: Write>(
     p: &Polynomial<E, P>,
     mut writer: W,
@@ -284,6 +319,7 @@ pub fn write_polynomial<E: Engine, P: PolynomialForm, W: Write>(
     Ok(())
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn read_polynomial_coeffs<E: Engine, R: Read>(
     mut reader: R,
 ) -> std::io::Result<Polynomial<E, Coefficients>> {
@@ -297,6 +333,7 @@ pub fn read_polynomial_coeffs<E: Engine, R: Read>(
     Ok(Polynomial::from_coeffs(poly_coeffs).expect("must fit into some domain"))
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn read_polynomial_values_unpadded<E: Engine, R: Read>(
     mut reader: R,
 ) -> std::io::Result<Polynomial<E, Values>> {
@@ -310,6 +347,7 @@ pub fn read_polynomial_values_unpadded<E: Engine, R: Read>(
     Ok(Polynomial::from_values_unpadded(poly_values).expect("must fit into some domain"))
 }
 
+#[cfg(not(feature = "verifier-only"))]
 impl<E: Engine, P: PlonkConstraintSystemParams<E>> SetupPolynomials<E, P> {
     pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
         writer.write_u64::<BigEndian>(self.n as u64)?;
@@ -372,6 +410,7 @@ impl<E: Engine, P: PlonkConstraintSystemParams<E>> SetupPolynomials<E, P> {
     }
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub struct SetupPolynomialsPrecomputations<E: Engine, P: PlonkConstraintSystemParams<E>> {
     pub selector_polynomials_on_coset_of_size_4n_bitreversed: Vec<Polynomial<E, Values>>,
     pub next_step_selector_polynomials_on_coset_of_size_4n_bitreversed: Vec<Polynomial<E, Values>>,
@@ -383,13 +422,26 @@ pub struct SetupPolynomialsPrecomputations<E: Engine, P: PlonkConstraintSystemPa
     pub(crate) _marker: std::marker::PhantomData<P>,
 }
 
+#[cfg(not(feature = "verifier-only"))]
+use crate::config::ProverConfig;
+#[cfg(not(feature = "verifier-only"))]
 use crate::locks::{LockedMultiFFTKernel, LockedMultiexpKernel};
+#[cfg(not(feature = "verifier-only"))]
 use crate::plonk::fft::cooley_tukey_ntt::{BitReversedOmegas, CTPrecomputations};
 
+#[cfg(not(feature = "verifier-only"))]
 impl<E: Engine, P: PlonkConstraintSystemParams<E>> SetupPolynomialsPrecomputations<E, P> {
     pub fn from_setup(
         setup: &SetupPolynomials<E, P>,
         worker: &Worker,
+    ) -> Result<Self, SynthesisError> {
+        Self::from_setup_with_config(setup, worker, &ProverConfig::from_env())
+    }
+
+    pub fn from_setup_with_config(
+        setup: &SetupPolynomials<E, P>,
+        worker: &Worker,
+        config: &ProverConfig,
     ) -> Result<Self, SynthesisError> {
         let mut new = Self {
             selector_polynomials_on_coset_of_size_4n_bitreversed: vec![],
@@ -416,7 +468,11 @@ impl<E: Engine, P: PlonkConstraintSystemParams<E>> SetupPolynomialsPrecomputatio
             log_d += 1;
         }
 
-        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new(log_d, false));
+        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         // we do not precompute q_const as we need to use it for public inputs;
         for p in setup.selector_polynomials[0..(setup.selector_polynomials.len() - 1)].iter() {
@@ -837,10 +893,21 @@ pub struct VerificationKey<E: Engine, P: PlonkConstraintSystemParams<E>> {
 }
 
 impl<E: Engine, P: PlonkConstraintSystemParams<E>> VerificationKey<E, P> {
+    #[cfg(not(feature = "verifier-only"))]
     pub fn from_setup(
         setup: &SetupPolynomials<E, P>,
         worker: &Worker,
         crs: &Crs<E, CrsForMonomialForm>,
+    ) -> Result<Self, SynthesisError> {
+        Self::from_setup_with_config(setup, worker, crs, &ProverConfig::from_env())
+    }
+
+    #[cfg(not(feature = "verifier-only"))]
+    pub fn from_setup_with_config(
+        setup: &SetupPolynomials<E, P>,
+        worker: &Worker,
+        crs: &Crs<E, CrsForMonomialForm>,
+        config: &ProverConfig,
     ) -> Result<Self, SynthesisError> {
         assert_eq!(setup.selector_polynomials.len(), P::STATE_WIDTH + 2);
         if P::CAN_ACCESS_NEXT_TRACE_STEP == false {
@@ -865,7 +932,11 @@ impl<E: Engine, P: PlonkConstraintSystemParams<E>> VerificationKey<E, P> {
         while (1 << log_d) < n {
             log_d += 1;
         }
-        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, false));
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         for p in setup.selector_polynomials.iter() {
             let commitment = commit_using_monomials(p, &crs, &worker, &mut multiexp_kern)?;
@@ -1,13 +1,19 @@
 pub mod cs;
+#[cfg(not(feature = "verifier-only"))]
 pub mod test_assembly;
+#[cfg(not(feature = "verifier-only"))]
 pub mod adaptor;
+#[cfg(not(feature = "verifier-only"))]
 pub mod generator;
 pub mod keys;
+#[cfg(not(feature = "verifier-only"))]
 pub mod prover;
 pub mod verifier;
+#[cfg(not(feature = "verifier-only"))]
 pub mod one_shot_test_assembly;
+#[cfg(not(feature = "verifier-only"))]
 pub mod fma_adaptor;
 
 pub(crate) mod utils;
 
-const LDE_FACTOR: usize = 4;
\ No newline at end of file
+pub(crate) const LDE_FACTOR: usize = 4;
\ No newline at end of file
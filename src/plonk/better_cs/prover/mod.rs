@@ -5,6 +5,7 @@ use crate::plonk::domains::*;
 use crate::plonk::polynomials::*;
 use crate::worker::Worker;
 use crate::SynthesisError;
+use log::info;
 
 use std::marker::PhantomData;
 
@@ -22,6 +23,7 @@ use crate::plonk::fft::cooley_tukey_ntt::*;
 use super::LDE_FACTOR;
 
 pub(crate) mod prove_steps;
+mod streaming_quotient;
 
 // #[derive(Debug, Clone)]
 pub struct ProverAssembly<E: Engine, P: PlonkConstraintSystemParams<E>> {
@@ -179,6 +181,14 @@ impl<E: Engine, P: PlonkConstraintSystemParams<E>> ProverAssembly<E, P> {
         self.n
     }
 
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn input_values(&self) -> &[E::Fr] {
+        &self.input_assingments
+    }
+
     pub fn finalize(&mut self) {
         if self.is_finalized {
             return;
@@ -315,8 +325,12 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
 
         // Commit wire polynomials
 
-        for wire_poly in full_assignments.iter() {
-            let commitment = commit_using_raw_values(&wire_poly, &crs_vals, &worker, &mut None)?;
+        for (i, wire_poly) in full_assignments.iter().enumerate() {
+            let (commitment, stats) = commit_using_sparse_raw_values(&wire_poly, &crs_vals, &worker, &mut None)?;
+            info!(
+                "Wire {} commitment skipped {} zero and {} one values out of {}",
+                i, stats.num_zeros, stats.num_ones, stats.num_bases
+            );
 
             commit_point_as_xy::<E, _>(&mut transcript, &commitment);
 
@@ -1276,4 +1290,68 @@ mod test {
 
         assert!(is_valid);
     }
+
+    #[test]
+    fn test_prove_within_memory_budget() {
+        use crate::config::ProverConfig;
+        use crate::pairing::bn256::{Bn256, Fr};
+        use crate::plonk::better_cs::generator::*;
+        use crate::plonk::better_cs::keys::*;
+        use crate::plonk::{
+            make_verification_key_with_config, prove_native_by_steps_with_config,
+            prove_native_by_steps_with_timings,
+        };
+        use crate::worker::Worker;
+
+        let circuit = TestCircuit4::<Bn256> {
+            _marker: PhantomData,
+        };
+
+        let mut assembly = GeneratorAssembly4WithNextStep::<Bn256>::new();
+        circuit.synthesize(&mut assembly).expect("must work");
+        assembly.finalize();
+
+        let worker = Worker::new();
+        let setup = assembly.setup(&worker).unwrap();
+        let size = setup.permutation_polynomials[0].size();
+        let crs_mons = Crs::<Bn256, CrsForMonomialForm>::crs_42(size, &worker);
+
+        let config = ProverConfig::cpu_only();
+        let verification_key = make_verification_key_with_config(&setup, &crs_mons, &config).unwrap();
+
+        // the third step holds the witness, setup and z LDEs at once
+        let lde_size = size * LDE_FACTOR * std::mem::size_of::<Fr>();
+        let budget = 64 * lde_size;
+        let (proof, timings) = prove_native_by_steps_with_timings::<_, _, Blake2sTranscript<Fr>>(
+            &circuit,
+            &setup,
+            None,
+            &crs_mons,
+            None,
+            &config.clone().with_memory_budget(budget),
+        )
+        .unwrap();
+        let steps: Vec<_> = timings.memory_reports.iter().map(|r| r.step).collect();
+        assert_eq!(steps, vec!["Second step", "Third step"]);
+        assert!(timings.memory_reports.iter().all(|r| r.peak_bytes <= budget));
+        assert!(timings.memory_reports[1].allocations > 0);
+        assert!(verify::<Bn256, PlonkCsWidth4WithNextStepParams, Blake2sTranscript<Fr>>(
+            &proof,
+            &verification_key,
+            None,
+        )
+        .unwrap());
+
+        match prove_native_by_steps_with_config::<_, _, Blake2sTranscript<Fr>>(
+            &circuit,
+            &setup,
+            None,
+            &crs_mons,
+            None,
+            &config.with_memory_budget(2 * lde_size),
+        ) {
+            Err(SynthesisError::MemoryBudgetExceeded) => {}
+            _ => panic!("the proof must not fit into two LDEs"),
+        }
+    }
 }
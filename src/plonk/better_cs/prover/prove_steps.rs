@@ -1,6 +1,8 @@
 use super::*;
 use crate::domain::fft_parallel;
+use crate::config::ProverConfig;
 use crate::locks::{LockedMultiFFTKernel, LockedMultiexpKernel};
+use crate::plonk::polynomials::pool::PolynomialPool;
 use crate::plonk::utils::fast_clone;
 
 pub(crate) enum PrecomputationsForPolynomial<'a, E: Engine> {
@@ -22,6 +24,13 @@ impl<'a, E: Engine> AsRef<Polynomial<E, Values>> for PrecomputationsForPolynomia
 }
 
 impl<'a, E: Engine> PrecomputationsForPolynomial<'a, E> {
+    /// Gives the owned values back to the pool they were taken from.
+    pub(crate) fn into_pool(self, pool: &PolynomialPool<E::Fr>) {
+        if let PrecomputationsForPolynomial::Owned(o) = self {
+            o.into_pool(pool);
+        }
+    }
+
     pub(crate) fn into_poly(self) -> Polynomial<E, Values> {
         match self {
             PrecomputationsForPolynomial::Borrowed(b) => b.clone(),
@@ -41,6 +50,7 @@ pub(crate) fn get_precomputed_permutation_poly_lde_for_index<'a, E: Engine>(
         &'a SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
     >,
     worker: &Worker,
+    pool: &PolynomialPool<E::Fr>,
     fft_kern: &mut Option<LockedMultiFFTKernel<E>>,
 ) -> Result<PrecomputationsForPolynomial<'a, E>, SynthesisError> {
     let coset_factor = E::Fr::multiplicative_generator();
@@ -50,9 +60,13 @@ pub(crate) fn get_precomputed_permutation_poly_lde_for_index<'a, E: Engine>(
 
         return Ok(PrecomputationsForPolynomial::Borrowed(p));
     } else {
-        let p = setup.permutation_polynomials[index]
-            .clone()
-            .bitreversed_lde_using_bitreversed_ntt(&worker, LDE_FACTOR, &coset_factor, fft_kern)?;
+        let p = setup.permutation_polynomials[index].bitreversed_lde_using_bitreversed_ntt_in(
+            pool,
+            &worker,
+            LDE_FACTOR,
+            &coset_factor,
+            fft_kern,
+        )?;
 
         return Ok(PrecomputationsForPolynomial::Owned(p));
     }
@@ -66,6 +80,7 @@ pub(crate) fn get_precomputed_selector_lde_for_index<'a, E: Engine>(
         &'a SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
     >,
     worker: &Worker,
+    pool: &PolynomialPool<E::Fr>,
     fft_kern: &mut Option<LockedMultiFFTKernel<E>>,
 ) -> Result<PrecomputationsForPolynomial<'a, E>, SynthesisError> {
     let coset_factor = E::Fr::multiplicative_generator();
@@ -75,9 +90,13 @@ pub(crate) fn get_precomputed_selector_lde_for_index<'a, E: Engine>(
 
         return Ok(PrecomputationsForPolynomial::Borrowed(p));
     } else {
-        let p = setup.selector_polynomials[index]
-            .clone()
-            .bitreversed_lde_using_bitreversed_ntt(&worker, LDE_FACTOR, &coset_factor, fft_kern)?;
+        let p = setup.selector_polynomials[index].bitreversed_lde_using_bitreversed_ntt_in(
+            pool,
+            &worker,
+            LDE_FACTOR,
+            &coset_factor,
+            fft_kern,
+        )?;
 
         return Ok(PrecomputationsForPolynomial::Owned(p));
     }
@@ -91,6 +110,7 @@ pub(crate) fn get_precomputed_next_step_selector_lde_for_index<'a, E: Engine>(
         &'a SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
     >,
     worker: &Worker,
+    pool: &PolynomialPool<E::Fr>,
     fft_kern: &mut Option<LockedMultiFFTKernel<E>>,
 ) -> Result<PrecomputationsForPolynomial<'a, E>, SynthesisError> {
     let coset_factor = E::Fr::multiplicative_generator();
@@ -100,9 +120,13 @@ pub(crate) fn get_precomputed_next_step_selector_lde_for_index<'a, E: Engine>(
 
         return Ok(PrecomputationsForPolynomial::Borrowed(p));
     } else {
-        let p = setup.next_step_selector_polynomials[index]
-            .clone()
-            .bitreversed_lde_using_bitreversed_ntt(&worker, LDE_FACTOR, &coset_factor, fft_kern)?;
+        let p = setup.next_step_selector_polynomials[index].bitreversed_lde_using_bitreversed_ntt_in(
+            pool,
+            &worker,
+            LDE_FACTOR,
+            &coset_factor,
+            fft_kern,
+        )?;
 
         return Ok(PrecomputationsForPolynomial::Owned(p));
     }
@@ -356,6 +380,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
     pub(crate) fn first_step_with_lagrange_form_key(
         self,
         worker: &Worker,
+        config: &ProverConfig,
         crs_vals: &Crs<E, CrsForLagrangeForm>,
     ) -> Result<
         (
@@ -394,11 +419,28 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             _marker: std::marker::PhantomData,
         };
 
-        for wire_poly in full_assignments.iter() {
-            let commitment = commit_using_raw_values(&wire_poly, &crs_vals, &worker, &mut None)?;
+        let mut log_d = 0;
+        while (1 << log_d) < required_domain_size {
+            log_d += 1;
+        }
+
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
+
+        for (i, wire_poly) in full_assignments.iter().enumerate() {
+            let (commitment, stats) =
+                commit_using_sparse_raw_values(&wire_poly, &crs_vals, &worker, &mut multiexp_kern)?;
+            info!(
+                "Wire {} commitment skipped {} zero and {} one values out of {}",
+                i, stats.num_zeros, stats.num_ones, stats.num_bases
+            );
 
             first_message.wire_commitments.push(commitment);
         }
+        drop(multiexp_kern);
 
         // now transform assignments in the polynomials
 
@@ -424,6 +466,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
     pub(crate) fn first_step_with_monomial_form_key(
         self,
         worker: &Worker,
+        config: &ProverConfig,
         crs_mons: &Crs<E, CrsForMonomialForm>,
     ) -> Result<
         (
@@ -470,7 +513,11 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             log_d += 1;
         }
         {
-            let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new(log_d, false));
+            let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new_with_config(
+                log_d,
+                false,
+                config.clone(),
+            ));
 
             let domain = Domain::<E::Fr>::new_for_size(required_domain_size as u64)?;
             let omegainv = domain.generator.inverse().unwrap();
@@ -503,7 +550,11 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         }
 
         //commit
-        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, false));
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         for as_coeffs in wire_polys_as_coefficients.iter() {
             let commitment =
@@ -543,6 +594,8 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             &SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
         >,
         worker: &Worker,
+        pool: &PolynomialPool<E::Fr>,
+        config: &ProverConfig,
     ) -> Result<
         (
             SecondPartialProverState<E, PlonkCsWidth4WithNextStepParams>,
@@ -561,7 +614,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         let mut grand_products_protos_with_gamma = {
             let mut witness_polys = Vec::with_capacity(4);
             for witness_poly in first_state.witness_polys_unpadded_values.iter() {
-                witness_polys.push(witness_poly.fast_clone(worker));
+                witness_polys.push(witness_poly.fast_clone_in(pool, worker)?);
             }
 
             witness_polys
@@ -593,9 +646,9 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
 
                 for (i, poly) in grand_products_protos_with_gamma.iter().enumerate() {
                     if i == 0 {
-                        z_1 = poly.fast_clone(worker);
+                        z_1 = poly.fast_clone_in(pool, worker)?;
                     } else {
-                        polys.push(poly.fast_clone(worker));
+                        polys.push(poly.fast_clone_in(pool, worker)?);
                     }
                 }
 
@@ -611,6 +664,9 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 p.add_assign_scaled(&worker, &domain_elements_poly_by_beta, non_res);
                 z_1.mul_assign(&worker, &p);
             }
+            for p in grand_products_proto_it.into_iter() {
+                p.into_pool(pool);
+            }
 
             z_1
         };
@@ -618,7 +674,11 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         // we take A, B, C, ... values and form (A + beta * perm_a + gamma), etc and calculate their grand product
         // fft context
         let log_d = domain.power_of_two as usize;
-        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new(log_d, false));
+        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         let mut permutation_polynomials_values_of_size_n_minus_one = vec![];
 
@@ -666,6 +726,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 // permutation polynomials
                 p.add_assign_scaled(&worker, perm.as_ref(), &beta);
                 z_2.mul_assign(&worker, &p);
+                p.into_pool(pool);
             }
             z_2.batch_inversion(&worker)?;
 
@@ -673,9 +734,10 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         };
 
         z_num.mul_assign(&worker, &z_den);
-        drop(z_den);
+        z_den.into_pool(pool);
 
         let z = z_num.calculate_shifted_grand_product(&worker)?;
+        z_num.into_pool(pool);
 
         assert!(z.size().is_power_of_two());
 
@@ -686,7 +748,11 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         drop(fft_kern);
 
         // multi-exp context
-        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, false));
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         let z_commitment =
             commit_using_monomials(&z_in_monomial_form, &crs_mons, &worker, &mut multiexp_kern)?;
@@ -720,6 +786,8 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             &SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
         >,
         worker: &Worker,
+        pool: &PolynomialPool<E::Fr>,
+        config: &ProverConfig,
     ) -> Result<
         (
             ThirdPartialProverState<E, PlonkCsWidth4WithNextStepParams>,
@@ -729,10 +797,6 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
     > {
         let z_in_monomial_form = second_state.z_in_monomial_form;
 
-        // those are z(x*Omega) formally
-        let mut z_shifted_in_monomial_form = z_in_monomial_form.fast_clone(worker);
-        z_shifted_in_monomial_form.distribute_powers(&worker, z_in_monomial_form.omega);
-
         // now we have to LDE everything and compute quotient polynomial
         // also to save on openings that we will have to do from the monomial form anyway
 
@@ -748,76 +812,114 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         }
 
         //fft kernel
-        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new(log_d, false));
-
-        let mut witness_ldes_on_coset = vec![];
-        let mut witness_next_ldes_on_coset = vec![];
-
-        for (idx, monomial) in witness_polys_in_monomial_form.iter().enumerate() {
-            // this is D polynomial and we need to make next
-            if idx
-                == <PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<E>>::STATE_WIDTH
-                    - 1
-            {
-                let mut d_next = monomial.fast_clone(worker);
-                d_next.distribute_powers(&worker, d_next.omega);
-                //disorder
-                let lde = d_next.bitreversed_lde_using_bitreversed_ntt(
-                    &worker,
-                    LDE_FACTOR,
-                    &coset_factor,
-                    &mut fft_kern,
-                )?;
-
-                witness_next_ldes_on_coset.push(lde);
-            }
-            //disorder
-            let lde = monomial
-                .fast_clone(worker)
-                .bitreversed_lde_using_bitreversed_ntt(
-                    &worker,
-                    LDE_FACTOR,
-                    &coset_factor,
-                    &mut fft_kern,
-                )?;
-            witness_ldes_on_coset.push(lde);
-        }
+        let mut fft_kern = Some(LockedMultiFFTKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         let SecondVerifierMessage {
             alpha, beta, gamma, ..
         } = second_verifier_message;
 
-        // calculate first part of the quotient polynomial - the gate itself
-        // A + B + C + D + AB + CONST + D_NEXT == 0 everywhere but on the last point of the domain
+        if let Some(memory_limit) = config.memory_limit {
+            let input_values = second_state.input_values;
 
-        let mut quotient_linearization_challenge = E::Fr::one();
-        let input_values = second_state.input_values;
-        let (mut t_1, mut tmp) = {
-            // Include the public inputs
             let mut inputs_poly =
                 Polynomial::<E, Values>::new_for_size(required_domain_size, worker)?;
             for (idx, &input) in input_values.iter().enumerate() {
                 inputs_poly.as_mut()[idx] = input;
             }
-            // go into monomial form
-
             let mut inputs_poly = inputs_poly.ifft(&worker, &mut fft_kern);
-
-            // add constants selectors vector
             inputs_poly.add_assign(&worker, setup.selector_polynomials.last().unwrap());
 
-            // LDE, disorder
-            let mut t_1 = inputs_poly.bitreversed_lde_using_bitreversed_ntt(
+            let l_0 = calculate_lagrange_poly::<E>(
+                &worker,
+                required_domain_size.next_power_of_two(),
+                0,
+                &mut fft_kern,
+            )?;
+            drop(fft_kern);
+
+            let non_residues = make_non_residues::<E::Fr>(
+                <PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<E>>::STATE_WIDTH - 1,
+            );
+
+            let t_poly_parts = super::streaming_quotient::compute_quotient_parts_streaming(
+                &witness_polys_in_monomial_form,
+                &z_in_monomial_form,
+                &inputs_poly,
+                &l_0,
+                setup,
+                setup_precomputations,
+                &non_residues,
+                alpha,
+                beta,
+                gamma,
+                memory_limit,
+                worker,
+            )?;
+
+            let state = ThirdPartialProverState::<E, PlonkCsWidth4WithNextStepParams> {
+                required_domain_size,
+                non_residues: second_state.non_residues,
+                input_values,
+                witness_polys_as_coeffs: witness_polys_in_monomial_form,
+                z_in_monomial_form,
+                t_poly_parts,
+
+                _marker: std::marker::PhantomData,
+            };
+
+            return Self::commit_to_quotient_parts(state, crs_mons, log_d, worker, config);
+        }
+
+        // public inputs with the constant selector, the wires and D(X*Omega) are all
+        // needed at the same time, so their LDEs are made in one batch
+
+        let input_values = second_state.input_values;
+        let mut inputs_poly = Polynomial::<E, Values>::new_for_size(required_domain_size, worker)?;
+        for (idx, &input) in input_values.iter().enumerate() {
+            inputs_poly.as_mut()[idx] = input;
+        }
+        // go into monomial form
+        let mut inputs_poly = inputs_poly.ifft(&worker, &mut fft_kern);
+        // add constants selectors vector
+        inputs_poly.add_assign(&worker, setup.selector_polynomials.last().unwrap());
+
+        // this is D polynomial and we need to make next
+        let mut d_next = witness_polys_in_monomial_form
+            [<PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<E>>::STATE_WIDTH - 1]
+            .fast_clone_in(pool, worker)?;
+        d_next.distribute_powers(&worker, d_next.omega);
+
+        let mut witness_ldes_on_coset = {
+            let mut polys: Vec<_> = witness_polys_in_monomial_form.iter().collect();
+            polys.push(&d_next);
+            polys.push(&inputs_poly);
+            //disorder
+            Polynomial::bitreversed_lde_using_bitreversed_ntt_batch_in(
+                &polys,
+                pool,
                 &worker,
                 LDE_FACTOR,
                 &coset_factor,
                 &mut fft_kern,
-            )?;
+            )?
+        };
+        d_next.into_pool(pool);
 
-            // t_1 is now q_constant
+        // t_1 is now q_constant
+        let mut t_1 = witness_ldes_on_coset.pop().unwrap();
+        let d_next_lde_on_coset = witness_ldes_on_coset.pop().unwrap();
 
+        // calculate first part of the quotient polynomial - the gate itself
+        // A + B + C + D + AB + CONST + D_NEXT == 0 everywhere but on the last point of the domain
+
+        let mut quotient_linearization_challenge = E::Fr::one();
+        let mut tmp = {
             // Q_A * A
-            let mut tmp = witness_ldes_on_coset[0].fast_clone(worker);
+            let mut tmp = witness_ldes_on_coset[0].fast_clone_in(pool, worker)?;
             //disorder
             let a_selector = get_precomputed_selector_lde_for_index(
                 0,
@@ -825,12 +927,12 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &setup,
                 &setup_precomputations,
                 &worker,
+                pool,
                 &mut fft_kern,
             )?;
             tmp.mul_assign(&worker, &a_selector.as_ref());
             t_1.add_assign(&worker, &tmp);
-
-            drop(a_selector);
+            a_selector.into_pool(pool);
 
             // Q_B * B
             tmp.reuse_allocation_parallel(&worker, &witness_ldes_on_coset[1]);
@@ -841,11 +943,12 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &setup,
                 &setup_precomputations,
                 &worker,
+                pool,
                 &mut fft_kern,
             )?;
             tmp.mul_assign(&worker, &b_selector.as_ref());
             t_1.add_assign(&worker, &tmp);
-            drop(b_selector);
+            b_selector.into_pool(pool);
 
             // Q_C * C
             tmp.reuse_allocation_parallel(&worker, &witness_ldes_on_coset[2]);
@@ -856,11 +959,12 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &setup,
                 &setup_precomputations,
                 &worker,
+                pool,
                 &mut fft_kern,
             )?;
             tmp.mul_assign(&worker, c_selector.as_ref());
             t_1.add_assign(&worker, &tmp);
-            drop(c_selector);
+            c_selector.into_pool(pool);
 
             // Q_D * D
             tmp.reuse_allocation_parallel(&worker, &witness_ldes_on_coset[3]);
@@ -871,11 +975,12 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &setup,
                 &setup_precomputations,
                 &worker,
+                pool,
                 &mut fft_kern,
             )?;
             tmp.mul_assign(&worker, d_selector.as_ref());
             t_1.add_assign(&worker, &tmp);
-            drop(d_selector);
+            d_selector.into_pool(pool);
 
             // Q_M * A * B
             tmp.reuse_allocation_parallel(&worker, &witness_ldes_on_coset[0]);
@@ -887,13 +992,14 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &setup,
                 &setup_precomputations,
                 &worker,
+                pool,
                 &mut fft_kern,
             )?;
             tmp.mul_assign(&worker, &m_selector.as_ref());
             t_1.add_assign(&worker, &tmp);
-            drop(m_selector);
+            m_selector.into_pool(pool);
 
-            tmp.reuse_allocation_parallel(&worker, &witness_next_ldes_on_coset[0]);
+            tmp.reuse_allocation_parallel(&worker, &d_next_lde_on_coset);
             //disorder
             let d_next_selector = get_precomputed_next_step_selector_lde_for_index(
                 0,
@@ -901,42 +1007,43 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &setup,
                 &setup_precomputations,
                 &worker,
+                pool,
                 &mut fft_kern,
             )?;
             tmp.mul_assign(&worker, d_next_selector.as_ref());
             t_1.add_assign(&worker, &tmp);
-            drop(d_next_selector);
+            d_next_selector.into_pool(pool);
 
-            (t_1, tmp)
+            tmp
         };
 
-        // drop(witness_ldes_on_coset);
-        drop(witness_next_ldes_on_coset);
+        d_next_lde_on_coset.into_pool(pool);
+
+        // now compute the permutation argument, Z and Z(X*Omega) in one batch
+
+        // those are z(x*Omega) formally
+        let mut z_shifted_in_monomial_form = z_in_monomial_form.fast_clone_in(pool, worker)?;
+        z_shifted_in_monomial_form.distribute_powers(&worker, z_in_monomial_form.omega);
 
-        // now compute the permutation argument
         //disorder
-        let z_coset_lde_bitreversed = z_in_monomial_form
-            .fast_clone(worker)
-            .bitreversed_lde_using_bitreversed_ntt(
-                &worker,
-                LDE_FACTOR,
-                &coset_factor,
-                &mut fft_kern,
-            )?;
+        let mut ldes = Polynomial::bitreversed_lde_using_bitreversed_ntt_batch_in(
+            &[&z_in_monomial_form, &z_shifted_in_monomial_form],
+            pool,
+            &worker,
+            LDE_FACTOR,
+            &coset_factor,
+            &mut fft_kern,
+        )?
+        .into_iter();
+        z_shifted_in_monomial_form.into_pool(pool);
+
+        let z_coset_lde_bitreversed = ldes.next().unwrap();
+        let z_shifted_coset_lde_bitreversed = ldes.next().unwrap();
 
         assert_eq!(
             z_coset_lde_bitreversed.size(),
             required_domain_size * LDE_FACTOR
         );
-        //disorder
-        let z_shifted_coset_lde_bitreversed = z_shifted_in_monomial_form
-            .bitreversed_lde_using_bitreversed_ntt(
-                &worker,
-                LDE_FACTOR,
-                &coset_factor,
-                &mut fft_kern,
-            )?;
-
         assert_eq!(
             z_shifted_coset_lde_bitreversed.size(),
             required_domain_size * LDE_FACTOR
@@ -955,7 +1062,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         quotient_linearization_challenge.mul_assign(&alpha);
 
         {
-            let mut contrib_z = z_coset_lde_bitreversed.fast_clone(worker);
+            let mut contrib_z = z_coset_lde_bitreversed.fast_clone_in(pool, worker)?;
 
             // A + beta*X + gamma
 
@@ -981,7 +1088,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
 
             t_1.add_assign_scaled(&worker, &contrib_z, &quotient_linearization_challenge);
 
-            drop(contrib_z);
+            contrib_z.into_pool(pool);
 
             let mut contrib_z = z_shifted_coset_lde_bitreversed;
 
@@ -995,23 +1102,28 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                     &setup,
                     &setup_precomputations,
                     &worker,
+                    pool,
                     &mut fft_kern,
                 )?;
                 tmp.reuse_allocation_parallel(&worker, &w);
                 tmp.add_constant(&worker, &gamma);
                 tmp.add_assign_scaled(&worker, perm.as_ref(), &beta);
                 contrib_z.mul_assign(&worker, &tmp);
+                perm.into_pool(pool);
             }
 
             t_1.sub_assign_scaled(&worker, &contrib_z, &quotient_linearization_challenge);
 
-            drop(contrib_z);
+            contrib_z.into_pool(pool);
+        }
+
+        for lde in witness_ldes_on_coset.into_iter() {
+            lde.into_pool(pool);
         }
 
         quotient_linearization_challenge.mul_assign(&alpha);
 
         // z(omega^0) - 1 == 0
-
         let l_0 = calculate_lagrange_poly::<E>(
             &worker,
             required_domain_size.next_power_of_two(),
@@ -1023,7 +1135,8 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             let mut z_minus_one_by_l_0 = z_coset_lde_bitreversed;
             z_minus_one_by_l_0.sub_constant(&worker, &E::Fr::one());
             //disorder
-            let l_coset_lde_bitreversed = l_0.bitreversed_lde_using_bitreversed_ntt(
+            let l_coset_lde_bitreversed = l_0.bitreversed_lde_using_bitreversed_ntt_in(
+                pool,
                 &worker,
                 LDE_FACTOR,
                 &coset_factor,
@@ -1031,6 +1144,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             )?;
 
             z_minus_one_by_l_0.mul_assign(&worker, &l_coset_lde_bitreversed);
+            l_coset_lde_bitreversed.into_pool(pool);
 
             t_1.add_assign_scaled(
                 &worker,
@@ -1038,10 +1152,10 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
                 &quotient_linearization_challenge,
             );
 
-            drop(z_minus_one_by_l_0);
+            z_minus_one_by_l_0.into_pool(pool);
         }
 
-        drop(tmp);
+        tmp.into_pool(pool);
 
         let divisor_inversed =
             get_precomputed_inverse_divisor(required_domain_size, setup_precomputations, &worker)?;
@@ -1057,6 +1171,8 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
 
         t_1.bitreverse_enumeration(&worker);
 
+        // the parts of the quotient outlive the step
+        t_1.detach_from_pool(pool);
         let t_poly_in_monomial_form = t_1.icoset_fft_for_generator(
             &worker,
             &E::Fr::multiplicative_generator(),
@@ -1077,13 +1193,33 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
             _marker: std::marker::PhantomData,
         };
 
+        Self::commit_to_quotient_parts(state, crs_mons, log_d, worker, config)
+    }
+
+    fn commit_to_quotient_parts(
+        state: ThirdPartialProverState<E, PlonkCsWidth4WithNextStepParams>,
+        crs_mons: &Crs<E, CrsForMonomialForm>,
+        log_d: usize,
+        worker: &Worker,
+        config: &ProverConfig,
+    ) -> Result<
+        (
+            ThirdPartialProverState<E, PlonkCsWidth4WithNextStepParams>,
+            ThirdProverMessage<E, PlonkCsWidth4WithNextStepParams>,
+        ),
+        SynthesisError,
+    > {
         let mut message = ThirdProverMessage::<E, PlonkCsWidth4WithNextStepParams> {
             quotient_poly_commitments: Vec::with_capacity(4),
 
             _marker: std::marker::PhantomData,
         };
 
-        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, false));
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
         for t_part in state.t_poly_parts.iter() {
             let t_part_commitment =
                 commit_using_monomials(&t_part, &crs_mons, &worker, &mut multiexp_kern)?;
@@ -1100,6 +1236,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         third_verifier_message: ThirdVerifierMessage<E, PlonkCsWidth4WithNextStepParams>,
         setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
         worker: &Worker,
+        config: &ProverConfig,
     ) -> Result<
         (
             FourthPartialProverState<E, PlonkCsWidth4WithNextStepParams>,
@@ -1374,6 +1511,7 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
         crs_mons: &Crs<E, CrsForMonomialForm>,
         worker: &Worker,
+        config: &ProverConfig,
     ) -> Result<FifthProverMessage<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
         let FourthVerifierMessage { z, v, .. } = fourth_verifier_message;
         let required_domain_size = fourth_state.required_domain_size;
@@ -1463,7 +1601,11 @@ impl<E: Engine> ProverAssembly4WithNextStep<E> {
         let open_at_z = polys.pop().unwrap().0;
 
         let log_d = domain.power_of_two as usize;
-        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, false));
+        let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new_with_config(
+            log_d,
+            false,
+            config.clone(),
+        ));
 
         let opening_at_z =
             commit_using_monomials(&open_at_z, &crs_mons, &worker, &mut multiexp_kern)?;
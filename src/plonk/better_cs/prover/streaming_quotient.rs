@@ -0,0 +1,451 @@
+//! Quotient computation that streams the coset values through disk.
+//!
+//! The LDE of size `4n` is split into the 4 cosets `s_c * <omega_n>` with
+//! `s_c = g * omega_4n^bitrev(c)`, the same chunks as in the bitreversed LDEs of the setup
+//! precomputations. On every coset the vanishing polynomial is the constant `s_c^n - 1`,
+//! so the quotient values of a coset only need the values of the same coset. Every coset
+//! is computed in blocks that fit into the memory limit and interpolated into
+//! `R_c(X) = t(X) mod (X^n - s_c^n) = sum_m s_c^(n * m) * t_m(X)`. The 4 residues are
+//! then combined into the parts `t_m` of the quotient, which is a transform of size 4.
+
+use super::*;
+use crate::plonk::fft::cooley_tukey_ntt::four_step::FourStepNtt;
+use crate::plonk::polynomials::disk::DiskPolynomial;
+
+/// Smallest block of values, regardless of the memory limit.
+const MIN_BLOCK_SIZE: usize = 1 << 10;
+
+const A: usize = 0;
+const D: usize = 3;
+const D_NEXT: usize = 4;
+const Z: usize = 5;
+const Z_SHIFTED: usize = 6;
+const INPUTS: usize = 7;
+const L_0: usize = 8;
+const SELECTORS: usize = 9;
+const Q_M: usize = SELECTORS + 4;
+const Q_D_NEXT: usize = SELECTORS + 5;
+const SIGMAS: usize = SELECTORS + 6;
+const NUM_STREAMS: usize = SIGMAS + 4;
+
+/// Values of a polynomial on one coset, either a chunk of a precomputed LDE or a
+/// transform on disk.
+enum CosetValues<'a, F: PrimeField> {
+    Borrowed(&'a [F]),
+    OnDisk(DiskPolynomial<F>),
+}
+
+impl<'a, F: PrimeField> CosetValues<'a, F> {
+    fn read_block(&mut self, offset: usize, dst: &mut [F]) -> Result<(), SynthesisError> {
+        match self {
+            CosetValues::Borrowed(values) => {
+                dst.copy_from_slice(&values[offset..(offset + dst.len())]);
+
+                Ok(())
+            }
+            CosetValues::OnDisk(poly) => poly.read_chunk(offset, dst),
+        }
+    }
+}
+
+/// Multiplies `block[i]` by `first_power * base^i`.
+fn distribute_powers_from<F: PrimeField>(block: &mut [F], first_power: F, base: F, worker: &Worker) {
+    worker.scope(block.len(), |scope, chunk| {
+        for (i, v) in block.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                let mut power = base.pow(&[(i * chunk) as u64]);
+                power.mul_assign(&first_power);
+                for v in v.iter_mut() {
+                    v.mul_assign(&power);
+                    power.mul_assign(&base);
+                }
+            });
+        }
+    });
+}
+
+struct CosetEvaluator<'w, F: PrimeField> {
+    size: usize,
+    block_size: usize,
+    memory_limit: usize,
+    ntt: FourStepNtt<F, BitReversedOmegas<F>>,
+    intt: FourStepNtt<F, OmegasInvBitreversed<F>>,
+    input: DiskPolynomial<F>,
+    scratch: DiskPolynomial<F>,
+    worker: &'w Worker,
+}
+
+impl<'w, F: PrimeField> CosetEvaluator<'w, F> {
+    fn new(size: usize, memory_limit: usize, worker: &'w Worker) -> Result<Self, SynthesisError> {
+        let block_size = memory_limit / (std::mem::size_of::<F>() * (NUM_STREAMS + 1));
+        let block_size = std::cmp::min(std::cmp::max(block_size, MIN_BLOCK_SIZE), size);
+
+        Ok(Self {
+            size,
+            block_size,
+            memory_limit,
+            ntt: FourStepNtt::new_for_size(size, worker)?,
+            intt: FourStepNtt::new_inverse_for_size(size, worker)?,
+            input: DiskPolynomial::new_for_size(size)?,
+            scratch: DiskPolynomial::new_for_size(size)?,
+            worker,
+        })
+    }
+
+    /// Values of the polynomial with the coefficients `coeffs` on `shift * <omega_n>`.
+    fn evaluate(&mut self, coeffs: &[F], shift: F) -> Result<DiskPolynomial<F>, SynthesisError> {
+        assert_eq!(coeffs.len(), self.size);
+
+        let mut block = vec![F::zero(); self.block_size];
+        for (i, c) in coeffs.chunks(self.block_size).enumerate() {
+            let offset = i * self.block_size;
+            let block = &mut block[..c.len()];
+            block.copy_from_slice(c);
+            distribute_powers_from(block, shift.pow(&[offset as u64]), shift, self.worker);
+            self.input.write_chunk(offset, block)?;
+        }
+
+        let mut values = DiskPolynomial::new_for_size(self.size)?;
+        self.ntt.transform(&mut self.input, &mut self.scratch, &mut values, self.memory_limit, self.worker)?;
+
+        Ok(values)
+    }
+
+    /// `n` times the coefficients of `R(X * shift)` for the values `values` of `R` on
+    /// `shift * <omega_n>`.
+    fn interpolate(&mut self, values: &mut DiskPolynomial<F>) -> Result<DiskPolynomial<F>, SynthesisError> {
+        let mut coeffs = DiskPolynomial::new_for_size(self.size)?;
+        self.intt.transform(values, &mut self.scratch, &mut coeffs, self.memory_limit, self.worker)?;
+
+        Ok(coeffs)
+    }
+}
+
+/// Parts of the quotient polynomial of the width 4 Plonk with the next step, each of
+/// size `n`, computed with at most about `memory_limit` bytes of coset values in memory.
+///
+/// The limit only bounds the coset values and the blocks read from disk. The monomials of
+/// the witness, `z`, the inputs and `l_0`, the precomputed setup LDEs if given and the 4
+/// resulting parts of size `n` stay in memory in full.
+pub(crate) fn compute_quotient_parts_streaming<E: Engine>(
+    witness_polys: &[Polynomial<E, Coefficients>],
+    z_poly: &Polynomial<E, Coefficients>,
+    inputs_poly: &Polynomial<E, Coefficients>,
+    l_0: &Polynomial<E, Coefficients>,
+    setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    setup_precomputations: &Option<&SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>>,
+    non_residues: &[E::Fr],
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    memory_limit: usize,
+    worker: &Worker,
+) -> Result<Vec<Polynomial<E, Coefficients>>, SynthesisError> {
+    let n = z_poly.size();
+    assert_eq!(witness_polys.len(), 4);
+    assert_eq!(non_residues.len(), 3);
+
+    let domain = Domain::<E::Fr>::new_for_size(n as u64)?;
+    let lde_domain = Domain::<E::Fr>::new_for_size((n * LDE_FACTOR) as u64)?;
+    let omega = domain.generator;
+    let coset_factor = E::Fr::multiplicative_generator();
+
+    let mut evaluator = CosetEvaluator::new(n, memory_limit, worker)?;
+    let block_size = evaluator.block_size;
+
+    let mut alpha_squared = alpha;
+    alpha_squared.square();
+    // beta * k_i for the copies of X
+    let mut beta_non_residues = vec![beta];
+    for non_res in non_residues.iter() {
+        let mut tmp = beta;
+        tmp.mul_assign(non_res);
+        beta_non_residues.push(tmp);
+    }
+
+    let mut residues = Vec::with_capacity(LDE_FACTOR);
+    let mut coset_shifts = Vec::with_capacity(LDE_FACTOR);
+
+    for coset_idx in 0..LDE_FACTOR {
+        let mut shift = lde_domain.generator.pow(&[bitreverse(coset_idx, 2) as u64]);
+        shift.mul_assign(&coset_factor);
+        let mut shift_next = shift;
+        shift_next.mul_assign(&omega);
+
+        let mut streams = Vec::with_capacity(NUM_STREAMS);
+        for w in witness_polys.iter() {
+            streams.push(CosetValues::OnDisk(evaluator.evaluate(w.as_ref(), shift)?));
+        }
+        streams.push(CosetValues::OnDisk(evaluator.evaluate(witness_polys[D].as_ref(), shift_next)?));
+        streams.push(CosetValues::OnDisk(evaluator.evaluate(z_poly.as_ref(), shift)?));
+        streams.push(CosetValues::OnDisk(evaluator.evaluate(z_poly.as_ref(), shift_next)?));
+        streams.push(CosetValues::OnDisk(evaluator.evaluate(inputs_poly.as_ref(), shift)?));
+        streams.push(CosetValues::OnDisk(evaluator.evaluate(l_0.as_ref(), shift)?));
+
+        // selectors A, B, C, D, M, D_NEXT and the permutations
+        let chunk = (coset_idx * n)..((coset_idx + 1) * n);
+        match setup_precomputations {
+            Some(prec) => {
+                let precomputed = prec.selector_polynomials_on_coset_of_size_4n_bitreversed[..5]
+                    .iter()
+                    .chain(prec.next_step_selector_polynomials_on_coset_of_size_4n_bitreversed[..1].iter())
+                    .chain(prec.permutation_polynomials_on_coset_of_size_4n_bitreversed[..4].iter());
+                for p in precomputed {
+                    streams.push(CosetValues::Borrowed(&p.as_ref()[chunk.clone()]));
+                }
+            }
+            None => {
+                let setup_polys = setup.selector_polynomials[..5]
+                    .iter()
+                    .chain(setup.next_step_selector_polynomials[..1].iter())
+                    .chain(setup.permutation_polynomials[..4].iter());
+                for p in setup_polys {
+                    streams.push(CosetValues::OnDisk(evaluator.evaluate(p.as_ref(), shift)?));
+                }
+            }
+        }
+        assert_eq!(streams.len(), NUM_STREAMS);
+
+        // Z_H(x) = s^n - 1 on the whole coset
+        let mut divisor_inversed = shift.pow(&[n as u64]);
+        divisor_inversed.sub_assign(&E::Fr::one());
+        let divisor_inversed = divisor_inversed.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+        let mut quotient = DiskPolynomial::new_for_size(n)?;
+        let mut blocks = vec![vec![E::Fr::zero(); block_size]; NUM_STREAMS];
+        let mut result = vec![E::Fr::zero(); block_size];
+
+        for offset in (0..n).step_by(block_size) {
+            let len = std::cmp::min(block_size, n - offset);
+            for (stream, block) in streams.iter_mut().zip(blocks.iter_mut()) {
+                stream.read_block(offset, &mut block[..len])?;
+            }
+
+            let blocks = &blocks;
+            let beta_non_residues = &beta_non_residues;
+            worker.scope(len, |scope, chunk| {
+                for (i, r) in result[..len].chunks_mut(chunk).enumerate() {
+                    scope.spawn(move |_| {
+                        let start = i * chunk;
+                        let mut x = omega.pow(&[(offset + start) as u64]);
+                        x.mul_assign(&shift);
+
+                        for (j, r) in r.iter_mut().enumerate() {
+                            let v = |stream: usize| blocks[stream][start + j];
+
+                            // gate with the public inputs and the constant selector
+                            let mut gate = v(INPUTS);
+                            for w in A..=D {
+                                let mut tmp = v(SELECTORS + w);
+                                tmp.mul_assign(&v(w));
+                                gate.add_assign(&tmp);
+                            }
+                            let mut tmp = v(Q_M);
+                            tmp.mul_assign(&v(A));
+                            tmp.mul_assign(&v(A + 1));
+                            gate.add_assign(&tmp);
+                            let mut tmp = v(Q_D_NEXT);
+                            tmp.mul_assign(&v(D_NEXT));
+                            gate.add_assign(&tmp);
+
+                            // grand product
+                            let mut lhs = v(Z);
+                            let mut rhs = v(Z_SHIFTED);
+                            for w in A..=D {
+                                let mut tmp = x;
+                                tmp.mul_assign(&beta_non_residues[w]);
+                                tmp.add_assign(&gamma);
+                                tmp.add_assign(&v(w));
+                                lhs.mul_assign(&tmp);
+
+                                let mut tmp = v(SIGMAS + w);
+                                tmp.mul_assign(&beta);
+                                tmp.add_assign(&gamma);
+                                tmp.add_assign(&v(w));
+                                rhs.mul_assign(&tmp);
+                            }
+                            lhs.sub_assign(&rhs);
+                            lhs.mul_assign(&alpha);
+                            gate.add_assign(&lhs);
+
+                            // z(omega^0) == 1
+                            let mut tmp = v(Z);
+                            tmp.sub_assign(&E::Fr::one());
+                            tmp.mul_assign(&v(L_0));
+                            tmp.mul_assign(&alpha_squared);
+                            gate.add_assign(&tmp);
+
+                            gate.mul_assign(&divisor_inversed);
+                            *r = gate;
+
+                            x.mul_assign(&omega);
+                        }
+                    });
+                }
+            });
+
+            quotient.write_chunk(offset, &result[..len])?;
+        }
+
+        drop(streams);
+
+        residues.push(evaluator.interpolate(&mut quotient)?);
+        coset_shifts.push(shift);
+    }
+
+    // R_c(X) = sum_m (g^n)^m * omega_4^(bitrev(c) * m) * t_m(X), so
+    // t_m = 1/4 * (g^n)^(-m) * sum_c omega_4^(-bitrev(c) * m) * R_c, where the residues
+    // are stored as n * R_c(X * s_c)
+    let omega_4_inv = lde_domain.generator.pow(&[n as u64]).inverse().expect("must exist");
+    let g_n_inv = coset_factor.pow(&[n as u64]).inverse().expect("must exist");
+    let mut normalization = E::Fr::from_str(&(n * LDE_FACTOR).to_string()).expect("must fit");
+    normalization = normalization.inverse().expect("must exist");
+
+    let mut weights = vec![vec![E::Fr::zero(); LDE_FACTOR]; LDE_FACTOR];
+    for (m, w) in weights.iter_mut().enumerate() {
+        for (coset_idx, w) in w.iter_mut().enumerate() {
+            let power = (bitreverse(coset_idx, 2) * m) % LDE_FACTOR;
+            *w = omega_4_inv.pow(&[power as u64]);
+            w.mul_assign(&g_n_inv.pow(&[m as u64]));
+            w.mul_assign(&normalization);
+        }
+    }
+    let shifts_inv: Vec<E::Fr> = coset_shifts
+        .iter()
+        .map(|s| s.inverse().expect("must exist"))
+        .collect();
+
+    let mut parts = vec![vec![E::Fr::zero(); n]; LDE_FACTOR];
+    let mut blocks = vec![vec![E::Fr::zero(); block_size]; LDE_FACTOR];
+    for offset in (0..n).step_by(block_size) {
+        let len = std::cmp::min(block_size, n - offset);
+        for ((residue, block), shift_inv) in residues.iter_mut().zip(blocks.iter_mut()).zip(shifts_inv.iter()) {
+            let block = &mut block[..len];
+            residue.read_chunk(offset, block)?;
+            distribute_powers_from(block, shift_inv.pow(&[offset as u64]), *shift_inv, worker);
+        }
+
+        for (part, w) in parts.iter_mut().zip(weights.iter()) {
+            let part = &mut part[offset..(offset + len)];
+            let blocks = &blocks;
+            worker.scope(len, |scope, chunk| {
+                for (i, p) in part.chunks_mut(chunk).enumerate() {
+                    scope.spawn(move |_| {
+                        for (j, p) in p.iter_mut().enumerate() {
+                            let idx = i * chunk + j;
+                            for (block, w) in blocks.iter().zip(w.iter()) {
+                                let mut tmp = block[idx];
+                                tmp.mul_assign(w);
+                                p.add_assign(&tmp);
+                            }
+                        }
+                    });
+                }
+            });
+        }
+    }
+
+    parts
+        .into_iter()
+        .map(Polynomial::<E, Coefficients>::from_coeffs)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::ProverConfig;
+    use crate::kate_commitment::*;
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::{Field, PrimeField};
+    use crate::plonk::better_cs::cs::*;
+    use crate::plonk::better_cs::generator::GeneratorAssembly4WithNextStep;
+    use crate::plonk::better_cs::keys::SetupPolynomialsPrecomputations;
+    use crate::plonk::better_cs::verifier::verify;
+    use crate::plonk::commitments::transcript::keccak_transcript::RollingKeccakTranscript;
+    use crate::plonk::{make_verification_key_with_config, prove_native_by_steps_with_config};
+    use crate::worker::Worker;
+    use crate::SynthesisError;
+
+    /// Chain of multiplications, long enough for the domain to span several blocks.
+    struct MulChain(usize);
+
+    impl Circuit<Bn256, PlonkCsWidth4WithNextStepParams> for MulChain {
+        fn synthesize<CS: ConstraintSystem<Bn256, PlonkCsWidth4WithNextStepParams>>(
+            &self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let zero = Fr::zero();
+            let one = Fr::one();
+            let mut negative_one = one;
+            negative_one.negate();
+
+            let dummy = cs.get_dummy_variable();
+
+            let mut value = Fr::from_str("3").unwrap();
+            let factor = value;
+            let mut acc = cs.alloc_input(|| Ok(value))?;
+            let b = cs.alloc(|| Ok(factor))?;
+            for _ in 0..self.0 {
+                value.mul_assign(&factor);
+                let c = cs.alloc(|| Ok(value))?;
+
+                // c - acc * b == 0
+                cs.new_gate(
+                    [acc, b, dummy, c],
+                    [zero, zero, zero, negative_one, one, zero],
+                    [zero],
+                )?;
+                acc = c;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_streaming_quotient_matches_in_memory() {
+        let worker = Worker::new();
+        let circuit = MulChain(1500);
+        let in_memory_config = ProverConfig::cpu_only();
+
+        let mut assembly = GeneratorAssembly4WithNextStep::<Bn256>::new();
+        circuit.synthesize(&mut assembly).unwrap();
+        assembly.finalize();
+        let setup = assembly.setup_with_config(&worker, &in_memory_config).unwrap();
+        assert!(setup.n > super::MIN_BLOCK_SIZE);
+        let crs = Crs::<Bn256, CrsForMonomialForm>::crs_42(setup.permutation_polynomials[0].size(), &worker);
+        let vk = make_verification_key_with_config(&setup, &crs, &in_memory_config).unwrap();
+        let precomputations =
+            SetupPolynomialsPrecomputations::from_setup_with_config(&setup, &worker, &in_memory_config).unwrap();
+
+        let expected = prove_native_by_steps_with_config::<_, _, RollingKeccakTranscript<Fr>>(
+            &circuit,
+            &setup,
+            None,
+            &crs,
+            None,
+            &in_memory_config,
+        )
+        .unwrap();
+        assert!(verify::<_, _, RollingKeccakTranscript<Fr>>(&expected, &vk, None).unwrap());
+
+        // a limit below the minimal block makes every pass use several blocks
+        for memory_limit in [0, usize::max_value()].iter() {
+            let config = ProverConfig::cpu_only().with_memory_limit(*memory_limit);
+            for precomputations in [None, Some(&precomputations)].iter() {
+                let proof = prove_native_by_steps_with_config::<_, _, RollingKeccakTranscript<Fr>>(
+                    &circuit,
+                    &setup,
+                    *precomputations,
+                    &crs,
+                    None,
+                    &config,
+                )
+                .unwrap();
+
+                assert_eq!(proof.quotient_poly_commitments, expected.quotient_poly_commitments);
+                assert!(verify::<_, _, RollingKeccakTranscript<Fr>>(&proof, &vk, None).unwrap());
+            }
+        }
+    }
+}
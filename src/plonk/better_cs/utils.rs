@@ -2,11 +2,15 @@ use crate::ff::{Field, PrimeField};
 use crate::pairing::{CurveAffine, Engine};
 use crate::plonk::commitments::transcript::Transcript;
 use crate::plonk::domains::Domain;
+#[cfg(not(feature = "verifier-only"))]
+use crate::locks::LockedMultiFFTKernel;
+#[cfg(not(feature = "verifier-only"))]
 use crate::plonk::polynomials::*;
+#[cfg(not(feature = "verifier-only"))]
 use crate::worker::Worker;
-use crate::locks::LockedMultiFFTKernel;
 use crate::SynthesisError;
 
+#[cfg(not(feature = "verifier-only"))]
 pub(crate) fn calculate_inverse_vanishing_polynomial_in_a_coset<E: Engine>(
     worker: &Worker,
     poly_size: usize,
@@ -84,6 +88,7 @@ pub(crate) fn evaluate_inverse_vanishing_poly_with_last_point_cut<F: PrimeField>
     numerator
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub(crate) fn calculate_lagrange_poly<E: Engine>(
     worker: &Worker,
     poly_size: usize,
@@ -98,6 +103,7 @@ pub(crate) fn calculate_lagrange_poly<E: Engine>(
     Ok(poly.ifft(&worker, fft_kern))
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub(crate) fn evaluate_vanishing_polynomial_of_degree_on_domain_size<E: Engine>(
     vanishing_degree: u64,
     coset_factor: &E::Fr,
@@ -190,21 +196,27 @@ pub(crate) fn evaluate_lagrange_poly_at_point<F: PrimeField>(
 }
 
 use crate::ff::SqrtField;
+#[cfg(not(feature = "verifier-only"))]
 use crate::plonk::utils::fast_initialize_to_element;
 
 pub fn make_non_residues<F: PrimeField + SqrtField>(num: usize) -> Vec<F> {
-    // create largest domain possible
-    assert!(F::S < 63);
-    let domain_size = 1u64 << (F::S as u64);
+    // create largest domain possible, its size 2^S does not have to fit into u64
+    let mut domain_size = vec![0u64; (F::S as usize) / 64 + 1];
+    domain_size[(F::S as usize) / 64] = 1u64 << (F::S % 64);
 
-    let domain = Domain::<F>::new_for_size(domain_size).expect("largest domain must exist");
-
-    make_non_residues_for_domain(num, &domain)
+    make_non_residues_for_domain_size(num, &domain_size)
 }
 
 pub fn make_non_residues_for_domain<F: PrimeField + SqrtField>(
     num: usize,
     domain: &Domain<F>,
+) -> Vec<F> {
+    make_non_residues_for_domain_size(num, &[domain.size])
+}
+
+fn make_non_residues_for_domain_size<F: PrimeField + SqrtField>(
+    num: usize,
+    domain_size: &[u64],
 ) -> Vec<F> {
     use crate::ff::LegendreSymbol;
 
@@ -223,14 +235,14 @@ pub fn make_non_residues_for_domain<F: PrimeField + SqrtField>(
                 let mut is_unique = true;
                 {
                     // first pow into the domain size
-                    let tmp = current.pow(&[domain.size]);
+                    let tmp = current.pow(domain_size);
                     // then check: if it's in some other coset, then
                     // X^N == other_k ^ N
                     for t in Some(one).iter().chain(non_residues.iter()) {
                         if !is_unique {
                             break;
                         }
-                        let t_in_domain_size = t.pow(&[domain.size]);
+                        let t_in_domain_size = t.pow(domain_size);
                         if tmp == t_in_domain_size {
                             is_unique = false;
                         }
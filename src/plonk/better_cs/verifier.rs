@@ -2,8 +2,6 @@ use crate::pairing::ff::{Field, PrimeField};
 use crate::pairing::Engine;
 
 use crate::plonk::domains::*;
-use crate::plonk::polynomials::*;
-use crate::worker::Worker;
 use crate::SynthesisError;
 
 use std::marker::PhantomData;
@@ -11,10 +9,7 @@ use std::marker::PhantomData;
 use super::cs::*;
 use super::keys::{Proof, VerificationKey};
 
-use crate::source::{DensityTracker, DensityTrackerersChain};
-
 use super::utils::*;
-use crate::kate_commitment::*;
 
 use crate::plonk::commitments::transcript::*;
 
@@ -0,0 +1,414 @@
+//! Arithmetization friendly hashes over a prime field.
+//!
+//! Both the Rescue and the Poseidon permutation are generic over `PrimeField`, their
+//! parameters are derived the same way as by the reference implementations: Rescue-Prime
+//! (`rescue_prime.sage`) expands the round constants with SHAKE256 and takes the MDS matrix
+//! from a Vandermonde matrix, Poseidon (`generate_parameters_grain.sage`) draws both the
+//! round constants and a Cauchy MDS matrix from the Grain LFSR. The S-box power is the
+//! smallest `alpha` such that `x^alpha` is a permutation of the field.
+
+use crate::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub mod poseidon;
+pub mod rescue;
+
+pub use self::poseidon::PoseidonParams;
+pub use self::rescue::RescueParams;
+
+pub trait AlgebraicPermutation<F: PrimeField>:
+    Clone + PartialEq + Eq + std::fmt::Debug + Send + Sync
+{
+    fn state_width(&self) -> usize;
+
+    /// The last element of the state is the capacity.
+    fn rate(&self) -> usize {
+        self.state_width() - 1
+    }
+
+    fn permute(&self, state: &mut [F]);
+}
+
+/// Permutations with default parameters shared by the whole process, for the trees of the
+/// trivial IOP that hash through static methods.
+pub trait SharedParams<F: PrimeField>: AlgebraicPermutation<F> {
+    fn shared() -> Arc<Self>;
+}
+
+/// Computes the parameters of type `P` once and hands out the same copy afterwards.
+pub(crate) fn shared_params<P: Send + Sync + 'static>(make: impl FnOnce() -> P) -> Arc<P> {
+    lazy_static! {
+        static ref SHARED_PARAMS: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>> =
+            Mutex::new(HashMap::new());
+    }
+
+    let mut shared = SHARED_PARAMS.lock().expect("is not poisoned");
+    let params = shared
+        .entry(TypeId::of::<P>())
+        .or_insert_with(|| Arc::new(make()) as Arc<dyn Any + Send + Sync>)
+        .clone();
+
+    params.downcast::<P>().expect("parameters are stored by their type")
+}
+
+/// Sponge hash of `input`, the capacity is initialized with the input length.
+pub fn sponge_hash<F: PrimeField, P: AlgebraicPermutation<F>>(params: &P, input: &[F]) -> F {
+    let mut state = vec![F::zero(); params.state_width()];
+    *state.last_mut().expect("state is not empty") =
+        F::from_str(&input.len().to_string()).expect("length fits into the field");
+
+    if input.is_empty() {
+        params.permute(&mut state);
+    }
+
+    for chunk in input.chunks(params.rate()) {
+        for (s, el) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(el);
+        }
+        params.permute(&mut state);
+    }
+
+    state[0]
+}
+
+/// Two-to-one compression for the Merkle tree nodes. The capacity is initialized with
+/// `-1`, which is never a valid input length of `sponge_hash`.
+pub fn compress<F: PrimeField, P: AlgebraicPermutation<F>>(params: &P, left: &F, right: &F) -> F {
+    assert!(params.rate() >= 2);
+
+    let mut state = vec![F::zero(); params.state_width()];
+    state[0] = *left;
+    state[1] = *right;
+    let mut minus_one = F::one();
+    minus_one.negate();
+    *state.last_mut().expect("state is not empty") = minus_one;
+
+    params.permute(&mut state);
+
+    state[0]
+}
+
+/// Duplex sponge for transcripts: absorbs values one by one and squeezes challenges.
+/// The sponge owns its parameters, so transcripts built on it carry no lifetime.
+#[derive(Clone)]
+pub struct StatefulSponge<F: PrimeField, P: AlgebraicPermutation<F>> {
+    params: P,
+    state: Vec<F>,
+    absorbed: usize,
+    squeezed: Option<usize>,
+}
+
+impl<F: PrimeField, P: AlgebraicPermutation<F>> StatefulSponge<F, P> {
+    pub fn new(params: P) -> Self {
+        let state = vec![F::zero(); params.state_width()];
+
+        Self {
+            params,
+            state,
+            absorbed: 0,
+            squeezed: None,
+        }
+    }
+
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+
+    pub fn absorb_single_value(&mut self, value: F) {
+        if self.squeezed.take().is_some() {
+            self.absorbed = 0;
+        }
+
+        self.state[self.absorbed].add_assign(&value);
+        self.absorbed += 1;
+
+        if self.absorbed == self.params.rate() {
+            self.params.permute(&mut self.state);
+            self.absorbed = 0;
+        }
+    }
+
+    pub fn absorb(&mut self, values: &[F]) {
+        for v in values.iter() {
+            self.absorb_single_value(*v);
+        }
+    }
+
+    /// Absorbs the length and then the bytes packed big endian into `F::CAPACITY / 8`
+    /// byte chunks.
+    pub fn absorb_bytes(&mut self, bytes: &[u8]) {
+        let repr_size = F::Repr::default().as_ref().len() * 8;
+        let chunk_size = (F::CAPACITY / 8) as usize;
+
+        self.absorb_single_value(
+            F::from_str(&bytes.len().to_string()).expect("length fits into the field"),
+        );
+
+        let mut buffer = vec![0u8; repr_size];
+        for chunk in bytes.chunks(chunk_size) {
+            for b in buffer.iter_mut() {
+                *b = 0;
+            }
+            buffer[(repr_size - chunk.len())..].copy_from_slice(chunk);
+
+            let mut repr = F::Repr::default();
+            repr.read_be(&buffer[..]).expect("will read");
+            self.absorb_single_value(F::from_repr(repr).expect("chunk is below the modulus"));
+        }
+    }
+
+    /// Absorbs an element of another prime field (e.g. a curve base field) as its
+    /// canonical representation split into 128 bit parts, lowest first.
+    pub fn absorb_foreign_field_element<FF: PrimeField>(&mut self, element: &FF) {
+        assert!(F::CAPACITY >= 128);
+
+        let repr = element.into_repr();
+        for limbs in repr.as_ref().chunks(2) {
+            let mut part = F::Repr::default();
+            part.as_mut()[..limbs.len()].copy_from_slice(limbs);
+            self.absorb_single_value(F::from_repr(part).expect("128 bits are below the modulus"));
+        }
+    }
+
+    pub fn squeeze_out_single(&mut self) -> F {
+        let idx = match self.squeezed {
+            None => {
+                // pad the absorbed values with a single one
+                self.state[self.absorbed].add_assign(&F::one());
+                self.params.permute(&mut self.state);
+
+                0
+            }
+            Some(idx) if idx == self.params.rate() => {
+                self.params.permute(&mut self.state);
+
+                0
+            }
+            Some(idx) => idx,
+        };
+
+        self.squeezed = Some(idx + 1);
+
+        self.state[idx]
+    }
+
+    /// Squeezes a single element and returns its canonical big endian encoding.
+    pub fn squeeze_out_bytes(&mut self) -> Vec<u8> {
+        let value = self.squeeze_out_single();
+        let mut bytes = vec![0u8; F::Repr::default().as_ref().len() * 8];
+        value.into_repr().write_be(&mut bytes[..]).expect("will write");
+
+        bytes
+    }
+}
+
+fn modulus_minus_one_rem<F: PrimeField>(divisor: u64) -> u64 {
+    let modulus = F::char();
+    let mut rem = 0u128;
+    for limb in modulus.as_ref().iter().rev() {
+        rem = ((rem << 64) | (*limb as u128)) % (divisor as u128);
+    }
+
+    (rem as u64 + divisor - 1) % divisor
+}
+
+/// `(p - 1) / divisor` as little endian limbs, `divisor` must divide `p - 1`.
+fn modulus_minus_one_div<F: PrimeField>(divisor: u64) -> Vec<u64> {
+    let mut value = F::char().as_ref().to_vec();
+    // the modulus is odd, so there is no borrow
+    value[0] -= 1;
+
+    let mut rem = 0u128;
+    for limb in value.iter_mut().rev() {
+        let tmp = (rem << 64) | (*limb as u128);
+        *limb = (tmp / (divisor as u128)) as u64;
+        rem = tmp % (divisor as u128);
+    }
+    debug_assert_eq!(rem, 0);
+
+    value
+}
+
+/// Decimal representation of the modulus.
+pub(crate) fn modulus_decimal<F: PrimeField>() -> String {
+    let mut limbs = F::char().as_ref().to_vec();
+    let mut digits = vec![];
+    while limbs.iter().any(|l| *l != 0) {
+        let mut rem = 0u128;
+        for limb in limbs.iter_mut().rev() {
+            let tmp = (rem << 64) | (*limb as u128);
+            *limb = (tmp / 10) as u64;
+            rem = tmp % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+    digits.reverse();
+
+    String::from_utf8(digits).expect("is ascii")
+}
+
+/// Smallest `g >= 2` that is not a `q`-th power for any prime `q < 2^16` dividing `p - 1`.
+/// The reference takes the smallest element of order `p - 1`; larger prime factors of
+/// `p - 1` are not checked here, which picks the same element for BN254 (5) and for the
+/// transparent field (3).
+pub(crate) fn primitive_element<F: PrimeField>() -> F {
+    let small_factors: Vec<u64> = (2u64..(1 << 16))
+        .filter(|q| (2..*q).take_while(|d| d * d <= *q).all(|d| q % d != 0))
+        .filter(|q| modulus_minus_one_rem::<F>(*q) == 0)
+        .collect();
+    let exponents: Vec<Vec<u64>> = small_factors
+        .iter()
+        .map(|q| modulus_minus_one_div::<F>(*q))
+        .collect();
+
+    let mut g = F::one();
+    loop {
+        g.add_assign(&F::one());
+        if exponents.iter().all(|e| g.pow(e) != F::one()) {
+            return g;
+        }
+    }
+}
+
+/// Smallest prime `alpha` coprime with `p - 1`, so `x^alpha` is a permutation.
+pub(crate) fn smallest_sbox_power<F: PrimeField>() -> u64 {
+    for alpha in [3u64, 5, 7, 11, 13].iter() {
+        if modulus_minus_one_rem::<F>(*alpha) != 0 {
+            return *alpha;
+        }
+    }
+
+    panic!("no small S-box power for this field");
+}
+
+/// `alpha^-1 mod (p - 1)` as little endian limbs, it is `(k * (p - 1) + 1) / alpha` for
+/// the `k < alpha` that makes the division exact.
+pub(crate) fn inverse_sbox_power<F: PrimeField>(alpha: u64) -> Vec<u64> {
+    let rem = modulus_minus_one_rem::<F>(alpha);
+    let k = (1..alpha)
+        .find(|k| (k * rem + 1) % alpha == 0)
+        .expect("alpha is coprime with p - 1");
+
+    let mut p_minus_one = F::char().as_ref().to_vec();
+    // the modulus is odd, so there is no borrow
+    p_minus_one[0] -= 1;
+
+    // k * (p - 1) + 1
+    let mut value = Vec::with_capacity(p_minus_one.len() + 1);
+    let mut carry = 1u128;
+    for limb in p_minus_one.iter() {
+        let tmp = (*limb as u128) * (k as u128) + carry;
+        value.push(tmp as u64);
+        carry = tmp >> 64;
+    }
+    value.push(carry as u64);
+
+    let mut rem = 0u128;
+    for limb in value.iter_mut().rev() {
+        let tmp = (rem << 64) | (*limb as u128);
+        *limb = (tmp / (alpha as u128)) as u64;
+        rem = tmp % (alpha as u128);
+    }
+    debug_assert_eq!(rem, 0);
+
+    value
+}
+
+pub(crate) fn mul_by_mds<F: PrimeField>(mds: &[Vec<F>], state: &mut [F]) {
+    let result: Vec<F> = mds
+        .iter()
+        .map(|row| {
+            let mut acc = F::zero();
+            for (m, s) in row.iter().zip(state.iter()) {
+                let mut tmp = *m;
+                tmp.mul_assign(s);
+                acc.add_assign(&tmp);
+            }
+
+            acc
+        })
+        .collect();
+
+    state.copy_from_slice(&result);
+}
+
+pub(crate) fn add_round_constants<F: PrimeField>(constants: &[F], state: &mut [F]) {
+    for (s, c) in state.iter_mut().zip(constants.iter()) {
+        s.add_assign(c);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+
+    fn fr(value: &str) -> Fr {
+        Fr::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_sbox_powers() {
+        let alpha = smallest_sbox_power::<Fr>();
+        assert_eq!(alpha, 5);
+
+        let x = fr("1234567");
+        let alpha_inv = inverse_sbox_power::<Fr>(alpha);
+        assert_eq!(x.pow(&[alpha]).pow(&alpha_inv), x);
+    }
+
+    #[test]
+    fn test_modulus_decimal() {
+        assert_eq!(
+            modulus_decimal::<Fr>(),
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+        );
+    }
+
+    // the sponge and the compression over the reference permutations
+    #[test]
+    fn test_sponge_reference_vectors() {
+        let rescue = RescueParams::<Fr>::new_2_into_1();
+        let poseidon = PoseidonParams::<Fr>::new_2_into_1();
+        let input = [fr("1"), fr("2"), fr("3"), fr("4")];
+
+        assert_eq!(
+            sponge_hash(&rescue, &input),
+            fr("15412502691809171875587229024404862327547857259816651324487529946678389134478")
+        );
+        assert_eq!(
+            sponge_hash(&poseidon, &input),
+            fr("10994551512864437460580012089172812872623699091722122767143664133270495347344")
+        );
+        assert_eq!(
+            compress(&rescue, &fr("1"), &fr("2")),
+            fr("4606759481896198949083458961919871177207024125838896724098785058529660323087")
+        );
+        assert_eq!(
+            compress(&poseidon, &fr("1"), &fr("2")),
+            fr("1026642792736295304382640489277747685338143003004321944929933532216373435386")
+        );
+    }
+
+    #[test]
+    fn test_stateful_sponge_is_deterministic() {
+        let params = RescueParams::<Fr>::new_2_into_1();
+
+        let mut a = StatefulSponge::new(params.clone());
+        let mut b = StatefulSponge::new(params.clone());
+        a.absorb(&[fr("1"), fr("2"), fr("3")]);
+        b.absorb(&[fr("1"), fr("2"), fr("3")]);
+
+        let challenges_a: Vec<_> = (0..3).map(|_| a.squeeze_out_single()).collect();
+        let challenges_b: Vec<_> = (0..3).map(|_| b.squeeze_out_single()).collect();
+        assert_eq!(challenges_a, challenges_b);
+        assert!(challenges_a[0] != challenges_a[1]);
+
+        // padding separates inputs that differ by trailing zeroes
+        let mut c = StatefulSponge::new(params);
+        c.absorb(&[fr("1"), fr("2"), fr("3"), Fr::zero()]);
+        assert!(c.squeeze_out_single() != challenges_a[0]);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use std::sync::Arc;
+
+use super::*;
+
+/// Poseidon permutation: `full_rounds / 2` full rounds, then `partial_rounds` rounds that
+/// apply the S-box to the first element only, then the remaining full rounds.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PoseidonParams<F: PrimeField> {
+    state_width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    alpha: u64,
+    mds: Vec<Vec<F>>,
+    round_constants: Vec<F>,
+}
+
+impl<F: PrimeField> PoseidonParams<F> {
+    /// Round constants and the MDS matrix of `generate_parameters_grain.sage` for a prime
+    /// field and the `x^alpha` S-box.
+    pub fn new(state_width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(state_width >= 2);
+        assert!(full_rounds % 2 == 0, "full rounds are split evenly around the partial ones");
+
+        let alpha = smallest_sbox_power::<F>();
+
+        let mut lfsr = GrainLfsr::new(
+            F::NUM_BITS as usize,
+            state_width,
+            full_rounds,
+            partial_rounds,
+        );
+        let round_constants = (0..((full_rounds + partial_rounds) * state_width))
+            .map(|_| lfsr.next_field_element::<F>())
+            .collect();
+        let mds = lfsr.next_cauchy_mds::<F>(state_width);
+
+        Self {
+            state_width,
+            full_rounds,
+            partial_rounds,
+            alpha,
+            mds,
+            round_constants,
+        }
+    }
+
+    /// Width 3 with rate 2, used for the Merkle trees and the transcripts. Partial rounds
+    /// are taken for the S-box power of the field.
+    pub fn new_2_into_1() -> Self {
+        let partial_rounds = if smallest_sbox_power::<F>() == 5 { 57 } else { 84 };
+
+        Self::new(3, 8, partial_rounds)
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    pub fn alpha(&self) -> u64 {
+        self.alpha
+    }
+}
+
+impl<F: PrimeField> SharedParams<F> for PoseidonParams<F> {
+    fn shared() -> Arc<Self> {
+        shared_params(Self::new_2_into_1)
+    }
+}
+
+/// Grain LFSR of the reference implementation, seeded with the parameters.
+struct GrainLfsr {
+    state: [bool; 80],
+    field_bits: usize,
+}
+
+impl GrainLfsr {
+    fn new(field_bits: usize, state_width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        // prime field (1), x^alpha S-box (0), field size, width and the numbers of rounds
+        let fields = [
+            (1, 2),
+            (0, 4),
+            (field_bits, 12),
+            (state_width, 12),
+            (full_rounds, 10),
+            (partial_rounds, 10),
+        ];
+
+        let mut state = [true; 80];
+        let mut pos = 0;
+        for (value, num_bits) in fields.iter() {
+            for i in (0..*num_bits).rev() {
+                state[pos] = (value >> i) & 1 == 1;
+                pos += 1;
+            }
+        }
+
+        let mut lfsr = Self { state, field_bits };
+        for _ in 0..160 {
+            lfsr.update();
+        }
+
+        lfsr
+    }
+
+    fn update(&mut self) -> bool {
+        let s = &self.state;
+        let new_bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.copy_within(1.., 0);
+        self.state[79] = new_bit;
+
+        new_bit
+    }
+
+    /// Bits come in pairs, the second one is output if the first one is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let first = self.update();
+            let second = self.update();
+            if first {
+                return second;
+            }
+        }
+    }
+
+    /// `field_bits` bits, the first one is the most significant.
+    fn next_repr<F: PrimeField>(&mut self) -> F::Repr {
+        let mut repr = F::Repr::default();
+        for _ in 0..self.field_bits {
+            repr.mul2();
+            if self.next_bit() {
+                repr.as_mut()[0] |= 1;
+            }
+        }
+
+        repr
+    }
+
+    /// Values that are not below the modulus are skipped.
+    fn next_field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            if let Ok(el) = F::from_repr(self.next_repr::<F>()) {
+                return el;
+            }
+        }
+    }
+
+    /// Values are reduced, they are below `2p` as `field_bits` is the size of the modulus.
+    fn next_reduced_field_element<F: PrimeField>(&mut self) -> F {
+        let mut repr = self.next_repr::<F>();
+        if repr >= F::char() {
+            repr.sub_noborrow(&F::char());
+        }
+
+        F::from_repr(repr).expect("is reduced")
+    }
+
+    /// Cauchy matrix `1 / (x_i + y_j)` for distinct `x_i` and `y_j` drawn after the round
+    /// constants. The reference also redraws the matrices with infinitely long invariant
+    /// subspace trails, that check is not done here.
+    fn next_cauchy_mds<F: PrimeField>(&mut self, state_width: usize) -> Vec<Vec<F>> {
+        loop {
+            let mut elements: Vec<F> = vec![];
+            while elements.len() != 2 * state_width {
+                elements = (0..(2 * state_width))
+                    .map(|_| self.next_reduced_field_element::<F>())
+                    .collect();
+                let mut sorted: Vec<_> = elements.iter().map(|el| el.into_repr()).collect();
+                sorted.sort();
+                sorted.dedup();
+                if sorted.len() != elements.len() {
+                    elements.clear();
+                }
+            }
+
+            let (xs, ys) = elements.split_at(state_width);
+            let mds: Option<Vec<Vec<F>>> = xs
+                .iter()
+                .map(|x| {
+                    ys.iter()
+                        .map(|y| {
+                            let mut sum = *x;
+                            sum.add_assign(y);
+                            sum.inverse()
+                        })
+                        .collect()
+                })
+                .collect();
+
+            if let Some(mds) = mds {
+                return mds;
+            }
+        }
+    }
+}
+
+impl<F: PrimeField> AlgebraicPermutation<F> for PoseidonParams<F> {
+    fn state_width(&self) -> usize {
+        self.state_width
+    }
+
+    fn permute(&self, state: &mut [F]) {
+        assert_eq!(state.len(), self.state_width);
+
+        let half_full_rounds = self.full_rounds / 2;
+        let partial_rounds_end = half_full_rounds + self.partial_rounds;
+
+        for (round, constants) in self
+            .round_constants
+            .chunks(self.state_width)
+            .enumerate()
+        {
+            add_round_constants(constants, state);
+
+            if round < half_full_rounds || round >= partial_rounds_end {
+                for s in state.iter_mut() {
+                    *s = s.pow(&[self.alpha]);
+                }
+            } else {
+                state[0] = state[0].pow(&[self.alpha]);
+            }
+
+            mul_by_mds(&self.mds, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check_permutation<F: PrimeField>(expected_constant: &str, expected: [&str; 3]) {
+        let params = PoseidonParams::<F>::new_2_into_1();
+        assert_eq!(params.round_constants[0], F::from_str(expected_constant).unwrap());
+
+        let mut state = vec![F::zero(), F::one(), F::from_str("2").unwrap()];
+        params.permute(&mut state);
+
+        let expected: Vec<F> = expected.iter().map(|v| F::from_str(v).unwrap()).collect();
+        assert_eq!(state, expected);
+    }
+
+    /// Test vector of the reference `poseidonperm_x5_254_3`, the first element is also
+    /// `poseidon([1, 2])` of circomlib.
+    #[test]
+    fn test_poseidon_bn256_reference_vector() {
+        use crate::pairing::bn256::Fr;
+
+        check_permutation::<Fr>(
+            "6745197990210204598374042828761989596302876299545964402857411729872131034734",
+            [
+                "7853200120776062878684798364095072458815029376092732009249414926327459813530",
+                "7142104613055408817911962100316808866448378443474503659992478482890339429929",
+                "6549537674122432311777789598043107870002137484850126429160507761192163713804",
+            ],
+        );
+    }
+
+    // values of a transcription of `generate_parameters_grain.sage` and the reference
+    // permutation with `alpha = 3`
+
+    #[cfg(not(feature = "verifier-only"))]
+    #[test]
+    fn test_poseidon_transparent_reference_vector() {
+        use crate::plonk::transparent_engine::Fr;
+
+        let params = PoseidonParams::<Fr>::new_2_into_1();
+        assert_eq!(params.partial_rounds(), 84);
+
+        check_permutation::<Fr>(
+            "3144010825426134897740329303007181754237747547137238645168856893158808188674",
+            [
+                "1388478418059606803137901151983139036629854691684326290112344606068670431171",
+                "899373783604551299884314770625976896660372137213211958369696101344454320277",
+                "2106933920644726068209103488464260634265635307746114666140270632423358326612",
+            ],
+        );
+
+        let input = [
+            Fr::from_str("1").unwrap(),
+            Fr::from_str("2").unwrap(),
+            Fr::from_str("3").unwrap(),
+            Fr::from_str("4").unwrap(),
+        ];
+        assert_eq!(
+            sponge_hash(&params, &input),
+            Fr::from_str("957918805888375225098887483344318596400206192416561860453635851319029853465").unwrap()
+        );
+        assert_eq!(
+            compress(&params, &input[0], &input[1]),
+            Fr::from_str("2263457952355824176760061731255784722009960105777027681958893457908328456509").unwrap()
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use crate::pairing::ff::{Field, PrimeField};
+use tiny_keccak::Keccak;
+use std::sync::Arc;
+
+use super::*;
+
+/// Rescue-Prime permutation: every round applies `x^alpha` and then `x^(1/alpha)` to the
+/// whole state, each followed by the MDS and a round constants injection.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RescueParams<F: PrimeField> {
+    state_width: usize,
+    num_rounds: usize,
+    alpha: u64,
+    alpha_inv: Vec<u64>,
+    mds: Vec<Vec<F>>,
+    round_constants: Vec<F>,
+}
+
+impl<F: PrimeField> RescueParams<F> {
+    /// The capacity is a single element, see `AlgebraicPermutation::rate`.
+    const CAPACITY: usize = 1;
+
+    /// Parameters of `rescue_prime.sage` for the given state width and security level.
+    pub fn new(state_width: usize, security_level: usize) -> Self {
+        assert!(state_width >= 2);
+
+        let alpha = smallest_sbox_power::<F>();
+        let alpha_inv = inverse_sbox_power::<F>(alpha);
+        let num_rounds = num_rounds(state_width, Self::CAPACITY, security_level, alpha);
+
+        Self {
+            state_width,
+            num_rounds,
+            alpha,
+            alpha_inv,
+            mds: vandermonde_mds(state_width),
+            round_constants: round_constants(
+                state_width,
+                Self::CAPACITY,
+                security_level,
+                num_rounds,
+            ),
+        }
+    }
+
+    /// Width 3 with rate 2 at 128 bits of security, used for the Merkle trees and the
+    /// transcripts.
+    pub fn new_2_into_1() -> Self {
+        Self::new(3, 128)
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.num_rounds
+    }
+
+    pub fn alpha(&self) -> u64 {
+        self.alpha
+    }
+
+    fn constants_for_step(&self, step: usize) -> &[F] {
+        let start = step * self.state_width;
+
+        &self.round_constants[start..(start + self.state_width)]
+    }
+}
+
+impl<F: PrimeField> SharedParams<F> for RescueParams<F> {
+    fn shared() -> Arc<Self> {
+        shared_params(Self::new_2_into_1)
+    }
+}
+
+/// Rounds against the Groebner basis attack plus 50%, at least 5 before the margin.
+fn num_rounds(state_width: usize, capacity: usize, security_level: usize, alpha: u64) -> usize {
+    let rate = state_width - capacity;
+    let alpha = alpha as usize;
+
+    let l1 = (1..25)
+        .find(|n| {
+            let dcon = (alpha - 1) * state_width * (n - 1) / 2 + 2;
+            let v = state_width * (n - 1) + rate;
+
+            binomial_squared_exceeds(v + dcon, v, security_level)
+        })
+        .unwrap_or(24);
+
+    (3 * std::cmp::max(5, l1) + 1) / 2
+}
+
+/// `binomial(n, k)^2 > 2^log_bound`
+fn binomial_squared_exceeds(n: usize, k: usize, log_bound: usize) -> bool {
+    assert!(log_bound <= 128);
+
+    // binomial(n, i) grows up to i = n / 2
+    let k = std::cmp::min(k, n - k);
+    let mut binomial = 1u128;
+    for i in 0..k {
+        binomial = binomial * ((n - i) as u128) / ((i + 1) as u128);
+        if binomial > 1u128 << 64 {
+            return true;
+        }
+    }
+
+    match binomial.checked_mul(binomial) {
+        Some(square) => log_bound < 128 && square > (1u128 << log_bound),
+        // binomial is exactly 2^64
+        None => log_bound < 128,
+    }
+}
+
+/// Transposed right half of the reduced echelon form of the `m x 2m` Vandermonde matrix
+/// `g^(i*j)` for a primitive element `g`.
+fn vandermonde_mds<F: PrimeField>(state_width: usize) -> Vec<Vec<F>> {
+    let g = primitive_element::<F>();
+    let mut matrix: Vec<Vec<F>> = (0..state_width)
+        .map(|i| {
+            (0..(2 * state_width))
+                .map(|j| g.pow([(i * j) as u64]))
+                .collect()
+        })
+        .collect();
+
+    // the left half is an invertible Vandermonde matrix, so pivots are on the diagonal
+    for col in 0..state_width {
+        let pivot = (col..state_width)
+            .find(|row| !matrix[*row][col].is_zero())
+            .expect("Vandermonde matrix is invertible");
+        matrix.swap(col, pivot);
+
+        let inv = matrix[col][col].inverse().expect("pivot is not zero");
+        for el in matrix[col].iter_mut() {
+            el.mul_assign(&inv);
+        }
+
+        for row in 0..state_width {
+            if row == col || matrix[row][col].is_zero() {
+                continue;
+            }
+            let factor = matrix[row][col];
+            for j in 0..(2 * state_width) {
+                let mut tmp = matrix[col][j];
+                tmp.mul_assign(&factor);
+                matrix[row][j].sub_assign(&tmp);
+            }
+        }
+    }
+
+    (0..state_width)
+        .map(|i| (0..state_width).map(|j| matrix[j][state_width + i]).collect())
+        .collect()
+}
+
+/// SHAKE256 of `Rescue-XLIX(p,m,capacity,security_level)`, split into little endian
+/// integers one byte longer than the modulus and reduced.
+fn round_constants<F: PrimeField>(
+    state_width: usize,
+    capacity: usize,
+    security_level: usize,
+    num_rounds: usize,
+) -> Vec<F> {
+    let bytes_per_int = ((F::NUM_BITS as usize) + 7) / 8 + 1;
+    let num_constants = 2 * state_width * num_rounds;
+
+    let seed = format!(
+        "Rescue-XLIX({},{},{},{})",
+        modulus_decimal::<F>(),
+        state_width,
+        capacity,
+        security_level
+    );
+    let mut bytes = vec![0u8; bytes_per_int * num_constants];
+    let mut shake = Keccak::new_shake256();
+    shake.update(seed.as_bytes());
+    shake.finalize(&mut bytes);
+
+    let byte_base = F::from_str("256").expect("fits into the field");
+
+    bytes
+        .chunks(bytes_per_int)
+        .map(|chunk| {
+            let mut value = F::zero();
+            for b in chunk.iter().rev() {
+                value.mul_assign(&byte_base);
+                value.add_assign(&F::from_str(&b.to_string()).expect("fits into the field"));
+            }
+
+            value
+        })
+        .collect()
+}
+
+impl<F: PrimeField> AlgebraicPermutation<F> for RescueParams<F> {
+    fn state_width(&self) -> usize {
+        self.state_width
+    }
+
+    fn permute(&self, state: &mut [F]) {
+        assert_eq!(state.len(), self.state_width);
+
+        for round in 0..self.num_rounds {
+            for s in state.iter_mut() {
+                *s = s.pow(&[self.alpha]);
+            }
+            mul_by_mds(&self.mds, state);
+            add_round_constants(self.constants_for_step(2 * round), state);
+
+            for s in state.iter_mut() {
+                *s = s.pow(&self.alpha_inv);
+            }
+            mul_by_mds(&self.mds, state);
+            add_round_constants(self.constants_for_step(2 * round + 1), state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // values of a transcription of `rescue_prime.sage` for `m = 3`, `capacity = 1` and
+    // 128 bits of security
+
+    fn check_permutation<F: PrimeField>(expected_constant: &str, expected: [&str; 3]) {
+        let params = RescueParams::<F>::new_2_into_1();
+        assert_eq!(params.round_constants[0], F::from_str(expected_constant).unwrap());
+
+        let mut state = vec![F::zero(), F::one(), F::from_str("2").unwrap()];
+        params.permute(&mut state);
+
+        let expected: Vec<F> = expected.iter().map(|v| F::from_str(v).unwrap()).collect();
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_rescue_bn256_reference_vector() {
+        use crate::pairing::bn256::Fr;
+
+        let params = RescueParams::<Fr>::new_2_into_1();
+        assert_eq!(params.alpha(), 5);
+        assert_eq!(params.num_rounds(), 14);
+        assert_eq!(primitive_element::<Fr>(), Fr::from_str("5").unwrap());
+
+        // Lagrange coefficients of 5^3 over the nodes 1, 5, 25
+        let mut minus_155 = Fr::from_str("155").unwrap();
+        minus_155.negate();
+        assert_eq!(
+            params.mds[0],
+            vec![Fr::from_str("125").unwrap(), minus_155, Fr::from_str("31").unwrap()]
+        );
+
+        check_permutation::<Fr>(
+            "16315208746038078395621556119853320273013100435293928429550050637277758017174",
+            [
+                "6224690566795026170272976986384432621080028281436539532889157379570648910802",
+                "11125085147280074555337181371265636082619440214910773293161304065299707718600",
+                "12118779605307541175395572293313884052054477690855880723785138715937774904848",
+            ],
+        );
+    }
+
+    #[cfg(not(feature = "verifier-only"))]
+    #[test]
+    fn test_rescue_transparent_reference_vector() {
+        use crate::plonk::transparent_engine::Fr;
+
+        let params = RescueParams::<Fr>::new_2_into_1();
+        assert_eq!(params.alpha(), 3);
+        assert_eq!(params.num_rounds(), 18);
+        assert_eq!(primitive_element::<Fr>(), Fr::from_str("3").unwrap());
+
+        check_permutation::<Fr>(
+            "3326422315174074064869921251356356130705113815520111777212424265788312277972",
+            [
+                "2810441655126264462177320833843227481558266664584059070862065867880373527625",
+                "3195429438043400815940646762125043044443264571320988329090019300295181245357",
+                "3315860011344884619541872770823973376036038794289263387776000968878995733692",
+            ],
+        );
+
+        let input = [
+            Fr::from_str("1").unwrap(),
+            Fr::from_str("2").unwrap(),
+            Fr::from_str("3").unwrap(),
+            Fr::from_str("4").unwrap(),
+        ];
+        assert_eq!(
+            sponge_hash(&params, &input),
+            Fr::from_str("1583801457608834171580390728787198101336013114341079590185242740574690242536").unwrap()
+        );
+        assert_eq!(
+            compress(&params, &input[0], &input[1]),
+            Fr::from_str("2364168710232599071727733282098883395929946268357744409300276002605852441064").unwrap()
+        );
+    }
+}
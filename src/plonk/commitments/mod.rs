@@ -1,13 +1,17 @@
 use crate::pairing::ff::PrimeField;
 
 use crate::plonk::commitments::transcript::*;
+#[cfg(not(feature = "verifier-only"))]
 use crate::plonk::polynomials::*;
 use pairing::Engine;
 
+pub mod algebraic_hash;
+#[cfg(not(feature = "verifier-only"))]
 pub mod transparent;
 
 pub mod transcript;
 
+#[cfg(not(feature = "verifier-only"))]
 pub trait CommitmentScheme<E: Engine> {
     type Commitment: std::fmt::Debug;
     type OpeningProof;
@@ -1,16 +1,19 @@
-use poseidon_hash::{PoseidonEngine, PoseidonHashParams, StatefulSponge};
-use crate::pairing::ff::{PrimeField, PrimeFieldRepr};
-use crate::byteorder::{ByteOrder, BigEndian};
+use crate::plonk::commitments::algebraic_hash::{PoseidonParams, SharedParams, StatefulSponge};
+use crate::pairing::ff::PrimeField;
 
 use super::*;
 
+/// Algebraic sponge transcript. The round constants are derived once with
+/// `PoseidonParams::new_2_into_1()` and passed to the prover and the verifier as the
+/// initialization parameters; `new()` clones the same default parameters from
+/// `SharedParams::shared()`, so they are derived once per process.
 #[derive(Clone)]
-pub struct PoseidonTranscript<'a, E: PoseidonEngine> {
-    state: StatefulSponge<'a, E>,
+pub struct PoseidonTranscript<F: PrimeField> {
+    state: StatefulSponge<F, PoseidonParams<F>>,
 }
 
-impl<'a, E: PoseidonEngine> PoseidonTranscript<'a, E> {
-    pub fn from_params(params: &'a E::Params) -> Self {
+impl<F: PrimeField> PoseidonTranscript<F> {
+    pub fn from_params(params: PoseidonParams<F>) -> Self {
         let stateful = StatefulSponge::new(params);
 
         Self {
@@ -20,29 +23,87 @@ impl<'a, E: PoseidonEngine> PoseidonTranscript<'a, E> {
 }
 
 
-impl<'a, E: PoseidonEngine> Prng<E::Fr> for PoseidonTranscript<'a, E> {
-    type Input = E::Fr;
-    type InitializationParameters = &'a E::Params;
+impl<F: PrimeField> Prng<F> for PoseidonTranscript<F> {
+    type Input = F;
+    type InitializationParameters = PoseidonParams<F>;
 
     fn new() -> Self {
-        unimplemented!()
+        Self::from_params((*PoseidonParams::shared()).clone())
     }
 
     fn new_from_params(params: Self::InitializationParameters) -> Self {
-        let stateful = StatefulSponge::new(params);
-
-        Self {
-            state: stateful
-        }
+        Self::from_params(params)
     }
 
     fn commit_input(&mut self, input: &Self::Input) {
         self.state.absorb_single_value(*input);
     }
 
-    fn get_challenge(&mut self) -> E::Fr {
+    fn get_challenge(&mut self) -> F {
         let value = self.state.squeeze_out_single();
 
         value
     }
-}
\ No newline at end of file
+}
+
+impl<F: PrimeField> Transcript<F> for PoseidonTranscript<F> {
+    fn commit_bytes(&mut self, bytes: &[u8]) {
+        self.state.absorb_bytes(bytes);
+    }
+
+    fn commit_field_element(&mut self, element: &F) {
+        self.state.absorb_single_value(*element);
+    }
+
+    fn get_challenge_bytes(&mut self) -> Vec<u8> {
+        self.state.squeeze_out_bytes()
+    }
+
+    fn commit_fe<FF: PrimeField>(&mut self, element: &FF) {
+        self.state.absorb_foreign_field_element(element);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::{Fq, Fr};
+
+    #[test]
+    fn test_poseidon_transcript_vectors() {
+        let mut transcript = PoseidonTranscript::<Fr>::new_from_params(PoseidonParams::new_2_into_1());
+        for v in ["1", "2", "3"].iter() {
+            transcript.commit_field_element(&Fr::from_str(v).unwrap());
+        }
+
+        assert_eq!(
+            transcript.get_challenge(),
+            Fr::from_str("8976436888206584235153576350010178372529654707254972921492554972317984026098").unwrap()
+        );
+        assert_eq!(
+            transcript.get_challenge(),
+            Fr::from_str("20061558158348574206318760244765886875174911454256536616983200497878948042555").unwrap()
+        );
+
+        let mut repr = <Fq as PrimeField>::Repr::default();
+        repr.as_mut()[..3].copy_from_slice(&[5, 7, 11]);
+        transcript.commit_bytes(b"redshift");
+        transcript.commit_fe(&Fq::from_repr(repr).unwrap());
+
+        let challenge = transcript.get_challenge();
+        assert_eq!(
+            challenge,
+            Fr::from_str("18744402914968330825482365415289495378461987296496715756178773407981988856766").unwrap()
+        );
+
+        // challenge bytes are the big endian encoding of the next challenge
+        let mut replay = transcript.clone();
+        let mut expected_bytes = vec![0u8; 32];
+        replay
+            .get_challenge()
+            .into_repr()
+            .write_be(&mut expected_bytes[..])
+            .unwrap();
+        assert_eq!(transcript.get_challenge_bytes(), expected_bytes);
+    }
+}
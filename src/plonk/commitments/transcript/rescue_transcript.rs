@@ -1,17 +1,20 @@
-use rescue_hash::{RescueEngine, RescueHashParams, StatefulRescue};
-use crate::pairing::ff::{PrimeField, PrimeFieldRepr};
-use crate::byteorder::{ByteOrder, BigEndian};
+use crate::plonk::commitments::algebraic_hash::{RescueParams, SharedParams, StatefulSponge};
+use crate::pairing::ff::PrimeField;
 
 use super::*;
 
+/// Algebraic sponge transcript. The round constants are derived once with
+/// `RescueParams::new_2_into_1()` and passed to the prover and the verifier as the
+/// initialization parameters; `new()` clones the same default parameters from
+/// `SharedParams::shared()`, so they are derived once per process.
 #[derive(Clone)]
-pub struct RescueTranscript<'a, E: RescueEngine> {
-    state: StatefulRescue<'a, E>,
+pub struct RescueTranscript<F: PrimeField> {
+    state: StatefulSponge<F, RescueParams<F>>,
 }
 
-impl<'a, E: RescueEngine> RescueTranscript<'a, E> {
-    pub fn from_params(params: &'a E::Params) -> Self {
-        let stateful = StatefulRescue::new(params);
+impl<F: PrimeField> RescueTranscript<F> {
+    pub fn from_params(params: RescueParams<F>) -> Self {
+        let stateful = StatefulSponge::new(params);
 
         Self {
             state: stateful
@@ -20,29 +23,153 @@ impl<'a, E: RescueEngine> RescueTranscript<'a, E> {
 }
 
 
-impl<'a, E: RescueEngine> Prng<E::Fr> for RescueTranscript<'a, E> {
-    type Input = E::Fr;
-    type InitializationParameters = &'a E::Params;
+impl<F: PrimeField> Prng<F> for RescueTranscript<F> {
+    type Input = F;
+    type InitializationParameters = RescueParams<F>;
 
     fn new() -> Self {
-        unimplemented!()
+        Self::from_params((*RescueParams::shared()).clone())
     }
 
     fn new_from_params(params: Self::InitializationParameters) -> Self {
-        let stateful = StatefulRescue::new(params);
-
-        Self {
-            state: stateful
-        }
+        Self::from_params(params)
     }
 
     fn commit_input(&mut self, input: &Self::Input) {
         self.state.absorb_single_value(*input);
     }
 
-    fn get_challenge(&mut self) -> E::Fr {
+    fn get_challenge(&mut self) -> F {
         let value = self.state.squeeze_out_single();
 
         value
     }
-}
\ No newline at end of file
+}
+
+impl<F: PrimeField> Transcript<F> for RescueTranscript<F> {
+    fn commit_bytes(&mut self, bytes: &[u8]) {
+        self.state.absorb_bytes(bytes);
+    }
+
+    fn commit_field_element(&mut self, element: &F) {
+        self.state.absorb_single_value(*element);
+    }
+
+    fn get_challenge_bytes(&mut self) -> Vec<u8> {
+        self.state.squeeze_out_bytes()
+    }
+
+    fn commit_fe<FF: PrimeField>(&mut self, element: &FF) {
+        self.state.absorb_foreign_field_element(element);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::{Bn256, Fq, Fr};
+
+    #[test]
+    fn test_rescue_transcript_vectors() {
+        let mut transcript = RescueTranscript::<Fr>::new();
+        for v in ["1", "2", "3"].iter() {
+            transcript.commit_field_element(&Fr::from_str(v).unwrap());
+        }
+
+        assert_eq!(
+            transcript.get_challenge(),
+            Fr::from_str("10426312538076513787842576207928300055993667092218520332880144400670314798844").unwrap()
+        );
+        assert_eq!(
+            transcript.get_challenge(),
+            Fr::from_str("4184464354440461145180986148039895060330502056651856767983333175045473850499").unwrap()
+        );
+
+        let mut repr = <Fq as PrimeField>::Repr::default();
+        repr.as_mut()[..3].copy_from_slice(&[5, 7, 11]);
+        transcript.commit_bytes(b"redshift");
+        transcript.commit_fe(&Fq::from_repr(repr).unwrap());
+
+        assert_eq!(
+            transcript.get_challenge(),
+            Fr::from_str("15399907801002971881461100071343906375287988734997720984720998824068780049351").unwrap()
+        );
+    }
+
+    #[cfg(not(feature = "verifier-only"))]
+    #[test]
+    fn test_prove_and_verify_with_rescue_transcript() {
+        use crate::config::ProverConfig;
+        use crate::kate_commitment::*;
+        use crate::pairing::ff::Field;
+        use crate::plonk::better_cs::cs::*;
+        use crate::plonk::better_cs::generator::GeneratorAssembly4WithNextStep;
+        use crate::plonk::better_cs::verifier::verify;
+        use crate::plonk::{make_verification_key_with_config, prove_native_by_steps_with_config};
+        use crate::worker::Worker;
+        use crate::SynthesisError;
+
+        struct MulCircuit;
+
+        impl Circuit<Bn256, PlonkCsWidth4WithNextStepParams> for MulCircuit {
+            fn synthesize<CS: ConstraintSystem<Bn256, PlonkCsWidth4WithNextStepParams>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let a = cs.alloc_input(|| Ok(Fr::from_str("10").unwrap()))?;
+                let b = cs.alloc(|| Ok(Fr::from_str("20").unwrap()))?;
+                let c = cs.alloc(|| Ok(Fr::from_str("200").unwrap()))?;
+
+                let zero = Fr::zero();
+                let one = Fr::one();
+                let mut negative_one = one;
+                negative_one.negate();
+
+                let dummy = cs.get_dummy_variable();
+
+                // c - a*b == 0
+                cs.new_gate(
+                    [a, b, dummy, c],
+                    [zero, zero, zero, negative_one, one, zero],
+                    [zero],
+                )?;
+
+                Ok(())
+            }
+        }
+
+        let config = ProverConfig::cpu_only();
+        let worker = Worker::new();
+
+        let mut assembly = GeneratorAssembly4WithNextStep::<Bn256>::new();
+        MulCircuit.synthesize(&mut assembly).unwrap();
+        assembly.finalize();
+        let setup = assembly.setup_with_config(&worker, &config).unwrap();
+
+        let crs = Crs::<Bn256, CrsForMonomialForm>::crs_42(
+            setup.permutation_polynomials[0].size(),
+            &worker,
+        );
+        let vk = make_verification_key_with_config(&setup, &crs, &config).unwrap();
+
+        let params = RescueParams::<Fr>::new_2_into_1();
+        let proof = prove_native_by_steps_with_config::<_, _, RescueTranscript<Fr>>(
+            &MulCircuit,
+            &setup,
+            None,
+            &crs,
+            Some(params.clone()),
+            &config,
+        )
+        .unwrap();
+
+        assert!(verify::<_, _, RescueTranscript<Fr>>(&proof, &vk, Some(params)).unwrap());
+        // default parameters are the same ones
+        assert!(verify::<_, _, RescueTranscript<Fr>>(&proof, &vk, None).unwrap());
+        // and differ from the ones of the other sponge
+        assert!(!verify::<_, _, super::super::poseidon_transcript::PoseidonTranscript<Fr>>(
+            &proof, &vk, None
+        )
+        .unwrap());
+    }
+}
@@ -0,0 +1,613 @@
+//! Batched FRI openings.
+//!
+//! LDEs of many polynomials are committed in a single Merkle tree whose leaf at the index
+//! `i` holds the evaluations of all the polynomials at `omega^i`. Openings of any number
+//! of polynomials from any number of such batches are proven with a single FRI instance
+//! over the random linear combination of the quotients `(f(x) - f(z)) / (x - z)`, and a
+//! single query phase, that opens one leaf per batch for every query index. Compared to
+//! the per polynomial trees of `StatelessTransparentCommitter` this needs one Merkle path
+//! per batch instead of one per polynomial, and one tree to build per batch.
+
+use crate::pairing::ff::{Field, PrimeField};
+use crate::pairing::Engine;
+use blake2s_simd::Params;
+
+use super::fri::naive_fri::naive_fri::{FRIProof as NaiveFRIProof, NaiveFriIop};
+use super::fri::*;
+use super::iop::blake2s_trivial_iop::{Blake2sLeafEncoder, Blake2sTreeHasher, TrivialBlake2sIOP};
+use super::iop::*;
+use super::precomputations::PrecomputedInvOmegas;
+use super::utils::log2_floor;
+use super::{derive_query_indexes, TransparentCommitterParameters};
+use crate::plonk::commitments::transcript::*;
+use crate::plonk::domains::*;
+use crate::plonk::fft::cooley_tukey_ntt::BitReversedOmegas;
+use crate::plonk::polynomials::*;
+use crate::worker::Worker;
+
+/// The FRI instance of the batched openings.
+pub type BatchedFri<E> = NaiveFriIop<E, TrivialBlake2sIOP<<E as Engine>::Fr>>;
+
+/// Merkle tree over rows of values, the leaf `i` commits to the `i`-th value of every
+/// column.
+#[derive(Clone, Debug)]
+pub struct BatchedBlake2sTree<F: PrimeField> {
+    size: usize,
+    width: usize,
+    nodes: Vec<[u8; 32]>,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> BatchedBlake2sTree<F> {
+    fn hash_row(values: impl Iterator<Item = F>) -> [u8; 32] {
+        let mut state = Params::new()
+            .hash_length(32)
+            .personal(b"FriBatch")
+            .to_state();
+        for value in values {
+            state.update(&<Blake2sLeafEncoder<F> as LeafEncoder<F>>::encode_leaf(&value));
+        }
+
+        *state.finalize().as_array()
+    }
+
+    fn hash_row_of_columns(columns: &[&[F]], index: usize) -> [u8; 32] {
+        Self::hash_row(columns.iter().map(|c| c[index]))
+    }
+
+    pub fn create(columns: &[&[F]], worker: &Worker) -> Self {
+        assert!(!columns.is_empty());
+        let size = columns[0].len();
+        assert!(size.is_power_of_two() && size >= 2);
+        for c in columns.iter() {
+            assert_eq!(c.len(), size, "all columns must have the same size");
+        }
+
+        let mut leaf_hashes = vec![[0u8; 32]; size];
+
+        worker.scope(leaf_hashes.len(), |scope, chunk| {
+            for (i, lh) in leaf_hashes.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    let base_idx = i * chunk;
+                    for (j, lh) in lh.iter_mut().enumerate() {
+                        *lh = Self::hash_row_of_columns(columns, base_idx + j);
+                    }
+                });
+            }
+        });
+
+        let mut nodes = vec![[0u8; 32]; size];
+
+        let num_levels = log2_floor(size) as usize;
+        let mut nodes_for_hashing = &mut nodes[..];
+
+        // separately hash last level, which hashes leaf hashes into first nodes
+        {
+            let level = num_levels - 1;
+            let (_, outputs) = nodes_for_hashing.split_at_mut(nodes_for_hashing.len() / 2);
+            Self::hash_level(&leaf_hashes, outputs, level, worker);
+        }
+
+        for level in (0..(num_levels - 1)).rev() {
+            let (next_levels, inputs) = nodes_for_hashing.split_at_mut(nodes_for_hashing.len() / 2);
+            let (_, outputs) = next_levels.split_at_mut(next_levels.len() / 2);
+            Self::hash_level(inputs, outputs, level, worker);
+
+            nodes_for_hashing = next_levels;
+        }
+
+        Self {
+            size,
+            width: columns.len(),
+            nodes,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn hash_level(inputs: &[[u8; 32]], outputs: &mut [[u8; 32]], level: usize, worker: &Worker) {
+        assert!(outputs.len() * 2 == inputs.len());
+
+        worker.scope(outputs.len(), |scope, chunk| {
+            for (o, i) in outputs.chunks_mut(chunk).zip(inputs.chunks(chunk * 2)) {
+                scope.spawn(move |_| {
+                    for (o, i) in o.iter_mut().zip(i.chunks(2)) {
+                        *o = <Blake2sTreeHasher<F> as IopTreeHasher<F>>::hash_node(i, level);
+                    }
+                });
+            }
+        });
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_root(&self) -> [u8; 32] {
+        self.nodes[1]
+    }
+
+    pub fn query(&self, natural_index: usize, columns: &[&[F]]) -> BatchedQuery<F> {
+        assert_eq!(columns.len(), self.width);
+        assert!(natural_index < self.size);
+
+        let mut path = vec![Self::hash_row_of_columns(columns, natural_index ^ 1)];
+
+        let mut nodes = &self.nodes[..];
+        let mut idx = natural_index >> 1;
+        for _ in 0..log2_floor(nodes.len() / 2) {
+            let half_len = nodes.len() / 2;
+            let (next_level, this_level) = nodes.split_at(half_len);
+            path.push(this_level[idx ^ 1usize]);
+            idx >>= 1;
+            nodes = next_level;
+        }
+
+        BatchedQuery {
+            natural_index,
+            values: columns.iter().map(|c| c[natural_index]).collect(),
+            path,
+        }
+    }
+
+    pub fn verify_query(root: &[u8; 32], query: &BatchedQuery<F>) -> bool {
+        let mut hash = Self::hash_row(query.values.iter().cloned());
+        let mut idx = query.natural_index;
+        for el in query.path.iter() {
+            hash = if idx & 1usize == 0 {
+                <Blake2sTreeHasher<F> as IopTreeHasher<F>>::hash_node(&[hash, *el], 0)
+            } else {
+                <Blake2sTreeHasher<F> as IopTreeHasher<F>>::hash_node(&[*el, hash], 0)
+            };
+            idx >>= 1;
+        }
+
+        // the path must lead all the way up to the root
+        idx == 0 && &hash == root
+    }
+}
+
+/// Values of all the polynomials of a batch at one index of the LDE domain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchedQuery<F: PrimeField> {
+    pub natural_index: usize,
+    pub values: Vec<F>,
+    pub path: Vec<[u8; 32]>,
+}
+
+/// LDEs of the polynomials of one batch and their tree.
+pub struct BatchedOracle<E: Engine> {
+    ldes: Vec<Polynomial<E, Values>>,
+    tree: BatchedBlake2sTree<E::Fr>,
+}
+
+impl<E: Engine> BatchedOracle<E> {
+    pub fn get_commitment(&self) -> [u8; 32] {
+        self.tree.get_root()
+    }
+
+    pub fn num_polynomials(&self) -> usize {
+        self.ldes.len()
+    }
+
+    fn columns(&self) -> Vec<&[E::Fr]> {
+        self.ldes.iter().map(|l| l.as_ref()).collect()
+    }
+}
+
+/// Claim that the polynomial number `polynomial` of the batch number `batch` is equal to
+/// `value` at `at_point`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpeningClaim<F: PrimeField> {
+    pub batch: usize,
+    pub polynomial: usize,
+    pub at_point: F,
+    pub value: F,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct BatchedOpeningProof<F: PrimeField> {
+    pub fri_proof: NaiveFRIProof<F, TrivialBlake2sIOP<F>>,
+    /// For every query index one query per batch, in the order of the batches.
+    pub queries: Vec<Vec<BatchedQuery<F>>>,
+    pub pow_nonce: u64,
+}
+
+/// Like `StatelessTransparentCommitter`, the commitments and the claims are expected to
+/// be in the transcript already, and the aggregation coefficient is drawn by the caller.
+pub struct BatchedTransparentCommitter<E: Engine, T: Transcript<E::Fr, Input = [u8; 32]>> {
+    max_degree_plus_one: usize,
+    lde_factor: usize,
+    output_coeffs_at_degree_plus_one: usize,
+    num_queries: usize,
+    grinding_bits: u32,
+    worker: Worker,
+    precomputed_inverse_omegas: PrecomputedInvOmegas<E::Fr>,
+    precomputed_bitreversed_omegas: BitReversedOmegas<E::Fr>,
+    _marker_t: std::marker::PhantomData<T>,
+}
+
+impl<E: Engine, T: Transcript<E::Fr, Input = [u8; 32]>> BatchedTransparentCommitter<E, T> {
+    pub fn new_for_size(
+        max_degree_plus_one: usize,
+        meta: TransparentCommitterParameters<E, BatchedFri<E>>,
+    ) -> Self {
+        let base_size = max_degree_plus_one.next_power_of_two();
+        assert!(meta.lde_factor.is_power_of_two());
+        assert!(meta.output_coeffs_at_degree_plus_one.is_power_of_two());
+        assert!(meta.grinding_bits <= super::pow::MAX_GRINDING_BITS);
+        let base_domain = Domain::<E::Fr>::new_for_size(base_size as u64)
+            .expect("domain of large enough size should exist");
+        let lde_domain = Domain::<E::Fr>::new_for_size((base_size * meta.lde_factor) as u64)
+            .expect("domain of large enough size should exist");
+        let worker = Worker::new();
+        let precomputed_inverse_omegas = PrecomputedInvOmegas::<E::Fr>::new_for_domain(&lde_domain, &worker);
+        let precomputed_bitreversed_omegas = BitReversedOmegas::<E::Fr>::new_for_domain(&base_domain, &worker);
+
+        Self {
+            max_degree_plus_one,
+            lde_factor: meta.lde_factor,
+            output_coeffs_at_degree_plus_one: meta.output_coeffs_at_degree_plus_one,
+            num_queries: meta.num_queries,
+            grinding_bits: meta.grinding_bits,
+            worker,
+            precomputed_inverse_omegas,
+            precomputed_bitreversed_omegas,
+            _marker_t: std::marker::PhantomData,
+        }
+    }
+
+    fn lde_size(&self) -> usize {
+        self.max_degree_plus_one.next_power_of_two() * self.lde_factor
+    }
+
+    /// Makes the LDEs of all the `polynomials` and commits to them in one tree.
+    pub fn commit_batch(&self, polynomials: &[&Polynomial<E, Coefficients>]) -> BatchedOracle<E> {
+        let ldes: Vec<_> = polynomials
+            .iter()
+            .map(|p| {
+                assert!(p.size() == self.max_degree_plus_one);
+                (*p).clone()
+                    .lde_using_bitreversed_ntt(&self.worker, self.lde_factor, &self.precomputed_bitreversed_omegas)
+                    .expect("must make an LDE")
+            })
+            .collect();
+
+        let columns: Vec<_> = ldes.iter().map(|l| l.as_ref()).collect();
+        let tree = BatchedBlake2sTree::create(&columns, &self.worker);
+
+        BatchedOracle { ldes, tree }
+    }
+
+    /// Sum of `aggregation_coefficient^k * (f_k(x) - y_k) / (x - z_k)` over the claims,
+    /// computed directly on the LDE domain, so no polynomial has to be divided or
+    /// extended again.
+    fn aggregated_quotient_lde(
+        &self,
+        oracles: &[&BatchedOracle<E>],
+        claims: &[OpeningClaim<E::Fr>],
+        aggregation_coefficient: E::Fr,
+    ) -> Polynomial<E, Values> {
+        let lde_size = self.lde_size();
+        let lde_domain = Domain::<E::Fr>::new_for_size(lde_size as u64).expect("domain exists");
+
+        let mut domain_elements = Polynomial::<E, Values>::from_values(vec![E::Fr::one(); lde_size])
+            .expect("is a power of two");
+        domain_elements.distribute_powers(&self.worker, lde_domain.generator);
+
+        let mut points: Vec<E::Fr> = vec![];
+        for claim in claims.iter() {
+            if !points.contains(&claim.at_point) {
+                points.push(claim.at_point);
+            }
+        }
+
+        let mut result = Polynomial::<E, Values>::from_values(vec![E::Fr::zero(); lde_size])
+            .expect("is a power of two");
+
+        for point in points.into_iter() {
+            let mut numerator = Polynomial::<E, Values>::from_values(vec![E::Fr::zero(); lde_size])
+                .expect("is a power of two");
+
+            let mut challenge = E::Fr::one();
+            for claim in claims.iter() {
+                if claim.at_point == point {
+                    let lde = &oracles[claim.batch].ldes[claim.polynomial];
+                    numerator.add_assign_scaled(&self.worker, lde, &challenge);
+
+                    let mut tmp = claim.value;
+                    tmp.mul_assign(&challenge);
+                    numerator.sub_constant(&self.worker, &tmp);
+                }
+                challenge.mul_assign(&aggregation_coefficient);
+            }
+
+            let mut inverse_denominator = domain_elements.clone();
+            inverse_denominator.sub_constant(&self.worker, &point);
+            inverse_denominator
+                .batch_inversion(&self.worker)
+                .expect("opening point is not in the LDE domain");
+
+            numerator.mul_assign(&self.worker, &inverse_denominator);
+            result.add_assign(&self.worker, &numerator);
+        }
+
+        result
+    }
+
+    pub fn open(
+        &self,
+        oracles: &[&BatchedOracle<E>],
+        claims: &[OpeningClaim<E::Fr>],
+        aggregation_coefficient: E::Fr,
+        prng: &mut T,
+    ) -> BatchedOpeningProof<E::Fr> {
+        assert!(!claims.is_empty());
+        for claim in claims.iter() {
+            assert!(claim.polynomial < oracles[claim.batch].num_polynomials());
+        }
+
+        let q_poly_lde = self.aggregated_quotient_lde(oracles, claims, aggregation_coefficient);
+        let lde_size = q_poly_lde.size();
+
+        let fri_proto = BatchedFri::<E>::proof_from_lde(
+            &q_poly_lde,
+            self.lde_factor,
+            self.output_coeffs_at_degree_plus_one,
+            &self.precomputed_inverse_omegas,
+            &self.worker,
+            prng,
+            &(),
+        )
+        .expect("FRI must succeed");
+
+        for c in fri_proto.get_final_coefficients().iter() {
+            prng.commit_field_element(&c);
+        }
+
+        let pow_nonce = super::pow::grind(prng, self.grinding_bits, &self.worker);
+
+        let domain_indexes = derive_query_indexes::<E::Fr, _, <TrivialBlake2sIOP<E::Fr> as IOP<E::Fr>>::Combiner>(
+            prng,
+            self.num_queries,
+            lde_size,
+        );
+
+        let fri_proof = BatchedFri::<E>::prototype_into_proof(fri_proto, &q_poly_lde, domain_indexes.clone(), &())
+            .expect("must generate a proper proof");
+
+        let columns: Vec<_> = oracles.iter().map(|o| o.columns()).collect();
+        let queries = domain_indexes
+            .into_iter()
+            .map(|idx| {
+                oracles
+                    .iter()
+                    .zip(columns.iter())
+                    .map(|(o, c)| o.tree.query(idx, c))
+                    .collect()
+            })
+            .collect();
+
+        BatchedOpeningProof {
+            fri_proof,
+            queries,
+            pow_nonce,
+        }
+    }
+
+    pub fn verify(
+        &self,
+        commitments: &[[u8; 32]],
+        claims: &[OpeningClaim<E::Fr>],
+        aggregation_coefficient: E::Fr,
+        proof: &BatchedOpeningProof<E::Fr>,
+        prng: &mut T,
+    ) -> bool {
+        let lde_size = self.lde_size();
+        let lde_domain = Domain::<E::Fr>::new_for_size(lde_size as u64).expect("domain exists");
+
+        let fri_challenges = BatchedFri::<E>::get_fri_challenges(&proof.fri_proof, prng, &());
+
+        for c in proof.fri_proof.get_final_coefficients().iter() {
+            prng.commit_field_element(&c);
+        }
+
+        if !super::pow::verify_grinding(prng, proof.pow_nonce, self.grinding_bits) {
+            return false;
+        }
+
+        let domain_indexes = derive_query_indexes::<E::Fr, _, <TrivialBlake2sIOP<E::Fr> as IOP<E::Fr>>::Combiner>(
+            prng,
+            self.num_queries,
+            lde_size,
+        );
+
+        if proof.queries.len() != domain_indexes.len() {
+            return false;
+        }
+
+        let mut simulated_q_poly_values = Vec::with_capacity(domain_indexes.len());
+
+        for (domain_idx, queries) in domain_indexes.iter().zip(proof.queries.iter()) {
+            if queries.len() != commitments.len() {
+                return false;
+            }
+
+            for (query, commitment) in queries.iter().zip(commitments.iter()) {
+                if query.natural_index != *domain_idx
+                    || !BatchedBlake2sTree::verify_query(commitment, query)
+                {
+                    return false;
+                }
+            }
+
+            let x = lde_domain.generator.pow(&[*domain_idx as u64]);
+
+            let mut value_at_x = E::Fr::zero();
+            let mut challenge = E::Fr::one();
+            for claim in claims.iter() {
+                let opened = match queries
+                    .get(claim.batch)
+                    .and_then(|q| q.values.get(claim.polynomial))
+                {
+                    Some(opened) => *opened,
+                    None => return false,
+                };
+
+                let mut num = opened;
+                num.sub_assign(&claim.value);
+
+                let mut den = x;
+                den.sub_assign(&claim.at_point);
+                let den_inversed = match den.inverse() {
+                    Some(inv) => inv,
+                    None => return false,
+                };
+
+                num.mul_assign(&den_inversed);
+                num.mul_assign(&challenge);
+                value_at_x.add_assign(&num);
+
+                challenge.mul_assign(&aggregation_coefficient);
+            }
+
+            simulated_q_poly_values.push(value_at_x);
+        }
+
+        BatchedFri::<E>::verify_proof_with_challenges(
+            &proof.fri_proof,
+            domain_indexes,
+            &simulated_q_poly_values,
+            &fri_challenges,
+            &(),
+        )
+        .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::plonk::utils::convert_to_field_elements;
+
+    const SIZE: usize = 16;
+
+    type Committer = BatchedTransparentCommitter<Bn256, Blake2sTranscript<Fr>>;
+
+    fn make_committer() -> Committer {
+        let meta = TransparentCommitterParameters {
+            lde_factor: 16,
+            num_queries: 4,
+            output_coeffs_at_degree_plus_one: 1,
+            grinding_bits: 4,
+            fri_params: (),
+        };
+
+        Committer::new_for_size(SIZE, meta)
+    }
+
+    fn make_polys(offset: usize, count: usize) -> Vec<Polynomial<Bn256, Coefficients>> {
+        let worker = Worker::new();
+        (0..count)
+            .map(|i| {
+                let coeffs: Vec<_> = (0..SIZE).map(|j| offset + i * SIZE + j).collect();
+                Polynomial::from_coeffs(convert_to_field_elements(&coeffs, &worker)).unwrap()
+            })
+            .collect()
+    }
+
+    fn make_claims(
+        batches: &[&Vec<Polynomial<Bn256, Coefficients>>],
+    ) -> Vec<OpeningClaim<Fr>> {
+        let worker = Worker::new();
+        let z = Fr::from_str("123").unwrap();
+        let mut z_omega = z;
+        z_omega.mul_assign(&Domain::<Fr>::new_for_size(SIZE as u64).unwrap().generator);
+
+        let mut claims = vec![];
+        for (batch, polys) in batches.iter().enumerate() {
+            for (polynomial, p) in polys.iter().enumerate() {
+                claims.push(OpeningClaim {
+                    batch,
+                    polynomial,
+                    at_point: z,
+                    value: p.evaluate_at(&worker, z),
+                });
+            }
+        }
+
+        // and the first polynomial at the shifted point
+        let p = &batches[0][0];
+        claims.push(OpeningClaim {
+            batch: 0,
+            polynomial: 0,
+            at_point: z_omega,
+            value: p.evaluate_at(&worker, z_omega),
+        });
+
+        claims
+    }
+
+    #[test]
+    fn test_batched_opening() {
+        let committer = make_committer();
+
+        let first = make_polys(0, 3);
+        let second = make_polys(1000, 2);
+
+        let first_oracle = committer.commit_batch(&first.iter().collect::<Vec<_>>());
+        let second_oracle = committer.commit_batch(&second.iter().collect::<Vec<_>>());
+        let commitments = [first_oracle.get_commitment(), second_oracle.get_commitment()];
+
+        let claims = make_claims(&[&first, &second]);
+        let aggregation_coefficient = Fr::from_str("7").unwrap();
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        let proof = committer.open(
+            &[&first_oracle, &second_oracle],
+            &claims,
+            aggregation_coefficient,
+            &mut transcript,
+        );
+
+        // one path per batch, not per polynomial
+        assert_eq!(proof.queries.len(), 4);
+        assert!(proof.queries.iter().all(|q| q.len() == 2));
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        assert!(committer.verify(&commitments, &claims, aggregation_coefficient, &proof, &mut transcript));
+
+        let mut wrong_claims = claims.clone();
+        wrong_claims[3].value.add_assign(&Fr::one());
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        assert!(!committer.verify(&commitments, &wrong_claims, aggregation_coefficient, &proof, &mut transcript));
+
+        let mut tampered = proof.clone();
+        tampered.queries[0][1].values[0].add_assign(&Fr::one());
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        assert!(!committer.verify(&commitments, &claims, aggregation_coefficient, &tampered, &mut transcript));
+    }
+
+    #[test]
+    fn test_wrong_opening_is_not_low_degree() {
+        let committer = make_committer();
+
+        let polys = make_polys(0, 2);
+        let oracle = committer.commit_batch(&polys.iter().collect::<Vec<_>>());
+
+        // the prover can not make a proof for a wrong value
+        let mut claims = make_claims(&[&polys]);
+        claims[1].value.add_assign(&Fr::one());
+        let aggregation_coefficient = Fr::from_str("7").unwrap();
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        let proof = committer.open(&[&oracle], &claims, aggregation_coefficient, &mut transcript);
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        assert!(!committer.verify(&[oracle.get_commitment()], &claims, aggregation_coefficient, &proof, &mut transcript));
+    }
+}
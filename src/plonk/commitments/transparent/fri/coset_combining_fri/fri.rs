@@ -7,26 +7,38 @@ use crate::SynthesisError;
 use crate::plonk::commitments::transparent::utils::log2_floor;
 use crate::plonk::commitments::transcript::Prng;
 use crate::plonk::commitments::transparent::precomputations::*;
+use crate::plonk::fft::cooley_tukey_ntt::bitreverse;
 use super::*;
 
-pub struct CosetCombiningFriIop<E: Engine> {
+/// Commits every layer with the tree `I`, Blake2s based by default. Algebraic trees
+/// (`FriSpecificRescueTree`, `FriSpecificPoseidonTree`) take their permutation parameters
+/// from `CosetParams::hash_params`.
+pub struct CosetCombiningFriIop<E: Engine, I = FriSpecificBlake2sTree<E::Fr>> {
     cosets_schedule: Vec<usize>,
     _marker_f: std::marker::PhantomData<E::Fr>,
+    _marker_i: std::marker::PhantomData<I>,
 }
 
 #[derive(Clone, Debug)]
-pub struct CosetParams<F: PrimeField> {
+pub struct CosetParams<F: PrimeField, H = ()> {
     pub cosets_schedule: Vec<usize>,
-    pub coset_factor: F
+    pub coset_factor: F,
+    pub hash_params: H,
+    /// Size of the LDE domain, the degree bound times the LDE factor. The verifier
+    /// rejects proofs that claim another one.
+    pub domain_size: usize,
 }
 
-impl<E: Engine> FriIop<E> for CosetCombiningFriIop<E> {
+/// Folding uses the inverse roots of unity from `OmegasInvBitreversed` of the LDE domain,
+/// and the verifier recomputes exactly those, so other precomputations would produce
+/// proofs that do not verify.
+impl<E: Engine, I: FriSpecificTree<E::Fr>> FriIop<E> for CosetCombiningFriIop<E, I> {
     const DEGREE: usize = 2;
 
-    type IopType = FriSpecificBlake2sTree<E::Fr>;
+    type IopType = I;
     type ProofPrototype = FRIProofPrototype<E, Self::IopType>;
     type Proof = FRIProof<E::Fr, Self::IopType>;
-    type Params = CosetParams<E::Fr>;
+    type Params = CosetParams<E::Fr, I::HashParams>;
 
     fn proof_from_lde<P: Prng<E::Fr, Input = <Self::IopType as IopInstance<E::Fr>>::Commitment>,
         C: FriPrecomputations<E::Fr>
@@ -56,8 +68,7 @@ impl<E: Engine> FriIop<E> for CosetCombiningFriIop<E> {
         natural_first_element_indexes: Vec<usize>,
         params: &Self::Params
     ) -> Result<Self::Proof, SynthesisError> {
-        unimplemented!()
-        // prototype.produce_proof(iop_values, natural_first_element_indexes)
+        prototype.produce_proof(iop_values, natural_first_element_indexes, params)
     }
 
     fn get_fri_challenges<P: Prng<E::Fr, Input = <Self::IopType as IopInstance<E::Fr>>::Commitment>>(
@@ -65,15 +76,15 @@ impl<E: Engine> FriIop<E> for CosetCombiningFriIop<E> {
         prng: &mut P,
         params: &Self::Params
     ) -> Vec<E::Fr> {
+        // mirror the prover: every root is followed by as many challenges
+        // as there are foldings by 2 in the corresponding step
         let mut fri_challenges = vec![];
 
-        for root in proof.roots.iter() {
-            let iop_challenge = {
-                prng.commit_input(&root);
-                prng.get_challenge()
-            };
-
-            fri_challenges.push(iop_challenge);
+        for (root, coset_factor) in proof.roots.iter().zip(params.cosets_schedule.iter()) {
+            prng.commit_input(&root);
+            for _ in 0..*coset_factor {
+                fri_challenges.push(prng.get_challenge());
+            }
         }
 
         fri_challenges
@@ -86,8 +97,13 @@ impl<E: Engine> FriIop<E> for CosetCombiningFriIop<E> {
         fri_challenges: &[E::Fr],
         params: &Self::Params
     ) -> Result<bool, SynthesisError> {
-        unimplemented!()
-        // Self::verify_proof_queries(proof, natural_element_indexes, Self::DEGREE, expected_value, fri_challenges)
+        Self::verify_proof_queries(
+            proof,
+            natural_element_indexes,
+            expected_value,
+            fri_challenges,
+            params
+        )
     }
 }
 
@@ -96,7 +112,7 @@ use pairing::Engine;
 
 #[derive(PartialEq, Eq, Clone)]
 pub struct FRIProofPrototype<E: Engine, I: IopInstance<E::Fr>> {
-    // pub l0_commitment: I,
+    pub l0_commitment: I,
     pub intermediate_commitments: Vec<I>,
     pub intermediate_values: Vec< Polynomial<E, Values> >,
     pub challenges: Vec<Vec<E::Fr>>,
@@ -110,7 +126,7 @@ pub struct FRIProofPrototype<E: Engine, I: IopInstance<E::Fr>> {
 impl<E: Engine, I: IopInstance<E::Fr>> FriProofPrototype<E::Fr, I> for FRIProofPrototype<E, I> {
     fn get_roots(&self) -> Vec<I::Commitment> {
         let mut roots = vec![];
-        // roots.push(self.l0_commitment.get_commitment().clone());
+        roots.push(self.l0_commitment.get_commitment().clone());
         for c in self.intermediate_commitments.iter() {
             roots.push(c.get_commitment().clone());
         }
@@ -147,7 +163,27 @@ impl<F: PrimeField, I: IopInstance<F>> FriProof<F, I> for FRIProof<F, I> {
     }
 }
 
-impl<E: Engine> CosetCombiningFriIop<E> {
+/// Layers are committed in the bitreversed enumeration, so the element with a natural
+/// `natural_index` is found in the leaf of `values_per_leaf` elements together with
+/// all the elements it is folded with. Returns the indexes of that leaf and the position
+/// of the element inside of it.
+pub fn coset_for_natural_index(
+    natural_index: usize,
+    domain_size: usize,
+    values_per_leaf: usize
+) -> (Vec<usize>, usize) {
+    debug_assert!(domain_size.is_power_of_two());
+    debug_assert!(values_per_leaf.is_power_of_two());
+    debug_assert!(natural_index < domain_size);
+
+    let log_n = log2_floor(domain_size) as usize;
+    let bitreversed_index = bitreverse(natural_index, log_n);
+    let start = bitreversed_index - (bitreversed_index % values_per_leaf);
+
+    ((start..(start + values_per_leaf)).collect(), bitreversed_index - start)
+}
+
+impl<E: Engine, I: FriSpecificTree<E::Fr>> CosetCombiningFriIop<E, I> {
     pub fn proof_from_lde_by_values<P: Prng<E::Fr, Input = <<Self as FriIop<E>>::IopType as IopInstance<E::Fr>>::Commitment>,
         C: FriPrecomputations<E::Fr>
     >(
@@ -169,13 +205,11 @@ impl<E: Engine> CosetCombiningFriIop<E> {
         }
 
         let mut roots = vec![];
-        // let tree_params = FriSpecificBlake2sTreeParams {
-        //     values_per_leaf: (1 << coset_factor)
-        // };
+        let tree_params = I::tree_params(1 << coset_factor, &params.hash_params);
 
-        // let l0_commitment = FriSpecificBlake2sTree::create(lde_values.as_ref(), &tree_params);
-        // let root = l0_commitment.get_commitment();
-        // roots.push(root);
+        let l0_commitment = I::create(lde_values.as_ref(), &tree_params);
+        let root = l0_commitment.get_commitment();
+        roots.push(root);
         let initial_domain_size = lde_values.size();
 
         assert_eq!(precomputations.domain_size(), initial_domain_size);
@@ -197,7 +231,7 @@ impl<E: Engine> CosetCombiningFriIop<E> {
         let mut challenges = vec![];
         let num_challenges = coset_factor;
         let mut next_domain_challenges = {
-            // prng.commit_input(&l0_commitment.get_commitment());
+            prng.commit_input(&l0_commitment.get_commitment());
             let mut challenges = vec![];
             for _ in 0..num_challenges {
                 challenges.push(prng.get_challenge());
@@ -315,10 +349,8 @@ impl<E: Engine> CosetCombiningFriIop<E> {
                 coset_schedule_index += 1;
                 this_domain_size = next_domain_size;
                 let coset_factor = params.cosets_schedule[coset_schedule_index];
-                let tree_params = FriSpecificBlake2sTreeParams {
-                    values_per_leaf: (1 << coset_factor)
-                };
-                let intermediate_iop = FriSpecificBlake2sTree::create(next_values.as_ref(), &tree_params);
+                let tree_params = I::tree_params(1 << coset_factor, &params.hash_params);
+                let intermediate_iop = I::create(next_values.as_ref(), &tree_params);
                 let root = intermediate_iop.get_commitment();
                 roots.push(root);
                 let num_challenges = coset_factor;
@@ -345,16 +377,18 @@ impl<E: Engine> CosetCombiningFriIop<E> {
         let final_root = roots.last().expect("will work").clone();
 
         assert_eq!(challenges.len(), num_steps);
-        // assert_eq!(roots.len(), num_steps);
+        assert_eq!(roots.len(), num_steps);
         assert_eq!(intermediate_commitments.len(), num_steps-1);
         assert_eq!(intermediate_values.len(), num_steps);
 
+        // the last layer lives on the LDE coset raised to the total wrapping factor
         let mut final_poly_values = Polynomial::from_values(values_slice.to_vec())?;
         final_poly_values.bitreverse_enumeration(&worker);
         let final_poly_coeffs:Polynomial<E, Coefficients> = if params.coset_factor == E::Fr::one() {
-            final_poly_values.icoset_fft(&worker, &mut None)
+            final_poly_values.ifft(&worker, &mut None)
         } else {
-            final_poly_values.icoset_fft_for_generator(&worker, &params.coset_factor, &mut None)
+            let final_coset_factor = params.coset_factor.pow([total_wrap_factor as u64]);
+            final_poly_values.icoset_fft_for_generator(&worker, &final_coset_factor, &mut None)
         };
 
         let mut final_poly_coeffs = final_poly_coeffs.into_coeffs();
@@ -373,7 +407,7 @@ impl<E: Engine> CosetCombiningFriIop<E> {
         final_poly_coeffs.truncate(output_coeffs_at_degree_plus_one);
 
         Ok(FRIProofPrototype {
-            // l0_commitment,
+            l0_commitment,
             intermediate_commitments,
             intermediate_values,
             challenges,
@@ -387,107 +421,360 @@ impl<E: Engine> CosetCombiningFriIop<E> {
     }
 }
 
-// #[cfg(test)]
-// mod test {
-//     #[test]
-//     fn test_bench_fri_with_coset_combining() {
-//         use crate::ff::Field;
-//         use crate::ff::PrimeField;
-//         use rand::{XorShiftRng, SeedableRng, Rand, Rng};
-//         use crate::plonk::transparent_engine::proth_engine::Fr;
-//         use crate::plonk::transparent_engine::PartialTwoBitReductionField;
-//         use crate::plonk::polynomials::*;
-//         use std::time::Instant;
-//         use crate::worker::Worker;
-//         use crate::plonk::commitments::transparent::utils::*;
-//         use crate::plonk::fft::cooley_tukey_ntt::{CTPrecomputations, BitReversedOmegas, OmegasInvBitreversed};
-//         use crate::plonk::commitments::transparent::fri::coset_combining_fri::FriPrecomputations;
-//         use crate::plonk::commitments::transparent::fri::coset_combining_fri::fri::*;
-//         use crate::plonk::commitments::transcript::*;
-//
-//         const SIZE: usize = 1024;
-//
-//         let mut transcript = Blake2sTranscript::<Fr>::new();
-//
-//         let worker = Worker::new();
-//
-//         let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
-//         let coeffs = (0..SIZE).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
-//
-//         let poly = Polynomial::<Fr, _>::from_coeffs(coeffs).unwrap();
-//         let precomp = BitReversedOmegas::<Fr>::new_for_domain_size(poly.size());
-//         let start = Instant::now();
-//         let coset_factor = Fr::multiplicative_generator();
-//         let eval_result = poly.bitreversed_lde_using_bitreversed_ntt(&worker, 16, &precomp, &coset_factor).unwrap();
-//         println!("LDE with factor 16 for size {} bitreversed {:?}", SIZE, start.elapsed());
-//
-//         let fri_precomp = <OmegasInvBitreversed::<Fr> as FriPrecomputations<Fr>>::new_for_domain_size(eval_result.size());
-//
-//         let params = CosetParams::<Fr> {
-//             cosets_schedule: vec![3, 3, 3],
-//             coset_factor: coset_factor
-//         };
-//
-//         let fri_proto = CosetCombiningFriIop::<Fr>::proof_from_lde(
-//             &eval_result,
-//             16,
-//             2,
-//             &fri_precomp,
-//             &worker,
-//             &mut transcript,
-//             &params
-//         ).expect("FRI must succeed");
-//     }
-//
-//     #[test]
-//     #[should_panic]
-//     fn test_invalid_eval_fri_with_coset_combining() {
-//         use crate::ff::Field;
-//         use crate::ff::PrimeField;
-//         use rand::{XorShiftRng, SeedableRng, Rand, Rng};
-//         use crate::plonk::transparent_engine::proth_engine::Fr;
-//         use crate::plonk::transparent_engine::PartialTwoBitReductionField;
-//         use crate::plonk::polynomials::*;
-//         use std::time::Instant;
-//         use crate::worker::Worker;
-//         use crate::plonk::commitments::transparent::utils::*;
-//         use crate::plonk::fft::cooley_tukey_ntt::{CTPrecomputations, BitReversedOmegas, OmegasInvBitreversed};
-//         use crate::plonk::commitments::transparent::fri::coset_combining_fri::FriPrecomputations;
-//         use crate::plonk::commitments::transparent::fri::coset_combining_fri::fri::*;
-//         use crate::plonk::commitments::transcript::*;
-//
-//         const SIZE: usize = 1024;
-//
-//         let mut transcript = Blake2sTranscript::<Fr>::new();
-//
-//         let worker = Worker::new_with_cpus(1);
-//
-//         let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
-//         let coeffs = (0..SIZE).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
-//
-//         let poly = Polynomial::<Fr, _>::from_coeffs(coeffs).unwrap();
-//         let precomp = BitReversedOmegas::<Fr>::new_for_domain_size(poly.size());
-//         let start = Instant::now();
-//         let coset_factor = Fr::multiplicative_generator();
-//         let mut eval_result = poly.bitreversed_lde_using_bitreversed_ntt(&worker, 16, &precomp, &coset_factor).unwrap();
-//         eval_result.as_mut()[1].sub_assign(&Fr::one());
-//         println!("LDE with factor 16 for size {} bitreversed {:?}", SIZE, start.elapsed());
-//
-//         let fri_precomp = <OmegasInvBitreversed::<Fr> as FriPrecomputations<Fr>>::new_for_domain_size(eval_result.size());
-//
-//         let params = CosetParams::<Fr> {
-//             cosets_schedule: vec![3, 3, 3],
-//             coset_factor: coset_factor
-//         };
-//
-//         let fri_proto = CosetCombiningFriIop::<Fr>::proof_from_lde(
-//             &eval_result,
-//             16,
-//             2,
-//             &fri_precomp,
-//             &worker,
-//             &mut transcript,
-//             &params
-//         ).expect("FRI must succeed");
-//     }
-// }
\ No newline at end of file
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::plonk::commitments::algebraic_hash::{PoseidonParams, RescueParams};
+    use crate::plonk::commitments::transcript::poseidon_transcript::PoseidonTranscript;
+    use crate::plonk::commitments::transcript::rescue_transcript::RescueTranscript;
+    use crate::plonk::commitments::transcript::*;
+    use crate::plonk::commitments::transparent::iop_compiler::coset_combining_algebraic_tree::*;
+    use crate::plonk::fft::cooley_tukey_ntt::OmegasInvBitreversed;
+    use crate::plonk::transparent_engine::{Fr, Transparent252};
+    use rand::{Rand, Rng, SeedableRng, XorShiftRng};
+
+    const SIZE: usize = 256;
+    const LDE_FACTOR: usize = 16;
+    const NUM_QUERIES: usize = 8;
+
+    type Iop = CosetCombiningFriIop<Transparent252>;
+    type Proof = FRIProof<Fr, FriSpecificBlake2sTree<Fr>>;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654])
+    }
+
+    // bitreversed values of a random polynomial of degree SIZE - 1 on the (coset) LDE domain
+    fn make_lde(coset_factor: Fr, worker: &Worker) -> Polynomial<Transparent252, Values> {
+        let rng = &mut rng();
+        let mut coeffs = (0..SIZE).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        coeffs.resize(SIZE * LDE_FACTOR, Fr::zero());
+        let poly = Polynomial::<Transparent252, _>::from_coeffs(coeffs).unwrap();
+
+        let mut lde = if coset_factor == Fr::one() {
+            poly.fft(worker, &mut None)
+        } else {
+            poly.coset_fft_for_generator(worker, coset_factor)
+        };
+        lde.bitreverse_enumeration(worker);
+
+        lde
+    }
+
+    fn make_prototype(
+        lde: &Polynomial<Transparent252, Values>,
+        params: &CosetParams<Fr>,
+        worker: &Worker,
+    ) -> FRIProofPrototype<Transparent252, FriSpecificBlake2sTree<Fr>> {
+        let total_wrap_factor = 1 << params.cosets_schedule.iter().sum::<usize>();
+        let fri_precomp =
+            <OmegasInvBitreversed<Fr> as FriPrecomputations<Fr>>::new_for_domain_size(lde.size());
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+
+        Iop::proof_from_lde(
+            lde,
+            LDE_FACTOR,
+            SIZE / total_wrap_factor,
+            &fri_precomp,
+            worker,
+            &mut transcript,
+            params,
+        )
+        .expect("FRI must succeed")
+    }
+
+    fn query_indexes() -> Vec<usize> {
+        let rng = &mut rng();
+        (0..NUM_QUERIES)
+            .map(|_| rng.gen::<usize>() % (SIZE * LDE_FACTOR))
+            .collect()
+    }
+
+    fn oracle_values(lde: &Polynomial<Transparent252, Values>, indexes: &[usize]) -> Vec<Fr> {
+        let log_n = log2_floor(lde.size()) as usize;
+        indexes
+            .iter()
+            .map(|idx| lde.as_ref()[bitreverse(*idx, log_n)])
+            .collect()
+    }
+
+    fn verify(proof: &Proof, indexes: Vec<usize>, expected: &[Fr], params: &CosetParams<Fr>) -> bool {
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        let challenges = Iop::get_fri_challenges(proof, &mut transcript, params);
+
+        Iop::verify_proof_with_challenges(proof, indexes, expected, &challenges, params)
+            .expect("must not error")
+    }
+
+    fn tree_round_trip<I: FriSpecificTree<Fr>, T: Prng<Fr, Input = I::Commitment>>(
+        cosets_schedule: Vec<usize>,
+        coset_factor: Fr,
+        hash_params: I::HashParams,
+    ) {
+        let worker = Worker::new();
+        let params = CosetParams {
+            cosets_schedule,
+            coset_factor,
+            hash_params,
+            domain_size: SIZE * LDE_FACTOR,
+        };
+
+        let total_wrap_factor = 1 << params.cosets_schedule.iter().sum::<usize>();
+        let lde = make_lde(coset_factor, &worker);
+        let fri_precomp =
+            <OmegasInvBitreversed<Fr> as FriPrecomputations<Fr>>::new_for_domain_size(lde.size());
+        let mut transcript = T::new();
+        let prototype = CosetCombiningFriIop::<Transparent252, I>::proof_from_lde(
+            &lde,
+            LDE_FACTOR,
+            SIZE / total_wrap_factor,
+            &fri_precomp,
+            &worker,
+            &mut transcript,
+            &params,
+        )
+        .expect("FRI must succeed");
+
+        let indexes = query_indexes();
+        let mut expected = oracle_values(&lde, &indexes);
+        let proof = CosetCombiningFriIop::<Transparent252, I>::prototype_into_proof(
+            prototype,
+            &lde,
+            indexes.clone(),
+            &params,
+        )
+        .unwrap();
+
+        let mut transcript = T::new();
+        let challenges =
+            CosetCombiningFriIop::<Transparent252, I>::get_fri_challenges(&proof, &mut transcript, &params);
+        let verify_values = |expected: &[Fr]| {
+            CosetCombiningFriIop::<Transparent252, I>::verify_proof_with_challenges(
+                &proof,
+                indexes.clone(),
+                expected,
+                &challenges,
+                &params,
+            )
+            .expect("must not error")
+        };
+
+        assert!(
+            verify_values(&expected),
+            "valid proof is rejected for schedule {:?}",
+            params.cosets_schedule
+        );
+
+        expected[0].double();
+        assert!(!verify_values(&expected), "proof of other values is accepted");
+    }
+
+    fn round_trip(cosets_schedule: Vec<usize>, coset_factor: Fr) {
+        tree_round_trip::<FriSpecificBlake2sTree<Fr>, Blake2sTranscript<Fr>>(
+            cosets_schedule,
+            coset_factor,
+            (),
+        );
+    }
+
+    #[test]
+    fn test_coset_combining_fri_round_trip() {
+        let coset_factor = Fr::multiplicative_generator();
+
+        // values per leaf are 2, 4 and 8, as well as different ones per layer
+        round_trip(vec![1, 1, 1], coset_factor);
+        round_trip(vec![2, 2], coset_factor);
+        round_trip(vec![3, 3], coset_factor);
+        round_trip(vec![3, 2, 1], coset_factor);
+        round_trip(vec![1, 3], coset_factor);
+        round_trip(vec![4], coset_factor);
+    }
+
+    #[test]
+    fn test_coset_combining_fri_round_trip_without_coset() {
+        round_trip(vec![2, 1, 2], Fr::one());
+    }
+
+    #[test]
+    fn test_coset_combining_fri_with_algebraic_trees() {
+        let coset_factor = Fr::multiplicative_generator();
+
+        tree_round_trip::<FriSpecificRescueTree<Fr>, RescueTranscript<Fr>>(
+            vec![2, 1, 1],
+            coset_factor,
+            RescueParams::new_2_into_1(),
+        );
+        tree_round_trip::<FriSpecificPoseidonTree<Fr>, PoseidonTranscript<Fr>>(
+            vec![3, 1],
+            coset_factor,
+            PoseidonParams::new_2_into_1(),
+        );
+    }
+
+    #[test]
+    fn test_coset_combining_fri_rejects_corrupted_queries() {
+        let worker = Worker::new();
+        let params = CosetParams::<Fr> {
+            cosets_schedule: vec![2, 2, 1],
+            coset_factor: Fr::multiplicative_generator(),
+            hash_params: (),
+            domain_size: SIZE * LDE_FACTOR,
+        };
+
+        let lde = make_lde(params.coset_factor, &worker);
+        let prototype = make_prototype(&lde, &params, &worker);
+        let indexes = query_indexes();
+        let expected = oracle_values(&lde, &indexes);
+
+        // queries over tampered values that keep the honest Merkle paths
+        let (l0_indexes, l0_position) =
+            coset_for_natural_index(indexes[0], lde.size(), 1 << params.cosets_schedule[0]);
+        let tampered_idx = l0_indexes[0] + l0_position;
+        let mut tampered_lde = lde.as_ref().to_vec();
+        tampered_lde[tampered_idx].add_assign(&Fr::one());
+        let tampered_l0_query = prototype
+            .l0_commitment
+            .produce_query(l0_indexes, &tampered_lde);
+
+        let layer = &prototype.intermediate_values[0];
+        let (layer_indexes, _) = coset_for_natural_index(
+            indexes[0] % layer.size(),
+            layer.size(),
+            1 << params.cosets_schedule[1],
+        );
+        let mut tampered_layer = layer.as_ref().to_vec();
+        tampered_layer[layer_indexes[1]].double();
+        let tampered_layer_query = prototype.intermediate_commitments[0]
+            .produce_query(layer_indexes, &tampered_layer);
+
+        let proof = Iop::prototype_into_proof(prototype, &lde, indexes.clone(), &params).unwrap();
+        assert!(verify(&proof, indexes.clone(), &expected, &params));
+
+        let corruptions: Vec<(&str, Box<dyn Fn(&mut Proof, &mut Vec<Fr>) + '_>)> = vec![
+            (
+                "initial value",
+                Box::new(|proof, expected| {
+                    expected[0] = tampered_lde[tampered_idx];
+                    proof.queries[0][0] = tampered_l0_query.clone();
+                }),
+            ),
+            (
+                "intermediate value",
+                Box::new(|proof, _| proof.queries[0][1] = tampered_layer_query.clone()),
+            ),
+            (
+                "query of other index",
+                Box::new(|proof, _| {
+                    let other = proof
+                        .queries
+                        .iter()
+                        .position(|q| q[1] != proof.queries[0][1])
+                        .expect("queries must differ");
+                    proof.queries[0][1] = proof.queries[other][1].clone();
+                }),
+            ),
+            (
+                "root",
+                Box::new(|proof, _| proof.roots[1] = proof.roots[2]),
+            ),
+            (
+                "missing layer",
+                Box::new(|proof, _| {
+                    proof.queries[0].pop();
+                }),
+            ),
+            (
+                "final coefficients",
+                Box::new(|proof, _| proof.final_coefficients[0].add_assign(&Fr::one())),
+            ),
+            (
+                "oracle value",
+                Box::new(|_, expected| expected[0].double()),
+            ),
+        ];
+
+        for (name, corrupt) in corruptions.into_iter() {
+            let mut proof = proof.clone();
+            let mut expected = expected.clone();
+            corrupt(&mut proof, &mut expected);
+            assert!(
+                !verify(&proof, indexes.clone(), &expected, &params),
+                "proof with corrupted {} is accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_coset_combining_fri_rejects_index_out_of_domain() {
+        let worker = Worker::new();
+        let params = CosetParams::<Fr> {
+            cosets_schedule: vec![2, 2],
+            coset_factor: Fr::multiplicative_generator(),
+            hash_params: (),
+            domain_size: SIZE * LDE_FACTOR,
+        };
+
+        let lde = make_lde(params.coset_factor, &worker);
+        let prototype = make_prototype(&lde, &params, &worker);
+        let mut indexes = query_indexes();
+        let expected = oracle_values(&lde, &indexes);
+        let proof = Iop::prototype_into_proof(prototype, &lde, indexes.clone(), &params).unwrap();
+
+        indexes[0] += lde.size();
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        let challenges = Iop::get_fri_challenges(&proof, &mut transcript, &params);
+        match Iop::verify_proof_with_challenges(&proof, indexes, &expected, &challenges, &params) {
+            Err(SynthesisError::MalformedProof) => {}
+            other => panic!("expected a malformed proof error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_coset_combining_fri_rejects_other_domain_size() {
+        let worker = Worker::new();
+        let params = CosetParams::<Fr> {
+            cosets_schedule: vec![2, 2],
+            coset_factor: Fr::multiplicative_generator(),
+            hash_params: (),
+            domain_size: SIZE * LDE_FACTOR,
+        };
+
+        let lde = make_lde(params.coset_factor, &worker);
+        let prototype = make_prototype(&lde, &params, &worker);
+        let indexes = query_indexes();
+        let expected = oracle_values(&lde, &indexes);
+        let proof = Iop::prototype_into_proof(prototype, &lde, indexes.clone(), &params).unwrap();
+        assert!(verify(&proof, indexes.clone(), &expected, &params));
+
+        // the product of the claimed sizes overflows
+        let mut overflowing = proof.clone();
+        overflowing.initial_degree_plus_one = 1 << (usize::max_value().count_ones() - 1);
+        overflowing.output_coeffs_at_degree_plus_one = overflowing.initial_degree_plus_one >> 4;
+        overflowing.lde_factor = 1 << 2;
+        assert!(!verify(&overflowing, indexes.clone(), &expected, &params));
+
+        // consistent with itself, but not the domain the verifier expects
+        let mut larger = proof.clone();
+        larger.initial_degree_plus_one *= 2;
+        larger.output_coeffs_at_degree_plus_one *= 2;
+        assert!(!verify(&larger, indexes, &expected, &params));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_eval_fri_with_coset_combining() {
+        let worker = Worker::new();
+        let params = CosetParams::<Fr> {
+            cosets_schedule: vec![3, 3],
+            coset_factor: Fr::multiplicative_generator(),
+            hash_params: (),
+            domain_size: SIZE * LDE_FACTOR,
+        };
+
+        let mut lde = make_lde(params.coset_factor, &worker);
+        lde.as_mut()[1].sub_assign(&Fr::one());
+
+        make_prototype(&lde, &params, &worker);
+    }
+}
@@ -1,69 +1,65 @@
-use super::super::*;
 use super::fri::*;
-use crate::pairing::ff::PrimeField;
-use crate::plonk::commitments::transparent::iop::*;
-use crate::plonk::commitments::transparent::iop::*;
-use crate::plonk::commitments::transparent::utils::log2_floor;
-use crate::plonk::domains::*;
+use super::*;
+use crate::pairing::Engine;
+use crate::plonk::commitments::transparent::iop_compiler::*;
 use crate::plonk::polynomials::*;
-use crate::worker::Worker;
 use crate::SynthesisError;
 
-impl<F: PrimeField, I: IOP<F>> FRIProofPrototype<F, I> {
-    pub fn produce_proof(
+impl<E: Engine, I: IopInstance<E::Fr>> FRIProofPrototype<E, I> {
+    /// Opens every committed layer at the leaf the query falls into. `iop_values` are the
+    /// (bitreversed) LDE values the prototype was made from.
+    pub fn produce_proof<H>(
         self,
-        iop_values: &Polynomial<F, Values>,
+        iop_values: &Polynomial<E, Values>,
         natural_first_element_indexes: Vec<usize>,
-    ) -> Result<FRIProof<F, I>, SynthesisError> {
+        params: &CosetParams<E::Fr, H>,
+    ) -> Result<FRIProof<E::Fr, I>, SynthesisError> {
         let domain_size = self.initial_degree_plus_one * self.lde_factor;
+        assert_eq!(iop_values.size(), domain_size);
+        assert_eq!(params.domain_size, domain_size);
+        assert_eq!(
+            self.intermediate_commitments.len() + 1,
+            params.cosets_schedule.len()
+        );
 
-        let mut roots = vec![];
-        let l0_commitment = Some(self.l0_commitment);
-
-        for iop in l0_commitment.iter().chain(&self.intermediate_commitments) {
-            roots.push(iop.get_root());
-        }
+        let roots = self.get_roots();
 
         let mut rounds = vec![];
 
         for natural_first_element_index in natural_first_element_indexes.into_iter() {
+            if natural_first_element_index >= domain_size {
+                return Err(SynthesisError::MalformedProof);
+            }
+
             let mut queries = vec![];
             let mut domain_idx = natural_first_element_index;
             let mut domain_size = domain_size;
 
-            for (iop, leaf_values) in l0_commitment
-                .iter()
+            for ((iop, leaf_values), coset_factor) in Some(&self.l0_commitment)
+                .into_iter()
                 .chain(&self.intermediate_commitments)
                 .zip(
                     Some(iop_values)
                         .into_iter()
                         .chain(&self.intermediate_values),
                 )
+                .zip(params.cosets_schedule.iter())
             {
-                let coset_values = <I::Combiner as CosetCombiner<F>>::get_coset_for_natural_index(
-                    domain_idx,
-                    domain_size,
-                );
-                if coset_values.len() != <I::Combiner as CosetCombiner<F>>::COSET_SIZE {
-                    return Err(SynthesisError::PolynomialDegreeTooLarge);
-                }
-
-                for idx in coset_values.into_iter() {
-                    let query = iop.query(idx, leaf_values.as_ref());
-                    queries.push(query);
-                }
+                let wrapping_factor = 1 << *coset_factor;
+                let (coset_indexes, _) =
+                    coset_for_natural_index(domain_idx, domain_size, wrapping_factor);
 
-                let (next_idx, next_size) =
-                    Domain::<F>::index_and_size_for_next_domain(domain_idx, domain_size);
+                let query = iop.produce_query(coset_indexes, leaf_values.as_ref());
+                queries.push(query);
 
-                domain_idx = next_idx;
-                domain_size = next_size;
+                domain_size /= wrapping_factor;
+                domain_idx %= domain_size;
             }
 
             rounds.push(queries);
         }
 
-        let proof = FRIProof::<F, I> {
+        let proof = FRIProof::<E::Fr, I> {
             queries: rounds,
             roots,
             final_coefficients: self.final_coefficients,
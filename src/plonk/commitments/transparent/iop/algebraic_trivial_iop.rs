@@ -0,0 +1,348 @@
+use super::super::utils::log2_floor;
+use super::trivial_coset_combiner::*;
+use super::*;
+use crate::pairing::ff::PrimeField;
+use crate::plonk::commitments::algebraic_hash::{
+    compress, sponge_hash, PoseidonParams, RescueParams, SharedParams,
+};
+use crate::worker::Worker;
+
+/// Leafs are the field elements themselves, the roots are challenges as they are.
+pub struct AlgebraicLeafEncoder<F: PrimeField> {
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> LeafEncoder<F> for AlgebraicLeafEncoder<F> {
+    type Output = F;
+
+    fn encode_leaf(value: &F) -> Self::Output {
+        *value
+    }
+}
+
+impl<F: PrimeField> FiatShamirHasher<F> for AlgebraicLeafEncoder<F> {
+    type Input = F;
+
+    fn transform(value: &Self::Input) -> F {
+        *value
+    }
+}
+
+impl HashFunctionOutput for crate::pairing::bn256::Fr {}
+impl HashFunctionOutput for crate::plonk::transparent_engine::Fr {}
+
+/// Leafs are hashed with the sponge and nodes with the two-to-one compression of the
+/// shared parameters of `P`.
+pub struct AlgebraicTreeHasher<F: PrimeField, P: SharedParams<F>> {
+    _marker: std::marker::PhantomData<(F, P)>,
+}
+
+impl<F: PrimeField + HashFunctionOutput, P: SharedParams<F>> IopTreeHasher<F>
+    for AlgebraicTreeHasher<F, P>
+{
+    type HashOutput = F;
+    type LeafEncoder = AlgebraicLeafEncoder<F>;
+
+    fn hash_leaf(value: &F) -> Self::HashOutput {
+        Self::hash_encoded_leaf(value)
+    }
+
+    fn hash_encoded_leaf(value: &F) -> Self::HashOutput {
+        sponge_hash(&*P::shared(), std::slice::from_ref(value))
+    }
+
+    fn hash_node(values: &[Self::HashOutput], _level: usize) -> Self::HashOutput {
+        debug_assert!(values.len() == 2);
+
+        compress(&*P::shared(), &values[0], &values[1])
+    }
+}
+
+pub struct AlgebraicIopTree<F: PrimeField, P: SharedParams<F>> {
+    size: usize,
+    nodes: Vec<F>,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<F: PrimeField, P: SharedParams<F>> AlgebraicIopTree<F, P> {
+    fn hash_level(inputs: &[F], outputs: &mut [F], params: &P, worker: &Worker) {
+        assert!(outputs.len() * 2 == inputs.len());
+        assert!(outputs.len().is_power_of_two());
+
+        worker.scope(outputs.len(), |scope, chunk| {
+            for (o, i) in outputs.chunks_mut(chunk).zip(inputs.chunks(chunk * 2)) {
+                scope.spawn(move |_| {
+                    for (o, i) in o.iter_mut().zip(i.chunks(2)) {
+                        *o = compress(params, &i[0], &i[1]);
+                    }
+                });
+            }
+        });
+    }
+}
+
+impl<F: PrimeField + HashFunctionOutput, P: SharedParams<F>> IopTree<F> for AlgebraicIopTree<F, P> {
+    type Combiner = TrivialCombiner<F>;
+    type TreeHasher = AlgebraicTreeHasher<F, P>;
+    type FiatShamirTransformer = AlgebraicLeafEncoder<F>;
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn create(leafs: &[F]) -> Self {
+        let num_leafs = leafs.len();
+        assert!(num_leafs == num_leafs.next_power_of_two());
+        assert!(num_leafs >= 2, "tree must have at least two leafs");
+
+        // take the parameters once instead of on every hash
+        let params = P::shared();
+        let params = &*params;
+
+        let mut nodes = vec![F::zero(); num_leafs];
+        let mut leaf_hashes = vec![F::zero(); num_leafs];
+
+        let worker = Worker::new();
+
+        worker.scope(leafs.len(), |scope, chunk| {
+            for (i, lh) in leaf_hashes.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    let base_idx = i * chunk;
+                    for (j, lh) in lh.iter_mut().enumerate() {
+                        let leaf_ref = <Self::Combiner as CosetCombiner<F>>::get_for_tree_index(
+                            &leafs,
+                            base_idx + j,
+                        );
+                        *lh = sponge_hash(params, std::slice::from_ref(leaf_ref));
+                    }
+                });
+            }
+        });
+
+        let num_levels = log2_floor(num_leafs) as usize;
+        let mut nodes_for_hashing = &mut nodes[..];
+
+        // leaf hashes go into the first level of nodes
+        {
+            let (_, outputs) = nodes_for_hashing.split_at_mut(nodes_for_hashing.len() / 2);
+            Self::hash_level(&leaf_hashes, outputs, params, &worker);
+        }
+
+        for _ in (0..(num_levels - 1)).rev() {
+            let (next_levels, inputs) = nodes_for_hashing.split_at_mut(nodes_for_hashing.len() / 2);
+            let (_, outputs) = next_levels.split_at_mut(next_levels.len() / 2);
+            Self::hash_level(inputs, outputs, params, &worker);
+
+            nodes_for_hashing = next_levels;
+        }
+
+        Self {
+            size: num_leafs,
+            nodes,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn get_root(&self) -> F {
+        self.nodes[1]
+    }
+
+    fn encode_root_into_challenge(root: &F) -> F {
+        <Self::FiatShamirTransformer as FiatShamirHasher<F>>::transform(&root)
+    }
+
+    fn get_challenge_scalar_from_root(&self) -> F {
+        let root = self.get_root();
+
+        Self::encode_root_into_challenge(&root)
+    }
+
+    fn verify(root: &F, leaf_value: &F, path: &[F], tree_index: usize) -> bool {
+        let params = P::shared();
+
+        let mut hash = sponge_hash(&*params, std::slice::from_ref(leaf_value));
+        let mut idx = tree_index;
+        for el in path.iter() {
+            hash = if idx & 1usize == 0 {
+                compress(&*params, &hash, el)
+            } else {
+                compress(&*params, el, &hash)
+            };
+            idx >>= 1;
+        }
+
+        &hash == root
+    }
+
+    fn get_path(&self, tree_index: usize, leafs_values: &[F]) -> Vec<F> {
+        assert!(self.size == self.nodes.len());
+        let mut nodes = &self.nodes[..];
+
+        let tree_pair_index = tree_index ^ 1usize;
+        let pair_natural_index =
+            <Self::Combiner as CosetCombiner<F>>::tree_index_into_natural_index(tree_pair_index);
+
+        let mut path = vec![];
+        path.push(<Self::TreeHasher as IopTreeHasher<F>>::hash_leaf(
+            &leafs_values[pair_natural_index],
+        ));
+
+        let mut idx = tree_index;
+        idx >>= 1;
+
+        for _ in 0..log2_floor(nodes.len() / 2) {
+            let half_len = nodes.len() / 2;
+            let (next_level, this_level) = nodes.split_at(half_len);
+            let pair_idx = idx ^ 1usize;
+            path.push(this_level[pair_idx]);
+            idx >>= 1;
+            nodes = next_level;
+        }
+
+        path
+    }
+}
+
+/// Trivial IOP over an algebraic tree, its roots are committed to the `RescueTranscript`
+/// or the `PoseidonTranscript` as field elements.
+pub struct TrivialAlgebraicIOP<F: PrimeField, P: SharedParams<F>> {
+    tree: AlgebraicIopTree<F, P>,
+}
+
+pub type TrivialRescueIOP<F> = TrivialAlgebraicIOP<F, RescueParams<F>>;
+pub type TrivialPoseidonIOP<F> = TrivialAlgebraicIOP<F, PoseidonParams<F>>;
+
+impl<F: PrimeField + HashFunctionOutput, P: SharedParams<F>> IOP<F> for TrivialAlgebraicIOP<F, P> {
+    type Combiner = TrivialCombiner<F>;
+    type Tree = AlgebraicIopTree<F, P>;
+    type Query = TrivialAlgebraicIopQuery<F, P>;
+
+    fn create(leafs: &[F]) -> Self {
+        let tree = Self::Tree::create(leafs);
+
+        Self { tree }
+    }
+
+    fn get_for_natural_index(leafs: &[F], natural_index: usize) -> &F {
+        <Self::Combiner as CosetCombiner<F>>::get_for_natural_index(leafs, natural_index)
+    }
+
+    fn get_for_tree_index(leafs: &[F], tree_index: usize) -> &F {
+        <Self::Combiner as CosetCombiner<F>>::get_for_tree_index(leafs, tree_index)
+    }
+
+    fn get_root(&self) -> F {
+        self.tree.get_root()
+    }
+
+    fn verify_query(query: &Self::Query, root: &F) -> bool {
+        Self::Tree::verify(root, &query.value(), &query.path(), query.tree_index())
+    }
+
+    fn query(&self, natural_index: usize, leafs: &[F]) -> Self::Query {
+        assert!(natural_index < self.tree.size());
+        assert!(natural_index < leafs.len());
+        let value = leafs[natural_index];
+
+        let tree_index =
+            <Self::Combiner as CosetCombiner<F>>::natural_index_into_tree_index(natural_index);
+
+        let path = self.tree.get_path(tree_index, leafs);
+
+        TrivialAlgebraicIopQuery {
+            index: natural_index,
+            value: vec![value],
+            path,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField + HashFunctionOutput, P: SharedParams<F>> PartialEq for TrivialAlgebraicIOP<F, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_root() == other.get_root()
+    }
+}
+
+impl<F: PrimeField + HashFunctionOutput, P: SharedParams<F>> Eq for TrivialAlgebraicIOP<F, P> {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrivialAlgebraicIopQuery<F: PrimeField, P: SharedParams<F>> {
+    index: usize,
+    value: Vec<F>,
+    path: Vec<F>,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<F: PrimeField + HashFunctionOutput, P: SharedParams<F>> IopQuery<F>
+    for TrivialAlgebraicIopQuery<F, P>
+{
+    type TreeHasher = AlgebraicTreeHasher<F, P>;
+
+    fn natural_index(&self) -> usize {
+        self.index
+    }
+
+    fn natural_indexes(&self) -> Vec<usize> {
+        vec![self.index]
+    }
+
+    fn tree_index(&self) -> usize {
+        self.index
+    }
+
+    fn value(&self) -> F {
+        self.value[0]
+    }
+
+    fn values(&self) -> &[F] {
+        &self.value
+    }
+
+    fn path(&self) -> &[F] {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ff::Field;
+    use crate::pairing::bn256::Fr;
+
+    const SIZE: usize = 64;
+
+    fn check_all_queries<P: SharedParams<Fr>>() {
+        let mut inputs = vec![];
+        let mut f = Fr::one();
+        for _ in 0..SIZE {
+            inputs.push(f);
+            f.double();
+        }
+
+        let iop = TrivialAlgebraicIOP::<Fr, P>::create(&inputs);
+        let root = iop.get_root();
+        for i in 0..SIZE {
+            let query = iop.query(i, &inputs);
+            assert!(
+                TrivialAlgebraicIOP::verify_query(&query, &root),
+                "invalid query for leaf {}",
+                i
+            );
+        }
+
+        let mut query = iop.query(1, &inputs);
+        query.value[0].add_assign(&Fr::one());
+        assert!(!TrivialAlgebraicIOP::verify_query(&query, &root));
+    }
+
+    #[test]
+    fn make_small_rescue_iop() {
+        check_all_queries::<RescueParams<Fr>>();
+    }
+
+    #[test]
+    fn make_small_poseidon_iop() {
+        check_all_queries::<PoseidonParams<Fr>>();
+    }
+}
@@ -5,6 +5,11 @@ use crate::pairing::ff::{PrimeField, PrimeFieldRepr};
 use crate::worker::Worker;
 use blake2s_simd::{Params, State};
 
+use crate::byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use crate::plonk::better_cs::keys::{read_fr, write_fr};
+use crate::plonk::commitments::transparent::serialization::{read_merkle_path, write_merkle_path};
+use std::io::{Read, Write};
+
 lazy_static! {
     static ref BASE_BLAKE2S_PARAMS: State = {
         Params::new()
@@ -361,6 +366,32 @@ pub struct TrivialBlake2sIopQuery<F: PrimeField> {
     path: Vec<[u8; 32]>,
 }
 
+impl<F: PrimeField> TrivialBlake2sIopQuery<F> {
+    pub(crate) fn from_parts(index: usize, value: F, path: Vec<[u8; 32]>) -> Self {
+        Self {
+            index,
+            value: vec![value],
+            path,
+        }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_u64::<BigEndian>(self.index as u64)?;
+        write_fr(&self.value(), &mut writer)?;
+        write_merkle_path(&self.path, &mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let index = reader.read_u64::<BigEndian>()?;
+        let value = read_fr(&mut reader)?;
+        let path = read_merkle_path(&mut reader)?;
+
+        Ok(Self::from_parts(index as usize, value, path))
+    }
+}
+
 impl<F: PrimeField> IopQuery<F> for TrivialBlake2sIopQuery<F> {
     type TreeHasher = Blake2sTreeHasher<F>;
 
@@ -0,0 +1,302 @@
+use super::super::utils::log2_floor;
+use super::*;
+use crate::pairing::ff::PrimeField;
+use crate::plonk::commitments::algebraic_hash::{
+    compress, sponge_hash, AlgebraicPermutation, PoseidonParams, RescueParams,
+};
+use crate::worker::Worker;
+
+/// Same layout as `FriSpecificBlake2sTree`, but leafs and nodes are field elements hashed
+/// with an algebraic permutation, so the paths are cheap to verify in a circuit.
+#[derive(Clone, Debug)]
+pub struct FriSpecificAlgebraicTree<F: PrimeField, P: AlgebraicPermutation<F>> {
+    size: usize,
+    nodes: Vec<F>,
+    params: FriSpecificAlgebraicTreeParams<P>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FriSpecificAlgebraicTreeParams<P> {
+    pub values_per_leaf: usize,
+    pub hash_params: P,
+}
+
+pub type FriSpecificRescueTree<F> = FriSpecificAlgebraicTree<F, RescueParams<F>>;
+pub type FriSpecificPoseidonTree<F> = FriSpecificAlgebraicTree<F, PoseidonParams<F>>;
+
+impl<F: PrimeField, P: AlgebraicPermutation<F>> FriSpecificAlgebraicTree<F, P> {
+    fn make_full_path(&self, leaf_index: usize, leaf_pair_hash: F) -> Vec<F> {
+        let mut nodes = &self.nodes[..];
+
+        let mut path = vec![];
+        path.push(leaf_pair_hash);
+
+        let mut idx = leaf_index;
+        idx >>= 1;
+
+        for _ in 0..log2_floor(nodes.len() / 2) {
+            let half_len = nodes.len() / 2;
+            let (next_level, this_level) = nodes.split_at(half_len);
+            let pair_idx = idx ^ 1usize;
+            let value = this_level[pair_idx];
+            path.push(value);
+            idx >>= 1;
+            nodes = next_level;
+        }
+
+        path
+    }
+
+    fn hash_level(inputs: &[F], outputs: &mut [F], hash_params: &P, worker: &Worker) {
+        assert!(outputs.len() * 2 == inputs.len());
+        assert!(outputs.len().is_power_of_two());
+
+        worker.scope(outputs.len(), |scope, chunk| {
+            for (o, i) in outputs.chunks_mut(chunk).zip(inputs.chunks(chunk * 2)) {
+                scope.spawn(move |_| {
+                    for (o, i) in o.iter_mut().zip(i.chunks(2)) {
+                        *o = compress(hash_params, &i[0], &i[1]);
+                    }
+                });
+            }
+        });
+    }
+}
+
+impl<F: PrimeField, P: AlgebraicPermutation<F>> IopInstance<F> for FriSpecificAlgebraicTree<F, P> {
+    type Commitment = F;
+    type Params = FriSpecificAlgebraicTreeParams<P>;
+    type Query = AlgebraicCosetCombinedQuery<F>;
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn create(values: &[F], params: &Self::Params) -> Self {
+        assert!(params.values_per_leaf.is_power_of_two());
+
+        let values_per_leaf = params.values_per_leaf;
+        let num_leafs = values.len() / values_per_leaf;
+        assert!(num_leafs.is_power_of_two());
+        assert!(num_leafs >= 2, "tree must have at least two leafs");
+
+        let size = values.len();
+        let hash_params = &params.hash_params;
+
+        let mut nodes = vec![F::zero(); num_leafs];
+        let mut leaf_hashes = vec![F::zero(); num_leafs];
+
+        let worker = Worker::new();
+
+        worker.scope(leaf_hashes.len(), |scope, chunk| {
+            for (i, lh) in leaf_hashes.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    let base_idx = i * chunk;
+                    for (j, lh) in lh.iter_mut().enumerate() {
+                        let values_start = (base_idx + j) * values_per_leaf;
+                        let values_end = values_start + values_per_leaf;
+                        *lh = sponge_hash(hash_params, &values[values_start..values_end]);
+                    }
+                });
+            }
+        });
+
+        let num_levels = log2_floor(num_leafs) as usize;
+        let mut nodes_for_hashing = &mut nodes[..];
+
+        // leaf hashes go into the first level of nodes
+        {
+            let (_, outputs) = nodes_for_hashing.split_at_mut(nodes_for_hashing.len() / 2);
+            Self::hash_level(&leaf_hashes, outputs, hash_params, &worker);
+        }
+
+        for _ in (0..(num_levels - 1)).rev() {
+            let (next_levels, inputs) = nodes_for_hashing.split_at_mut(nodes_for_hashing.len() / 2);
+            let (_, outputs) = next_levels.split_at_mut(next_levels.len() / 2);
+            Self::hash_level(inputs, outputs, hash_params, &worker);
+
+            nodes_for_hashing = next_levels;
+        }
+
+        Self {
+            size,
+            nodes,
+            params: params.clone(),
+        }
+    }
+
+    fn get_commitment(&self) -> Self::Commitment {
+        self.nodes[1]
+    }
+
+    fn produce_query(&self, indexes: Vec<usize>, values: &[F]) -> Self::Query {
+        let values_per_leaf = self.params.values_per_leaf;
+
+        // we never expect that query is mis-alligned, so check it
+        debug_assert!(indexes[0] % values_per_leaf == 0);
+        debug_assert!(
+            indexes == (indexes[0]..(indexes[0] + values_per_leaf)).collect::<Vec<_>>()
+        );
+        debug_assert!(*indexes.last().expect("is some") < self.size());
+        debug_assert!(*indexes.last().expect("is some") < values.len());
+
+        let query_values = Vec::from(&values[indexes[0]..(indexes[0] + values_per_leaf)]);
+
+        let leaf_index = indexes[0] / values_per_leaf;
+        let pair_index = leaf_index ^ 1;
+
+        let leaf_pair_hash = sponge_hash(
+            &self.params.hash_params,
+            &values[(pair_index * values_per_leaf)..((pair_index + 1) * values_per_leaf)],
+        );
+
+        let path = self.make_full_path(leaf_index, leaf_pair_hash);
+
+        AlgebraicCosetCombinedQuery::<F> {
+            indexes,
+            values: query_values,
+            path,
+        }
+    }
+
+    fn verify_query(
+        commitment: &Self::Commitment,
+        query: &Self::Query,
+        params: &Self::Params,
+    ) -> bool {
+        if query.values().len() != params.values_per_leaf || query.indexes.is_empty() {
+            return false;
+        }
+
+        let mut hash = sponge_hash(&params.hash_params, query.values());
+        let mut idx = query.indexes[0] / params.values_per_leaf;
+
+        for el in query.path.iter() {
+            hash = if idx & 1usize == 0 {
+                compress(&params.hash_params, &hash, el)
+            } else {
+                compress(&params.hash_params, el, &hash)
+            };
+            idx >>= 1;
+        }
+
+        &hash == commitment
+    }
+}
+
+impl<F: PrimeField, P: AlgebraicPermutation<F>> FriSpecificTree<F> for FriSpecificAlgebraicTree<F, P> {
+    type HashParams = P;
+
+    fn tree_params(values_per_leaf: usize, hash_params: &P) -> Self::Params {
+        FriSpecificAlgebraicTreeParams {
+            values_per_leaf,
+            hash_params: hash_params.clone(),
+        }
+    }
+}
+
+impl<F: PrimeField, P: AlgebraicPermutation<F>> PartialEq for FriSpecificAlgebraicTree<F, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_commitment() == other.get_commitment()
+    }
+}
+
+impl<F: PrimeField, P: AlgebraicPermutation<F>> Eq for FriSpecificAlgebraicTree<F, P> {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlgebraicCosetCombinedQuery<F: PrimeField> {
+    indexes: Vec<usize>,
+    values: Vec<F>,
+    path: Vec<F>,
+}
+
+impl<F: PrimeField> AlgebraicCosetCombinedQuery<F> {
+    pub fn path(&self) -> &[F] {
+        &self.path
+    }
+}
+
+impl<F: PrimeField> IopQuery<F> for AlgebraicCosetCombinedQuery<F> {
+    fn indexes(&self) -> Vec<usize> {
+        self.indexes.clone()
+    }
+
+    fn values(&self) -> &[F] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ff::Field;
+    use crate::pairing::bn256::Fr;
+
+    const SIZE: usize = 16;
+    const VALUES_PER_LEAF: usize = 4;
+
+    fn make_inputs() -> Vec<Fr> {
+        let mut inputs = vec![];
+        let mut f = Fr::one();
+        for _ in 0..SIZE {
+            inputs.push(f);
+            f.double();
+        }
+
+        inputs
+    }
+
+    fn check_all_leafs<P: AlgebraicPermutation<Fr>>(hash_params: P) {
+        let params = FriSpecificAlgebraicTreeParams {
+            values_per_leaf: VALUES_PER_LEAF,
+            hash_params,
+        };
+
+        let inputs = make_inputs();
+        let iop = FriSpecificAlgebraicTree::create(&inputs, &params);
+        let commitment = iop.get_commitment();
+        assert!(iop.size() == SIZE);
+        assert!(iop.nodes.len() == (SIZE / VALUES_PER_LEAF));
+
+        for i in 0..(SIZE / VALUES_PER_LEAF) {
+            let indexes: Vec<_> =
+                ((i * VALUES_PER_LEAF)..(VALUES_PER_LEAF + i * VALUES_PER_LEAF)).collect();
+            let query = iop.produce_query(indexes, &inputs);
+            let valid = FriSpecificAlgebraicTree::verify_query(&commitment, &query, &params);
+            assert!(valid, "invalid query for leaf index {}", i);
+        }
+    }
+
+    #[test]
+    fn make_small_rescue_iop() {
+        check_all_leafs(RescueParams::<Fr>::new_2_into_1());
+    }
+
+    #[test]
+    fn make_small_poseidon_iop() {
+        check_all_leafs(PoseidonParams::<Fr>::new_2_into_1());
+    }
+
+    #[test]
+    fn test_tampered_query_is_rejected() {
+        let params = FriSpecificAlgebraicTreeParams {
+            values_per_leaf: VALUES_PER_LEAF,
+            hash_params: RescueParams::<Fr>::new_2_into_1(),
+        };
+
+        let inputs = make_inputs();
+        let iop = FriSpecificRescueTree::create(&inputs, &params);
+        let commitment = iop.get_commitment();
+
+        let indexes: Vec<_> = (VALUES_PER_LEAF..(2 * VALUES_PER_LEAF)).collect();
+        let mut query = iop.produce_query(indexes, &inputs);
+        query.values[1].add_assign(&Fr::one());
+        assert!(!FriSpecificRescueTree::verify_query(&commitment, &query, &params));
+
+        // a valid query for one leaf does not open another one
+        let indexes: Vec<_> = (0..VALUES_PER_LEAF).collect();
+        let mut query = iop.produce_query(indexes, &inputs);
+        query.indexes = (VALUES_PER_LEAF..(2 * VALUES_PER_LEAF)).collect();
+        assert!(!FriSpecificRescueTree::verify_query(&commitment, &query, &params));
+    }
+}
@@ -4,7 +4,12 @@ use crate::pairing::ff::{PrimeField, PrimeFieldRepr};
 use crate::worker::Worker;
 use blake2s_const::blake2s_const;
 
-#[derive(Debug)]
+use crate::byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use crate::plonk::better_cs::keys::{read_fr_vec, write_fr_vec};
+use crate::plonk::commitments::transparent::serialization::{read_merkle_path, write_merkle_path};
+use std::io::{Read, Write};
+
+#[derive(Clone, Debug)]
 pub struct FriSpecificBlake2sTree<F: PrimeField> {
     size: usize,
     nodes: Vec<[u8; 32]>,
@@ -17,6 +22,14 @@ pub struct FriSpecificBlake2sTreeParams {
     pub values_per_leaf: usize,
 }
 
+impl<F: PrimeField> FriSpecificTree<F> for FriSpecificBlake2sTree<F> {
+    type HashParams = ();
+
+    fn tree_params(values_per_leaf: usize, _hash_params: &()) -> Self::Params {
+        FriSpecificBlake2sTreeParams { values_per_leaf }
+    }
+}
+
 // impl<F: PrimeField> FriSpecificBlake2sTree<F> {
 //     pub fn new() -> Self {
 //         Self {
@@ -275,6 +288,57 @@ pub struct CosetCombinedQuery<F: PrimeField> {
     path: Vec<[u8; 32]>,
 }
 
+impl<F: PrimeField> CosetCombinedQuery<F> {
+    /// Query of the leaf with `values` that starts at the index `first_index`.
+    pub(crate) fn from_parts(first_index: usize, values: Vec<F>, path: Vec<[u8; 32]>) -> Self {
+        Self {
+            indexes: (first_index..(first_index + values.len())).collect(),
+            values,
+            path,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &[[u8; 32]] {
+        &self.path
+    }
+
+    /// Leaf indexes are consecutive, so only the first one is written.
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let first_index = self.first_index()?;
+        writer.write_u64::<BigEndian>(first_index as u64)?;
+        write_fr_vec(&self.values, &mut writer)?;
+        write_merkle_path(&self.path, &mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let first_index = reader.read_u64::<BigEndian>()?;
+        let values = read_fr_vec(&mut reader)?;
+        let path = read_merkle_path(&mut reader)?;
+
+        Ok(Self::from_parts(first_index as usize, values, path))
+    }
+
+    pub(crate) fn first_index(&self) -> std::io::Result<usize> {
+        let first_index = self.indexes.first().cloned().unwrap_or(0);
+        let is_consecutive = self.indexes.len() == self.values.len()
+            && self
+                .indexes
+                .iter()
+                .enumerate()
+                .all(|(i, idx)| *idx == first_index + i);
+        if !is_consecutive {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "query indexes must be consecutive and match the values",
+            ));
+        }
+
+        Ok(first_index)
+    }
+}
+
 impl<F: PrimeField> IopQuery<F> for CosetCombinedQuery<F> {
     fn indexes(&self) -> Vec<usize> {
         self.indexes.clone()
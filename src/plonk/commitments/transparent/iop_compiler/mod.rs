@@ -1,5 +1,6 @@
 use crate::ff::PrimeField;
 
+pub mod coset_combining_algebraic_tree;
 pub mod coset_combining_blake2s_tree;
 
 pub trait Commitment: Clone + Eq + PartialEq + std::fmt::Debug {}
@@ -16,6 +17,15 @@ pub trait IopInstance<F: PrimeField>: PartialEq + Eq {
     fn produce_query(&self, indexes: Vec<usize>, values: &[F]) -> Self::Query;
 }
 
+/// Trees that put all the values folded together by one FRI step into the same leaf.
+/// Every layer of a FRI proof is committed with its own number of values per leaf,
+/// but with the same hash parameters.
+pub trait FriSpecificTree<F: PrimeField>: IopInstance<F> {
+    type HashParams: Clone + Eq + PartialEq + std::fmt::Debug;
+
+    fn tree_params(values_per_leaf: usize, hash_params: &Self::HashParams) -> Self::Params;
+}
+
 pub trait IopQuery<F: PrimeField>: 'static + PartialEq + Eq + Clone + std::fmt::Debug {
     fn indexes(&self) -> Vec<usize>;
     fn values(&self) -> &[F];
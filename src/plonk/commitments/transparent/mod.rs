@@ -6,8 +6,12 @@ use super::CommitmentScheme;
 
 pub mod precomputations;
 pub mod iop;
+pub mod batched;
 pub mod fri;
 pub mod iop_compiler;
+pub mod pow;
+pub mod security;
+pub mod serialization;
 
 pub mod utils;
 
@@ -29,6 +33,7 @@ pub struct StatelessTransparentCommitter<
     lde_factor: usize,
     output_coeffs_at_degree_plus_one: usize,
     num_queries: usize,
+    grinding_bits: u32,
     worker: Worker,
     precomputed_inverse_omegas: PrecomputedInvOmegas<E::Fr>,
     precomputed_bitreversed_omegas: BitReversedOmegas<E::Fr>,
@@ -52,15 +57,32 @@ pub struct TransparentCommitterParameters<E: Engine, FRI: FriIop<E>>{
     pub lde_factor: usize,
     pub num_queries: usize,
     pub output_coeffs_at_degree_plus_one: usize,
+    /// Proof-of-work bits required before the query phase, zero disables the grinding.
+    pub grinding_bits: u32,
     pub fri_params: <FRI as FriIop<E>>::Params,
 }
 
+impl<E: Engine, FRI: FriIop<E>> TransparentCommitterParameters<E, FRI> {
+    /// Estimated soundness of the openings of polynomials of `max_degree_plus_one`
+    /// coefficients, see `security::estimate_security_bits`.
+    pub fn estimated_security_bits(&self, max_degree_plus_one: usize, model: self::security::FriSecurityModel) -> f64 {
+        let settings = self::security::FriSecuritySettings {
+            lde_factor: self.lde_factor,
+            num_queries: self.num_queries,
+            grinding_bits: self.grinding_bits,
+        };
+
+        self::security::estimate_security_bits(E::Fr::NUM_BITS, max_degree_plus_one, &settings, model)
+    }
+}
+
 impl<E: Engine, FRI: FriIop<E>> Clone for TransparentCommitterParameters<E, FRI> {
     fn clone(&self) -> Self {
         TransparentCommitterParameters::<E, FRI>{
             lde_factor: self.lde_factor,
             num_queries: self.num_queries,
             output_coeffs_at_degree_plus_one: self.output_coeffs_at_degree_plus_one,
+            grinding_bits: self.grinding_bits,
             fri_params: self.fri_params.clone(),
         }
     }
@@ -75,7 +97,8 @@ impl<
     T: Transcript<E::Fr, Input = < < < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Tree as IopTree<E::Fr> >::TreeHasher as IopTreeHasher<E::Fr>>::HashOutput >
 > CommitmentScheme<E> for StatelessTransparentCommitter<E, FRI, T> {
     type Commitment = < < < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Tree as IopTree<E::Fr> >::TreeHasher as IopTreeHasher<E::Fr>>::HashOutput;
-    type OpeningProof = (FRI::Proof, Vec<Vec< (E::Fr, < < FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Query) > >);
+    // FRI proof, queries to the original polynomials and the proof-of-work nonce
+    type OpeningProof = (FRI::Proof, Vec<Vec< (E::Fr, < < FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Query) > >, u64);
     type IntermediateData = (Polynomial<E, Values>, < FRI as FriIop<E> >::IopType);
     type Meta = TransparentCommitterParameters<E, FRI>;
     type Prng = T;
@@ -87,6 +110,7 @@ impl<
         let base_size = max_degree_plus_one.next_power_of_two();
         assert!(meta.lde_factor.is_power_of_two());
         assert!(meta.output_coeffs_at_degree_plus_one.is_power_of_two());
+        assert!(meta.grinding_bits <= self::pow::MAX_GRINDING_BITS);
         let lde_domain_size = base_size*meta.lde_factor;
         let base_domain = Domain::<E::Fr>::new_for_size(base_size as u64).expect("domain of large enough size should exist");
         let lde_domain = Domain::<E::Fr>::new_for_size(lde_domain_size as u64).expect("domain of large enough size should exist");
@@ -99,6 +123,7 @@ impl<
             lde_factor: meta.lde_factor,
             output_coeffs_at_degree_plus_one: meta.output_coeffs_at_degree_plus_one,
             num_queries: meta.num_queries,
+            grinding_bits: meta.grinding_bits,
             worker: worker,
             precomputed_inverse_omegas: omegas_inv_precomp,
             precomputed_bitreversed_omegas: omegas_bitrev_precomp,
@@ -174,27 +199,9 @@ impl<
             prng.commit_field_element(&c);
         }
 
-        let mut used_queries: Vec<usize> = vec![];
+        let pow_nonce = self::pow::grind(prng, self.grinding_bits, &self.worker);
 
-        let mut domain_indexes = vec![];
-
-        // even while this is conditional, it can be changed to unconditional given large enough field
-
-        while domain_indexes.len() < self.num_queries {
-            let domain_idx = bytes_to_challenge_index(prng.get_challenge_bytes(), lde_size);
-            let coset_index_values = < < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner as CosetCombiner<E::Fr>>::get_coset_for_natural_index(domain_idx, lde_size);
-            let mut can_use = true;
-            for v in coset_index_values.iter() {
-                if used_queries.contains(&v) {
-                    can_use = false;
-                    break
-                }
-            }
-            if can_use {
-                domain_indexes.push(domain_idx);
-                used_queries.extend(coset_index_values);
-            }
-        }
+        let domain_indexes = derive_query_indexes::<E::Fr, _, < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner>(prng, self.num_queries, lde_size);
 
         let q_poly_fri_proof = FRI::prototype_into_proof(fri_proto, &q_poly_lde, domain_indexes.clone(), &self.fri_params).expect("must generate a proper proof");
 
@@ -223,7 +230,7 @@ impl<
         println!("Done in {:?} for max degree {}", start.elapsed(), poly.size());
         println!("Done open single");
 
-        (q_poly_fri_proof, vec![original_poly_queries])
+        (q_poly_fri_proof, vec![original_poly_queries], pow_nonce)
 
     }
 
@@ -303,27 +310,9 @@ impl<
             prng.commit_field_element(&c);
         }
 
-        let mut used_queries: Vec<usize> = vec![];
-
-        let mut domain_indexes = vec![];
+        let pow_nonce = self::pow::grind(prng, self.grinding_bits, &self.worker);
 
-        // even while this is conditional, it can be changed to unconditional given large enough field
-
-        while domain_indexes.len() < self.num_queries {
-            let domain_idx = bytes_to_challenge_index(prng.get_challenge_bytes(), lde_size);
-            let coset_index_values = < < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner as CosetCombiner<E::Fr>>::get_coset_for_natural_index(domain_idx, lde_size);
-            let mut can_use = true;
-            for v in coset_index_values.iter() {
-                if used_queries.contains(&v) {
-                    can_use = false;
-                    break
-                }
-            }
-            if can_use {
-                domain_indexes.push(domain_idx);
-                used_queries.extend(coset_index_values);
-            }
-        }
+        let domain_indexes = derive_query_indexes::<E::Fr, _, < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner>(prng, self.num_queries, lde_size);
 
         let q_poly_fri_proof = FRI::prototype_into_proof(
             fri_proto, 
@@ -378,11 +367,11 @@ impl<
         println!("Done in {:?} for max degree {}", start.elapsed(), max_degree);
         println!("Done open multiple");
 
-        (q_poly_fri_proof, queries)
+        (q_poly_fri_proof, queries, pow_nonce)
     }
 
     fn verify_single(&self, commitment: &Self::Commitment, at_point: E::Fr, claimed_value: E::Fr, proof: &Self::OpeningProof, prng: &mut Self::Prng) -> bool {
-        let (q_poly_fri_proof, original_poly_queries_vec) = proof;
+        let (q_poly_fri_proof, original_poly_queries_vec, pow_nonce) = proof;
 
         assert!(original_poly_queries_vec.len() == 1);
 
@@ -399,29 +388,13 @@ impl<
             prng.commit_field_element(&c);
         }
 
-        // then make expected query locations
-
-        let mut used_queries: Vec<usize> = vec![];
-
-        let mut domain_indexes = vec![];
+        if !self::pow::verify_grinding(prng, *pow_nonce, self.grinding_bits) {
+            return false;
+        }
 
-        // even while this is conditional, it can be changed to unconditional given large enough field
+        // then make expected query locations
 
-        while domain_indexes.len() < self.num_queries {
-            let domain_idx = bytes_to_challenge_index(prng.get_challenge_bytes(), lde_size);
-            let coset_index_values = < < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner as CosetCombiner<E::Fr>>::get_coset_for_natural_index(domain_idx, lde_size);
-            let mut can_use = true;
-            for v in coset_index_values.iter() {
-                if used_queries.contains(&v) {
-                    can_use = false;
-                    break
-                }
-            }
-            if can_use {
-                domain_indexes.push(domain_idx);
-                used_queries.extend(coset_index_values);
-            }
-        }
+        let domain_indexes = derive_query_indexes::<E::Fr, _, < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner>(prng, self.num_queries, lde_size);
 
         // now simulate expected values
 
@@ -472,7 +445,7 @@ impl<
         proof: &Self::OpeningProof, 
         prng: &mut Self::Prng
     ) -> bool {
-        let (q_poly_fri_proof, original_poly_queries_vec) = proof;
+        let (q_poly_fri_proof, original_poly_queries_vec, pow_nonce) = proof;
 
         let lde_size = self.max_degree_plus_one.next_power_of_two() * self.lde_factor;
         let lde_domain = Domain::<E::Fr>::new_for_size(lde_size as u64).expect("large enough domain must exist");
@@ -485,29 +458,13 @@ impl<
             prng.commit_field_element(&c);
         }
 
-        // then make expected query locations
-
-        let mut used_queries: Vec<usize> = vec![];
-
-        let mut domain_indexes = vec![];
+        if !self::pow::verify_grinding(prng, *pow_nonce, self.grinding_bits) {
+            return false;
+        }
 
-        // even while this is conditional, it can be changed to unconditional given large enough field
+        // then make expected query locations
 
-        while domain_indexes.len() < self.num_queries {
-            let domain_idx = bytes_to_challenge_index(prng.get_challenge_bytes(), lde_size);
-            let coset_index_values = < < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner as CosetCombiner<E::Fr>>::get_coset_for_natural_index(domain_idx, lde_size);
-            let mut can_use = true;
-            for v in coset_index_values.iter() {
-                if used_queries.contains(&v) {
-                    can_use = false;
-                    break
-                }
-            }
-            if can_use {
-                domain_indexes.push(domain_idx);
-                used_queries.extend(coset_index_values);
-            }
-        }
+        let domain_indexes = derive_query_indexes::<E::Fr, _, < <FRI as FriIop<E> >::IopType as IOP<E::Fr> >::Combiner>(prng, self.num_queries, lde_size);
 
         // now simulate expected values
 
@@ -609,6 +566,38 @@ fn kate_divison_with_same_return_size<F: PrimeField>(a: &[F], mut b: F) -> Vec<F
     q
 }
 
+/// Draws `num_queries` indexes of the LDE domain from the transcript, skipping the ones
+/// whose coset is already queried.
+pub(crate) fn derive_query_indexes<F: PrimeField, T: Transcript<F>, C: CosetCombiner<F>>(
+    prng: &mut T,
+    num_queries: usize,
+    lde_size: usize
+) -> Vec<usize> {
+    let mut used_queries: Vec<usize> = vec![];
+
+    let mut domain_indexes = vec![];
+
+    // even while this is conditional, it can be changed to unconditional given large enough field
+
+    while domain_indexes.len() < num_queries {
+        let domain_idx = bytes_to_challenge_index(prng.get_challenge_bytes(), lde_size);
+        let coset_index_values = C::get_coset_for_natural_index(domain_idx, lde_size);
+        let mut can_use = true;
+        for v in coset_index_values.iter() {
+            if used_queries.contains(&v) {
+                can_use = false;
+                break
+            }
+        }
+        if can_use {
+            domain_indexes.push(domain_idx);
+            used_queries.extend(coset_index_values);
+        }
+    }
+
+    domain_indexes
+}
+
 // this one is not ZK cause will expose values not from LDE, but from the original domain too
 fn bytes_to_challenge_index<S: AsRef<[u8]>>(bytes: S, lde_size: usize) -> usize {
     use byteorder::{BigEndian, ByteOrder};
@@ -638,7 +627,12 @@ mod test {
     use crate::plonk::commitments::transcript::*;
     use crate::plonk::commitments::transparent::fri::naive_fri::naive_fri::*;
     use crate::plonk::commitments::transparent::iop::blake2s_trivial_iop::*;
+    use crate::plonk::commitments::transparent::iop::algebraic_trivial_iop::*;
+    use crate::plonk::commitments::transcript::rescue_transcript::RescueTranscript;
+    use crate::plonk::commitments::transcript::poseidon_transcript::PoseidonTranscript;
+    use crate::plonk::commitments::algebraic_hash::{PoseidonParams, RescueParams, SharedParams};
     use crate::plonk::commitments::*;
+    use crate::pairing::bn256::Fr;
     
 
     #[test]
@@ -665,6 +659,7 @@ mod test {
             lde_factor: 16,
             num_queries: 2,
             output_coeffs_at_degree_plus_one: 1,
+            grinding_bits: 0,
             fri_params: ()
         };
 
@@ -685,6 +680,95 @@ mod test {
         assert!(valid);
     }
 
+    #[test]
+    fn test_transparent_commitment_with_grinding() {
+        use crate::pairing::bn256::{Bn256, Fr};
+
+        const SIZE:usize = 16;
+
+        let worker = Worker::new();
+
+        let coeffs: Vec<_> = (0..SIZE).collect();
+        let coeffs = convert_to_field_elements(&coeffs, &worker);
+        let poly = Polynomial::<Bn256, _>::from_coeffs(coeffs).unwrap();
+
+        type Iop = TrivialBlake2sIOP<Fr>;
+        type Fri = NaiveFriIop<Bn256, Iop>;
+        type Committer = StatelessTransparentCommitter<Bn256, Fri, Blake2sTranscript<Fr>>;
+
+        let meta = TransparentCommitterParameters {
+            lde_factor: 16,
+            num_queries: 2,
+            output_coeffs_at_degree_plus_one: 1,
+            grinding_bits: 10,
+            fri_params: ()
+        };
+
+        let committer = <Committer as CommitmentScheme<Bn256>>::new_for_size(SIZE, meta);
+
+        let (commitment, aux_data) = committer.commit_single(&poly);
+
+        let open_at = Fr::from_str("123").unwrap();
+        let expected_at_z = poly.evaluate_at(&worker, open_at);
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        let proof = committer.open_single(&poly, open_at, expected_at_z, &aux_data.as_ref(), &mut transcript);
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        assert!(committer.verify_single(&commitment, open_at, expected_at_z, &proof, &mut transcript));
+
+        let (fri_proof, queries, nonce) = proof;
+        let tampered = (fri_proof, queries, nonce + 1);
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        assert!(!committer.verify_single(&commitment, open_at, expected_at_z, &tampered, &mut transcript));
+    }
+
+    fn algebraic_commitment_round_trip<P: SharedParams<Fr>, T: Transcript<Fr, Input = Fr>>() {
+        use crate::pairing::bn256::Bn256;
+
+        const SIZE:usize = 16;
+
+        let worker = Worker::new();
+
+        let coeffs: Vec<_> = (0..SIZE).collect();
+        let coeffs = convert_to_field_elements(&coeffs, &worker);
+        let poly = Polynomial::<Bn256, _>::from_coeffs(coeffs).unwrap();
+
+        type Fri<H> = NaiveFriIop<Bn256, TrivialAlgebraicIOP<Fr, H>>;
+
+        let meta = TransparentCommitterParameters::<Bn256, Fri<P>> {
+            lde_factor: 16,
+            num_queries: 2,
+            output_coeffs_at_degree_plus_one: 1,
+            grinding_bits: 0,
+            fri_params: ()
+        };
+
+        let committer = <StatelessTransparentCommitter<Bn256, Fri<P>, T> as CommitmentScheme<Bn256>>::new_for_size(SIZE, meta);
+
+        let (commitment, aux_data) = committer.commit_single(&poly);
+
+        let open_at = Fr::from_str("123").unwrap();
+        let expected_at_z = poly.evaluate_at(&worker, open_at);
+
+        let mut transcript = T::new();
+        let proof = committer.open_single(&poly, open_at, expected_at_z, &aux_data.as_ref(), &mut transcript);
+
+        let mut transcript = T::new();
+        assert!(committer.verify_single(&commitment, open_at, expected_at_z, &proof, &mut transcript));
+
+        let mut wrong_value = expected_at_z;
+        wrong_value.add_assign(&Fr::one());
+        let mut transcript = T::new();
+        assert!(!committer.verify_single(&commitment, open_at, wrong_value, &proof, &mut transcript));
+    }
+
+    #[test]
+    fn test_transparent_commitment_with_algebraic_trees() {
+        algebraic_commitment_round_trip::<RescueParams<Fr>, RescueTranscript<Fr>>();
+        algebraic_commitment_round_trip::<PoseidonParams<Fr>, PoseidonTranscript<Fr>>();
+    }
+
     #[test]
     fn test_large_transparent_commitment() {
         use std::time::Instant;
@@ -709,6 +793,7 @@ mod test {
             lde_factor: 16,
             num_queries: 6, // ~100 bits of security
             output_coeffs_at_degree_plus_one: 16,
+            grinding_bits: 0,
             fri_params: ()
         };
 
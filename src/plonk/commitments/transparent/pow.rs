@@ -0,0 +1,156 @@
+//! Proof-of-work grinding before the FRI query phase.
+//!
+//! The prover draws a seed from the transcript and searches for the smallest nonce such
+//! that `Blake2s(seed || nonce)` starts with `grinding_bits` zero bits, then commits the
+//! nonce back into the transcript. A cheating prover has to redo this search for every
+//! attempt to get favourable query positions, which adds `grinding_bits` of security.
+
+use crate::pairing::ff::PrimeField;
+use crate::plonk::commitments::transcript::Transcript;
+use crate::worker::Worker;
+use blake2s_simd::Params;
+
+/// Largest supported grinding, larger values would take too long to prove anyway.
+pub const MAX_GRINDING_BITS: u32 = 32;
+
+const NONCES_PER_THREAD: u64 = 1 << 12;
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut result = 0;
+    for byte in hash.iter() {
+        if *byte == 0 {
+            result += 8;
+        } else {
+            result += byte.leading_zeros();
+            break;
+        }
+    }
+
+    result
+}
+
+fn pow_hash_is_valid(seed: &[u8], nonce: u64, grinding_bits: u32) -> bool {
+    let mut state = Params::new()
+        .hash_length(32)
+        .personal(b"FRI_PoW_")
+        .to_state();
+    state.update(seed);
+    state.update(&nonce.to_be_bytes());
+
+    leading_zero_bits(state.finalize().as_bytes()) >= grinding_bits
+}
+
+/// Smallest nonce that satisfies the grinding condition for `seed`. The search is split
+/// between the threads in batches, so the result does not depend on the number of threads.
+pub fn find_nonce(seed: &[u8], grinding_bits: u32, worker: &Worker) -> u64 {
+    assert!(grinding_bits <= MAX_GRINDING_BITS);
+
+    let num_threads = worker.get_num_cpus() as u64;
+    let batch_size = num_threads * NONCES_PER_THREAD;
+
+    let mut batch_start = 0u64;
+    loop {
+        let mut found = vec![None; num_threads as usize];
+
+        worker.scope(found.len(), |scope, chunk| {
+            for (i, found) in found.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    for (j, found) in found.iter_mut().enumerate() {
+                        let start = batch_start + ((i * chunk + j) as u64) * NONCES_PER_THREAD;
+                        *found = (start..(start + NONCES_PER_THREAD))
+                            .find(|nonce| pow_hash_is_valid(seed, *nonce, grinding_bits));
+                    }
+                });
+            }
+        });
+
+        if let Some(nonce) = found.into_iter().filter_map(|el| el).min() {
+            return nonce;
+        }
+
+        batch_start = batch_start
+            .checked_add(batch_size)
+            .expect("nonce space is exhausted");
+    }
+}
+
+/// Prover side: grinds on a seed drawn from the transcript and commits the nonce.
+/// Does not touch the transcript if `grinding_bits` is zero.
+pub fn grind<F: PrimeField, T: Transcript<F>>(
+    transcript: &mut T,
+    grinding_bits: u32,
+    worker: &Worker,
+) -> u64 {
+    if grinding_bits == 0 {
+        return 0;
+    }
+
+    let seed = transcript.get_challenge_bytes();
+    let nonce = find_nonce(&seed, grinding_bits, worker);
+    transcript.commit_bytes(&nonce.to_be_bytes());
+
+    nonce
+}
+
+/// Verifier side of the `grind`, leaves the transcript in the same state as the prover's.
+pub fn verify_grinding<F: PrimeField, T: Transcript<F>>(
+    transcript: &mut T,
+    nonce: u64,
+    grinding_bits: u32,
+) -> bool {
+    if grinding_bits == 0 {
+        return nonce == 0;
+    }
+
+    if grinding_bits > MAX_GRINDING_BITS {
+        return false;
+    }
+
+    let seed = transcript.get_challenge_bytes();
+    let valid = pow_hash_is_valid(&seed, nonce, grinding_bits);
+    transcript.commit_bytes(&nonce.to_be_bytes());
+
+    valid
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+    use crate::plonk::commitments::transcript::{Blake2sTranscript, Prng};
+
+    #[test]
+    fn test_grinding_round_trip() {
+        let worker = Worker::new();
+        const BITS: u32 = 12;
+
+        let mut prover_transcript = Blake2sTranscript::<Fr>::new();
+        prover_transcript.commit_bytes(b"grinding");
+        let nonce = grind(&mut prover_transcript, BITS, &worker);
+
+        let mut verifier_transcript = Blake2sTranscript::<Fr>::new();
+        verifier_transcript.commit_bytes(b"grinding");
+        assert!(verify_grinding(&mut verifier_transcript, nonce, BITS));
+
+        // transcripts stay in sync after the grinding
+        let prover_challenge: Fr = prover_transcript.get_challenge();
+        let verifier_challenge: Fr = verifier_transcript.get_challenge();
+        assert_eq!(prover_challenge, verifier_challenge);
+
+        // the nonce is the smallest one, so any smaller one is rejected
+        for wrong in 0..nonce {
+            let mut transcript = Blake2sTranscript::<Fr>::new();
+            transcript.commit_bytes(b"grinding");
+            assert!(!verify_grinding(&mut transcript, wrong, BITS));
+        }
+    }
+
+    #[test]
+    fn test_nonce_does_not_depend_on_threads() {
+        let seed = [7u8; 32];
+        let single = Worker::builder().num_threads(1).build().unwrap();
+        let many = Worker::builder().num_threads(4).build().unwrap();
+
+        assert_eq!(find_nonce(&seed, 10, &single), find_nonce(&seed, 10, &many));
+    }
+}
@@ -0,0 +1,201 @@
+//! Soundness estimates for the FRI parameters.
+//!
+//! Two models are supported:
+//! - `Conjectured`: every query contributes `log2(lde_factor)` bits, as in the ethSTARK
+//!   conjecture, and the field must be large enough for the folding challenges;
+//! - `Proven`: the list-decoding (Johnson bound) regime of "Proximity Gaps for Reed-Solomon
+//!   Codes" (BCIKS20, Theorem 8.3) with the multiplicity parameter `m = 3`, where every
+//!   query only contributes about `log2(lde_factor) / 2` bits and the commit phase error
+//!   grows with the square of the LDE size.
+//!
+//! In both models the proof-of-work grinding adds its bits to the query phase only.
+
+/// Multiplicity parameter of the proven bound, larger values tighten the per query error
+/// at the cost of the commit phase error.
+const JOHNSON_MULTIPLICITY: f64 = 3.0;
+
+/// Largest LDE factor the recommendation ever considers.
+pub const MAX_RECOMMENDED_LDE_FACTOR: usize = 1 << 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FriSecurityModel {
+    Conjectured,
+    Proven,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FriSecuritySettings {
+    pub lde_factor: usize,
+    pub num_queries: usize,
+    pub grinding_bits: u32,
+}
+
+fn log2(value: usize) -> f64 {
+    (value as f64).log2()
+}
+
+/// Bits of security contributed by a single query.
+fn bits_per_query(lde_factor: usize, model: FriSecurityModel) -> f64 {
+    match model {
+        FriSecurityModel::Conjectured => log2(lde_factor),
+        FriSecurityModel::Proven => {
+            // alpha = sqrt(rho) * (1 + 1/(2m))
+            let alpha = (1.0 / (lde_factor as f64)).sqrt() * (1.0 + 1.0 / (2.0 * JOHNSON_MULTIPLICITY));
+
+            -alpha.log2()
+        }
+    }
+}
+
+/// Bits of security of the commit phase, limited by the field size.
+fn commit_phase_bits(field_bits: u32, lde_size: usize, lde_factor: usize, model: FriSecurityModel) -> f64 {
+    match model {
+        FriSecurityModel::Conjectured => (field_bits as f64) - log2(lde_size),
+        FriSecurityModel::Proven => {
+            // (m + 1/2)^7 / (3 * rho^(3/2)) * n^2 / |F|
+            let rho = 1.0 / (lde_factor as f64);
+            let factor = (JOHNSON_MULTIPLICITY + 0.5).powi(7) / (3.0 * rho.powf(1.5));
+
+            (field_bits as f64) - factor.log2() - 2.0 * log2(lde_size)
+        }
+    }
+}
+
+/// Estimated bits of security of FRI for a polynomial of `degree_plus_one` coefficients
+/// over a field of `field_bits` bits.
+pub fn estimate_security_bits(
+    field_bits: u32,
+    degree_plus_one: usize,
+    settings: &FriSecuritySettings,
+    model: FriSecurityModel,
+) -> f64 {
+    assert!(settings.lde_factor.is_power_of_two() && settings.lde_factor >= 2);
+
+    let lde_size = degree_plus_one.next_power_of_two() * settings.lde_factor;
+
+    let query_bits = (settings.num_queries as f64) * bits_per_query(settings.lde_factor, model)
+        + (settings.grinding_bits as f64);
+    let commit_bits = commit_phase_bits(field_bits, lde_size, settings.lde_factor, model);
+
+    query_bits.min(commit_bits)
+}
+
+/// Recommends the settings that reach `target_bits` with the smallest query part of the
+/// proof, estimated as `num_queries * log2(lde_size)` Merkle path nodes. Larger LDE
+/// factors make the prover slower, so they are capped by `max_lde_factor`. Returns `None`
+/// if the field is too small for the target.
+pub fn recommend_settings(
+    target_bits: u32,
+    field_bits: u32,
+    degree_plus_one: usize,
+    max_lde_factor: usize,
+    max_grinding_bits: u32,
+    model: FriSecurityModel,
+) -> Option<FriSecuritySettings> {
+    assert!(max_grinding_bits <= super::pow::MAX_GRINDING_BITS);
+
+    let target = target_bits as f64;
+    let max_lde_factor = std::cmp::min(max_lde_factor, MAX_RECOMMENDED_LDE_FACTOR);
+
+    let mut best: Option<(usize, FriSecuritySettings)> = None;
+
+    let mut lde_factor = 2;
+    while lde_factor <= max_lde_factor {
+        let lde_size = degree_plus_one.next_power_of_two() * lde_factor;
+        if commit_phase_bits(field_bits, lde_size, lde_factor, model) < target {
+            lde_factor *= 2;
+            continue;
+        }
+
+        let per_query = bits_per_query(lde_factor, model);
+        let from_queries = (target - max_grinding_bits as f64).max(0.0);
+        let num_queries = std::cmp::max(1, (from_queries / per_query).ceil() as usize);
+        // grind only as much as the queries are missing
+        let grinding_bits = (target - (num_queries as f64) * per_query).max(0.0).ceil() as u32;
+
+        let settings = FriSecuritySettings {
+            lde_factor,
+            num_queries,
+            grinding_bits,
+        };
+        debug_assert!(estimate_security_bits(field_bits, degree_plus_one, &settings, model) >= target);
+
+        let cost = num_queries * (log2(lde_size) as usize);
+        let is_better = best.as_ref().map(|(best_cost, _)| cost < *best_cost).unwrap_or(true);
+        if is_better {
+            best = Some((cost, settings));
+        }
+
+        lde_factor *= 2;
+    }
+
+    best.map(|(_, settings)| settings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIELD_BITS: u32 = 254;
+    const DEGREE: usize = 1 << 20;
+
+    #[test]
+    fn test_conjectured_estimate() {
+        let settings = FriSecuritySettings {
+            lde_factor: 8,
+            num_queries: 40,
+            grinding_bits: 0,
+        };
+        let bits = estimate_security_bits(FIELD_BITS, DEGREE, &settings, FriSecurityModel::Conjectured);
+        assert!((bits - 120.0).abs() < 1e-9);
+
+        let with_grinding = FriSecuritySettings {
+            grinding_bits: 16,
+            ..settings
+        };
+        let bits = estimate_security_bits(FIELD_BITS, DEGREE, &with_grinding, FriSecurityModel::Conjectured);
+        assert!((bits - 136.0).abs() < 1e-9);
+
+        // small fields are limited by the commit phase
+        let bits = estimate_security_bits(64, DEGREE, &settings, FriSecurityModel::Conjectured);
+        assert!((bits - 41.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_proven_is_below_conjectured() {
+        let settings = FriSecuritySettings {
+            lde_factor: 16,
+            num_queries: 30,
+            grinding_bits: 10,
+        };
+        let conjectured = estimate_security_bits(FIELD_BITS, DEGREE, &settings, FriSecurityModel::Conjectured);
+        let proven = estimate_security_bits(FIELD_BITS, DEGREE, &settings, FriSecurityModel::Proven);
+        assert!(proven < conjectured);
+        // grinding bits are the same in both models
+        assert!(proven > (settings.grinding_bits as f64) + 30.0 * 1.5);
+    }
+
+    #[test]
+    fn test_recommendation() {
+        let settings = recommend_settings(100, FIELD_BITS, DEGREE, 16, 20, FriSecurityModel::Conjectured).unwrap();
+        assert_eq!(
+            settings,
+            FriSecuritySettings {
+                lde_factor: 16,
+                num_queries: 20,
+                grinding_bits: 20,
+            }
+        );
+
+        for model in [FriSecurityModel::Conjectured, FriSecurityModel::Proven].iter() {
+            for max_grinding_bits in [0, 8, 20].iter() {
+                let settings = recommend_settings(128, FIELD_BITS, DEGREE, 64, *max_grinding_bits, *model).unwrap();
+                assert!(settings.grinding_bits <= *max_grinding_bits);
+                assert!(estimate_security_bits(FIELD_BITS, DEGREE, &settings, *model) >= 128.0);
+            }
+        }
+
+        // 64 bit field can not give 100 bits of security
+        assert!(recommend_settings(100, 64, DEGREE, 16, 20, FriSecurityModel::Conjectured).is_none());
+    }
+}
@@ -0,0 +1,722 @@
+//! Binary encoding of the FRI proofs over Blake2s Merkle trees.
+//!
+//! Queries of one proof that land in the same tree share the upper nodes of their Merkle
+//! paths. A proof stores every such node only once: for each query just the leaf position
+//! is written, and the paths are rebuilt from the shared set of nodes on reading.
+
+use crate::byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use crate::pairing::ff::PrimeField;
+use crate::plonk::better_cs::keys::{read_fr, read_fr_vec_bounded, write_fr, write_fr_vec};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Read, Write};
+
+use super::fri::coset_combining_fri::fri::FRIProof as CosetCombiningFRIProof;
+use super::fri::naive_fri::naive_fri::FRIProof as NaiveFRIProof;
+use super::iop::blake2s_trivial_iop::{TrivialBlake2sIOP, TrivialBlake2sIopQuery};
+use super::iop::IopQuery;
+use super::iop_compiler::coset_combining_blake2s_tree::{CosetCombinedQuery, FriSpecificBlake2sTree};
+use super::iop_compiler::IopQuery as CosetIopQuery;
+
+pub fn write_merkle_path<W: Write>(path: &[[u8; 32]], mut writer: W) -> std::io::Result<()> {
+    writer.write_u64::<BigEndian>(path.len() as u64)?;
+    for node in path.iter() {
+        writer.write_all(&node[..])?;
+    }
+
+    Ok(())
+}
+
+pub fn read_merkle_path<R: Read>(mut reader: R) -> std::io::Result<Vec<[u8; 32]>> {
+    let path_len = read_path_len(&mut reader)?;
+    let mut path = Vec::with_capacity(path_len);
+    for _ in 0..path_len {
+        let mut node = [0u8; 32];
+        reader.read_exact(&mut node[..])?;
+        path.push(node);
+    }
+
+    Ok(path)
+}
+
+/// Largest number of foldings by 2 in one step of the coset combining FRI, so a leaf of
+/// a proof holds at most `1 << MAX_COSET_FACTOR` values.
+const MAX_COSET_FACTOR: usize = 8;
+
+fn read_path_len<R: Read>(mut reader: R) -> std::io::Result<usize> {
+    let path_len = reader.read_u64::<BigEndian>()?;
+    if path_len >= usize::max_value().count_ones() as u64 {
+        return Err(Error::new(ErrorKind::InvalidData, "Merkle path is too long"));
+    }
+
+    Ok(path_len as usize)
+}
+
+/// Tree (the layer of FRI), level in this tree and position on this level.
+type NodeKey = (usize, usize, usize);
+
+/// Nodes of all the Merkle paths of a proof, every node stored once.
+struct MerklePathNodes {
+    nodes: BTreeMap<NodeKey, [u8; 32]>,
+}
+
+impl MerklePathNodes {
+    fn new() -> Self {
+        Self {
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    // the path of a leaf consists of the siblings of the leaf and of all its ancestors
+    fn path_keys(tree: usize, leaf_index: usize, path_len: usize) -> impl Iterator<Item = NodeKey> {
+        (0..path_len).map(move |level| (tree, level, (leaf_index >> level) ^ 1))
+    }
+
+    fn insert_path(&mut self, tree: usize, leaf_index: usize, path: &[[u8; 32]]) -> std::io::Result<()> {
+        for (key, node) in Self::path_keys(tree, leaf_index, path.len()).zip(path.iter()) {
+            let existing = self.nodes.entry(key).or_insert(*node);
+            if existing != node {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "queries disagree on a shared Merkle path node",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_u64::<BigEndian>(self.nodes.len() as u64)?;
+        for node in self.nodes.values() {
+            writer.write_all(&node[..])?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the nodes of the paths for `leafs` given as `(tree, leaf_index, path_len)`.
+    fn read<R: Read>(leafs: &[(usize, usize, usize)], mut reader: R) -> std::io::Result<Self> {
+        let mut nodes = BTreeMap::new();
+        for &(tree, leaf_index, path_len) in leafs.iter() {
+            for key in Self::path_keys(tree, leaf_index, path_len) {
+                nodes.insert(key, [0u8; 32]);
+            }
+        }
+
+        let num_nodes = reader.read_u64::<BigEndian>()?;
+        if num_nodes != nodes.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "number of Merkle path nodes does not match the queries",
+            ));
+        }
+
+        for node in nodes.values_mut() {
+            reader.read_exact(&mut node[..])?;
+        }
+
+        Ok(Self { nodes })
+    }
+
+    fn path(&self, tree: usize, leaf_index: usize, path_len: usize) -> Vec<[u8; 32]> {
+        Self::path_keys(tree, leaf_index, path_len)
+            .map(|key| self.nodes[&key])
+            .collect()
+    }
+}
+
+fn invalid_input(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
+
+fn invalid_data(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn write_proof_header<F: PrimeField, W: Write>(
+    initial_degree_plus_one: usize,
+    output_coeffs_at_degree_plus_one: usize,
+    lde_factor: usize,
+    roots: &[[u8; 32]],
+    final_coefficients: &[F],
+    mut writer: W,
+) -> std::io::Result<()> {
+    writer.write_u64::<BigEndian>(initial_degree_plus_one as u64)?;
+    writer.write_u64::<BigEndian>(output_coeffs_at_degree_plus_one as u64)?;
+    writer.write_u64::<BigEndian>(lde_factor as u64)?;
+
+    writer.write_u64::<BigEndian>(roots.len() as u64)?;
+    for root in roots.iter() {
+        writer.write_all(&root[..])?;
+    }
+
+    write_fr_vec(final_coefficients, &mut writer)?;
+
+    Ok(())
+}
+
+struct ProofHeader<F: PrimeField> {
+    /// `initial_degree_plus_one * lde_factor`, checked not to overflow.
+    domain_size: usize,
+    initial_degree_plus_one: usize,
+    output_coeffs_at_degree_plus_one: usize,
+    lde_factor: usize,
+    roots: Vec<[u8; 32]>,
+    final_coefficients: Vec<F>,
+}
+
+/// Sizes are checked before anything is allocated for them: the domain must be a power
+/// of two that fits into `usize`, there is at most one root per folding of the domain and
+/// at most `output_coeffs_at_degree_plus_one` final coefficients.
+fn read_proof_header<F: PrimeField, R: Read>(mut reader: R) -> std::io::Result<ProofHeader<F>> {
+    let initial_degree_plus_one = reader.read_u64::<BigEndian>()? as usize;
+    let output_coeffs_at_degree_plus_one = reader.read_u64::<BigEndian>()? as usize;
+    let lde_factor = reader.read_u64::<BigEndian>()? as usize;
+
+    if !initial_degree_plus_one.is_power_of_two()
+        || !lde_factor.is_power_of_two()
+        || output_coeffs_at_degree_plus_one > initial_degree_plus_one
+    {
+        return Err(invalid_data("invalid FRI domain"));
+    }
+    let domain_size = initial_degree_plus_one
+        .checked_mul(lde_factor)
+        .ok_or_else(|| invalid_data("FRI domain is too large"))?;
+
+    let num_roots = reader.read_u64::<BigEndian>()?;
+    if num_roots > domain_size.trailing_zeros() as u64 {
+        return Err(invalid_data("more roots than foldings of the domain"));
+    }
+    let mut roots = Vec::with_capacity(num_roots as usize);
+    for _ in 0..num_roots {
+        let mut root = [0u8; 32];
+        reader.read_exact(&mut root[..])?;
+        roots.push(root);
+    }
+
+    let final_coefficients = read_fr_vec_bounded(output_coeffs_at_degree_plus_one, &mut reader)?;
+
+    Ok(ProofHeader {
+        domain_size,
+        initial_degree_plus_one,
+        output_coeffs_at_degree_plus_one,
+        lde_factor,
+        roots,
+        final_coefficients,
+    })
+}
+
+/// Every query round holds the same number of queries into every tree, the queries into
+/// the tree `i` follow the ones into the tree `i - 1`.
+impl<F: PrimeField> NaiveFRIProof<F, TrivialBlake2sIOP<F>> {
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_proof_header(
+            self.initial_degree_plus_one,
+            self.output_coeffs_at_degree_plus_one,
+            self.lde_factor,
+            &self.roots,
+            &self.final_coefficients,
+            &mut writer,
+        )?;
+
+        let num_trees = self.roots.len();
+        let queries_per_round = self.queries.first().map(|q| q.len()).unwrap_or(0);
+        if (num_trees == 0 && queries_per_round != 0) || queries_per_round % num_trees.max(1) != 0 {
+            return Err(invalid_input("queries do not split evenly between the trees"));
+        }
+        let queries_per_tree = queries_per_round / num_trees.max(1);
+
+        let mut path_lens = vec![0usize; num_trees];
+        if let Some(round) = self.queries.first() {
+            for (tree, query) in round.iter().step_by(queries_per_tree.max(1)).enumerate() {
+                path_lens[tree] = query.path().len();
+            }
+        }
+
+        writer.write_u64::<BigEndian>(self.queries.len() as u64)?;
+        writer.write_u64::<BigEndian>(queries_per_round as u64)?;
+        for path_len in path_lens.iter() {
+            writer.write_u64::<BigEndian>(*path_len as u64)?;
+        }
+
+        let mut nodes = MerklePathNodes::new();
+        for round in self.queries.iter() {
+            if round.len() != queries_per_round {
+                return Err(invalid_input("all rounds must have the same number of queries"));
+            }
+
+            for (i, query) in round.iter().enumerate() {
+                let tree = i / queries_per_tree;
+                if query.path().len() != path_lens[tree] {
+                    return Err(invalid_input("paths into the same tree must have equal length"));
+                }
+                nodes.insert_path(tree, query.tree_index(), query.path())?;
+
+                writer.write_u64::<BigEndian>(query.natural_index() as u64)?;
+                write_fr(&query.value(), &mut writer)?;
+            }
+        }
+
+        nodes.write(&mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let header = read_proof_header::<F, _>(&mut reader)?;
+
+        let num_trees = header.roots.len();
+        let num_rounds = reader.read_u64::<BigEndian>()?;
+        let queries_per_round = reader.read_u64::<BigEndian>()?;
+        if num_rounds > header.domain_size as u64
+            || queries_per_round > (num_trees as u64).saturating_mul(header.domain_size as u64)
+        {
+            return Err(invalid_data("more queries than domain elements"));
+        }
+        let num_rounds = num_rounds as usize;
+        let queries_per_round = queries_per_round as usize;
+        if (num_trees == 0 && queries_per_round != 0) || queries_per_round % num_trees.max(1) != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid number of queries"));
+        }
+        let queries_per_tree = queries_per_round / num_trees.max(1);
+
+        let mut path_lens = vec![];
+        for _ in 0..num_trees {
+            path_lens.push(read_path_len(&mut reader)?);
+        }
+
+        let mut leafs = vec![];
+        let mut values = vec![];
+        for _ in 0..num_rounds {
+            for i in 0..queries_per_round {
+                let tree = i / queries_per_tree;
+                let index = reader.read_u64::<BigEndian>()? as usize;
+                let value = read_fr::<F, _>(&mut reader)?;
+
+                leafs.push((tree, index, path_lens[tree]));
+                values.push(value);
+            }
+        }
+
+        let nodes = MerklePathNodes::read(&leafs, &mut reader)?;
+
+        let mut queries = vec![];
+        let mut it = leafs.into_iter().zip(values.into_iter());
+        for _ in 0..num_rounds {
+            let round = it
+                .by_ref()
+                .take(queries_per_round)
+                .map(|((tree, index, path_len), value)| {
+                    let path = nodes.path(tree, index, path_len);
+                    TrivialBlake2sIopQuery::from_parts(index, value, path)
+                })
+                .collect();
+            queries.push(round);
+        }
+
+        Ok(Self {
+            queries,
+            roots: header.roots,
+            final_coefficients: header.final_coefficients,
+            initial_degree_plus_one: header.initial_degree_plus_one,
+            output_coeffs_at_degree_plus_one: header.output_coeffs_at_degree_plus_one,
+            lde_factor: header.lde_factor,
+        })
+    }
+}
+
+/// Every query round holds one query per committed layer, so the number of values per
+/// leaf and the path length are written once per layer.
+impl<F: PrimeField> CosetCombiningFRIProof<F, FriSpecificBlake2sTree<F>> {
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_proof_header(
+            self.initial_degree_plus_one,
+            self.output_coeffs_at_degree_plus_one,
+            self.lde_factor,
+            &self.roots,
+            &self.final_coefficients,
+            &mut writer,
+        )?;
+
+        let layers: Vec<(usize, usize)> = self
+            .queries
+            .first()
+            .map(|round| {
+                round
+                    .iter()
+                    .map(|q| (q.values().len(), q.path().len()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        writer.write_u64::<BigEndian>(self.queries.len() as u64)?;
+        writer.write_u64::<BigEndian>(layers.len() as u64)?;
+        for (values_per_leaf, path_len) in layers.iter() {
+            writer.write_u64::<BigEndian>(*values_per_leaf as u64)?;
+            writer.write_u64::<BigEndian>(*path_len as u64)?;
+        }
+
+        let mut nodes = MerklePathNodes::new();
+        for round in self.queries.iter() {
+            if round.len() != layers.len() {
+                return Err(invalid_input("all rounds must query the same layers"));
+            }
+
+            for (layer, (query, (values_per_leaf, path_len))) in
+                round.iter().zip(layers.iter()).enumerate()
+            {
+                if query.values().len() != *values_per_leaf || query.path().len() != *path_len {
+                    return Err(invalid_input("queries into the same layer must have equal shape"));
+                }
+
+                let first_index = query.first_index()?;
+                if *values_per_leaf == 0 || first_index % values_per_leaf != 0 {
+                    return Err(invalid_input("query is not aligned to a leaf"));
+                }
+                nodes.insert_path(layer, first_index / values_per_leaf, query.path())?;
+
+                writer.write_u64::<BigEndian>(first_index as u64)?;
+                for value in query.values().iter() {
+                    write_fr(value, &mut writer)?;
+                }
+            }
+        }
+
+        nodes.write(&mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let header = read_proof_header::<F, _>(&mut reader)?;
+
+        // rounds are only stored as they are read, so a large count fails on the missing
+        // bytes instead of allocating for them
+        let num_rounds = reader.read_u64::<BigEndian>()?;
+        if num_rounds > header.domain_size as u64 {
+            return Err(invalid_data("more query rounds than domain elements"));
+        }
+        let num_rounds = num_rounds as usize;
+        let num_layers = reader.read_u64::<BigEndian>()?;
+        if num_layers != header.roots.len() as u64 && !(num_rounds == 0 && num_layers == 0) {
+            return Err(invalid_data("number of queried layers does not match the roots"));
+        }
+        let num_layers = num_layers as usize;
+
+        let max_values_per_leaf = std::cmp::min(1 << MAX_COSET_FACTOR, header.domain_size);
+        let mut layers = Vec::with_capacity(num_layers);
+        for _ in 0..num_layers {
+            let values_per_leaf = reader.read_u64::<BigEndian>()?;
+            let path_len = read_path_len(&mut reader)?;
+            if !values_per_leaf.is_power_of_two() || values_per_leaf > max_values_per_leaf as u64 {
+                return Err(invalid_data("invalid number of values per leaf"));
+            }
+            layers.push((values_per_leaf as usize, path_len));
+        }
+
+        let mut leafs = vec![];
+        let mut values = vec![];
+        for _ in 0..num_rounds {
+            for (layer, (values_per_leaf, path_len)) in layers.iter().enumerate() {
+                let first_index = reader.read_u64::<BigEndian>()? as usize;
+                if first_index % values_per_leaf != 0 {
+                    return Err(Error::new(ErrorKind::InvalidData, "query is not aligned to a leaf"));
+                }
+
+                let mut leaf_values = Vec::with_capacity(*values_per_leaf);
+                for _ in 0..*values_per_leaf {
+                    leaf_values.push(read_fr::<F, _>(&mut reader)?);
+                }
+
+                leafs.push((layer, first_index / values_per_leaf, *path_len));
+                values.push(leaf_values);
+            }
+        }
+
+        let nodes = MerklePathNodes::read(&leafs, &mut reader)?;
+
+        let mut queries = vec![];
+        let mut it = leafs.into_iter().zip(values.into_iter());
+        for _ in 0..num_rounds {
+            let round = it
+                .by_ref()
+                .take(num_layers)
+                .map(|((layer, leaf_index, path_len), values)| {
+                    let path = nodes.path(layer, leaf_index, path_len);
+                    let first_index = leaf_index * values.len();
+                    CosetCombinedQuery::from_parts(first_index, values, path)
+                })
+                .collect();
+            queries.push(round);
+        }
+
+        Ok(Self {
+            queries,
+            roots: header.roots,
+            final_coefficients: header.final_coefficients,
+            initial_degree_plus_one: header.initial_degree_plus_one,
+            output_coeffs_at_degree_plus_one: header.output_coeffs_at_degree_plus_one,
+            lde_factor: header.lde_factor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::ff::Field;
+    use crate::plonk::commitments::transcript::*;
+    use crate::plonk::commitments::transparent::fri::coset_combining_fri::fri::*;
+    use crate::plonk::commitments::transparent::fri::naive_fri::naive_fri::NaiveFriIop;
+    use crate::plonk::commitments::transparent::fri::*;
+    use crate::plonk::commitments::transparent::iop::IOP;
+    use crate::plonk::commitments::transparent::iop_compiler::coset_combining_blake2s_tree::FriSpecificBlake2sTreeParams;
+    use crate::plonk::commitments::transparent::iop_compiler::IopInstance;
+    use crate::plonk::commitments::transparent::precomputations::PrecomputedInvOmegas;
+    use crate::plonk::domains::Domain;
+    use crate::plonk::fft::cooley_tukey_ntt::OmegasInvBitreversed;
+    use crate::plonk::polynomials::*;
+    use crate::plonk::transparent_engine::{Fr, Transparent252};
+    use crate::worker::Worker;
+    use rand::{Rand, Rng, SeedableRng, XorShiftRng};
+    use std::collections::HashSet;
+
+    const SIZE: usize = 256;
+    const LDE_FACTOR: usize = 16;
+    const NUM_QUERIES: usize = 16;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654])
+    }
+
+    fn fr_size() -> usize {
+        let mut buffer = vec![];
+        write_fr(&Fr::one(), &mut buffer).unwrap();
+
+        buffer.len()
+    }
+
+    fn random_values(num: usize) -> Vec<Fr> {
+        let rng = &mut rng();
+        (0..num).map(|_| Fr::rand(rng)).collect()
+    }
+
+    fn make_lde(worker: &Worker) -> Polynomial<Transparent252, Values> {
+        let mut coeffs = random_values(SIZE);
+        coeffs.resize(SIZE * LDE_FACTOR, Fr::zero());
+
+        Polynomial::<Transparent252, _>::from_coeffs(coeffs)
+            .unwrap()
+            .fft(worker, &mut None)
+    }
+
+    fn query_indexes() -> Vec<usize> {
+        let rng = &mut rng();
+        (0..NUM_QUERIES)
+            .map(|_| rng.gen::<usize>() % (SIZE * LDE_FACTOR))
+            .collect()
+    }
+
+    fn num_unique_nodes(paths: &[(usize, usize, usize)]) -> usize {
+        let mut keys = HashSet::new();
+        for &(tree, leaf_index, path_len) in paths.iter() {
+            for level in 0..path_len {
+                keys.insert((tree, level, (leaf_index >> level) ^ 1));
+            }
+        }
+
+        keys.len()
+    }
+
+    fn header_size(num_roots: usize, num_coeffs: usize) -> usize {
+        8 * 3 + 8 + 32 * num_roots + 8 + fr_size() * num_coeffs
+    }
+
+    #[test]
+    fn test_query_round_trip() {
+        let values = random_values(64);
+
+        let iop = TrivialBlake2sIOP::<Fr>::create(&values);
+        let query = iop.query(13, &values);
+
+        let mut buffer = vec![];
+        query.write(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), 8 + fr_size() + 8 + 32 * query.path().len());
+        assert_eq!(TrivialBlake2sIopQuery::<Fr>::read(&buffer[..]).unwrap(), query);
+
+        let params = FriSpecificBlake2sTreeParams { values_per_leaf: 4 };
+        let tree = FriSpecificBlake2sTree::<Fr>::create(&values, &params);
+        let query = tree.produce_query((8..12).collect(), &values);
+
+        let mut buffer = vec![];
+        query.write(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), 8 + 8 + 4 * fr_size() + 8 + 32 * query.path().len());
+
+        let read = CosetCombinedQuery::<Fr>::read(&buffer[..]).unwrap();
+        assert_eq!(read, query);
+        assert!(FriSpecificBlake2sTree::verify_query(
+            &tree.get_commitment(),
+            &read,
+            &params
+        ));
+    }
+
+    #[test]
+    fn test_naive_fri_proof_round_trip() {
+        type Iop = NaiveFriIop<Transparent252, TrivialBlake2sIOP<Fr>>;
+
+        let worker = Worker::new();
+        let lde = make_lde(&worker);
+        let domain = Domain::<Fr>::new_for_size(lde.size() as u64).unwrap();
+        let precomputations = PrecomputedInvOmegas::<Fr>::new_for_domain(&domain, &worker);
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+
+        let prototype = Iop::proof_from_lde(
+            &lde,
+            LDE_FACTOR,
+            1,
+            &precomputations,
+            &worker,
+            &mut transcript,
+            &(),
+        )
+        .unwrap();
+        let proof = Iop::prototype_into_proof(prototype, &lde, query_indexes(), &()).unwrap();
+
+        let mut buffer = vec![];
+        proof.write(&mut buffer).unwrap();
+
+        let read = NaiveFRIProof::<Fr, TrivialBlake2sIOP<Fr>>::read(&buffer[..]).unwrap();
+        assert!(read == proof);
+
+        // every round queries a coset of two values in every tree
+        let queries_per_tree = proof.queries[0].len() / proof.roots.len();
+        let mut paths = vec![];
+        let mut undeduplicated_size = 0;
+        for round in proof.queries.iter() {
+            for (i, query) in round.iter().enumerate() {
+                paths.push((i / queries_per_tree, query.tree_index(), query.path().len()));
+
+                let mut query_buffer = vec![];
+                query.write(&mut query_buffer).unwrap();
+                undeduplicated_size += query_buffer.len();
+            }
+        }
+
+        let num_queries = paths.len();
+        let expected_size = header_size(proof.roots.len(), proof.final_coefficients.len())
+            + 8
+            + 8
+            + 8 * proof.roots.len()
+            + num_queries * (8 + fr_size())
+            + 8
+            + 32 * num_unique_nodes(&paths);
+        assert_eq!(buffer.len(), expected_size);
+        assert!(buffer.len() < undeduplicated_size);
+
+        // truncated proof can not be read
+        assert!(NaiveFRIProof::<Fr, TrivialBlake2sIOP<Fr>>::read(&buffer[..(buffer.len() - 1)]).is_err());
+    }
+
+    #[test]
+    fn test_coset_combining_fri_proof_round_trip() {
+        type Iop = CosetCombiningFriIop<Transparent252>;
+
+        let worker = Worker::new();
+        let params = CosetParams::<Fr> {
+            cosets_schedule: vec![3, 2, 1],
+            coset_factor: Fr::one(),
+            hash_params: (),
+            domain_size: SIZE * LDE_FACTOR,
+        };
+
+        let mut lde = make_lde(&worker);
+        lde.bitreverse_enumeration(&worker);
+
+        let precomputations =
+            <OmegasInvBitreversed<Fr> as FriPrecomputations<Fr>>::new_for_domain_size(lde.size());
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+
+        let prototype = Iop::proof_from_lde(
+            &lde,
+            LDE_FACTOR,
+            SIZE / (1 << 6),
+            &precomputations,
+            &worker,
+            &mut transcript,
+            &params,
+        )
+        .unwrap();
+        let proof = Iop::prototype_into_proof(prototype, &lde, query_indexes(), &params).unwrap();
+
+        let mut buffer = vec![];
+        proof.write(&mut buffer).unwrap();
+
+        let read = FRIProof::<Fr, FriSpecificBlake2sTree<Fr>>::read(&buffer[..]).unwrap();
+        assert!(read == proof);
+
+        let mut paths = vec![];
+        let mut values_size = 0;
+        let mut undeduplicated_size = 0;
+        for round in proof.queries.iter() {
+            for (layer, query) in round.iter().enumerate() {
+                let values_per_leaf = 1 << params.cosets_schedule[layer];
+                paths.push((
+                    layer,
+                    query.indexes()[0] / values_per_leaf,
+                    query.path().len(),
+                ));
+                values_size += 8 + values_per_leaf * fr_size();
+
+                let mut query_buffer = vec![];
+                query.write(&mut query_buffer).unwrap();
+                undeduplicated_size += query_buffer.len();
+            }
+        }
+
+        let expected_size = header_size(proof.roots.len(), proof.final_coefficients.len())
+            + 8
+            + 8
+            + 16 * params.cosets_schedule.len()
+            + values_size
+            + 8
+            + 32 * num_unique_nodes(&paths);
+        assert_eq!(buffer.len(), expected_size);
+        assert!(buffer.len() < undeduplicated_size);
+
+        // the number of shared nodes must match the queries
+        let mut corrupted = buffer.clone();
+        let nodes_count_offset = expected_size - 32 * num_unique_nodes(&paths) - 8;
+        corrupted[nodes_count_offset + 7] ^= 1;
+        assert!(FRIProof::<Fr, FriSpecificBlake2sTree<Fr>>::read(&corrupted[..]).is_err());
+
+        // crafted sizes are rejected before anything is allocated for them
+        let set_u64 = |buffer: &mut Vec<u8>, offset: usize, value: u64| {
+            (&mut buffer[offset..(offset + 8)])
+                .write_u64::<BigEndian>(value)
+                .unwrap();
+        };
+        let header = header_size(proof.roots.len(), proof.final_coefficients.len());
+        let final_coeffs_offset = header - 8 - fr_size() * proof.final_coefficients.len();
+        let crafted: Vec<(&str, usize, u64)> = vec![
+            ("overflowing domain", 0, 1 << 62),
+            ("non power of two LDE factor", 16, 3),
+            ("too many roots", 24, u64::max_value()),
+            ("too many final coefficients", final_coeffs_offset, u64::max_value()),
+            ("too many rounds", header, u64::max_value()),
+            ("too many layers", header + 8, u64::max_value()),
+            ("huge leaf", header + 16, 1 << 62),
+            ("leaf larger than the domain", header + 16, (SIZE * LDE_FACTOR * 2) as u64),
+            ("non power of two leaf", header + 16, 3),
+        ];
+        for (name, offset, value) in crafted.into_iter() {
+            let mut corrupted = buffer.clone();
+            set_u64(&mut corrupted, offset, value);
+            assert!(
+                FRIProof::<Fr, FriSpecificBlake2sTree<Fr>>::read(&corrupted[..]).is_err(),
+                "proof with {} is read",
+                name
+            );
+        }
+    }
+}
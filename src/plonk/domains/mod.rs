@@ -1,4 +1,5 @@
-use crate::pairing::ff::PrimeField;
+use crate::pairing::ff::{PrimeField, PrimeFieldRepr};
+#[cfg(not(feature = "verifier-only"))]
 use crate::worker::Worker;
 use crate::SynthesisError;
 
@@ -9,6 +10,40 @@ pub struct Domain<F: PrimeField> {
     pub generator: F,
 }
 
+/// Divides `repr` by a small `divisor` in place and returns the remainder.
+fn div_rem_small<R: PrimeFieldRepr>(repr: &mut R, divisor: u64) -> u64 {
+    let mut rem = 0u128;
+    for limb in repr.as_mut().iter_mut().rev() {
+        let current = (rem << 64) | (*limb as u128);
+        *limb = (current / divisor as u128) as u64;
+        rem = current % divisor as u128;
+    }
+
+    rem as u64
+}
+
+/// Largest `k` such that `3^k` divides the order of the multiplicative group of the field,
+/// and a generator of its subgroup of order `3^k`.
+pub fn three_adic_root_of_unity<F: PrimeField>() -> (u32, F) {
+    let mut order = F::char();
+    order.sub_noborrow(&F::Repr::from(1));
+
+    let mut power_of_three = 0;
+    loop {
+        let mut quotient = order;
+        if div_rem_small(&mut quotient, 3) != 0 {
+            break;
+        }
+        order = quotient;
+        power_of_three += 1;
+    }
+
+    // the multiplicative generator to the power of (p - 1) / 3^k
+    let root = F::multiplicative_generator().pow(order.as_ref());
+
+    (power_of_three, root)
+}
+
 impl<F: PrimeField> Domain<F> {
     pub fn new_for_size(size: u64) -> Result<Self, SynthesisError> {
         let size = size.next_power_of_two();
@@ -35,6 +70,79 @@ impl<F: PrimeField> Domain<F> {
         })
     }
 
+    /// Smallest domain of size `2^a * 3^b` that holds `size` elements. Fields without
+    /// a 3-adic subgroup only have the radix-2 domains, same as `new_for_size`.
+    ///
+    /// Only the `Polynomial` and `EvaluationDomain` FFTs work on these domains so far,
+    /// the setup, the prover and the verifier still use `new_for_size`.
+    pub fn new_for_size_mixed_radix(size: u64) -> Result<Self, SynthesisError> {
+        let (max_power_of_three, root_of_three) = three_adic_root_of_unity::<F>();
+        let max_power_of_two = F::S as u64;
+
+        // (size, power of two, power of three) of the best candidate so far
+        let mut best: Option<(u64, u64, u64)> = None;
+        let mut power_of_three_size = 1u64;
+        for power_of_three in 0..=(max_power_of_three as u64) {
+            let power_of_two_size = ((size + power_of_three_size - 1) / power_of_three_size)
+                .max(1)
+                .next_power_of_two();
+            let power_of_two = power_of_two_size.trailing_zeros() as u64;
+            if power_of_two <= max_power_of_two {
+                if let Some(candidate) = power_of_two_size.checked_mul(power_of_three_size) {
+                    if best.map(|(best_size, _, _)| candidate < best_size).unwrap_or(true) {
+                        best = Some((candidate, power_of_two, power_of_three));
+                    }
+                }
+            }
+
+            if power_of_three_size >= size {
+                break;
+            }
+            power_of_three_size = match power_of_three_size.checked_mul(3) {
+                Some(s) => s,
+                None => break,
+            };
+        }
+
+        let (size, power_of_two, power_of_three) =
+            best.ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let mut generator = F::root_of_unity();
+        for _ in power_of_two..max_power_of_two {
+            generator.square();
+        }
+        let mut generator_of_three = root_of_three;
+        for _ in power_of_three..(max_power_of_three as u64) {
+            let tmp = generator_of_three;
+            generator_of_three.square();
+            generator_of_three.mul_assign(&tmp);
+        }
+        generator.mul_assign(&generator_of_three);
+
+        Ok(Self {
+            size,
+            power_of_two,
+            generator,
+        })
+    }
+
+    /// Power of three in the size, non-zero only for the mixed radix domains where
+    /// `size = 2^power_of_two * 3^power_of_three`.
+    pub fn power_of_three(&self) -> u64 {
+        let mut rest = self.size >> self.power_of_two;
+        let mut power_of_three = 0;
+        while rest > 1 && rest % 3 == 0 {
+            rest /= 3;
+            power_of_three += 1;
+        }
+
+        power_of_three
+    }
+
+    pub fn is_mixed_radix(&self) -> bool {
+        !self.size.is_power_of_two()
+    }
+
     pub fn coset_for_natural_index_and_size(
         natural_index: usize,
         domain_size: usize,
@@ -69,6 +177,7 @@ impl<F: PrimeField> Domain<F> {
     }
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub(crate) fn materialize_domain_elements_with_natural_enumeration<F: PrimeField>(
     domain: &Domain<F>,
     worker: &Worker,
@@ -91,3 +200,49 @@ pub(crate) fn materialize_domain_elements_with_natural_enumeration<F: PrimeField
 
     values
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+    use crate::pairing::ff::Field;
+
+    #[test]
+    fn test_mixed_radix_domains() {
+        let (power_of_three, root) = three_adic_root_of_unity::<Fr>();
+        assert_eq!(power_of_three, 2);
+        assert_ne!(root.pow(&[3u64]), Fr::one());
+        assert_eq!(root.pow(&[9u64]), Fr::one());
+
+        for (requested, expected, power_of_two, power_of_three) in [
+            (1u64, 1u64, 0u64, 0u64),
+            (5, 6, 1, 1),
+            (10, 12, 2, 1),
+            (17, 18, 1, 2),
+            (64, 64, 6, 0),
+            (65, 72, 3, 2),
+            ((1 << 20) + 1, 9 << 17, 17, 2),
+        ]
+        .iter()
+        {
+            let domain = Domain::<Fr>::new_for_size_mixed_radix(*requested).unwrap();
+            assert_eq!(domain.size, *expected, "domain for {}", requested);
+            assert_eq!(domain.power_of_two, *power_of_two);
+            assert_eq!(domain.power_of_three(), *power_of_three);
+
+            // the generator has the exact order of the domain
+            assert_eq!(domain.generator.pow(&[domain.size]), Fr::one());
+            for p in [2u64, 3].iter() {
+                if domain.size % p == 0 {
+                    assert_ne!(domain.generator.pow(&[domain.size / p]), Fr::one());
+                }
+            }
+        }
+
+        // radix-2 sizes get the same domains as before
+        assert_eq!(
+            Domain::<Fr>::new_for_size_mixed_radix(1 << 10).unwrap(),
+            Domain::<Fr>::new_for_size(1 << 10).unwrap()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Out-of-core four-step NTT over the disk-backed polynomials.
+//!
+//! The transform of size `N = n1 * n2` is computed as `n2` column transforms of size `n1`,
+//! a multiplication by the twiddles `omega_N^(j2 * k1)` and `n1` row transforms of size
+//! `n2`:
+//!
+//! `X[k1 + n1 * k2] = sum_j2 omega_n2^(j2 * k2) * omega_N^(j2 * k1) * sum_j1 omega_n1^(j1 * k1) * x[n2 * j1 + j2]`
+//!
+//! Both passes move blocks of consecutive columns (rows) through memory, so only
+//! `memory_limit` bytes of values are kept at once. Every small transform is a regular
+//! Cooley-Tukey NTT with the bitreversed omegas, followed by the bitreversal of its output.
+
+use super::*;
+use crate::pairing::ff::Field;
+use crate::plonk::polynomials::disk::DiskPolynomial;
+use crate::SynthesisError;
+
+/// Smallest block the passes ever use, in values, regardless of the memory limit.
+const MIN_BLOCK_SIZE: usize = 1 << 10;
+
+pub struct FourStepNtt<F: PrimeField, P: CTPrecomputations<F>> {
+    log_n1: u32,
+    log_n2: u32,
+    column_omegas: P,
+    row_omegas: P,
+    /// Generator of the full domain (or its inverse), powers of it are the twiddles
+    omega: F,
+}
+
+fn split_size(size: usize) -> (u32, u32) {
+    assert!(size.is_power_of_two() && size >= 4, "four step NTT needs at least 4 values");
+    let log_n = log2_floor(size);
+    let log_n1 = log_n / 2;
+
+    (log_n1, log_n - log_n1)
+}
+
+impl<F: PrimeField> FourStepNtt<F, BitReversedOmegas<F>> {
+    pub fn new_for_size(size: usize, worker: &Worker) -> Result<Self, SynthesisError> {
+        let (log_n1, log_n2) = split_size(size);
+        let domain = Domain::<F>::new_for_size(size as u64)?;
+
+        Ok(Self {
+            log_n1,
+            log_n2,
+            column_omegas: BitReversedOmegas::new_for_domain(&Domain::new_for_size(1u64 << log_n1)?, worker),
+            row_omegas: BitReversedOmegas::new_for_domain(&Domain::new_for_size(1u64 << log_n2)?, worker),
+            omega: domain.generator,
+        })
+    }
+}
+
+impl<F: PrimeField> FourStepNtt<F, OmegasInvBitreversed<F>> {
+    /// Inverse transform, without the division by the size.
+    pub fn new_inverse_for_size(size: usize, worker: &Worker) -> Result<Self, SynthesisError> {
+        let (log_n1, log_n2) = split_size(size);
+        let domain = Domain::<F>::new_for_size(size as u64)?;
+
+        Ok(Self {
+            log_n1,
+            log_n2,
+            column_omegas: OmegasInvBitreversed::new_for_domain(&Domain::new_for_size(1u64 << log_n1)?, worker),
+            row_omegas: OmegasInvBitreversed::new_for_domain(&Domain::new_for_size(1u64 << log_n2)?, worker),
+            omega: domain.generator.inverse().expect("must exist"),
+        })
+    }
+}
+
+fn bitreverse_enumeration<F: PrimeField>(a: &mut [F], log_n: u32) {
+    for k in 0..a.len() {
+        let rk = bitreverse(k, log_n as usize);
+        if k < rk {
+            a.swap(rk, k);
+        }
+    }
+}
+
+impl<F: PrimeField, P: CTPrecomputations<F>> FourStepNtt<F, P> {
+    pub fn size(&self) -> usize {
+        1 << (self.log_n1 + self.log_n2)
+    }
+
+    /// Number of whole transforms of `transform_size` values that fit into `memory_limit`.
+    fn block_size(transform_size: usize, num_transforms: usize, memory_limit: usize) -> usize {
+        let limit_in_values = memory_limit / std::mem::size_of::<F>();
+        let block = std::cmp::max(limit_in_values, MIN_BLOCK_SIZE) / transform_size;
+
+        std::cmp::min(std::cmp::max(block, 1), num_transforms)
+    }
+
+    /// Transforms `input` into `output`, both in the natural order, keeping at most
+    /// about `memory_limit` bytes of values in memory. `scratch` holds the intermediate
+    /// matrix between the passes.
+    pub fn transform(
+        &self,
+        input: &mut DiskPolynomial<F>,
+        scratch: &mut DiskPolynomial<F>,
+        output: &mut DiskPolynomial<F>,
+        memory_limit: usize,
+        worker: &Worker,
+    ) -> Result<(), SynthesisError> {
+        let n1 = 1usize << self.log_n1;
+        let n2 = 1usize << self.log_n2;
+        assert_eq!(input.len(), self.size());
+        assert_eq!(scratch.len(), self.size());
+        assert_eq!(output.len(), self.size());
+
+        // columns j2: x[n2 * j1 + j2] for all j1, stored as scratch[j2 * n1 + k1]
+        let columns_per_block = Self::block_size(n1, n2, memory_limit);
+        let mut segment = vec![F::zero(); columns_per_block];
+        let mut block = vec![F::zero(); columns_per_block * n1];
+        for first_column in (0..n2).step_by(columns_per_block) {
+            let num_columns = std::cmp::min(columns_per_block, n2 - first_column);
+            let block = &mut block[..(num_columns * n1)];
+
+            // transpose while reading, every column becomes contiguous
+            for j1 in 0..n1 {
+                let segment = &mut segment[..num_columns];
+                input.read_chunk(n2 * j1 + first_column, segment)?;
+                for (c, value) in segment.iter().enumerate() {
+                    block[c * n1 + j1] = *value;
+                }
+            }
+
+            self.columns_and_twiddles(block, first_column, worker);
+            scratch.write_chunk(first_column * n1, block)?;
+        }
+
+        // rows k1: scratch[j2 * n1 + k1] for all j2, stored as output[k1 + n1 * k2]
+        let rows_per_block = Self::block_size(n2, n1, memory_limit);
+        let mut segment = vec![F::zero(); rows_per_block];
+        let mut block = vec![F::zero(); rows_per_block * n2];
+        for first_row in (0..n1).step_by(rows_per_block) {
+            let num_rows = std::cmp::min(rows_per_block, n1 - first_row);
+            let block = &mut block[..(num_rows * n2)];
+
+            for j2 in 0..n2 {
+                let segment = &mut segment[..num_rows];
+                scratch.read_chunk(j2 * n1 + first_row, segment)?;
+                for (r, value) in segment.iter().enumerate() {
+                    block[r * n2 + j2] = *value;
+                }
+            }
+
+            self.rows(block, worker);
+
+            // transpose back while writing
+            for k2 in 0..n2 {
+                let segment = &mut segment[..num_rows];
+                for (r, value) in segment.iter_mut().enumerate() {
+                    *value = block[r * n2 + k2];
+                }
+                output.write_chunk(first_row + n1 * k2, segment)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn columns_and_twiddles(&self, block: &mut [F], first_column: usize, worker: &Worker) {
+        let n1 = 1usize << self.log_n1;
+        let log_n1 = self.log_n1;
+        let omegas = &self.column_omegas;
+        let omega = self.omega;
+
+        worker.scope(block.len() / n1, |scope, chunk| {
+            for (i, columns) in block.chunks_mut(chunk * n1).enumerate() {
+                scope.spawn(move |_| {
+                    for (c, column) in columns.chunks_mut(n1).enumerate() {
+                        serial_ct_ntt(column, log_n1, omegas);
+                        bitreverse_enumeration(column, log_n1);
+
+                        let j2 = first_column + i * chunk + c;
+                        let twiddle = omega.pow(&[j2 as u64]);
+                        let mut power = twiddle;
+                        for value in column.iter_mut().skip(1) {
+                            value.mul_assign(&power);
+                            power.mul_assign(&twiddle);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    fn rows(&self, block: &mut [F], worker: &Worker) {
+        let n2 = 1usize << self.log_n2;
+        let log_n2 = self.log_n2;
+        let omegas = &self.row_omegas;
+
+        worker.scope(block.len() / n2, |scope, chunk| {
+            for rows in block.chunks_mut(chunk * n2) {
+                scope.spawn(move |_| {
+                    for row in rows.chunks_mut(n2) {
+                        serial_ct_ntt(row, log_n2, omegas);
+                        bitreverse_enumeration(row, log_n2);
+                    }
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+    use crate::plonk::fft::fft::serial_fft;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    #[test]
+    fn test_four_step_ntt() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        for log_n in [2u32, 3, 8, 11].iter() {
+            let size = 1usize << log_n;
+            let values: Vec<Fr> = (0..size).map(|_| Fr::rand(rng)).collect();
+            let domain = Domain::<Fr>::new_for_size(size as u64).unwrap();
+
+            let mut expected = values.clone();
+            serial_fft(&mut expected, &domain.generator, *log_n);
+
+            // small limits split the passes into several blocks, large ones use a single block
+            for memory_limit in [0usize, 1 << 12, usize::max_value()].iter() {
+                let mut input = DiskPolynomial::from_values(&values).unwrap();
+                let mut scratch = DiskPolynomial::new_for_size(size).unwrap();
+                let mut output = DiskPolynomial::new_for_size(size).unwrap();
+
+                let ntt = FourStepNtt::new_for_size(size, &worker).unwrap();
+                ntt.transform(&mut input, &mut scratch, &mut output, *memory_limit, &worker)
+                    .unwrap();
+                let result = output.into_values().unwrap();
+                assert_eq!(result, expected, "forward, size {}, limit {}", size, memory_limit);
+
+                // and back
+                let mut input = DiskPolynomial::from_values(&result).unwrap();
+                let mut output = DiskPolynomial::new_for_size(size).unwrap();
+                let intt = FourStepNtt::new_inverse_for_size(size, &worker).unwrap();
+                intt.transform(&mut input, &mut scratch, &mut output, *memory_limit, &worker)
+                    .unwrap();
+
+                let size_inv = Fr::from_str(&size.to_string()).unwrap().inverse().unwrap();
+                let mut back = output.into_values().unwrap();
+                for v in back.iter_mut() {
+                    v.mul_assign(&size_inv);
+                }
+                assert_eq!(back, values, "inverse, size {}, limit {}", size, memory_limit);
+            }
+        }
+    }
+}
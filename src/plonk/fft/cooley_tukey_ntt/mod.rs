@@ -2,6 +2,7 @@ use crate::pairing::ff::PrimeField;
 use crate::plonk::domains::*;
 use crate::worker::Worker;
 
+pub mod four_step;
 pub(crate) mod partial_reduction;
 
 pub trait CTPrecomputations<F: PrimeField>: Send + Sync {
@@ -87,6 +87,19 @@ pub(crate) fn parallel_fft<F: PrimeField>(
     omega: &F,
     log_n: u32,
     log_cpus: u32,
+) {
+    parallel_fft_with_kernel(a, worker, omega, log_n, log_cpus, serial_fft);
+}
+
+/// Splits the transform into `2^log_cpus` independent ones of the smaller size and runs
+/// `kernel` on every one of them.
+pub(crate) fn parallel_fft_with_kernel<F: PrimeField, K: Fn(&mut [F], &F, u32) + Copy + Send>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    log_n: u32,
+    log_cpus: u32,
+    kernel: K,
 ) {
     assert!(log_n >= log_cpus);
 
@@ -118,7 +131,7 @@ pub(crate) fn parallel_fft<F: PrimeField>(
                 }
 
                 // Perform sub-FFT
-                serial_fft(tmp, &new_omega, log_new_n);
+                kernel(tmp, &new_omega, log_new_n);
             });
         }
     });
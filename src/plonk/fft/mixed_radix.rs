@@ -0,0 +1,290 @@
+//! FFT over the mixed radix domains of size `n = 3^k * 2^l`.
+//!
+//! The transform is split as `n = n1 * n2` with `n1 = 3^k` and `n2 = 2^l`:
+//!
+//! `X[k1 + n1 * k2] = sum_j2 omega_n2^(j2 * k2) * omega_n^(j2 * k1) * sum_j1 omega_n1^(j1 * k1) * x[n2 * j1 + j2]`
+//!
+//! so `n2` radix-3 transforms of the columns are followed by the twiddles and `n1` regular
+//! radix-2 transforms of the rows.
+
+use super::best_fft;
+use crate::pairing::ff::{Field, PrimeField};
+use crate::worker::Worker;
+
+/// Arithmetic on the transformed values: the field elements themselves here, the group
+/// elements of `domain::EvaluationDomain` there.
+pub(crate) trait MixedRadixOps<F: PrimeField, T> {
+    fn zero() -> T;
+    fn add_assign(a: &mut T, b: &T);
+    fn sub_assign(a: &mut T, b: &T);
+    fn mul_assign(a: &mut T, by: &F);
+}
+
+pub(crate) struct FieldOps;
+
+impl<F: PrimeField> MixedRadixOps<F, F> for FieldOps {
+    fn zero() -> F {
+        F::zero()
+    }
+
+    fn add_assign(a: &mut F, b: &F) {
+        a.add_assign(b);
+    }
+
+    fn sub_assign(a: &mut F, b: &F) {
+        a.sub_assign(b);
+    }
+
+    fn mul_assign(a: &mut F, by: &F) {
+        a.mul_assign(by);
+    }
+}
+
+pub(crate) fn digit_reverse_3(mut n: usize, l: u32) -> usize {
+    let mut r = 0;
+    for _ in 0..l {
+        r = r * 3 + n % 3;
+        n /= 3;
+    }
+
+    r
+}
+
+pub(crate) fn serial_radix_3_fft<F: PrimeField, T: Copy, O: MixedRadixOps<F, T>>(
+    a: &mut [T],
+    omega: &F,
+    log_3: u32,
+) {
+    let n = a.len();
+    assert_eq!(n, 3usize.pow(log_3));
+    if n == 1 {
+        return;
+    }
+
+    for k in 0..n {
+        let rk = digit_reverse_3(k, log_3);
+        if k < rk {
+            a.swap(rk, k);
+        }
+    }
+
+    // primitive cube root of unity, w^2 = -1 - w
+    let w3 = omega.pow(&[(n / 3) as u64]);
+
+    let mut m = 1;
+    for _ in 0..log_3 {
+        let w_m = omega.pow(&[(n / (3 * m)) as u64]);
+
+        let mut k = 0;
+        while k < n {
+            let mut w = F::one();
+            for j in 0..m {
+                let t0 = a[k + j];
+                let mut t1 = a[k + j + m];
+                O::mul_assign(&mut t1, &w);
+                let mut w_squared = w;
+                w_squared.square();
+                let mut t2 = a[k + j + 2 * m];
+                O::mul_assign(&mut t2, &w_squared);
+
+                // w3 * (t1 - t2)
+                let mut d = t1;
+                O::sub_assign(&mut d, &t2);
+                O::mul_assign(&mut d, &w3);
+
+                let mut y0 = t0;
+                O::add_assign(&mut y0, &t1);
+                O::add_assign(&mut y0, &t2);
+
+                let mut y1 = t0;
+                O::sub_assign(&mut y1, &t2);
+                O::add_assign(&mut y1, &d);
+
+                let mut y2 = t0;
+                O::sub_assign(&mut y2, &t1);
+                O::sub_assign(&mut y2, &d);
+
+                a[k + j] = y0;
+                a[k + j + m] = y1;
+                a[k + j + 2 * m] = y2;
+
+                w.mul_assign(&w_m);
+            }
+
+            k += 3 * m;
+        }
+
+        m *= 3;
+    }
+}
+
+/// Transforms `a` of size `2^log_2 * 3^log_3`, `omega` is a generator of that order.
+/// `radix_2` transforms a row of size `2^log_2` for the given generator of its order.
+pub(crate) fn mixed_radix_fft<F, T, O, R, Err>(
+    a: &mut [T],
+    worker: &Worker,
+    omega: &F,
+    log_2: u32,
+    log_3: u32,
+    mut radix_2: R,
+) -> Result<(), Err>
+where
+    F: PrimeField,
+    T: Copy + Send + Sync,
+    O: MixedRadixOps<F, T>,
+    R: FnMut(&mut [T], &F) -> Result<(), Err>,
+{
+    let n1 = 3usize.pow(log_3);
+    let n2 = 1usize << log_2;
+    assert_eq!(a.len(), n1 * n2);
+
+    if log_3 == 0 {
+        return radix_2(a, omega);
+    }
+    if log_2 == 0 {
+        serial_radix_3_fft::<F, T, O>(a, omega, log_3);
+        return Ok(());
+    }
+
+    let column_omega = omega.pow(&[n2 as u64]);
+    let row_omega = omega.pow(&[n1 as u64]);
+    let omega = *omega;
+
+    // every column j2 becomes contiguous: tmp[j2 * n1 + j1] = a[n2 * j1 + j2]
+    let mut tmp = vec![O::zero(); a.len()];
+    {
+        let a = &*a;
+        worker.scope(n2, |scope, chunk| {
+            for (i, columns) in tmp.chunks_mut(chunk * n1).enumerate() {
+                scope.spawn(move |_| {
+                    for (c, column) in columns.chunks_mut(n1).enumerate() {
+                        let j2 = i * chunk + c;
+                        for (j1, value) in column.iter_mut().enumerate() {
+                            *value = a[n2 * j1 + j2];
+                        }
+
+                        serial_radix_3_fft::<F, T, O>(column, &column_omega, log_3);
+
+                        let twiddle = omega.pow(&[j2 as u64]);
+                        let mut power = twiddle;
+                        for value in column.iter_mut().skip(1) {
+                            O::mul_assign(value, &power);
+                            power.mul_assign(&twiddle);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    // rows k1: a[k1 * n2 + j2] = tmp[j2 * n1 + k1]
+    {
+        let tmp = &tmp;
+        worker.scope(n1, |scope, chunk| {
+            for (i, rows) in a.chunks_mut(chunk * n2).enumerate() {
+                scope.spawn(move |_| {
+                    for (r, row) in rows.chunks_mut(n2).enumerate() {
+                        let k1 = i * chunk + r;
+                        for (j2, value) in row.iter_mut().enumerate() {
+                            *value = tmp[j2 * n1 + k1];
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    // there are few long rows, so every one of them uses all the threads
+    for row in a.chunks_mut(n2) {
+        radix_2(row, &row_omega)?;
+    }
+
+    // output order: X[k1 + n1 * k2] = a[k1 * n2 + k2]
+    {
+        let a = &*a;
+        worker.scope(n2, |scope, chunk| {
+            for (i, out) in tmp.chunks_mut(chunk * n1).enumerate() {
+                scope.spawn(move |_| {
+                    for (c, out) in out.chunks_mut(n1).enumerate() {
+                        let k2 = i * chunk + c;
+                        for (k1, value) in out.iter_mut().enumerate() {
+                            *value = a[k1 * n2 + k2];
+                        }
+                    }
+                });
+            }
+        });
+    }
+    a.copy_from_slice(&tmp);
+
+    Ok(())
+}
+
+/// Transforms the field elements `a` of size `2^log_2 * 3^log_3`, `omega` is a generator
+/// of that order.
+pub(crate) fn best_mixed_radix_fft<F: PrimeField>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    log_2: u32,
+    log_3: u32,
+) {
+    let result: Result<(), ()> = mixed_radix_fft::<F, F, FieldOps, _, _>(
+        a,
+        worker,
+        omega,
+        log_2,
+        log_3,
+        |row, row_omega| {
+            best_fft(row, worker, row_omega, log_2, None);
+
+            Ok(())
+        },
+    );
+
+    result.expect("radix-2 transforms of field elements do not fail")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+    use crate::pairing::ff::Field;
+    use crate::plonk::domains::Domain;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    fn naive_dft(a: &[Fr], omega: &Fr) -> Vec<Fr> {
+        (0..a.len())
+            .map(|k| {
+                let point = omega.pow(&[k as u64]);
+                let mut result = Fr::zero();
+                for c in a.iter().rev() {
+                    result.mul_assign(&point);
+                    result.add_assign(c);
+                }
+
+                result
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mixed_radix_fft() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        for size in [3u64, 9, 6, 12, 18, 72, 96, 9 << 6].iter() {
+            let domain = Domain::<Fr>::new_for_size_mixed_radix(*size).unwrap();
+            assert_eq!(domain.size, *size);
+            let log_2 = domain.power_of_two as u32;
+            let log_3 = domain.power_of_three() as u32;
+
+            let values: Vec<Fr> = (0..*size).map(|_| Fr::rand(rng)).collect();
+            let expected = naive_dft(&values, &domain.generator);
+
+            let mut result = values.clone();
+            best_mixed_radix_fft(&mut result, &worker, &domain.generator, log_2, log_3);
+            assert_eq!(result, expected, "size {}", size);
+        }
+    }
+}
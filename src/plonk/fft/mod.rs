@@ -1,7 +1,10 @@
 pub mod cooley_tukey_ntt;
 pub(crate) mod fft;
 pub(crate) mod lde;
-pub(crate) mod radix_4;
+pub(crate) mod mixed_radix;
+pub mod radix_4;
+pub mod split_radix;
+pub mod strategy;
 pub(crate) mod with_precomputation;
 
 use cfg_if;
@@ -1,32 +1,22 @@
+//! Radix-4 transforms. Every pass over the values does the work of two radix-2 rounds,
+//! so the large transforms that do not fit into the cache read the memory half as often.
+//! Odd sizes do one radix-2 round on top.
+
 use crate::ff::PrimeField;
+use crate::plonk::fft::cooley_tukey_ntt::CTPrecomputations;
+use crate::plonk::fft::fft::parallel_fft_with_kernel;
 use crate::worker::Worker;
 
-pub(crate) fn best_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
-    assert!(log_n % 2 == 0); // TODO: For now
-    let mut log_cpus = worker.log_num_cpus();
-    if log_cpus % 2 != 0 {
-        log_cpus -= 1;
-    }
+pub fn best_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    let log_cpus = worker.log_num_cpus();
 
-    // we split into radix-4 kernels, so we need more points to start
-    if log_n <= log_cpus {
+    if log_cpus == 0 || log_n <= log_cpus {
         serial_fft_radix_4(a, omega, log_n);
     } else {
         parallel_fft_radix_4(a, worker, omega, log_n, log_cpus);
     }
 }
 
-#[inline(always)]
-fn base_4_digit_reverse(mut n: u64, l: u64) -> u64 {
-    let mut r = 0u64;
-    for _ in 0..l {
-        r = (r << 2) | (n & 3);
-        n >>= 2;
-    }
-
-    r
-}
-
 #[inline(always)]
 fn bitreverse(mut n: u64, l: u64) -> u64 {
     let mut r = 0;
@@ -37,25 +27,41 @@ fn bitreverse(mut n: u64, l: u64) -> u64 {
     r
 }
 
-pub(crate) fn serial_fft_radix_4<F: PrimeField>(a: &mut [F], omega: &F, log_n: u32) {
+/// Decimation in time over the bitreversed input. In the bitreversed order a block of
+/// `4m` values holds the transforms of its residues 0, 2, 1 and 3 modulo 4, so the middle
+/// inputs of every butterfly are swapped.
+pub fn serial_fft_radix_4<F: PrimeField>(a: &mut [F], omega: &F, log_n: u32) {
     let n = a.len() as u64;
     assert_eq!(n, 1 << log_n);
 
-    assert!(log_n % 2 == 0);
-    let num_digits = (log_n / 2) as u64;
-
     for k in 0..n {
-        let rk = base_4_digit_reverse(k, num_digits);
+        let rk = bitreverse(k, log_n as u64);
         if k < rk {
             a.swap(rk as usize, k as usize);
         }
     }
 
+    let mut m = 1;
+    if log_n % 2 == 1 {
+        for pair in a.chunks_mut(2) {
+            let mut tmp = pair[0];
+            tmp.sub_assign(&pair[1]);
+            let t = pair[1];
+            pair[0].add_assign(&t);
+            pair[1] = tmp;
+        }
+
+        m = 2;
+    }
+
+    if n < 4 {
+        return;
+    }
+
     // v = W_4
     let v = omega.pow(&[(n / 4) as u64]);
 
-    let mut m = 1;
-    for _ in 0..(log_n / 2) {
+    while m < n {
         let w_m = omega.pow(&[(n / (4 * m)) as u64]);
 
         let mut k = 0;
@@ -71,10 +77,10 @@ pub(crate) fn serial_fft_radix_4<F: PrimeField>(a: &mut [F], omega: &F, log_n: u
 
                 let x0 = a[(k + j) as usize];
 
-                let mut x1 = a[(k + j + m) as usize];
+                let mut x1 = a[(k + j + 2 * m) as usize];
                 x1.mul_assign(&w);
 
-                let mut x2 = a[(k + j + 2 * m) as usize];
+                let mut x2 = a[(k + j + m) as usize];
                 u.mul_assign(&w);
                 x2.mul_assign(&u);
 
@@ -115,63 +121,154 @@ pub(crate) fn serial_fft_radix_4<F: PrimeField>(a: &mut [F], omega: &F, log_n: u
     }
 }
 
-pub(crate) fn parallel_fft_radix_4<F: PrimeField>(
+pub fn parallel_fft_radix_4<F: PrimeField>(
     a: &mut [F],
     worker: &Worker,
     omega: &F,
     log_n: u32,
     log_cpus: u32,
 ) {
-    assert!(log_n >= log_cpus);
-
-    //we need log_n and log_cpu to be even
-    assert!(log_n % 2 == 0);
-    assert!(log_cpus % 2 == 0);
-
-    let num_cpus = 1 << log_cpus;
-    let log_new_n = log_n - log_cpus;
-    let mut tmp = vec![vec![F::zero(); 1 << log_new_n]; num_cpus];
-    let new_omega = omega.pow(&[num_cpus as u64]);
-
-    worker.scope(0, |scope, _| {
-        let a = &*a;
-
-        for (j, tmp) in tmp.iter_mut().enumerate() {
-            scope.spawn(move |_| {
-                // Shuffle into a sub-FFT
-                let omega_j = omega.pow(&[j as u64]);
-                let omega_step = omega.pow(&[(j as u64) << log_new_n]);
-
-                let mut elt = F::one();
-                for i in 0..(1 << log_new_n) {
-                    for s in 0..num_cpus {
-                        let idx = i + (s << log_new_n);
-                        let mut t = a[idx];
-                        t.mul_assign(&elt);
-                        tmp[i].add_assign(&t);
-                        elt.mul_assign(&omega_step);
-                    }
-                    elt.mul_assign(&omega_j);
-                }
+    parallel_fft_with_kernel(a, worker, omega, log_n, log_cpus, serial_fft_radix_4);
+}
 
-                // Perform sub-FFT
-                serial_fft_radix_4(tmp, &new_omega, log_new_n);
-            });
-        }
-    });
-
-    worker.scope(a.len(), |scope, chunk| {
-        let tmp = &tmp;
-
-        for (idx, a) in a.chunks_mut(chunk).enumerate() {
-            scope.spawn(move |_| {
-                let mut idx = idx * chunk;
-                let mask = (1 << log_cpus) - 1;
-                for a in a {
-                    *a = tmp[idx & mask][idx >> log_cpus];
-                    idx += 1;
+/// Two rounds of the Cooley-Tukey NTT (`cooley_tukey_ntt::serial_ct_ntt`) fused into one
+/// pass. `s` is the twiddle of the group in the first round, `s_even` and `s_odd` are the
+/// twiddles of its two halves in the second one.
+#[inline(always)]
+fn ct_radix_4_butterflies<F: PrimeField>(
+    x0: &mut [F],
+    x1: &mut [F],
+    x2: &mut [F],
+    x3: &mut [F],
+    s: &F,
+    s_even: &F,
+    s_odd: &F,
+) {
+    for (((x0, x1), x2), x3) in x0
+        .iter_mut()
+        .zip(x1.iter_mut())
+        .zip(x2.iter_mut())
+        .zip(x3.iter_mut())
+    {
+        let mut s_x2 = *x2;
+        s_x2.mul_assign(s);
+        let mut s_x3 = *x3;
+        s_x3.mul_assign(s);
+
+        let mut b0 = *x0;
+        b0.add_assign(&s_x2);
+        let mut b2 = *x0;
+        b2.sub_assign(&s_x2);
+        let mut b1 = *x1;
+        b1.add_assign(&s_x3);
+        let mut b3 = *x1;
+        b3.sub_assign(&s_x3);
+
+        b1.mul_assign(s_even);
+        b3.mul_assign(s_odd);
+
+        *x0 = b0;
+        x0.add_assign(&b1);
+        *x1 = b0;
+        x1.sub_assign(&b1);
+        *x2 = b2;
+        x2.add_assign(&b3);
+        *x3 = b2;
+        x3.sub_assign(&b3);
+    }
+}
+
+/// Radix-4 version of `cooley_tukey_ntt::best_ct_ntt`: natural input, bitreversed output,
+/// same precomputed omegas.
+pub fn best_ct_ntt_radix_4<F: PrimeField, P: CTPrecomputations<F>>(
+    a: &mut [F],
+    worker: &Worker,
+    log_n: u32,
+    precomputed_omegas: &P,
+) {
+    assert_eq!(a.len(), precomputed_omegas.domain_size());
+    assert_eq!(a.len(), (1 << log_n) as usize);
+
+    let n = a.len();
+    if n == 1 {
+        return;
+    }
+    let omegas_bit_reversed = precomputed_omegas.bit_reversed_omegas();
+    let num_cpus = worker.get_num_cpus();
+
+    let mut num_groups = 1;
+    let mut remaining_rounds = log_n;
+    while remaining_rounds >= 2 {
+        // every group of 4 * quarter values is split into 4 quarters
+        let quarter = n / (num_groups * 4);
+
+        if num_groups >= num_cpus {
+            worker.scope(num_groups, |scope, chunk| {
+                for (i, groups) in a.chunks_mut(chunk * 4 * quarter).enumerate() {
+                    scope.spawn(move |_| {
+                        for (g, group) in groups.chunks_mut(4 * quarter).enumerate() {
+                            let k = i * chunk + g;
+                            let (x01, x23) = group.split_at_mut(2 * quarter);
+                            let (x0, x1) = x01.split_at_mut(quarter);
+                            let (x2, x3) = x23.split_at_mut(quarter);
+                            ct_radix_4_butterflies(
+                                x0,
+                                x1,
+                                x2,
+                                x3,
+                                &omegas_bit_reversed[k],
+                                &omegas_bit_reversed[2 * k],
+                                &omegas_bit_reversed[2 * k + 1],
+                            );
+                        }
+                    });
                 }
             });
+        } else {
+            // few large groups, every one of them is split between the threads
+            for (k, group) in a.chunks_mut(4 * quarter).enumerate() {
+                let (x01, x23) = group.split_at_mut(2 * quarter);
+                let (x0, x1) = x01.split_at_mut(quarter);
+                let (x2, x3) = x23.split_at_mut(quarter);
+                let s = &omegas_bit_reversed[k];
+                let s_even = &omegas_bit_reversed[2 * k];
+                let s_odd = &omegas_bit_reversed[2 * k + 1];
+
+                worker.scope(quarter, |scope, chunk| {
+                    for (((x0, x1), x2), x3) in x0
+                        .chunks_mut(chunk)
+                        .zip(x1.chunks_mut(chunk))
+                        .zip(x2.chunks_mut(chunk))
+                        .zip(x3.chunks_mut(chunk))
+                    {
+                        scope.spawn(move |_| {
+                            ct_radix_4_butterflies(x0, x1, x2, x3, s, s_even, s_odd);
+                        });
+                    }
+                });
+            }
         }
-    });
+
+        num_groups *= 4;
+        remaining_rounds -= 2;
+    }
+
+    if remaining_rounds == 1 {
+        // last radix-2 round over the neighbouring pairs
+        worker.scope(num_groups, |scope, chunk| {
+            for (i, pairs) in a.chunks_mut(chunk * 2).enumerate() {
+                scope.spawn(move |_| {
+                    for (p, pair) in pairs.chunks_mut(2).enumerate() {
+                        let mut v = pair[1];
+                        v.mul_assign(&omegas_bit_reversed[i * chunk + p]);
+
+                        let mut tmp = pair[0];
+                        tmp.sub_assign(&v);
+                        pair[1] = tmp;
+                        pair[0].add_assign(&v);
+                    }
+                });
+            }
+        });
+    }
 }
@@ -0,0 +1,118 @@
+//! Split-radix transform: the even half goes through a transform of half the size, the
+//! odd values with the residues 1 and 3 modulo 4 through two transforms of a quarter of
+//! the size:
+//!
+//! `X[k] = U[k] + w^k * Z1[k] + w^(3k) * Z3[k]`
+//!
+//! Sub-transforms are done depth first, so once they fit into the cache they stay there
+//! until they are done. The twiddles are computed on the fly, so the only allocation is
+//! a scratch space of the size of the input.
+
+use crate::pairing::ff::PrimeField;
+use crate::plonk::fft::fft::parallel_fft_with_kernel;
+use crate::worker::Worker;
+
+pub fn best_split_radix_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    let log_cpus = worker.log_num_cpus();
+
+    if log_cpus == 0 || log_n <= log_cpus {
+        serial_split_radix_fft(a, omega, log_n);
+    } else {
+        parallel_fft_with_kernel(a, worker, omega, log_n, log_cpus, serial_split_radix_fft);
+    }
+}
+
+pub fn serial_split_radix_fft<F: PrimeField>(a: &mut [F], omega: &F, log_n: u32) {
+    let n = a.len();
+    assert_eq!(n, 1 << log_n);
+    if n == 1 {
+        return;
+    }
+
+    // 4-th root of unity, the same on every level
+    let i = if n >= 4 { omega.pow(&[(n / 4) as u64]) } else { F::one() };
+
+    let mut scratch = vec![F::zero(); n];
+    split_radix_step(a, &mut scratch, omega, &i);
+}
+
+/// Transforms `a` with the root `omega` using `scratch` of the same size.
+fn split_radix_step<F: PrimeField>(a: &mut [F], scratch: &mut [F], omega: &F, i: &F) {
+    let n = a.len();
+    if n == 1 {
+        return;
+    }
+    if n == 2 {
+        let mut tmp = a[0];
+        tmp.sub_assign(&a[1]);
+        let t = a[1];
+        a[0].add_assign(&t);
+        a[1] = tmp;
+
+        return;
+    }
+
+    let half = n / 2;
+    let quarter = n / 4;
+
+    {
+        let (u, z) = scratch.split_at_mut(half);
+        let (z1, z3) = z.split_at_mut(quarter);
+        for (m, u) in u.iter_mut().enumerate() {
+            *u = a[2 * m];
+        }
+        for m in 0..quarter {
+            z1[m] = a[4 * m + 1];
+            z3[m] = a[4 * m + 3];
+        }
+
+        // the values are gathered, so `a` is the scratch space of the sub-transforms
+        let (a_u, a_z) = a.split_at_mut(half);
+        let (a_z1, a_z3) = a_z.split_at_mut(quarter);
+        let mut omega_squared = *omega;
+        omega_squared.square();
+        let mut omega_fourth = omega_squared;
+        omega_fourth.square();
+
+        split_radix_step(u, a_u, &omega_squared, i);
+        split_radix_step(z1, a_z1, &omega_fourth, i);
+        split_radix_step(z3, a_z3, &omega_fourth, i);
+    }
+
+    let mut omega_cubed = *omega;
+    omega_cubed.square();
+    omega_cubed.mul_assign(omega);
+
+    // omega^k and omega^(3k)
+    let mut w = F::one();
+    let mut w_cubed = F::one();
+
+    let (u, z) = scratch.split_at(half);
+    let (z1, z3) = z.split_at(quarter);
+    for k in 0..quarter {
+        let mut t1 = z1[k];
+        let mut t3 = z3[k];
+        if k != 0 {
+            t1.mul_assign(&w);
+            t3.mul_assign(&w_cubed);
+        }
+        w.mul_assign(omega);
+        w_cubed.mul_assign(&omega_cubed);
+
+        let mut sum = t1;
+        sum.add_assign(&t3);
+        // i * (t1 - t3)
+        let mut diff = t1;
+        diff.sub_assign(&t3);
+        diff.mul_assign(i);
+
+        a[k] = u[k];
+        a[k].add_assign(&sum);
+        a[k + half] = u[k];
+        a[k + half].sub_assign(&sum);
+        a[k + quarter] = u[k + quarter];
+        a[k + quarter].add_assign(&diff);
+        a[k + half + quarter] = u[k + quarter];
+        a[k + half + quarter].sub_assign(&diff);
+    }
+}
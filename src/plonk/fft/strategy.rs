@@ -0,0 +1,275 @@
+//! Runtime choice between the radix-2, radix-4 and split-radix transforms.
+//!
+//! The radix-2 transforms are used unless the [`NttSelection`] of the worker says otherwise,
+//! provers take it from [`ProverConfig::ntt`](crate::config::ProverConfig::ntt). It either
+//! forces one of them or picks one by size. Transforms that fit into the cache are memory
+//! bound only once, so the choice by size uses the split-radix one with the depth first
+//! recursion for them. Larger ones are bound by the passes over the memory and use the
+//! radix-4 one that makes half as many.
+
+use super::cooley_tukey_ntt::{self, CTPrecomputations};
+use super::radix_4;
+use super::split_radix;
+use crate::pairing::ff::PrimeField;
+use crate::worker::Worker;
+
+/// Cache size assumed by [`NttSelection::auto`], a common size of L2.
+pub const DEFAULT_CACHE_SIZE: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NttStrategy {
+    Radix2,
+    Radix4,
+    SplitRadix,
+}
+
+/// How the CPU transforms of a worker are chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NttSelection {
+    /// The same transform for every size.
+    Fixed(NttStrategy),
+    /// The one of [`NttStrategy::for_size`] with a cache of `cache_size` bytes.
+    BySize { cache_size: usize },
+}
+
+/// Radix-2 for every size.
+impl Default for NttSelection {
+    fn default() -> Self {
+        NttSelection::Fixed(NttStrategy::Radix2)
+    }
+}
+
+impl NttSelection {
+    /// By size with the [`DEFAULT_CACHE_SIZE`].
+    pub fn auto() -> Self {
+        NttSelection::BySize {
+            cache_size: DEFAULT_CACHE_SIZE,
+        }
+    }
+
+    /// `auto` or one of the names of [`NttStrategy::from_name`].
+    pub fn from_name(name: &str, cache_size: usize) -> Option<Self> {
+        if name.eq_ignore_ascii_case("auto") {
+            return Some(NttSelection::BySize { cache_size });
+        }
+
+        NttStrategy::from_name(name).map(NttSelection::Fixed)
+    }
+
+    /// Strategy for the transform of `2^log_n` elements of `F`.
+    pub fn strategy_for<F: PrimeField>(&self, log_n: u32) -> NttStrategy {
+        match *self {
+            NttSelection::Fixed(strategy) => strategy,
+            NttSelection::BySize { cache_size } => {
+                NttStrategy::for_size(log_n, std::mem::size_of::<F>(), cache_size)
+            }
+        }
+    }
+}
+
+impl NttStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "radix2" | "radix-2" => Some(NttStrategy::Radix2),
+            "radix4" | "radix-4" => Some(NttStrategy::Radix4),
+            "split_radix" | "split-radix" => Some(NttStrategy::SplitRadix),
+            _ => None,
+        }
+    }
+
+    /// Strategy for the transform of `2^log_n` values of `element_size` bytes.
+    pub fn for_size(log_n: u32, element_size: usize, cache_size: usize) -> Self {
+        if log_n < 2 {
+            return NttStrategy::Radix2;
+        }
+
+        if element_size << log_n <= cache_size {
+            NttStrategy::SplitRadix
+        } else {
+            NttStrategy::Radix4
+        }
+    }
+}
+
+/// Natural order in and out, with the strategy of the worker.
+pub fn best_ntt<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    let strategy = worker.ntt().strategy_for::<F>(log_n);
+    best_ntt_with_strategy(a, worker, omega, log_n, strategy)
+}
+
+pub fn best_ntt_with_strategy<F: PrimeField>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    log_n: u32,
+    strategy: NttStrategy,
+) {
+    match strategy {
+        NttStrategy::Radix2 => super::best_fft(a, worker, omega, log_n, None),
+        NttStrategy::Radix4 => radix_4::best_fft(a, worker, omega, log_n),
+        NttStrategy::SplitRadix => split_radix::best_split_radix_fft(a, worker, omega, log_n),
+    }
+}
+
+/// Natural order in, bitreversed order out, with the precomputed bitreversed omegas and
+/// the strategy of the worker.
+/// There is no split-radix version, the radix-2 one is used instead.
+pub fn best_bitreversed_ntt<F: PrimeField, P: CTPrecomputations<F>>(
+    a: &mut [F],
+    worker: &Worker,
+    log_n: u32,
+    precomputed_omegas: &P,
+) {
+    let strategy = worker.ntt().strategy_for::<F>(log_n);
+    best_bitreversed_ntt_with_strategy(a, worker, log_n, precomputed_omegas, strategy)
+}
+
+pub fn best_bitreversed_ntt_with_strategy<F: PrimeField, P: CTPrecomputations<F>>(
+    a: &mut [F],
+    worker: &Worker,
+    log_n: u32,
+    precomputed_omegas: &P,
+    strategy: NttStrategy,
+) {
+    match strategy {
+        NttStrategy::Radix4 => radix_4::best_ct_ntt_radix_4(a, worker, log_n, precomputed_omegas),
+        NttStrategy::Radix2 | NttStrategy::SplitRadix => cooley_tukey_ntt::best_ct_ntt(
+            a,
+            worker,
+            log_n,
+            Some(worker.get_num_cpus()),
+            precomputed_omegas,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+    use crate::pairing::ff::Field;
+    use crate::plonk::domains::Domain;
+    use crate::plonk::fft::cooley_tukey_ntt::{bitreverse, BitReversedOmegas, OmegasInvBitreversed};
+    use crate::plonk::fft::serial_fft;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    const STRATEGIES: [NttStrategy; 3] = [
+        NttStrategy::Radix2,
+        NttStrategy::Radix4,
+        NttStrategy::SplitRadix,
+    ];
+
+    #[test]
+    fn test_strategies_match_serial_fft() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        // a single thread runs the serial kernels, the default one the parallel ones
+        let workers = [Worker::builder().num_threads(1).build().unwrap(), Worker::new()];
+
+        for log_n in 0..14u32 {
+            let values: Vec<Fr> = (0..(1 << log_n)).map(|_| Fr::rand(rng)).collect();
+            let domain = Domain::<Fr>::new_for_size(1 << log_n).unwrap();
+
+            for omega in [domain.generator, domain.generator.inverse().unwrap()].iter() {
+                let mut expected = values.clone();
+                serial_fft(&mut expected, omega, log_n);
+
+                for worker in workers.iter() {
+                    for strategy in STRATEGIES.iter() {
+                        let mut result = values.clone();
+                        best_ntt_with_strategy(&mut result, worker, omega, log_n, *strategy);
+                        assert_eq!(
+                            result, expected,
+                            "{:?}, 2^{}, {} threads",
+                            strategy, log_n, worker.get_num_cpus()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitreversed_strategies_match_serial_fft() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let workers = [Worker::builder().num_threads(1).build().unwrap(), Worker::new()];
+
+        for log_n in 1..14u32 {
+            let size = 1usize << log_n;
+            let values: Vec<Fr> = (0..size).map(|_| Fr::rand(rng)).collect();
+            let domain = Domain::<Fr>::new_for_size(size as u64).unwrap();
+
+            for worker in workers.iter() {
+                let omegas = BitReversedOmegas::<Fr>::new_for_domain(&domain, worker);
+                let omegas_inv = OmegasInvBitreversed::<Fr>::new_for_domain(&domain, worker);
+
+                let mut expected = values.clone();
+                serial_fft(&mut expected, &domain.generator, log_n);
+                let mut expected_inv = values.clone();
+                serial_fft(&mut expected_inv, &domain.generator.inverse().unwrap(), log_n);
+
+                for strategy in STRATEGIES.iter() {
+                    let mut result = values.clone();
+                    best_bitreversed_ntt_with_strategy(&mut result, worker, log_n, &omegas, *strategy);
+                    let mut result_inv = values.clone();
+                    best_bitreversed_ntt_with_strategy(
+                        &mut result_inv,
+                        worker,
+                        log_n,
+                        &omegas_inv,
+                        *strategy,
+                    );
+
+                    for k in 0..size {
+                        let rk = bitreverse(k, log_n as usize);
+                        assert_eq!(result[rk], expected[k], "{:?}, 2^{}", strategy, log_n);
+                        assert_eq!(result_inv[rk], expected_inv[k], "{:?}, 2^{}", strategy, log_n);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_strategy_selection() {
+        let fr_size = std::mem::size_of::<Fr>();
+
+        assert_eq!(NttStrategy::for_size(1, fr_size, 1 << 20), NttStrategy::Radix2);
+        assert_eq!(NttStrategy::for_size(10, fr_size, 1 << 20), NttStrategy::SplitRadix);
+        assert_eq!(NttStrategy::for_size(15, fr_size, 1 << 20), NttStrategy::SplitRadix);
+        assert_eq!(NttStrategy::for_size(16, fr_size, 1 << 20), NttStrategy::Radix4);
+        assert_eq!(NttStrategy::from_name("Split-Radix"), Some(NttStrategy::SplitRadix));
+        assert_eq!(NttStrategy::from_name("radix8"), None);
+
+        assert_eq!(NttSelection::default().strategy_for::<Fr>(16), NttStrategy::Radix2);
+        assert_eq!(NttSelection::from_name("radix8", 1 << 20), None);
+        assert_eq!(
+            NttSelection::from_name("radix4", 1 << 20),
+            Some(NttSelection::Fixed(NttStrategy::Radix4))
+        );
+
+        let by_size = NttSelection::from_name("Auto", 1 << 10).unwrap();
+        assert_eq!(by_size, NttSelection::BySize { cache_size: 1 << 10 });
+        assert_eq!(by_size.strategy_for::<Fr>(5), NttStrategy::SplitRadix);
+        assert_eq!(by_size.strategy_for::<Fr>(6), NttStrategy::Radix4);
+    }
+
+    #[test]
+    fn test_strategy_of_worker_is_used() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let log_n = 10u32;
+        let values: Vec<Fr> = (0..(1 << log_n)).map(|_| Fr::rand(rng)).collect();
+        let domain = Domain::<Fr>::new_for_size(1 << log_n).unwrap();
+
+        let mut expected = values.clone();
+        serial_fft(&mut expected, &domain.generator, log_n);
+
+        for strategy in STRATEGIES.iter() {
+            let worker = Worker::new().with_ntt(NttSelection::Fixed(*strategy));
+            assert_eq!(worker.ntt(), NttSelection::Fixed(*strategy));
+
+            let mut result = values.clone();
+            best_ntt(&mut result, &worker, &domain.generator, log_n);
+            assert_eq!(result, expected, "{:?}", strategy);
+        }
+    }
+}
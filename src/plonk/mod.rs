@@ -2,27 +2,43 @@ pub mod better_cs;
 pub mod commitments;
 pub mod cs;
 pub mod domains;
+#[cfg(not(feature = "verifier-only"))]
 pub mod fft;
+#[cfg(not(feature = "verifier-only"))]
 pub mod polynomials;
+#[cfg(not(feature = "verifier-only"))]
+pub mod redshift;
+#[cfg(not(feature = "verifier-only"))]
 pub mod transparent_engine;
+#[cfg(not(feature = "verifier-only"))]
 pub mod utils;
 
-
+#[cfg(not(feature = "verifier-only"))]
 pub use self::better_cs::adaptor::{Adaptor, AdaptorCircuit, TranspilationVariant, Transpiler};
-pub use self::better_cs::keys::{
-    Proof, SetupPolynomials, SetupPolynomialsPrecomputations, VerificationKey,
-};
+#[cfg(not(feature = "verifier-only"))]
+pub use self::better_cs::keys::{SetupPolynomials, SetupPolynomialsPrecomputations};
+pub use self::better_cs::keys::{Proof, VerificationKey};
+
+#[cfg(not(feature = "verifier-only"))]
+pub use crate::config::ProverConfig;
 
 use self::better_cs::cs::{PlonkConstraintSystemParams, PlonkCsWidth4WithNextStepParams};
-use crate::locks::LockedMultiFFTKernel;
 
+#[cfg(not(feature = "verifier-only"))]
 use crate::kate_commitment::*;
+#[cfg(not(feature = "verifier-only"))]
+use crate::locks::LockedMultiFFTKernel;
+#[cfg(not(feature = "verifier-only"))]
+use crate::plonk::fft::cooley_tukey_ntt::*;
+#[cfg(not(feature = "verifier-only"))]
+use crate::plonk::polynomials::pool::{PolynomialPool, StepMemoryReport};
 use crate::pairing::Engine;
 use crate::plonk::commitments::transcript::*;
-use crate::plonk::fft::cooley_tukey_ntt::*;
-use crate::worker::Worker;
 use crate::{Circuit, SynthesisError};
+#[cfg(not(feature = "verifier-only"))]
+use log::info;
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn transpile<E: Engine, C: crate::Circuit<E>>(
     circuit: C,
 ) -> Result<Vec<(usize, TranspilationVariant)>, SynthesisError> {
@@ -37,6 +53,7 @@ pub fn transpile<E: Engine, C: crate::Circuit<E>>(
     Ok(hints)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn transpile_with_gates_count<E: Engine, C: crate::Circuit<E>>(
     circuit: C,
 ) -> Result<(usize, Vec<(usize, TranspilationVariant)>), SynthesisError> {
@@ -51,6 +68,7 @@ pub fn transpile_with_gates_count<E: Engine, C: crate::Circuit<E>>(
     Ok((n, hints))
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn is_satisfied<E: Engine, C: crate::Circuit<E>>(
     circuit: C,
     hints: &Vec<(usize, TranspilationVariant)>,
@@ -65,6 +83,7 @@ pub fn is_satisfied<E: Engine, C: crate::Circuit<E>>(
     adapted_curcuit.synthesize(&mut assembly)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn is_satisfied_using_one_shot_check<E: Engine, C: crate::Circuit<E>>(
     circuit: C,
     hints: &Vec<(usize, TranspilationVariant)>,
@@ -87,9 +106,19 @@ pub fn is_satisfied_using_one_shot_check<E: Engine, C: crate::Circuit<E>>(
     }
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn setup<E: Engine, C: crate::Circuit<E>>(
     circuit: C,
     hints: &Vec<(usize, TranspilationVariant)>,
+) -> Result<SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
+    setup_with_config(circuit, hints, &ProverConfig::from_env())
+}
+
+#[cfg(not(feature = "verifier-only"))]
+pub fn setup_with_config<E: Engine, C: crate::Circuit<E>>(
+    circuit: C,
+    hints: &Vec<(usize, TranspilationVariant)>,
+    config: &ProverConfig,
 ) -> Result<SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
     use crate::plonk::better_cs::cs::Circuit;
 
@@ -102,31 +131,53 @@ pub fn setup<E: Engine, C: crate::Circuit<E>>(
     adapted_curcuit.synthesize(&mut assembly)?;
     assembly.finalize();
 
-    let worker = Worker::new();
+    let worker = config.worker();
 
-    assembly.setup(&worker)
+    assembly.setup_with_config(&worker, config)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn make_verification_key<E: Engine, P: PlonkConstraintSystemParams<E>>(
     setup: &SetupPolynomials<E, P>,
     crs: &Crs<E, CrsForMonomialForm>,
 ) -> Result<VerificationKey<E, P>, SynthesisError> {
-    let worker = Worker::new();
-    let verification_key = VerificationKey::from_setup(&setup, &worker, &crs)?;
+    make_verification_key_with_config(setup, crs, &ProverConfig::from_env())
+}
+
+#[cfg(not(feature = "verifier-only"))]
+pub fn make_verification_key_with_config<E: Engine, P: PlonkConstraintSystemParams<E>>(
+    setup: &SetupPolynomials<E, P>,
+    crs: &Crs<E, CrsForMonomialForm>,
+    config: &ProverConfig,
+) -> Result<VerificationKey<E, P>, SynthesisError> {
+    let worker = config.worker();
+    let verification_key =
+        VerificationKey::from_setup_with_config(&setup, &worker, &crs, config)?;
 
     Ok(verification_key)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn make_precomputations<E: Engine, P: PlonkConstraintSystemParams<E>>(
     setup: &SetupPolynomials<E, P>,
 ) -> Result<SetupPolynomialsPrecomputations<E, P>, SynthesisError> {
-    let worker = Worker::new();
+    make_precomputations_with_config(setup, &ProverConfig::from_env())
+}
+
+#[cfg(not(feature = "verifier-only"))]
+pub fn make_precomputations_with_config<E: Engine, P: PlonkConstraintSystemParams<E>>(
+    setup: &SetupPolynomials<E, P>,
+    config: &ProverConfig,
+) -> Result<SetupPolynomialsPrecomputations<E, P>, SynthesisError> {
+    let worker = config.worker();
 
-    let precomputations = SetupPolynomialsPrecomputations::from_setup(&setup, &worker)?;
+    let precomputations =
+        SetupPolynomialsPrecomputations::from_setup_with_config(&setup, &worker, config)?;
 
     Ok(precomputations)
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn prove_native_by_steps<
     E: Engine,
     C: crate::plonk::better_cs::cs::Circuit<E, PlonkCsWidth4WithNextStepParams>,
@@ -140,6 +191,72 @@ pub fn prove_native_by_steps<
     csr_mon_basis: &Crs<E, CrsForMonomialForm>,
     transcript_init_params: Option<<T as Prng<E::Fr>>::InitializationParameters>,
 ) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
+    prove_native_by_steps_with_config::<_, _, T>(
+        circuit,
+        setup,
+        setup_precomputations,
+        csr_mon_basis,
+        transcript_init_params,
+        &ProverConfig::from_env(),
+    )
+}
+
+#[cfg(not(feature = "verifier-only"))]
+pub fn prove_native_by_steps_with_config<
+    E: Engine,
+    C: crate::plonk::better_cs::cs::Circuit<E, PlonkCsWidth4WithNextStepParams>,
+    T: Transcript<E::Fr>,
+>(
+    circuit: &C,
+    setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    setup_precomputations: Option<
+        &SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
+    >,
+    csr_mon_basis: &Crs<E, CrsForMonomialForm>,
+    transcript_init_params: Option<<T as Prng<E::Fr>>::InitializationParameters>,
+    config: &ProverConfig,
+) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
+    prove_native_by_steps_with_timings::<_, _, T>(
+        circuit,
+        setup,
+        setup_precomputations,
+        csr_mon_basis,
+        transcript_init_params,
+        config,
+    )
+    .map(|(proof, _)| proof)
+}
+
+/// Wall clock time of the synthesis and of every step of one proof.
+#[cfg(not(feature = "verifier-only"))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProverTimings {
+    pub synthesis: std::time::Duration,
+    /// Witness commitment, grand product commitment, quotient, openings at z and the
+    /// opening proofs, in this order.
+    pub steps: [std::time::Duration; 5],
+    /// Polynomial pool usage of the second and third steps, the only ones under the
+    /// memory budget.
+    pub memory_reports: Vec<StepMemoryReport>,
+}
+
+/// Same as `prove_native_by_steps_with_config`, also returns how long every step took
+/// and how much of the memory budget the pooled steps used.
+#[cfg(not(feature = "verifier-only"))]
+pub fn prove_native_by_steps_with_timings<
+    E: Engine,
+    C: crate::plonk::better_cs::cs::Circuit<E, PlonkCsWidth4WithNextStepParams>,
+    T: Transcript<E::Fr>,
+>(
+    circuit: &C,
+    setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    setup_precomputations: Option<
+        &SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
+    >,
+    csr_mon_basis: &Crs<E, CrsForMonomialForm>,
+    transcript_init_params: Option<<T as Prng<E::Fr>>::InitializationParameters>,
+    config: &ProverConfig,
+) -> Result<(Proof<E, PlonkCsWidth4WithNextStepParams>, ProverTimings), SynthesisError> {
     use crate::plonk::better_cs::prover::prove_steps::{
         FirstVerifierMessage, FourthVerifierMessage, SecondVerifierMessage, ThirdVerifierMessage,
     };
@@ -155,9 +272,11 @@ pub fn prove_native_by_steps<
     circuit.synthesize(&mut assembly)?;
     assembly.finalize();
 
-    println!("Synthesis taken {:?}", subtime.elapsed());
+    let mut timings = ProverTimings::default();
+    timings.synthesis = subtime.elapsed();
+    info!("Synthesis taken {:?}", timings.synthesis);
 
-    let worker = Worker::new();
+    let worker = config.worker();
 
     let now = Instant::now();
 
@@ -169,13 +288,17 @@ pub fn prove_native_by_steps<
 
     let mut proof = Proof::<E, PlonkCsWidth4WithNextStepParams>::empty();
 
+    // large polynomials of the second and third steps are taken from here
+    let pool = PolynomialPool::<E::Fr>::new(config.memory_budget);
+
     let subtime = Instant::now();
 
     let (first_state, first_message) =
-        assembly.first_step_with_monomial_form_key(&worker, csr_mon_basis)?;
-    println!(
+        assembly.first_step_with_monomial_form_key(&worker, config, csr_mon_basis)?;
+    timings.steps[0] = subtime.elapsed();
+    info!(
         "First step (witness commitment) taken {:?}",
-        subtime.elapsed()
+        timings.steps[0]
     );
 
     proof.n = first_message.n;
@@ -203,6 +326,7 @@ pub fn prove_native_by_steps<
 
     let subtime = Instant::now();
 
+    pool.begin_step("Second step");
     let (second_state,
         second_message) =
         self::better_cs::prover::ProverAssembly::second_step_from_first_step(
@@ -212,10 +336,14 @@ pub fn prove_native_by_steps<
             csr_mon_basis,
             &setup_precomputations,
             &worker,
+            &pool,
+            config,
         )?;
-    println!(
+    pool.end_step();
+    timings.steps[1] = subtime.elapsed();
+    info!(
         "Second step (grand product commitment) taken {:?}",
-        subtime.elapsed()
+        timings.steps[1]
     );
 
     proof.grand_product_commitment = second_message.z_commitment;
@@ -233,6 +361,7 @@ pub fn prove_native_by_steps<
 
     let subtime = Instant::now();
 
+    pool.begin_step("Third step");
     let (third_state,
         third_message) =
         self::better_cs::prover::ProverAssembly::third_step_from_second_step(
@@ -242,10 +371,17 @@ pub fn prove_native_by_steps<
             csr_mon_basis,
             &setup_precomputations,
             &worker,
+            &pool,
+            config,
         )?;
-    println!(
+    pool.end_step();
+    // the remaining steps do not use the pool
+    pool.release_cached();
+    timings.memory_reports = pool.step_reports();
+    timings.steps[2] = subtime.elapsed();
+    info!(
         "Third step (quotient calculation and commitment) taken {:?}",
-        subtime.elapsed()
+        timings.steps[2]
     );
 
     proof.quotient_poly_commitments = third_message.quotient_poly_commitments;
@@ -274,8 +410,10 @@ pub fn prove_native_by_steps<
             third_verifier_message,
             &setup,
             &worker,
+            config,
         )?;
-    println!("Fourth step (openings at z) taken {:?}", subtime.elapsed());
+    timings.steps[3] = subtime.elapsed();
+    info!("Fourth step (openings at z) taken {:?}", timings.steps[3]);
 
     proof.wire_values_at_z = fourth_message.wire_values_at_z;
     proof.wire_values_at_z_omega = fourth_message.wire_values_at_z_omega;
@@ -320,21 +458,24 @@ pub fn prove_native_by_steps<
         &setup,
         csr_mon_basis,
         &worker,
+        config,
     )?;
 
-    println!(
+    timings.steps[4] = subtime.elapsed();
+    info!(
         "Fifth step (proving opening at z) taken {:?}",
-        subtime.elapsed()
+        timings.steps[4]
     );
 
     proof.opening_at_z_proof = fifth_message.opening_proof_at_z;
     proof.opening_at_z_omega_proof = fifth_message.opening_proof_at_z_omega;
 
-    println!("Proving taken {:?}", now.elapsed());
+    info!("Proving taken {:?}", now.elapsed());
 
-    Ok(proof)
+    Ok((proof, timings))
 }
 
+#[cfg(not(feature = "verifier-only"))]
 pub fn prove_by_steps<E: Engine, C: crate::Circuit<E>, T: Transcript<E::Fr>>(
     circuit: C,
     hints: &Vec<(usize, TranspilationVariant)>,
@@ -344,6 +485,29 @@ pub fn prove_by_steps<E: Engine, C: crate::Circuit<E>, T: Transcript<E::Fr>>(
     >,
     csr_mon_basis: &Crs<E, CrsForMonomialForm>,
     transcript_init_params: Option<<T as Prng<E::Fr>>::InitializationParameters>,
+) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
+    prove_by_steps_with_config::<_, _, T>(
+        circuit,
+        hints,
+        setup,
+        setup_precomputations,
+        csr_mon_basis,
+        transcript_init_params,
+        &ProverConfig::from_env(),
+    )
+}
+
+#[cfg(not(feature = "verifier-only"))]
+pub fn prove_by_steps_with_config<E: Engine, C: crate::Circuit<E>, T: Transcript<E::Fr>>(
+    circuit: C,
+    hints: &Vec<(usize, TranspilationVariant)>,
+    setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    setup_precomputations: Option<
+        &SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
+    >,
+    csr_mon_basis: &Crs<E, CrsForMonomialForm>,
+    transcript_init_params: Option<<T as Prng<E::Fr>>::InitializationParameters>,
+    config: &ProverConfig,
 ) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
     use crate::plonk::better_cs::cs::Circuit;
     use crate::plonk::better_cs::prover::prove_steps::{
@@ -354,12 +518,13 @@ pub fn prove_by_steps<E: Engine, C: crate::Circuit<E>, T: Transcript<E::Fr>>(
     let adapted_curcuit =
         AdaptorCircuit::<E, PlonkCsWidth4WithNextStepParams, _>::new(circuit, &hints);
 
-    prove_native_by_steps::<_, _, T>(
+    prove_native_by_steps_with_config::<_, _, T>(
         &adapted_curcuit,
         setup,
         setup_precomputations,
         csr_mon_basis,
         transcript_init_params,
+        config,
     )
 }
 
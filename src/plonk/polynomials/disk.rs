@@ -0,0 +1,182 @@
+//! Disk-backed storage for the polynomials that do not fit into memory.
+//!
+//! Values are stored in the raw (Montgomery) representation, so reading and writing
+//! does not convert the elements. The backing file lives in the temporary directory
+//! (or `BELLMAN_DISK_POLYNOMIALS_DIR` if it is set) and is removed on drop.
+
+use crate::pairing::ff::{PrimeField, PrimeFieldRepr};
+use crate::SynthesisError;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of elements moved through a single buffer by the sequential reads and writes.
+const IO_BUFFER_ELEMENTS: usize = 1 << 16;
+
+fn storage_dir() -> PathBuf {
+    std::env::var_os("BELLMAN_DISK_POLYNOMIALS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+pub struct DiskPolynomial<F: PrimeField> {
+    file: File,
+    path: PathBuf,
+    len: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> DiskPolynomial<F> {
+    /// Bytes taken by a single element in the file.
+    pub fn element_size() -> usize {
+        F::zero().into_raw_repr().as_ref().len() * 8
+    }
+
+    /// Creates a polynomial of `len` zero values.
+    pub fn new_for_size(len: usize) -> Result<Self, SynthesisError> {
+        let name = format!(
+            "bellman_gpu_{}_{}.poly",
+            std::process::id(),
+            FILE_COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+        let path = storage_dir().join(name);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        // sparse file, zeroes are the raw representation of zero
+        file.set_len((len * Self::element_size()) as u64)?;
+
+        Ok(Self {
+            file,
+            path,
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn from_values(values: &[F]) -> Result<Self, SynthesisError> {
+        let mut poly = Self::new_for_size(values.len())?;
+        for (i, chunk) in values.chunks(IO_BUFFER_ELEMENTS).enumerate() {
+            poly.write_chunk(i * IO_BUFFER_ELEMENTS, chunk)?;
+        }
+
+        Ok(poly)
+    }
+
+    pub fn into_values(mut self) -> Result<Vec<F>, SynthesisError> {
+        let mut values = vec![F::zero(); self.len];
+        for (i, chunk) in values.chunks_mut(IO_BUFFER_ELEMENTS).enumerate() {
+            self.read_chunk(i * IO_BUFFER_ELEMENTS, chunk)?;
+        }
+
+        Ok(values)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.len * Self::element_size()
+    }
+
+    fn seek_to(&mut self, offset: usize, num_elements: usize) -> io::Result<()> {
+        if offset + num_elements > self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "access past the end of the disk polynomial",
+            ));
+        }
+        self.file
+            .seek(SeekFrom::Start((offset * Self::element_size()) as u64))?;
+
+        Ok(())
+    }
+
+    /// Reads `dst.len()` values starting at the index `offset`.
+    pub fn read_chunk(&mut self, offset: usize, dst: &mut [F]) -> Result<(), SynthesisError> {
+        self.seek_to(offset, dst.len())?;
+
+        let element_size = Self::element_size();
+        let mut buffer = vec![0u8; std::cmp::min(dst.len(), IO_BUFFER_ELEMENTS) * element_size];
+        let mut repr = F::zero().into_raw_repr();
+        for dst in dst.chunks_mut(IO_BUFFER_ELEMENTS) {
+            let buffer = &mut buffer[..(dst.len() * element_size)];
+            self.file.read_exact(buffer)?;
+            for (d, bytes) in dst.iter_mut().zip(buffer.chunks(element_size)) {
+                repr.read_le(bytes)?;
+                *d = F::from_raw_repr(repr)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes `src` starting at the index `offset`.
+    pub fn write_chunk(&mut self, offset: usize, src: &[F]) -> Result<(), SynthesisError> {
+        self.seek_to(offset, src.len())?;
+
+        let element_size = Self::element_size();
+        let mut buffer = vec![0u8; std::cmp::min(src.len(), IO_BUFFER_ELEMENTS) * element_size];
+        for src in src.chunks(IO_BUFFER_ELEMENTS) {
+            let buffer = &mut buffer[..(src.len() * element_size)];
+            for (s, bytes) in src.iter().zip(buffer.chunks_mut(element_size)) {
+                s.into_raw_repr().write_le(bytes)?;
+            }
+            self.file.write_all(buffer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> Drop for DiskPolynomial<F> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+    use crate::pairing::ff::Field;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    #[test]
+    fn test_disk_polynomial_roundtrip() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let values: Vec<Fr> = (0..(IO_BUFFER_ELEMENTS + 100)).map(|_| Fr::rand(rng)).collect();
+        let mut poly = DiskPolynomial::from_values(&values).unwrap();
+        let path = poly.path.clone();
+        assert_eq!(poly.len(), values.len());
+
+        let mut chunk = vec![Fr::rand(rng); 10];
+        poly.read_chunk(IO_BUFFER_ELEMENTS - 5, &mut chunk).unwrap();
+        assert_eq!(&chunk[..], &values[(IO_BUFFER_ELEMENTS - 5)..(IO_BUFFER_ELEMENTS + 5)]);
+
+        let update: Vec<Fr> = (0..7).map(|_| Fr::rand(rng)).collect();
+        poly.write_chunk(3, &update).unwrap();
+        assert!(poly.read_chunk(values.len() - 1, &mut chunk).is_err());
+
+        let mut expected = values.clone();
+        expected[3..10].copy_from_slice(&update);
+        assert_eq!(poly.into_values().unwrap(), expected);
+
+        // the file is removed with the polynomial
+        assert!(!path.exists());
+
+        // new polynomials are zero
+        let zero = DiskPolynomial::<Fr>::new_for_size(5).unwrap();
+        assert_eq!(zero.into_values().unwrap(), vec![Fr::zero(); 5]);
+    }
+}
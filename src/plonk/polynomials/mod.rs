@@ -17,6 +17,8 @@ use crate::locks::LockedMultiexpKernel;
 // use ec_gpu_gen::EcResult;
 
 use crate::plonk::fft::cooley_tukey_ntt;
+use crate::plonk::fft::mixed_radix::best_mixed_radix_fft;
+use crate::plonk::fft::strategy::best_bitreversed_ntt;
 use crate::plonk::fft::cooley_tukey_ntt::partial_reduction;
 use crate::plonk::fft::cooley_tukey_ntt::CTPrecomputations;
 
@@ -25,6 +27,15 @@ use crate::plonk::transparent_engine::PartialTwoBitReductionField;
 use crate::plonk::utils::{fast_clone, fast_initialize_to_element};
 use std::time::Instant;
 
+pub mod disk;
+pub mod pool;
+
+use self::pool::PolynomialPool;
+
+/// Most cosets a batched LDE hands to one `fft_multiple` call, the cosets of a single
+/// LDE with the prover's `LDE_FACTOR`, so the GPU buffers stay the size of one LDE.
+const MAX_COSETS_PER_FFT: usize = 4;
+
 pub trait PolynomialForm: Sized + Copy + Clone + Send {}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -66,6 +77,43 @@ impl<E: Engine, P: PolynomialForm> Polynomial<E, P> {
         self.coeffs
     }
 
+    /// Pads `values` to the `domain` that may be a mixed radix one.
+    fn from_values_and_domain(mut values: Vec<E::Fr>, domain: &Domain<E::Fr>) -> Self {
+        let m = domain.size as usize;
+        let omega = domain.generator;
+        values.resize(m, E::Fr::zero());
+
+        Polynomial::<E, P> {
+            coeffs: values,
+            exp: domain.power_of_two as u32,
+            omega: omega,
+            omegainv: omega.inverse().unwrap(),
+            geninv: E::Fr::multiplicative_generator().inverse().unwrap(),
+            minv: E::Fr::from_str(&format!("{}", m))
+                .unwrap()
+                .inverse()
+                .unwrap(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Power of three in the size of a mixed radix polynomial, zero for the radix-2 ones.
+    /// `exp` is always the power of two.
+    pub fn power_of_three(&self) -> u32 {
+        if self.coeffs.len().is_power_of_two() {
+            return 0;
+        }
+        let mut rest = self.coeffs.len() >> self.exp;
+        let mut power_of_three = 0;
+        while rest > 1 && rest % 3 == 0 {
+            rest /= 3;
+            power_of_three += 1;
+        }
+        debug_assert_eq!(rest, 1, "size is not 2^exp * 3^k");
+
+        power_of_three
+    }
+
     pub fn fast_clone(&self, worker: &Worker) -> Polynomial<E, P> {
         let size = self.size();
         let mut coeffs: Vec<E::Fr> = Vec::with_capacity(size);
@@ -86,6 +134,37 @@ impl<E: Engine, P: PolynomialForm> Polynomial<E, P> {
         }
     }
 
+    /// Same as `fast_clone`, but the copy is taken from the `pool`.
+    pub fn fast_clone_in(
+        &self,
+        pool: &PolynomialPool<E::Fr>,
+        worker: &Worker,
+    ) -> Result<Polynomial<E, P>, SynthesisError> {
+        // every value is overwritten by the copy
+        let mut coeffs = unsafe { pool.take(self.size())? };
+        fast_clone(self.as_ref(), &mut coeffs, worker);
+
+        Ok(Polynomial {
+            coeffs,
+            exp: self.exp,
+            omega: self.omega,
+            omegainv: self.omegainv,
+            geninv: self.geninv,
+            minv: self.minv,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Gives the values back to the pool they were taken from.
+    pub fn into_pool(self, pool: &PolynomialPool<E::Fr>) {
+        pool.give_back(self.coeffs);
+    }
+
+    /// Values taken from the `pool` are going to outlive it or be dropped normally.
+    pub fn detach_from_pool(&self, pool: &PolynomialPool<E::Fr>) {
+        pool.detach(self.coeffs.capacity());
+    }
+
     pub fn distribute_powers(&mut self, worker: &Worker, g: E::Fr) {
         domain::distribute_powers::<E>(&mut self.coeffs, &worker, g);
     }
@@ -363,6 +442,16 @@ impl<E: Engine> Polynomial<E, Coefficients> {
         Self::from_coeffs(coeffs)
     }
 
+    /// Pads the coefficients to the smallest domain of size `2^a * 3^b` instead of
+    /// the next power of two.
+    pub fn from_coeffs_mixed_radix(
+        coeffs: Vec<E::Fr>,
+    ) -> Result<Polynomial<E, Coefficients>, SynthesisError> {
+        let domain = Domain::new_for_size_mixed_radix(coeffs.len() as u64)?;
+
+        Ok(Self::from_values_and_domain(coeffs, &domain))
+    }
+
     pub fn from_coeffs(
         mut coeffs: Vec<E::Fr>,
     ) -> Result<Polynomial<E, Coefficients>, SynthesisError> {
@@ -1079,10 +1168,14 @@ impl<E: Engine> Polynomial<E, Coefficients> {
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
     ) -> Polynomial<E, Values> {
-        debug_assert!(self.coeffs.len().is_power_of_two());
-
-        best_fft_multiple_gpu(kern, &mut [&mut self.coeffs], worker, &self.omega, self.exp)
-            .unwrap();
+        let power_of_three = self.power_of_three();
+        if power_of_three != 0 {
+            best_mixed_radix_fft(&mut self.coeffs, worker, &self.omega, self.exp, power_of_three);
+        } else {
+            debug_assert!(self.coeffs.len().is_power_of_two());
+            best_fft_multiple_gpu(kern, &mut [&mut self.coeffs], worker, &self.omega, self.exp)
+                .unwrap();
+        }
 
         Polynomial::<E, Values> {
             coeffs: self.coeffs,
@@ -1141,13 +1234,11 @@ impl<E: Engine> Polynomial<E, Coefficients> {
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
     ) -> Polynomial<E, Values> {
-        debug_assert!(self.coeffs.len().is_power_of_two());
         self.distribute_powers(worker, E::Fr::multiplicative_generator());
         self.fft(worker, kern)
     }
 
     pub fn coset_fft_for_generator(mut self, worker: &Worker, gen: E::Fr) -> Polynomial<E, Values> {
-        debug_assert!(self.coeffs.len().is_power_of_two());
         self.distribute_powers(worker, gen);
         self.fft(worker, &mut None)
     }
@@ -1311,6 +1402,16 @@ impl<E: Engine> Polynomial<E, Values> {
         Self::from_values(coeffs)
     }
 
+    /// Pads the values to the smallest domain of size `2^a * 3^b` instead of the next
+    /// power of two.
+    pub fn from_values_mixed_radix(
+        values: Vec<E::Fr>,
+    ) -> Result<Polynomial<E, Values>, SynthesisError> {
+        let domain = Domain::new_for_size_mixed_radix(values.len() as u64)?;
+
+        Ok(Self::from_values_and_domain(values, &domain))
+    }
+
     pub fn from_values(mut values: Vec<E::Fr>) -> Result<Polynomial<E, Values>, SynthesisError> {
         let coeffs_len = values.len();
 
@@ -1469,9 +1570,11 @@ impl<E: Engine> Polynomial<E, Values> {
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
     ) -> Polynomial<E, Coefficients> {
-        debug_assert!(self.coeffs.len().is_power_of_two());
-
-        if self.exp != 28 {
+        let power_of_three = self.power_of_three();
+        if power_of_three != 0 {
+            best_mixed_radix_fft(&mut self.coeffs, worker, &self.omegainv, self.exp, power_of_three);
+        } else if self.exp != 28 {
+            debug_assert!(self.coeffs.len().is_power_of_two());
             best_fft_multiple_gpu(
                 kern,
                 &mut [&mut self.coeffs],
@@ -1580,7 +1683,6 @@ impl<E: Engine> Polynomial<E, Values> {
         worker: &Worker,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
     ) -> Polynomial<E, Coefficients> {
-        debug_assert!(self.coeffs.len().is_power_of_two());
         let geninv = self.geninv;
         let mut res = self.ifft(worker, kern);
         res.distribute_powers(worker, geninv);
@@ -1594,7 +1696,6 @@ impl<E: Engine> Polynomial<E, Values> {
         coset_generator: &E::Fr,
         kern: &mut Option<LockedMultiFFTKernel<E>>,
     ) -> Polynomial<E, Coefficients> {
-        debug_assert!(self.coeffs.len().is_power_of_two());
         let geninv = coset_generator.inverse().expect("must exist");
         let mut res = self.ifft(worker, kern);
         res.distribute_powers(worker, geninv);
@@ -2519,7 +2620,7 @@ impl<E: Engine> Polynomial<E, Values> {
 
         let mut coeffs: Vec<E::Fr> = self.coeffs;
         let exp = self.exp;
-        cooley_tukey_ntt::best_ct_ntt(&mut coeffs, worker, exp, Some(cpu_num), precomputed_omegas);
+        best_bitreversed_ntt(&mut coeffs, worker, exp, precomputed_omegas);
         let mut this: Polynomial<E, Coefficients> = Polynomial::from_coeffs(coeffs)?;
 
         this.bitreverse_enumeration(&worker);
@@ -2559,16 +2660,52 @@ impl<E: Engine> Polynomial<E, Coefficients> {
         if factor == 1 {
             return Ok(self.fft(&worker, fft_kern));
         }
+
+        let new_size = self.coeffs.len() * factor;
+        let mut result = Vec::with_capacity(new_size);
+        unsafe { result.set_len(new_size) };
+
+        self.bitreversed_lde_into(result, worker, factor, coset_factor, fft_kern)
+    }
+
+    /// Same as `bitreversed_lde_using_bitreversed_ntt`, but the LDE is taken from the
+    /// `pool` and `self` is left as is.
+    pub fn bitreversed_lde_using_bitreversed_ntt_in(
+        &self,
+        pool: &PolynomialPool<E::Fr>,
+        worker: &Worker,
+        factor: usize,
+        coset_factor: &E::Fr,
+        fft_kern: &mut Option<LockedMultiFFTKernel<E>>,
+    ) -> Result<Polynomial<E, Values>, SynthesisError> {
+        debug_assert!(self.coeffs.len().is_power_of_two());
+
+        if factor == 1 {
+            return Ok(self.fast_clone_in(pool, worker)?.fft(&worker, fft_kern));
+        }
+
+        // every coset is written by `bitreversed_lde_into`
+        let result = unsafe { pool.take(self.coeffs.len() * factor)? };
+
+        self.bitreversed_lde_into(result, worker, factor, coset_factor, fft_kern)
+    }
+
+    fn bitreversed_lde_into(
+        &self,
+        mut result: Vec<E::Fr>,
+        worker: &Worker,
+        factor: usize,
+        coset_factor: &E::Fr,
+        fft_kern: &mut Option<LockedMultiFFTKernel<E>>,
+    ) -> Result<Polynomial<E, Values>, SynthesisError> {
         // assert!(factor.is_power_of_two());
         assert_eq!(factor, 4, "factor == 4");
 
         let current_size = self.coeffs.len();
         let new_size = self.coeffs.len() * factor;
+        assert_eq!(result.len(), new_size);
         let new_domain = Domain::<E::Fr>::new_for_size(new_size as u64)?;
 
-        let mut result = Vec::with_capacity(new_size);
-        unsafe { result.set_len(new_size) };
-
         let r = &mut result[..] as *mut [E::Fr];
 
         let coset_omega = new_domain.generator;
@@ -2632,6 +2769,114 @@ impl<E: Engine> Polynomial<E, Coefficients> {
         Polynomial::from_values(result)
     }
 
+    /// `bitreversed_lde_using_bitreversed_ntt_in` of every polynomial in `polys` at once.
+    /// The polynomials must be of the same size. The coset powers are computed once for
+    /// the whole batch and applied while the coefficients are copied, every coefficient is
+    /// read once for all the cosets, and the cosets go through `fft_multiple` at most
+    /// `MAX_COSETS_PER_FFT` at a time, so a call never needs more GPU memory than one LDE.
+    pub fn bitreversed_lde_using_bitreversed_ntt_batch_in(
+        polys: &[&Self],
+        pool: &PolynomialPool<E::Fr>,
+        worker: &Worker,
+        factor: usize,
+        coset_factor: &E::Fr,
+        fft_kern: &mut Option<LockedMultiFFTKernel<E>>,
+    ) -> Result<Vec<Polynomial<E, Values>>, SynthesisError> {
+        assert!(factor.is_power_of_two() && factor > 1);
+        if polys.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let current_size = polys[0].coeffs.len();
+        debug_assert!(current_size.is_power_of_two());
+        for p in polys.iter() {
+            assert_eq!(p.coeffs.len(), current_size, "polynomials must be of the same size");
+        }
+        let new_size = current_size * factor;
+        let new_domain = Domain::<E::Fr>::new_for_size(new_size as u64)?;
+
+        let mut results = Vec::with_capacity(polys.len());
+        for _ in 0..polys.len() {
+            // every coset is written below before the FFT
+            match unsafe { pool.take(new_size) } {
+                Ok(r) => results.push(r),
+                Err(e) => {
+                    for r in results.into_iter() {
+                        pool.give_back(r);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        // same coset order as in `bitreversed_lde_into`
+        let factor_log = log2_floor(factor) as usize;
+        let coset_generators: Vec<E::Fr> = (0..factor)
+            .map(|coset_idx| {
+                let bitreversed_power = cooley_tukey_ntt::bitreverse(coset_idx, factor_log);
+                let mut coset_generator = new_domain.generator.pow(&[bitreversed_power as u64]);
+                coset_generator.mul_assign(&coset_factor);
+
+                coset_generator
+            })
+            .collect();
+
+        {
+            let results = &mut results;
+            let coset_generators = &coset_generators[..];
+            worker.scope(current_size, |scope, chunk| {
+                // outputs[i][p * factor + c] is the i-th chunk of the coset c of the p-th LDE
+                let mut outputs: Vec<Vec<&mut [E::Fr]>> = vec![];
+                for r in results.iter_mut() {
+                    for coset in r.chunks_mut(current_size) {
+                        for (i, part) in coset.chunks_mut(chunk).enumerate() {
+                            if outputs.len() == i {
+                                outputs.push(Vec::with_capacity(polys.len() * factor));
+                            }
+                            outputs[i].push(part);
+                        }
+                    }
+                }
+
+                for (i, mut outputs) in outputs.into_iter().enumerate() {
+                    scope.spawn(move |_| {
+                        let start = i * chunk;
+                        let len = outputs[0].len();
+                        let mut powers: Vec<E::Fr> = coset_generators
+                            .iter()
+                            .map(|g| g.pow(&[start as u64]))
+                            .collect();
+
+                        for j in 0..len {
+                            for (c, power) in powers.iter_mut().enumerate() {
+                                for (p, poly) in polys.iter().enumerate() {
+                                    let mut value = poly.coeffs[start + j];
+                                    value.mul_assign(power);
+                                    outputs[p * factor + c][j] = value;
+                                }
+                                power.mul_assign(&coset_generators[c]);
+                            }
+                        }
+                    });
+                }
+            });
+        }
+
+        {
+            let mut cosets: Vec<&mut [E::Fr]> = results
+                .iter_mut()
+                .flat_map(|r| r.chunks_mut(current_size))
+                .collect();
+
+            for cosets in cosets.chunks_mut(MAX_COSETS_PER_FFT) {
+                best_fft_multiple_gpu(fft_kern, cosets, &worker, &polys[0].omega, polys[0].exp)
+                    .unwrap();
+            }
+        }
+
+        results.into_iter().map(Polynomial::from_values).collect()
+    }
+
     /// taken in natural enumeration
     /// outputs in natural enumeration
     pub fn fft_using_bitreversed_ntt<P: CTPrecomputations<E::Fr>>(
@@ -2651,13 +2896,7 @@ impl<E: Engine> Polynomial<E, Coefficients> {
 
         let mut coeffs: Vec<_> = this.coeffs;
         let exp = this.exp;
-        cooley_tukey_ntt::best_ct_ntt(
-            &mut coeffs,
-            worker,
-            exp,
-            Some(worker.get_num_cpus()),
-            precomputed_omegas,
-        );
+        best_bitreversed_ntt(&mut coeffs, worker, exp, precomputed_omegas);
         let mut this = Polynomial::from_values(coeffs)?;
 
         this.bitreverse_enumeration(&worker);
@@ -2684,13 +2923,7 @@ impl<E: Engine> Polynomial<E, Coefficients> {
 
         let mut coeffs: Vec<_> = this.coeffs;
         let exp = this.exp;
-        cooley_tukey_ntt::best_ct_ntt(
-            &mut coeffs,
-            worker,
-            exp,
-            Some(worker.get_num_cpus()),
-            precomputed_omegas,
-        );
+        best_bitreversed_ntt(&mut coeffs, worker, exp, precomputed_omegas);
         let this = Polynomial::from_values(coeffs)?;
 
         Ok(this)
@@ -2915,4 +3148,87 @@ mod test {
             assert_eq!(manual[idx], as_poly[idx], "failed at idx = {}", idx);
         }
     }
+
+    #[test]
+    fn test_mixed_radix_polynomial_fft() {
+        use super::*;
+        use crate::ff::{Field, PrimeField};
+        use crate::pairing::bn256::Fr;
+
+        use crate::worker::Worker;
+        use rand::{Rand, SeedableRng, XorShiftRng};
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        // 2^10 + 1 coefficients fit into 9 * 2^7 instead of 2^11
+        let coeffs: Vec<Fr> = (0..((1 << 10) + 1)).map(|_| Fr::rand(rng)).collect();
+        let poly = Polynomial::<Bn256, Coefficients>::from_coeffs_mixed_radix(coeffs).unwrap();
+        assert_eq!(poly.size(), 9 << 7);
+        assert_eq!(poly.exp, 7);
+        assert_eq!(poly.power_of_three(), 2);
+
+        let values = poly.clone().fft(&worker, &mut None);
+        for idx in [0usize, 1, 5, 1000].iter() {
+            let point = poly.omega.pow(&[*idx as u64]);
+            assert_eq!(values.as_ref()[*idx], poly.evaluate_at(&worker, point));
+        }
+        let back = values.ifft(&worker, &mut None);
+        assert_eq!(back, poly);
+
+        let on_coset = poly.clone().coset_fft(&worker, &mut None);
+        let mut point = Fr::multiplicative_generator();
+        point.mul_assign(&poly.omega);
+        assert_eq!(on_coset.as_ref()[1], poly.evaluate_at(&worker, point));
+        assert_eq!(on_coset.icoset_fft(&worker, &mut None), poly);
+
+        let values = Polynomial::<Bn256, Values>::from_values_mixed_radix(vec![Fr::one(); 5]).unwrap();
+        assert_eq!(values.size(), 6);
+    }
+
+    #[test]
+    fn test_batched_lde_matches_single() {
+        use super::*;
+        use crate::ff::PrimeField;
+        use crate::pairing::bn256::Fr;
+
+        use crate::worker::Worker;
+        use rand::{Rand, SeedableRng, XorShiftRng};
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let workers = [Worker::builder().num_threads(1).build().unwrap(), Worker::new()];
+        let coset_factor = Fr::multiplicative_generator();
+
+        let polys: Vec<Polynomial<Bn256, Coefficients>> = (0..3)
+            .map(|_| {
+                let coeffs = (0..(1 << 8)).map(|_| Fr::rand(rng)).collect();
+                Polynomial::from_coeffs(coeffs).unwrap()
+            })
+            .collect();
+        let refs: Vec<_> = polys.iter().collect();
+
+        for worker in workers.iter() {
+            let pool = PolynomialPool::<Fr>::new(None);
+            let ldes = Polynomial::bitreversed_lde_using_bitreversed_ntt_batch_in(
+                &refs,
+                &pool,
+                worker,
+                4,
+                &coset_factor,
+                &mut None,
+            )
+            .unwrap();
+            assert_eq!(ldes.len(), polys.len());
+
+            for (poly, lde) in polys.iter().zip(ldes.into_iter()) {
+                let expected = poly
+                    .bitreversed_lde_using_bitreversed_ntt_in(&pool, worker, 4, &coset_factor, &mut None)
+                    .unwrap();
+                assert_eq!(lde, expected);
+                expected.into_pool(&pool);
+                lde.into_pool(&pool);
+            }
+            assert_eq!(pool.in_use_bytes(), 0);
+        }
+    }
 }
@@ -0,0 +1,278 @@
+//! Pool of the polynomial buffers under a memory budget.
+//!
+//! The prover steps take their large buffers from the pool and give them back when a
+//! polynomial is not needed anymore, so the next polynomial of the same size reuses the
+//! allocation instead of going through the allocator. The pool counts every byte it has
+//! handed out or keeps cached, and refuses an allocation that would go over the budget
+//! with `SynthesisError::MemoryBudgetExceeded` before the OS has to kill the process.
+//!
+//! Only the buffers of the grand product and the quotient go through the pool, the
+//! monomials and the setup precomputations are not part of the budget.
+
+use crate::pairing::ff::PrimeField;
+use crate::SynthesisError;
+use std::sync::Mutex;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepMemoryReport {
+    pub step: &'static str,
+    /// Largest number of bytes held by the pool during the step, used and cached ones.
+    pub peak_bytes: usize,
+    /// Buffers allocated anew during the step.
+    pub allocations: usize,
+    /// Buffers taken from the cache during the step.
+    pub reuses: usize,
+}
+
+struct PoolState<F: PrimeField> {
+    free: Vec<Vec<F>>,
+    in_use_bytes: usize,
+    cached_bytes: usize,
+    peak_bytes: usize,
+    step: Option<StepMemoryReport>,
+    reports: Vec<StepMemoryReport>,
+}
+
+impl<F: PrimeField> PoolState<F> {
+    fn update_peak(&mut self) {
+        let total = self.in_use_bytes + self.cached_bytes;
+        self.peak_bytes = std::cmp::max(self.peak_bytes, total);
+        if let Some(step) = self.step.as_mut() {
+            step.peak_bytes = std::cmp::max(step.peak_bytes, total);
+        }
+    }
+}
+
+pub struct PolynomialPool<F: PrimeField> {
+    budget: Option<usize>,
+    state: Mutex<PoolState<F>>,
+}
+
+fn bytes_for<F: PrimeField>(capacity: usize) -> usize {
+    capacity * std::mem::size_of::<F>()
+}
+
+impl<F: PrimeField> PolynomialPool<F> {
+    /// Pool that never holds more than `budget` bytes, `None` only tracks the usage.
+    pub fn new(budget: Option<usize>) -> Self {
+        Self {
+            budget,
+            state: Mutex::new(PoolState {
+                free: vec![],
+                in_use_bytes: 0,
+                cached_bytes: 0,
+                peak_bytes: 0,
+                step: None,
+                reports: vec![],
+            }),
+        }
+    }
+
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Buffer of `len` values. The values are not initialized, they are either left over
+    /// from the previous user of the buffer or never written at all.
+    ///
+    /// # Safety
+    ///
+    /// The caller must overwrite every element before reading any of them.
+    pub(crate) unsafe fn take(&self, len: usize) -> Result<Vec<F>, SynthesisError> {
+        let mut state = self.state.lock().unwrap();
+
+        // the smallest cached buffer that is large enough
+        let cached = state
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.capacity() >= len)
+            .min_by_key(|(_, b)| b.capacity())
+            .map(|(i, _)| i);
+
+        let mut buffer = if let Some(idx) = cached {
+            let buffer = state.free.swap_remove(idx);
+            let bytes = bytes_for::<F>(buffer.capacity());
+            state.cached_bytes -= bytes;
+            state.in_use_bytes += bytes;
+            if let Some(step) = state.step.as_mut() {
+                step.reuses += 1;
+            }
+
+            buffer
+        } else {
+            let bytes = bytes_for::<F>(len);
+            if let Some(budget) = self.budget {
+                // the cached buffers that are too small go first
+                while state.in_use_bytes + state.cached_bytes + bytes > budget {
+                    match state.free.pop() {
+                        Some(b) => state.cached_bytes -= bytes_for::<F>(b.capacity()),
+                        None => break,
+                    }
+                }
+                if state.in_use_bytes + bytes > budget {
+                    error!(
+                        "polynomial of {} values ({} bytes) does not fit into the memory budget of {} bytes, {} bytes are in use",
+                        len, bytes, budget, state.in_use_bytes
+                    );
+                    return Err(SynthesisError::MemoryBudgetExceeded);
+                }
+            }
+            let buffer = Vec::with_capacity(len);
+            state.in_use_bytes += bytes_for::<F>(buffer.capacity());
+            if let Some(step) = state.step.as_mut() {
+                step.allocations += 1;
+            }
+
+            buffer
+        };
+
+        state.update_peak();
+        buffer.set_len(len);
+
+        Ok(buffer)
+    }
+
+    /// Returns a buffer that was taken from the pool.
+    pub fn give_back(&self, mut buffer: Vec<F>) {
+        let mut state = self.state.lock().unwrap();
+        let bytes = bytes_for::<F>(buffer.capacity());
+        debug_assert!(state.in_use_bytes >= bytes, "buffer was not taken from the pool");
+        state.in_use_bytes -= std::cmp::min(bytes, state.in_use_bytes);
+
+        buffer.clear();
+        state.cached_bytes += bytes;
+        state.free.push(buffer);
+    }
+
+    /// Stops counting a buffer of `capacity` values that was taken from the pool and
+    /// is not going to be given back.
+    pub fn detach(&self, capacity: usize) {
+        let mut state = self.state.lock().unwrap();
+        let bytes = bytes_for::<F>(capacity);
+        debug_assert!(state.in_use_bytes >= bytes, "buffer was not taken from the pool");
+        state.in_use_bytes -= std::cmp::min(bytes, state.in_use_bytes);
+    }
+
+    /// Frees all the cached buffers.
+    pub fn release_cached(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.free.clear();
+        state.cached_bytes = 0;
+    }
+
+    pub fn in_use_bytes(&self) -> usize {
+        self.state.lock().unwrap().in_use_bytes
+    }
+
+    pub fn cached_bytes(&self) -> usize {
+        self.state.lock().unwrap().cached_bytes
+    }
+
+    /// Largest number of bytes ever held by the pool.
+    pub fn peak_bytes(&self) -> usize {
+        self.state.lock().unwrap().peak_bytes
+    }
+
+    /// Starts collecting the report of `step`, finishing the previous one if needed.
+    pub fn begin_step(&self, step: &'static str) {
+        self.end_step();
+
+        let mut state = self.state.lock().unwrap();
+        let total = state.in_use_bytes + state.cached_bytes;
+        state.step = Some(StepMemoryReport {
+            step,
+            peak_bytes: total,
+            allocations: 0,
+            reuses: 0,
+        });
+    }
+
+    pub fn end_step(&self) -> Option<StepMemoryReport> {
+        let mut state = self.state.lock().unwrap();
+        let report = state.step.take()?;
+        info!(
+            "{}: peak polynomial memory {} MiB, {} allocations, {} reuses",
+            report.step,
+            report.peak_bytes >> 20,
+            report.allocations,
+            report.reuses
+        );
+        state.reports.push(report.clone());
+
+        Some(report)
+    }
+
+    /// Reports of all finished steps, in order.
+    pub fn step_reports(&self) -> Vec<StepMemoryReport> {
+        self.state.lock().unwrap().reports.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pairing::bn256::Fr;
+
+    const FR_SIZE: usize = 32;
+
+    #[test]
+    fn test_pool_reuses_buffers() {
+        let pool = PolynomialPool::<Fr>::new(None);
+
+        pool.begin_step("first");
+        let a = unsafe { pool.take(100) }.unwrap();
+        let b = unsafe { pool.take(50) }.unwrap();
+        assert_eq!(a.len(), 100);
+        assert_eq!(pool.in_use_bytes(), 150 * FR_SIZE);
+
+        pool.give_back(a);
+        pool.give_back(b);
+        assert_eq!(pool.in_use_bytes(), 0);
+        assert_eq!(pool.cached_bytes(), 150 * FR_SIZE);
+
+        // best fit, the small buffer is reused for the small request
+        pool.begin_step("second");
+        let c = unsafe { pool.take(40) }.unwrap();
+        assert_eq!(c.capacity(), 50);
+        let d = unsafe { pool.take(100) }.unwrap();
+        assert_eq!(d.capacity(), 100);
+        pool.give_back(c);
+        pool.detach(d.capacity());
+        drop(d);
+        pool.end_step();
+
+        let reports = pool.step_reports();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].step, "first");
+        assert_eq!(reports[0].allocations, 2);
+        assert_eq!(reports[0].peak_bytes, 150 * FR_SIZE);
+        assert_eq!(reports[1].reuses, 2);
+        assert_eq!(reports[1].allocations, 0);
+        assert_eq!(pool.peak_bytes(), 150 * FR_SIZE);
+
+        pool.release_cached();
+        assert_eq!(pool.cached_bytes(), 0);
+    }
+
+    #[test]
+    fn test_pool_budget() {
+        let pool = PolynomialPool::<Fr>::new(Some(100 * FR_SIZE));
+
+        let a = unsafe { pool.take(60) }.unwrap();
+        match unsafe { pool.take(60) } {
+            Err(SynthesisError::MemoryBudgetExceeded) => {}
+            _ => panic!("budget must be enforced"),
+        }
+
+        // cached buffers are freed to make room
+        let b = unsafe { pool.take(30) }.unwrap();
+        pool.give_back(b);
+        pool.give_back(a);
+        let c = unsafe { pool.take(80) }.unwrap();
+        assert_eq!(pool.cached_bytes(), 0);
+        assert_eq!(pool.in_use_bytes(), 80 * FR_SIZE);
+        assert!(pool.peak_bytes() <= 100 * FR_SIZE);
+        pool.give_back(c);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::pairing::Engine;
+
+use crate::config::ProverConfig;
+use crate::plonk::better_cs::cs::*;
+use crate::plonk::better_cs::generator::GeneratorAssembly4WithNextStep;
+use crate::plonk::better_cs::keys::{SetupPolynomials, SetupPolynomialsPrecomputations};
+use crate::plonk::better_cs::utils::make_non_residues;
+use crate::plonk::commitments::CommitmentScheme;
+use crate::plonk::polynomials::*;
+use crate::worker::Worker;
+use crate::SynthesisError;
+
+pub struct RedshiftVerificationKey<E: Engine, C: CommitmentScheme<E>> {
+    pub n: usize,
+    pub num_inputs: usize,
+    pub selector_commitments: Vec<C::Commitment>,
+    pub next_step_selector_commitments: Vec<C::Commitment>,
+    pub permutation_commitments: Vec<C::Commitment>,
+
+    pub non_residues: Vec<E::Fr>,
+}
+
+/// Everything the prover needs on top of the setup polynomials: their LDEs for the
+/// quotient and the committer's auxiliary data for the openings.
+pub struct RedshiftSetupPrecomputations<E: Engine, C: CommitmentScheme<E>> {
+    pub polynomials: SetupPolynomialsPrecomputations<E, PlonkCsWidth4WithNextStepParams>,
+    pub selector_data: Vec<Option<C::IntermediateData>>,
+    pub next_step_selector_data: Vec<Option<C::IntermediateData>>,
+    pub permutation_data: Vec<Option<C::IntermediateData>>,
+}
+
+/// Same setup polynomials as for the Kate prover. The transparent fields are not
+/// supported by the GPU kernels, so everything runs on the CPU.
+pub fn setup<E: Engine, C: Circuit<E, PlonkCsWidth4WithNextStepParams>>(
+    circuit: &C,
+) -> Result<SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
+    let mut assembly = GeneratorAssembly4WithNextStep::<E>::new();
+
+    circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+
+    let worker = Worker::new();
+
+    assembly.setup_with_config(&worker, &ProverConfig::cpu_only())
+}
+
+fn commit_all<E: Engine, C: CommitmentScheme<E>>(
+    polys: &[Polynomial<E, Coefficients>],
+    committer: &C,
+) -> (Vec<C::Commitment>, Vec<Option<C::IntermediateData>>) {
+    polys.iter().map(|p| committer.commit_single(p)).unzip()
+}
+
+/// Commits to the setup polynomials. `committer` must be made for the domain size of the
+/// `setup`, that is `setup.n + 1`.
+pub fn make_verification_key_and_precomputations<E: Engine, C: CommitmentScheme<E>>(
+    setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    committer: &C,
+    worker: &Worker,
+) -> Result<(RedshiftVerificationKey<E, C>, RedshiftSetupPrecomputations<E, C>), SynthesisError> {
+    let (selector_commitments, selector_data) =
+        commit_all(&setup.selector_polynomials, committer);
+    let (next_step_selector_commitments, next_step_selector_data) =
+        commit_all(&setup.next_step_selector_polynomials, committer);
+    let (permutation_commitments, permutation_data) =
+        commit_all(&setup.permutation_polynomials, committer);
+
+    let polynomials =
+        SetupPolynomialsPrecomputations::from_setup_with_config(setup, worker, &ProverConfig::cpu_only())?;
+
+    let vk = RedshiftVerificationKey {
+        n: setup.n,
+        num_inputs: setup.num_inputs,
+        selector_commitments,
+        next_step_selector_commitments,
+        permutation_commitments,
+        non_residues: make_non_residues::<E::Fr>(
+            <PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<E>>::STATE_WIDTH - 1,
+        ),
+    };
+
+    let precomputations = RedshiftSetupPrecomputations {
+        polynomials,
+        selector_data,
+        next_step_selector_data,
+        permutation_data,
+    };
+
+    Ok((vk, precomputations))
+}
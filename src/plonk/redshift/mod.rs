@@ -0,0 +1,124 @@
+//! Transparent Plonk (RedShift).
+//!
+//! Proves the same width-4 `better_cs` circuits with the same setup polynomials as the
+//! Kate based prover, but every polynomial is committed with a FRI based
+//! [`CommitmentScheme`] from `plonk::commitments::transparent`, so there is no trusted
+//! setup. The commitments are not homomorphic, so instead of a linearization polynomial
+//! every setup polynomial is opened at `z` and the verifier checks the quotient identity
+//! on the opened values directly.
+
+pub mod generator;
+pub mod prover;
+pub mod verifier;
+
+pub use self::generator::{make_verification_key_and_precomputations, setup};
+pub use self::generator::{RedshiftSetupPrecomputations, RedshiftVerificationKey};
+pub use self::prover::prove;
+pub use self::verifier::verify;
+
+use crate::pairing::ff::PrimeField;
+use crate::pairing::Engine;
+use crate::plonk::commitments::transcript::*;
+use crate::plonk::commitments::transparent::fri::naive_fri::naive_fri::NaiveFriIop;
+use crate::plonk::commitments::transparent::iop::blake2s_trivial_iop::TrivialBlake2sIOP;
+use crate::plonk::commitments::transparent::{
+    StatelessTransparentCommitter, TransparentCommitterParameters,
+};
+use crate::plonk::commitments::CommitmentScheme;
+use crate::plonk::transparent_engine::{Fr, Transparent252};
+
+pub type TransparentFri = NaiveFriIop<Transparent252, TrivialBlake2sIOP<Fr>>;
+
+/// FRI commitments over the `Transparent252` field with Blake2s Merkle trees.
+pub type TransparentCommitter =
+    StatelessTransparentCommitter<Transparent252, TransparentFri, Blake2sTranscript<Fr>>;
+
+/// Makes a committer for a circuit with `domain_size` rows (see [`setup`]) that runs
+/// `num_queries` FRI queries on an LDE of `lde_factor` after `grinding_bits` of
+/// proof-of-work.
+pub fn make_transparent_committer(
+    domain_size: usize,
+    lde_factor: usize,
+    num_queries: usize,
+    grinding_bits: u32,
+) -> TransparentCommitter {
+    let meta = TransparentCommitterParameters {
+        lde_factor,
+        num_queries,
+        output_coeffs_at_degree_plus_one: 1,
+        grinding_bits,
+        fri_params: (),
+    };
+
+    <TransparentCommitter as CommitmentScheme<Transparent252>>::new_for_size(domain_size, meta)
+}
+
+/// Values of the committed polynomials, all at `z` except of the ones named `at_z_omega`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedshiftEvaluations<F: PrimeField> {
+    pub wire_values_at_z: Vec<F>,
+    pub wire_values_at_z_omega: Vec<F>,
+    pub grand_product_at_z: F,
+    pub grand_product_at_z_omega: F,
+    pub quotient_polynomial_parts_at_z: Vec<F>,
+    pub selector_values_at_z: Vec<F>,
+    pub next_step_selector_values_at_z: Vec<F>,
+    pub permutation_polynomials_at_z: Vec<F>,
+}
+
+impl<F: PrimeField> RedshiftEvaluations<F> {
+    pub fn commit_to_transcript<T: Transcript<F>>(&self, transcript: &mut T) {
+        for el in self.wire_values_at_z.iter() {
+            transcript.commit_field_element(el);
+        }
+
+        for el in self.wire_values_at_z_omega.iter() {
+            transcript.commit_field_element(el);
+        }
+
+        transcript.commit_field_element(&self.grand_product_at_z);
+        transcript.commit_field_element(&self.grand_product_at_z_omega);
+
+        for el in self
+            .quotient_polynomial_parts_at_z
+            .iter()
+            .chain(self.selector_values_at_z.iter())
+            .chain(self.next_step_selector_values_at_z.iter())
+            .chain(self.permutation_polynomials_at_z.iter())
+        {
+            transcript.commit_field_element(el);
+        }
+    }
+
+    /// Claimed values in the order the polynomials are opened: wires, grand product,
+    /// quotient parts, selectors, next step selectors and permutations at `z`,
+    /// then the last wire and the grand product at `z*omega`.
+    pub fn opening_values(&self) -> (Vec<F>, Vec<F>) {
+        let mut at_z = vec![];
+        at_z.extend_from_slice(&self.wire_values_at_z);
+        at_z.push(self.grand_product_at_z);
+        at_z.extend_from_slice(&self.quotient_polynomial_parts_at_z);
+        at_z.extend_from_slice(&self.selector_values_at_z);
+        at_z.extend_from_slice(&self.next_step_selector_values_at_z);
+        at_z.extend_from_slice(&self.permutation_polynomials_at_z);
+
+        let mut at_z_omega = vec![];
+        at_z_omega.extend_from_slice(&self.wire_values_at_z_omega);
+        at_z_omega.push(self.grand_product_at_z_omega);
+
+        (at_z, at_z_omega)
+    }
+}
+
+pub struct RedshiftProof<E: Engine, C: CommitmentScheme<E>> {
+    pub n: usize,
+    pub num_inputs: usize,
+    pub input_values: Vec<E::Fr>,
+    pub wire_commitments: Vec<C::Commitment>,
+    pub grand_product_commitment: C::Commitment,
+    pub quotient_poly_commitments: Vec<C::Commitment>,
+
+    pub evaluations: RedshiftEvaluations<E::Fr>,
+
+    pub opening_proof: C::OpeningProof,
+}
@@ -0,0 +1,582 @@
+use crate::pairing::ff::{Field, PrimeField};
+use crate::pairing::Engine;
+
+use crate::plonk::better_cs::cs::*;
+use crate::plonk::better_cs::keys::SetupPolynomials;
+use crate::plonk::better_cs::prover::ProverAssembly4WithNextStep;
+use crate::plonk::better_cs::utils::*;
+use crate::plonk::better_cs::LDE_FACTOR;
+use crate::plonk::commitments::transcript::*;
+use crate::plonk::commitments::CommitmentScheme;
+use crate::plonk::domains::*;
+use crate::plonk::fft::cooley_tukey_ntt::*;
+use crate::plonk::polynomials::*;
+use crate::worker::Worker;
+use crate::SynthesisError;
+
+use super::generator::RedshiftSetupPrecomputations;
+use super::verifier::quotient_identity_holds;
+use super::{RedshiftEvaluations, RedshiftProof};
+
+pub fn prove<E, C, CS>(
+    circuit: &C,
+    setup: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    precomputations: &RedshiftSetupPrecomputations<E, CS>,
+    committer: &CS,
+    worker: &Worker,
+) -> Result<RedshiftProof<E, CS>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E, PlonkCsWidth4WithNextStepParams>,
+    CS: CommitmentScheme<E>,
+    CS::Prng: Transcript<E::Fr, Input = CS::Commitment>,
+{
+    let mut assembly = ProverAssembly4WithNextStep::<E>::new();
+    circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+
+    let n = assembly.num_gates();
+    let num_inputs = assembly.num_inputs();
+    let input_values = assembly.input_values().to_vec();
+
+    if n != setup.n || num_inputs != setup.num_inputs {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    let required_domain_size = n + 1;
+    assert!(required_domain_size.is_power_of_two());
+
+    let mut transcript = CS::Prng::new();
+
+    for inp in input_values.iter() {
+        transcript.commit_field_element(inp);
+    }
+
+    let full_assignments = assembly.make_witness_polynomials(worker)?;
+
+    let setup_precomputations = &precomputations.polynomials;
+
+    let coset_factor = E::Fr::multiplicative_generator();
+
+    let omegas_inv_bitreversed =
+        <OmegasInvBitreversed<E::Fr> as CTPrecomputations<E::Fr>>::new_for_domain_size(
+            required_domain_size,
+        );
+
+    let mut assignment_polynomials = vec![];
+    for p in full_assignments.into_iter() {
+        let p = Polynomial::from_values_unpadded(p)?;
+        assignment_polynomials.push(p);
+    }
+
+    // commit wire polynomials in the monomial form
+
+    let mut witness_polys_in_monomial_form = vec![];
+    for w in assignment_polynomials.iter() {
+        let monomial = w
+            .clone_padded_to_domain(worker)?
+            .ifft_using_bitreversed_ntt(worker, &omegas_inv_bitreversed, &E::Fr::one())?;
+        witness_polys_in_monomial_form.push(monomial);
+    }
+
+    let mut wire_commitments = vec![];
+    let mut wire_data = vec![];
+    for p in witness_polys_in_monomial_form.iter() {
+        let (commitment, data) = committer.commit_single(p);
+        transcript.commit_input(&commitment);
+        wire_commitments.push(commitment);
+        wire_data.push(data);
+    }
+
+    // grand product
+
+    let beta = transcript.get_challenge();
+    let gamma = transcript.get_challenge();
+
+    let mut grand_products_protos_with_gamma = assignment_polynomials;
+    for p in grand_products_protos_with_gamma.iter_mut() {
+        p.add_constant(worker, &gamma);
+    }
+
+    let domain = Domain::new_for_size(required_domain_size as u64)?;
+
+    let mut domain_elements =
+        materialize_domain_elements_with_natural_enumeration(&domain, worker);
+    domain_elements
+        .pop()
+        .expect("must pop last element for omega^i");
+
+    let mut domain_elements_poly_by_beta = Polynomial::from_values_unpadded(domain_elements)?;
+    domain_elements_poly_by_beta.scale(worker, beta);
+
+    let non_residues = make_non_residues::<E::Fr>(
+        <PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<E>>::STATE_WIDTH - 1,
+    );
+
+    let mut z_num = {
+        let mut grand_products_proto_it = grand_products_protos_with_gamma.iter().cloned();
+
+        let mut z_1 = grand_products_proto_it.next().unwrap();
+        z_1.add_assign(worker, &domain_elements_poly_by_beta);
+
+        for (mut p, non_res) in grand_products_proto_it.zip(non_residues.iter()) {
+            p.add_assign_scaled(worker, &domain_elements_poly_by_beta, non_res);
+            z_1.mul_assign(worker, &p);
+        }
+
+        z_1
+    };
+
+    let z_den = {
+        let mut grand_products_proto_it = grand_products_protos_with_gamma.into_iter();
+        let mut permutation_polys_it = setup_precomputations
+            .permutation_polynomials_values_of_size_n_minus_one
+            .iter();
+
+        let mut z_2 = grand_products_proto_it.next().unwrap();
+        z_2.add_assign_scaled(worker, permutation_polys_it.next().unwrap(), &beta);
+
+        for (mut p, perm) in grand_products_proto_it.zip(permutation_polys_it) {
+            p.add_assign_scaled(worker, perm, &beta);
+            z_2.mul_assign(worker, &p);
+        }
+
+        z_2.batch_inversion(worker)?;
+
+        z_2
+    };
+
+    z_num.mul_assign(worker, &z_den);
+    drop(z_den);
+
+    let z = z_num.calculate_shifted_grand_product(worker)?;
+    assert!(z.as_ref()[0] == E::Fr::one());
+
+    let z_in_monomial_form =
+        z.ifft_using_bitreversed_ntt(worker, &omegas_inv_bitreversed, &E::Fr::one())?;
+
+    let (grand_product_commitment, grand_product_data) =
+        committer.commit_single(&z_in_monomial_form);
+    transcript.commit_input(&grand_product_commitment);
+
+    let alpha = transcript.get_challenge();
+
+    // quotient polynomial, computed the same way as by the Kate prover
+
+    let mut z_shifted_in_monomial_form = z_in_monomial_form.clone();
+    z_shifted_in_monomial_form.distribute_powers(worker, z_in_monomial_form.omega);
+
+    let mut witness_ldes_on_coset = vec![];
+    for w in witness_polys_in_monomial_form.iter() {
+        let lde = w.clone().bitreversed_lde_using_bitreversed_ntt(
+            worker,
+            LDE_FACTOR,
+            &coset_factor,
+            &mut None,
+        )?;
+        witness_ldes_on_coset.push(lde);
+    }
+
+    let d_next_lde_on_coset = {
+        let mut d_next = witness_polys_in_monomial_form
+            .last()
+            .expect("last wire exists")
+            .clone();
+        d_next.distribute_powers(worker, d_next.omega);
+
+        d_next.bitreversed_lde_using_bitreversed_ntt(worker, LDE_FACTOR, &coset_factor, &mut None)?
+    };
+
+    let mut quotient_linearization_challenge = E::Fr::one();
+
+    let (mut t_1, mut tmp) = {
+        let mut inputs_poly = Polynomial::<E, Values>::new_for_size(required_domain_size, worker)?;
+        for (idx, &input) in input_values.iter().enumerate() {
+            inputs_poly.as_mut()[idx] = input;
+        }
+
+        let mut inputs_poly =
+            inputs_poly.ifft_using_bitreversed_ntt(worker, &omegas_inv_bitreversed, &E::Fr::one())?;
+
+        inputs_poly.add_assign(worker, setup.selector_polynomials.last().unwrap());
+
+        let mut t_1 =
+            inputs_poly.bitreversed_lde_using_bitreversed_ntt(worker, LDE_FACTOR, &coset_factor, &mut None)?;
+
+        let selectors = &setup_precomputations.selector_polynomials_on_coset_of_size_4n_bitreversed;
+
+        // Q_A * A + Q_B * B + Q_C * C + Q_D * D
+        let mut tmp = selectors[0].clone();
+        tmp.mul_assign(worker, &witness_ldes_on_coset[0]);
+        t_1.add_assign(worker, &tmp);
+
+        for i in 1..4 {
+            tmp.reuse_allocation(&selectors[i]);
+            tmp.mul_assign(worker, &witness_ldes_on_coset[i]);
+            t_1.add_assign(worker, &tmp);
+        }
+
+        // Q_M * A * B
+        tmp.reuse_allocation(&selectors[4]);
+        tmp.mul_assign(worker, &witness_ldes_on_coset[0]);
+        tmp.mul_assign(worker, &witness_ldes_on_coset[1]);
+        t_1.add_assign(worker, &tmp);
+
+        // Q_D_Next * D_Next
+        tmp.reuse_allocation(
+            &setup_precomputations.next_step_selector_polynomials_on_coset_of_size_4n_bitreversed[0],
+        );
+        tmp.mul_assign(worker, &d_next_lde_on_coset);
+        t_1.add_assign(worker, &tmp);
+
+        (t_1, tmp)
+    };
+
+    drop(d_next_lde_on_coset);
+
+    let z_coset_lde_bitreversed = z_in_monomial_form.clone().bitreversed_lde_using_bitreversed_ntt(
+        worker,
+        LDE_FACTOR,
+        &coset_factor,
+        &mut None,
+    )?;
+
+    let z_shifted_coset_lde_bitreversed = z_shifted_in_monomial_form
+        .bitreversed_lde_using_bitreversed_ntt(worker, LDE_FACTOR, &coset_factor, &mut None)?;
+
+    quotient_linearization_challenge.mul_assign(&alpha);
+
+    {
+        let mut contrib_z = z_coset_lde_bitreversed.clone();
+
+        for (w, non_res) in witness_ldes_on_coset
+            .iter()
+            .zip(Some(E::Fr::one()).iter().chain(non_residues.iter()))
+        {
+            let mut factor = beta;
+            factor.mul_assign(non_res);
+
+            tmp.reuse_allocation(w);
+            tmp.add_constant(worker, &gamma);
+            tmp.add_assign_scaled(
+                worker,
+                &setup_precomputations.x_on_coset_of_size_4n_bitreversed,
+                &factor,
+            );
+            contrib_z.mul_assign(worker, &tmp);
+        }
+
+        t_1.add_assign_scaled(worker, &contrib_z, &quotient_linearization_challenge);
+
+        drop(contrib_z);
+
+        let mut contrib_z = z_shifted_coset_lde_bitreversed;
+
+        for (w, perm) in witness_ldes_on_coset.iter().zip(
+            setup_precomputations
+                .permutation_polynomials_on_coset_of_size_4n_bitreversed
+                .iter(),
+        ) {
+            tmp.reuse_allocation(w);
+            tmp.add_constant(worker, &gamma);
+            tmp.add_assign_scaled(worker, perm, &beta);
+            contrib_z.mul_assign(worker, &tmp);
+        }
+
+        t_1.sub_assign_scaled(worker, &contrib_z, &quotient_linearization_challenge);
+    }
+
+    drop(witness_ldes_on_coset);
+
+    quotient_linearization_challenge.mul_assign(&alpha);
+
+    {
+        let l_0 = calculate_lagrange_poly::<E>(worker, required_domain_size, 0, &mut None)?;
+
+        let mut z_minus_one_by_l_0 = z_coset_lde_bitreversed;
+        z_minus_one_by_l_0.sub_constant(worker, &E::Fr::one());
+
+        let l_coset_lde_bitreversed =
+            l_0.bitreversed_lde_using_bitreversed_ntt(worker, LDE_FACTOR, &coset_factor, &mut None)?;
+
+        z_minus_one_by_l_0.mul_assign(worker, &l_coset_lde_bitreversed);
+
+        t_1.add_assign_scaled(worker, &z_minus_one_by_l_0, &quotient_linearization_challenge);
+    }
+
+    drop(tmp);
+
+    t_1.mul_assign(
+        worker,
+        &setup_precomputations.inverse_divisor_on_coset_of_size_4n_bitreversed,
+    );
+
+    t_1.bitreverse_enumeration(worker);
+
+    let t_poly_in_monomial_form = t_1.icoset_fft_for_generator(worker, &coset_factor, &mut None);
+
+    let t_poly_parts = t_poly_in_monomial_form.break_into_multiples(required_domain_size)?;
+
+    let mut quotient_poly_commitments = vec![];
+    let mut quotient_data = vec![];
+    for t_part in t_poly_parts.iter() {
+        let (commitment, data) = committer.commit_single(t_part);
+        transcript.commit_input(&commitment);
+        quotient_poly_commitments.push(commitment);
+        quotient_data.push(data);
+    }
+
+    // every committed polynomial is opened, so all of them are evaluated
+
+    let z = transcript.get_challenge();
+    let mut z_by_omega = z;
+    z_by_omega.mul_assign(&domain.generator);
+
+    let evaluate_all = |polys: &[Polynomial<E, Coefficients>]| -> Vec<E::Fr> {
+        polys.iter().map(|p| p.evaluate_at(worker, z)).collect()
+    };
+
+    let d_poly = witness_polys_in_monomial_form
+        .last()
+        .expect("last wire exists");
+
+    let evaluations = RedshiftEvaluations {
+        wire_values_at_z: evaluate_all(&witness_polys_in_monomial_form),
+        wire_values_at_z_omega: vec![d_poly.evaluate_at(worker, z_by_omega)],
+        grand_product_at_z: z_in_monomial_form.evaluate_at(worker, z),
+        grand_product_at_z_omega: z_in_monomial_form.evaluate_at(worker, z_by_omega),
+        quotient_polynomial_parts_at_z: evaluate_all(&t_poly_parts),
+        selector_values_at_z: evaluate_all(&setup.selector_polynomials),
+        next_step_selector_values_at_z: evaluate_all(&setup.next_step_selector_polynomials),
+        permutation_polynomials_at_z: evaluate_all(&setup.permutation_polynomials),
+    };
+
+    // sanity check - verification
+    if !quotient_identity_holds(
+        &evaluations,
+        &input_values,
+        &domain,
+        &non_residues,
+        alpha,
+        beta,
+        gamma,
+        z,
+    )? {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    evaluations.commit_to_transcript(&mut transcript);
+
+    let v = transcript.get_challenge();
+
+    // open in the same order as `RedshiftEvaluations::opening_values`
+
+    let mut polys: Vec<&Polynomial<E, Coefficients>> = vec![];
+    polys.extend(witness_polys_in_monomial_form.iter());
+    polys.push(&z_in_monomial_form);
+    polys.extend(t_poly_parts.iter());
+    polys.extend(setup.selector_polynomials.iter());
+    polys.extend(setup.next_step_selector_polynomials.iter());
+    polys.extend(setup.permutation_polynomials.iter());
+
+    let num_at_z = polys.len();
+
+    polys.push(d_poly);
+    polys.push(&z_in_monomial_form);
+
+    let data: Option<Vec<&CS::IntermediateData>> = wire_data
+        .iter()
+        .chain(Some(&grand_product_data))
+        .chain(quotient_data.iter())
+        .chain(precomputations.selector_data.iter())
+        .chain(precomputations.next_step_selector_data.iter())
+        .chain(precomputations.permutation_data.iter())
+        .chain(wire_data.last())
+        .chain(Some(&grand_product_data))
+        .map(|d| d.as_ref())
+        .collect();
+
+    let (values_at_z, values_at_z_omega) = evaluations.opening_values();
+    assert_eq!(values_at_z.len(), num_at_z);
+
+    let mut at_points = vec![z; num_at_z];
+    at_points.extend(vec![z_by_omega; values_at_z_omega.len()]);
+
+    let mut opening_values = values_at_z;
+    opening_values.extend(values_at_z_omega);
+
+    let degrees = vec![required_domain_size; polys.len()];
+
+    let opening_proof = committer.open_multiple(
+        polys,
+        degrees,
+        v,
+        at_points,
+        opening_values,
+        &data,
+        &mut transcript,
+    );
+
+    Ok(RedshiftProof {
+        n,
+        num_inputs,
+        input_values,
+        wire_commitments,
+        grand_product_commitment,
+        quotient_poly_commitments,
+        evaluations,
+        opening_proof,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+    use super::*;
+
+    use crate::plonk::transparent_engine::{Fr, Transparent252};
+    use std::marker::PhantomData;
+
+    #[derive(Clone)]
+    struct TestCircuit4<E: Engine> {
+        _marker: PhantomData<E>,
+    }
+
+    impl<E: Engine> Circuit<E, PlonkCsWidth4WithNextStepParams> for TestCircuit4<E> {
+        fn synthesize<CS: ConstraintSystem<E, PlonkCsWidth4WithNextStepParams>>(
+            &self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let a = cs.alloc_input(|| Ok(E::Fr::from_str("10").unwrap()))?;
+            let b = cs.alloc_input(|| Ok(E::Fr::from_str("20").unwrap()))?;
+            let c = cs.alloc(|| Ok(E::Fr::from_str("200").unwrap()))?;
+            let d = cs.alloc(|| Ok(E::Fr::from_str("100").unwrap()))?;
+
+            let zero = E::Fr::zero();
+
+            let one = E::Fr::one();
+
+            let mut two = one;
+            two.double();
+
+            let mut negative_one = one;
+            negative_one.negate();
+
+            let ten = E::Fr::from_str("10").unwrap();
+
+            let dummy = cs.get_dummy_variable();
+
+            // 2a - b == 0
+            cs.new_gate(
+                [a, b, dummy, dummy],
+                [two, negative_one, zero, zero, zero, zero],
+                [zero],
+            )?;
+
+            // 10b - c == 0
+            cs.new_gate(
+                [dummy, c, b, dummy],
+                [zero, negative_one, ten, zero, zero, zero],
+                [zero],
+            )?;
+
+            // c - a*b == 0
+            cs.new_gate(
+                [a, b, dummy, c],
+                [zero, zero, zero, negative_one, one, zero],
+                [zero],
+            )?;
+
+            // 10a + 10b - c - d == 0
+            cs.new_gate(
+                [a, d, b, c],
+                [ten, negative_one, ten, negative_one, zero, zero],
+                [zero],
+            )?;
+
+            // 10a + 10b - c - (d on the next step) == 0, then d - d == 0
+            cs.new_gate(
+                [a, b, c, dummy],
+                [ten, ten, negative_one, zero, zero, zero],
+                [negative_one],
+            )?;
+
+            cs.new_gate(
+                [d, dummy, dummy, d],
+                [one, zero, zero, negative_one, zero, zero],
+                [zero],
+            )?;
+
+            // internal constant
+            cs.new_gate(
+                [d, dummy, dummy, dummy],
+                [
+                    negative_one,
+                    zero,
+                    zero,
+                    zero,
+                    zero,
+                    E::Fr::from_str("100").unwrap(),
+                ],
+                [zero],
+            )?;
+
+            Ok(())
+        }
+    }
+
+    fn make_proof() -> (
+        RedshiftProof<Transparent252, TransparentCommitter>,
+        RedshiftVerificationKey<Transparent252, TransparentCommitter>,
+        TransparentCommitter,
+    ) {
+        let worker = Worker::new();
+
+        let circuit = TestCircuit4::<Transparent252> {
+            _marker: PhantomData,
+        };
+
+        let setup = setup(&circuit).unwrap();
+
+        let committer =
+            make_transparent_committer(setup.permutation_polynomials[0].size(), 16, 4, 8);
+
+        let (vk, precomputations) =
+            make_verification_key_and_precomputations(&setup, &committer, &worker).unwrap();
+
+        let proof = prove(&circuit, &setup, &precomputations, &committer, &worker).unwrap();
+
+        (proof, vk, committer)
+    }
+
+    #[test]
+    fn test_redshift_prove_and_verify() {
+        let (proof, vk, committer) = make_proof();
+
+        assert_eq!(proof.input_values.len(), 2);
+
+        let valid = verify(&proof, &vk, &committer).unwrap();
+        assert!(valid);
+    }
+
+    #[test]
+    fn test_redshift_rejects_wrong_inputs() {
+        let (mut proof, vk, committer) = make_proof();
+
+        proof.input_values[0] = Fr::from_str("11").unwrap();
+
+        let valid = verify(&proof, &vk, &committer).unwrap();
+        assert!(!valid);
+    }
+
+    #[test]
+    fn test_redshift_rejects_tampered_evaluations() {
+        let (proof, vk, committer) = make_proof();
+
+        let mut tampered = proof;
+        tampered.evaluations.wire_values_at_z[0].add_assign(&Fr::one());
+
+        let valid = verify(&tampered, &vk, &committer).unwrap();
+        assert!(!valid);
+    }
+}
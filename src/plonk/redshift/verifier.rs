@@ -0,0 +1,241 @@
+use crate::pairing::ff::{Field, PrimeField};
+use crate::pairing::Engine;
+
+use crate::plonk::better_cs::cs::*;
+use crate::plonk::better_cs::utils::*;
+use crate::plonk::commitments::transcript::*;
+use crate::plonk::commitments::CommitmentScheme;
+use crate::plonk::domains::*;
+use crate::SynthesisError;
+
+use super::generator::RedshiftVerificationKey;
+use super::{RedshiftEvaluations, RedshiftProof};
+
+pub fn verify<E, CS>(
+    proof: &RedshiftProof<E, CS>,
+    verification_key: &RedshiftVerificationKey<E, CS>,
+    committer: &CS,
+) -> Result<bool, SynthesisError>
+where
+    E: Engine,
+    CS: CommitmentScheme<E>,
+    CS::Prng: Transcript<E::Fr, Input = CS::Commitment>,
+{
+    let state_width =
+        <PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<E>>::STATE_WIDTH;
+    let num_selectors = verification_key.selector_commitments.len();
+    let num_next_step_selectors = verification_key.next_step_selector_commitments.len();
+
+    if proof.n != verification_key.n
+        || proof.num_inputs != verification_key.num_inputs
+        || proof.input_values.len() != proof.num_inputs
+    {
+        return Ok(false);
+    }
+
+    let evaluations = &proof.evaluations;
+    if proof.wire_commitments.len() != state_width
+        || proof.quotient_poly_commitments.len() != state_width
+        || evaluations.wire_values_at_z.len() != state_width
+        || evaluations.wire_values_at_z_omega.len() != 1
+        || evaluations.quotient_polynomial_parts_at_z.len() != state_width
+        || evaluations.selector_values_at_z.len() != num_selectors
+        || evaluations.next_step_selector_values_at_z.len() != num_next_step_selectors
+        || evaluations.permutation_polynomials_at_z.len() != state_width
+    {
+        return Ok(false);
+    }
+
+    let required_domain_size = proof.n + 1;
+    if !required_domain_size.is_power_of_two() {
+        return Ok(false);
+    }
+
+    let domain = Domain::<E::Fr>::new_for_size(required_domain_size as u64)?;
+
+    let mut transcript = CS::Prng::new();
+
+    for inp in proof.input_values.iter() {
+        transcript.commit_field_element(inp);
+    }
+
+    for c in proof.wire_commitments.iter() {
+        transcript.commit_input(c);
+    }
+
+    let beta = transcript.get_challenge();
+    let gamma = transcript.get_challenge();
+
+    transcript.commit_input(&proof.grand_product_commitment);
+
+    let alpha = transcript.get_challenge();
+
+    for c in proof.quotient_poly_commitments.iter() {
+        transcript.commit_input(c);
+    }
+
+    let z = transcript.get_challenge();
+
+    if !quotient_identity_holds(
+        evaluations,
+        &proof.input_values,
+        &domain,
+        &verification_key.non_residues,
+        alpha,
+        beta,
+        gamma,
+        z,
+    )? {
+        return Ok(false);
+    }
+
+    evaluations.commit_to_transcript(&mut transcript);
+
+    let v = transcript.get_challenge();
+
+    let mut commitments = vec![];
+    commitments.extend(proof.wire_commitments.iter());
+    commitments.push(&proof.grand_product_commitment);
+    commitments.extend(proof.quotient_poly_commitments.iter());
+    commitments.extend(verification_key.selector_commitments.iter());
+    commitments.extend(verification_key.next_step_selector_commitments.iter());
+    commitments.extend(verification_key.permutation_commitments.iter());
+
+    let num_at_z = commitments.len();
+
+    commitments.push(proof.wire_commitments.last().expect("last wire exists"));
+    commitments.push(&proof.grand_product_commitment);
+
+    let (values_at_z, values_at_z_omega) = evaluations.opening_values();
+    assert_eq!(values_at_z.len(), num_at_z);
+
+    let mut z_by_omega = z;
+    z_by_omega.mul_assign(&domain.generator);
+
+    let mut at_points = vec![z; num_at_z];
+    at_points.extend(vec![z_by_omega; values_at_z_omega.len()]);
+
+    let mut claimed_values = values_at_z;
+    claimed_values.extend(values_at_z_omega);
+
+    let valid = committer.verify_multiple_openings(
+        commitments,
+        at_points,
+        &claimed_values,
+        v,
+        &proof.opening_proof,
+        &mut transcript,
+    );
+
+    Ok(valid)
+}
+
+/// Checks `t(z) * (z^n - 1)` against the gate, copy-permutation and `Z(1) = 1` terms
+/// recomputed from the opened values.
+pub(crate) fn quotient_identity_holds<F: PrimeField>(
+    evaluations: &RedshiftEvaluations<F>,
+    input_values: &[F],
+    domain: &Domain<F>,
+    non_residues: &[F],
+    alpha: F,
+    beta: F,
+    gamma: F,
+    z: F,
+) -> Result<bool, SynthesisError> {
+    let wires = &evaluations.wire_values_at_z;
+    let selectors = &evaluations.selector_values_at_z;
+
+    assert_eq!(wires.len(), non_residues.len() + 1);
+    assert_eq!(wires.len(), evaluations.permutation_polynomials_at_z.len());
+
+    let t_at_z = {
+        let mut result = F::zero();
+        let mut current = F::one();
+        let z_in_domain_size = z.pow(&[domain.size]);
+        for part in evaluations.quotient_polynomial_parts_at_z.iter() {
+            let mut tmp = *part;
+            tmp.mul_assign(&current);
+            result.add_assign(&tmp);
+            current.mul_assign(&z_in_domain_size);
+        }
+
+        result
+    };
+
+    let mut lhs = t_at_z;
+    lhs.mul_assign(&evaluate_vanishing_for_size(&z, domain.size));
+
+    // Q_A * A + Q_B * B + Q_C * C + Q_D * D + Q_M * A * B + Q_CONST + Q_D_NEXT * D_NEXT
+    let mut rhs = selectors[5];
+    for (s, w) in selectors[0..4].iter().zip(wires.iter()) {
+        let mut tmp = *s;
+        tmp.mul_assign(w);
+        rhs.add_assign(&tmp);
+    }
+
+    let mut tmp = selectors[4];
+    tmp.mul_assign(&wires[0]);
+    tmp.mul_assign(&wires[1]);
+    rhs.add_assign(&tmp);
+
+    let mut tmp = evaluations.next_step_selector_values_at_z[0];
+    tmp.mul_assign(&evaluations.wire_values_at_z_omega[0]);
+    rhs.add_assign(&tmp);
+
+    for (idx, input) in input_values.iter().enumerate() {
+        let mut tmp = evaluate_lagrange_poly_at_point(idx, domain, z)?;
+        tmp.mul_assign(input);
+        rhs.add_assign(&tmp);
+    }
+
+    let mut quotient_challenge = alpha;
+
+    // Z(z) * (A + beta * z + gamma) * ... - Z(z * omega) * (A + beta * perm_a(z) + gamma) * ...
+    {
+        let mut z_part = evaluations.grand_product_at_z;
+        for (w, non_res) in wires
+            .iter()
+            .zip(Some(F::one()).iter().chain(non_residues.iter()))
+        {
+            let mut tmp = z;
+            tmp.mul_assign(non_res);
+            tmp.mul_assign(&beta);
+            tmp.add_assign(w);
+            tmp.add_assign(&gamma);
+
+            z_part.mul_assign(&tmp);
+        }
+
+        let mut z_shifted_part = evaluations.grand_product_at_z_omega;
+        for (w, perm) in wires
+            .iter()
+            .zip(evaluations.permutation_polynomials_at_z.iter())
+        {
+            let mut tmp = *perm;
+            tmp.mul_assign(&beta);
+            tmp.add_assign(w);
+            tmp.add_assign(&gamma);
+
+            z_shifted_part.mul_assign(&tmp);
+        }
+
+        z_part.sub_assign(&z_shifted_part);
+        z_part.mul_assign(&quotient_challenge);
+
+        rhs.add_assign(&z_part);
+    }
+
+    quotient_challenge.mul_assign(&alpha);
+
+    // (Z(z) - 1) * L_0(z)
+    {
+        let mut tmp = evaluations.grand_product_at_z;
+        tmp.sub_assign(&F::one());
+        tmp.mul_assign(&evaluate_l0_at_point(domain.size, z)?);
+        tmp.mul_assign(&quotient_challenge);
+
+        rhs.add_assign(&tmp);
+    }
+
+    Ok(lhs == rhs)
+}
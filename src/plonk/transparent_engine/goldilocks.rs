@@ -0,0 +1,792 @@
+//! 64-bit prime field with the modulus `p = 2^64 - 2^32 + 1`.
+//!
+//! Elements are stored as a single `u64` in the canonical (not Montgomery) form. As
+//! `2^64 = 2^32 - 1 (mod p)`, any carry out of the limb is folded back by adding
+//! `2^32 - 1`, so the partially reduced elements never need more than one limb: they are
+//! just the values in `[p, 2^64)` that are not yet brought below the modulus.
+
+use super::{PartialReductionField, PartialTwoBitReductionField};
+use crate::pairing::ff::*;
+use crate::plonk::commitments::transcript::Prng;
+
+#[derive(Copy, Clone, PartialEq, Eq, Default, Hash)]
+pub struct FrRepr(pub [u64; 1usize]);
+
+pub struct Fr(u64);
+
+const MODULUS: u64 = 0xffff_ffff_0000_0001;
+
+/// `2^64 mod p`
+const EPSILON: u64 = 0xffff_ffff;
+
+const MODULUS_BITS: u32 = 64u32;
+
+const S: u32 = 32u32;
+
+const GENERATOR: u64 = 7u64;
+
+// 7^((p - 1) / 2^32)
+const ROOT_OF_UNITY: u64 = 1753635133440165772u64;
+
+#[inline(always)]
+fn canonicalize(value: u64) -> u64 {
+    if value >= MODULUS {
+        value - MODULUS
+    } else {
+        value
+    }
+}
+
+#[inline(always)]
+fn add_unreduced(a: u64, b: u64) -> u64 {
+    let (sum, over) = a.overflowing_add(b);
+    let (sum, over) = sum.overflowing_add((over as u64) * EPSILON);
+    if over {
+        // only possible if both inputs were close to 2^64, then the sum is small
+        sum + EPSILON
+    } else {
+        sum
+    }
+}
+
+#[inline(always)]
+fn sub_unreduced(a: u64, b: u64) -> u64 {
+    // with canonical b the wrapped difference is at least 2^32 and can not borrow twice
+    let (diff, under) = a.overflowing_sub(canonicalize(b));
+    if under {
+        diff - EPSILON
+    } else {
+        diff
+    }
+}
+
+#[inline(always)]
+fn reduce_u128(value: u128) -> u64 {
+    let lo = value as u64;
+    let hi = (value >> 64) as u64;
+    let hi_hi = hi >> 32;
+    let hi_lo = hi & EPSILON;
+
+    // 2^96 = -1 (mod p)
+    let (mut t0, borrow) = lo.overflowing_sub(hi_hi);
+    if borrow {
+        t0 -= EPSILON;
+    }
+    // 2^64 = 2^32 - 1 (mod p)
+    let t1 = hi_lo * EPSILON;
+
+    add_unreduced(t0, t1)
+}
+
+impl ::std::fmt::Debug for FrRepr {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "0x{:016x}", self.0[0])
+    }
+}
+
+impl ::rand::Rand for FrRepr {
+    #[inline(always)]
+    fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+        FrRepr(rng.gen())
+    }
+}
+
+impl ::std::fmt::Display for FrRepr {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "0x{:016x}", self.0[0])
+    }
+}
+
+impl AsRef<[u64]> for FrRepr {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+impl AsMut<[u64]> for FrRepr {
+    #[inline(always)]
+    fn as_mut(&mut self) -> &mut [u64] {
+        &mut self.0
+    }
+}
+
+impl From<u64> for FrRepr {
+    #[inline(always)]
+    fn from(val: u64) -> FrRepr {
+        FrRepr([val])
+    }
+}
+
+impl Ord for FrRepr {
+    #[inline(always)]
+    fn cmp(&self, other: &FrRepr) -> ::std::cmp::Ordering {
+        self.0[0].cmp(&other.0[0])
+    }
+}
+
+impl PartialOrd for FrRepr {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &FrRepr) -> Option<::std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl crate::ff::PrimeFieldRepr for FrRepr {
+    #[inline(always)]
+    fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+    #[inline(always)]
+    fn is_even(&self) -> bool {
+        !self.is_odd()
+    }
+    #[inline(always)]
+    fn is_zero(&self) -> bool {
+        self.0[0] == 0
+    }
+    #[inline(always)]
+    fn shr(&mut self, n: u32) {
+        self.0[0] = self.0[0].checked_shr(n).unwrap_or(0);
+    }
+    #[inline(always)]
+    fn div2(&mut self) {
+        self.0[0] >>= 1;
+    }
+    #[inline(always)]
+    fn mul2(&mut self) {
+        self.0[0] <<= 1;
+    }
+    #[inline(always)]
+    fn shl(&mut self, n: u32) {
+        self.0[0] = self.0[0].checked_shl(n).unwrap_or(0);
+    }
+    #[inline(always)]
+    fn num_bits(&self) -> u32 {
+        64 - self.0[0].leading_zeros()
+    }
+    #[inline(always)]
+    fn add_nocarry(&mut self, other: &FrRepr) {
+        self.0[0] = self.0[0].wrapping_add(other.0[0]);
+    }
+    #[inline(always)]
+    fn sub_noborrow(&mut self, other: &FrRepr) {
+        self.0[0] = self.0[0].wrapping_sub(other.0[0]);
+    }
+}
+
+impl ::std::marker::Copy for Fr {}
+impl ::std::clone::Clone for Fr {
+    fn clone(&self) -> Fr {
+        *self
+    }
+}
+
+// partially reduced elements are equal to their canonical forms
+impl ::std::cmp::PartialEq for Fr {
+    fn eq(&self, other: &Fr) -> bool {
+        canonicalize(self.0) == canonicalize(other.0)
+    }
+}
+impl ::std::cmp::Eq for Fr {}
+
+impl ::std::hash::Hash for Fr {
+    fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+        canonicalize(self.0).hash(state);
+    }
+}
+
+impl Ord for Fr {
+    #[inline(always)]
+    fn cmp(&self, other: &Fr) -> ::std::cmp::Ordering {
+        canonicalize(self.0).cmp(&canonicalize(other.0))
+    }
+}
+
+impl PartialOrd for Fr {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Fr) -> Option<::std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ::std::fmt::Debug for Fr {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}({:?})", "Fr", self.into_repr())
+    }
+}
+
+impl ::std::fmt::Display for Fr {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}({:?})", "Fr", self.into_repr())
+    }
+}
+
+impl ::rand::Rand for Fr {
+    fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+        loop {
+            let value: u64 = rng.gen();
+            if value < MODULUS {
+                return Fr(value);
+            }
+        }
+    }
+}
+
+impl From<Fr> for FrRepr {
+    fn from(e: Fr) -> FrRepr {
+        e.into_repr()
+    }
+}
+
+impl crate::ff::PrimeField for Fr {
+    type Repr = FrRepr;
+
+    fn from_repr(r: FrRepr) -> Result<Fr, crate::ff::PrimeFieldDecodingError> {
+        if r.0[0] < MODULUS {
+            Ok(Fr(r.0[0]))
+        } else {
+            Err(crate::ff::PrimeFieldDecodingError::NotInField(format!("{}", r)))
+        }
+    }
+
+    fn from_raw_repr(r: FrRepr) -> Result<Self, crate::ff::PrimeFieldDecodingError> {
+        Self::from_repr(r)
+    }
+
+    fn into_repr(&self) -> FrRepr {
+        FrRepr([canonicalize(self.0)])
+    }
+
+    fn into_raw_repr(&self) -> FrRepr {
+        self.into_repr()
+    }
+
+    fn char() -> FrRepr {
+        FrRepr([MODULUS])
+    }
+
+    const NUM_BITS: u32 = MODULUS_BITS;
+    const CAPACITY: u32 = Self::NUM_BITS - 1;
+
+    fn multiplicative_generator() -> Self {
+        Fr(GENERATOR)
+    }
+
+    const S: u32 = S;
+    fn root_of_unity() -> Self {
+        Fr(ROOT_OF_UNITY)
+    }
+}
+
+impl crate::ff::Field for Fr {
+    #[inline]
+    fn zero() -> Self {
+        Fr(0)
+    }
+
+    #[inline]
+    fn one() -> Self {
+        Fr(1)
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        canonicalize(self.0) == 0
+    }
+
+    #[inline]
+    fn add_assign(&mut self, other: &Fr) {
+        self.0 = canonicalize(add_unreduced(self.0, other.0));
+    }
+
+    #[inline]
+    fn double(&mut self) {
+        self.0 = canonicalize(add_unreduced(self.0, self.0));
+    }
+
+    #[inline]
+    fn sub_assign(&mut self, other: &Fr) {
+        self.0 = canonicalize(sub_unreduced(self.0, other.0));
+    }
+
+    #[inline]
+    fn negate(&mut self) {
+        let value = canonicalize(self.0);
+        self.0 = if value == 0 { 0 } else { MODULUS - value };
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(self.pow([MODULUS - 2]))
+        }
+    }
+
+    #[inline(always)]
+    fn frobenius_map(&mut self, _: usize) {}
+
+    #[inline]
+    fn mul_assign(&mut self, other: &Fr) {
+        self.0 = canonicalize(reduce_u128((self.0 as u128) * (other.0 as u128)));
+    }
+
+    #[inline]
+    fn square(&mut self) {
+        self.0 = canonicalize(reduce_u128((self.0 as u128) * (self.0 as u128)));
+    }
+}
+
+impl std::default::Default for Fr {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl crate::ff::SqrtField for Fr {
+    fn legendre(&self) -> crate::ff::LegendreSymbol {
+        // (p - 1) / 2
+        let s = self.pow([0x7fff_ffff_8000_0000u64]);
+        if s == Self::zero() {
+            crate::ff::LegendreSymbol::Zero
+        } else if s == Self::one() {
+            crate::ff::LegendreSymbol::QuadraticResidue
+        } else {
+            crate::ff::LegendreSymbol::QuadraticNonResidue
+        }
+    }
+    fn sqrt(&self) -> Option<Self> {
+        match self.legendre() {
+            crate::ff::LegendreSymbol::Zero => Some(*self),
+            crate::ff::LegendreSymbol::QuadraticNonResidue => None,
+            crate::ff::LegendreSymbol::QuadraticResidue => {
+                let mut c = Fr(ROOT_OF_UNITY);
+                // (t + 1) / 2 and t for p - 1 = 2^S * t
+                let mut r = self.pow([0x8000_0000u64]);
+                let mut t = self.pow([0xffff_ffffu64]);
+                let mut m = S;
+                while t != Self::one() {
+                    let mut i = 1;
+                    {
+                        let mut t2i = t;
+                        t2i.square();
+                        loop {
+                            if t2i == Self::one() {
+                                break;
+                            }
+                            t2i.square();
+                            i += 1;
+                        }
+                    }
+                    for _ in 0..(m - i - 1) {
+                        c.square();
+                    }
+                    r.mul_assign(&c);
+                    c.square();
+                    t.mul_assign(&c);
+                    m = i;
+                }
+                Some(r)
+            }
+        }
+    }
+}
+
+impl PartialReductionField for Fr {
+    #[inline(always)]
+    fn add_assign_unreduced(&mut self, other: &Fr) {
+        self.0 = add_unreduced(self.0, other.0);
+    }
+
+    #[inline(always)]
+    fn sub_assign_unreduced(&mut self, other: &Self) {
+        self.0 = sub_unreduced(self.0, other.0);
+    }
+
+    #[inline(always)]
+    fn mul_assign_unreduced(&mut self, other: &Fr) {
+        self.0 = reduce_u128((self.0 as u128) * (other.0 as u128));
+    }
+
+    #[inline(always)]
+    fn reduce_once(&mut self) {
+        self.0 = canonicalize(self.0);
+    }
+
+    #[inline(always)]
+    fn reduce_completely(&mut self) {
+        self.reduce_once();
+    }
+
+    fn overflow_factor(&self) -> usize {
+        if self.0 >= MODULUS {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl PartialTwoBitReductionField for Fr {
+    #[inline(always)]
+    fn sub_assign_twice_unreduced(&mut self, other: &Self) {
+        self.sub_assign_unreduced(other);
+    }
+
+    #[inline(always)]
+    fn reduce_twice(&mut self) {
+        // a single limb never holds more than p + 2^32 - 2
+    }
+
+    #[inline(always)]
+    fn reduce_completely(&mut self) {
+        self.reduce_once();
+    }
+}
+
+/// Quadratic non-residue that defines the extension.
+const NON_RESIDUE: u64 = 7u64;
+
+/// Quadratic extension `Fr[X] / (X^2 - 7)`. The base field only gives 64 bits, which is
+/// too little for the verifier challenges, so they are sampled from the extension.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Fr2 {
+    pub c0: Fr,
+    pub c1: Fr,
+}
+
+impl Fr2 {
+    pub fn from_base(value: Fr) -> Self {
+        Fr2 {
+            c0: value,
+            c1: Fr::zero(),
+        }
+    }
+
+    pub fn mul_by_base(&mut self, value: &Fr) {
+        self.c0.mul_assign(value);
+        self.c1.mul_assign(value);
+    }
+
+    /// Draws a challenge from the extension as two base field challenges.
+    pub fn get_challenge<P: Prng<Fr>>(prng: &mut P) -> Self {
+        let c0 = prng.get_challenge();
+        let c1 = prng.get_challenge();
+
+        Fr2 { c0, c1 }
+    }
+}
+
+impl ::std::fmt::Debug for Fr2 {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "Fr2({:?} + {:?} * X)", self.c0, self.c1)
+    }
+}
+
+impl ::std::fmt::Display for Fr2 {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "Fr2({} + {} * X)", self.c0, self.c1)
+    }
+}
+
+impl ::rand::Rand for Fr2 {
+    fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+        Fr2 {
+            c0: rng.gen(),
+            c1: rng.gen(),
+        }
+    }
+}
+
+impl crate::ff::Field for Fr2 {
+    fn zero() -> Self {
+        Fr2 {
+            c0: Fr::zero(),
+            c1: Fr::zero(),
+        }
+    }
+
+    fn one() -> Self {
+        Fr2 {
+            c0: Fr::one(),
+            c1: Fr::zero(),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero()
+    }
+
+    fn square(&mut self) {
+        let other = *self;
+        self.mul_assign(&other);
+    }
+
+    fn double(&mut self) {
+        self.c0.double();
+        self.c1.double();
+    }
+
+    fn negate(&mut self) {
+        self.c0.negate();
+        self.c1.negate();
+    }
+
+    fn add_assign(&mut self, other: &Self) {
+        self.c0.add_assign(&other.c0);
+        self.c1.add_assign(&other.c1);
+    }
+
+    fn sub_assign(&mut self, other: &Self) {
+        self.c0.sub_assign(&other.c0);
+        self.c1.sub_assign(&other.c1);
+    }
+
+    fn mul_assign(&mut self, other: &Self) {
+        let mut a0b0 = self.c0;
+        a0b0.mul_assign(&other.c0);
+        let mut a1b1 = self.c1;
+        a1b1.mul_assign(&other.c1);
+        a1b1.mul_assign(&Fr(NON_RESIDUE));
+
+        let mut c1 = self.c0;
+        c1.mul_assign(&other.c1);
+        let mut tmp = self.c1;
+        tmp.mul_assign(&other.c0);
+        c1.add_assign(&tmp);
+
+        a0b0.add_assign(&a1b1);
+        self.c0 = a0b0;
+        self.c1 = c1;
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        // (c0 + c1 * X)^-1 = (c0 - c1 * X) / (c0^2 - 7 * c1^2)
+        let mut norm = self.c0;
+        norm.square();
+        let mut tmp = self.c1;
+        tmp.square();
+        tmp.mul_assign(&Fr(NON_RESIDUE));
+        norm.sub_assign(&tmp);
+
+        norm.inverse().map(|norm_inv| {
+            let mut c0 = self.c0;
+            c0.mul_assign(&norm_inv);
+            let mut c1 = self.c1;
+            c1.mul_assign(&norm_inv);
+            c1.negate();
+
+            Fr2 { c0, c1 }
+        })
+    }
+
+    fn frobenius_map(&mut self, power: usize) {
+        // X^p = 7^((p - 1) / 2) * X = -X
+        if power % 2 == 1 {
+            self.c1.negate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654])
+    }
+
+    fn reference_mul(a: u64, b: u64) -> u64 {
+        ((a as u128) * (b as u128) % (MODULUS as u128)) as u64
+    }
+
+    #[test]
+    fn test_goldilocks_arithmetic() {
+        let rng = &mut rng();
+
+        let mut edge_values = vec![0, 1, 2, EPSILON, MODULUS - 1, MODULUS - 2, 1 << 63];
+        edge_values.extend((0..100).map(|_| Fr::rand(rng).0));
+
+        for a in edge_values.iter() {
+            for b in edge_values.iter() {
+                let (fa, fb) = (Fr(*a), Fr(*b));
+
+                let mut sum = fa;
+                sum.add_assign(&fb);
+                assert_eq!(sum.0, ((*a as u128 + *b as u128) % (MODULUS as u128)) as u64);
+
+                let mut diff = fa;
+                diff.sub_assign(&fb);
+                assert_eq!(diff.0, ((*a as u128 + MODULUS as u128 - *b as u128) % (MODULUS as u128)) as u64);
+
+                let mut product = fa;
+                product.mul_assign(&fb);
+                assert_eq!(product.0, reference_mul(*a, *b));
+            }
+
+            let fa = Fr(*a);
+            if let Some(inv) = fa.inverse() {
+                let mut tmp = inv;
+                tmp.mul_assign(&fa);
+                assert_eq!(tmp, Fr::one());
+            } else {
+                assert!(fa.is_zero());
+            }
+
+            let mut square = fa;
+            square.square();
+            assert_eq!(square.sqrt().map(|mut s| { s.square(); s }), Some(square));
+        }
+
+        let omega = Fr::root_of_unity();
+        assert_eq!(omega.pow([1u64 << 32]), Fr::one());
+        assert!(omega.pow([1u64 << 31]) != Fr::one());
+        assert_eq!(Fr::multiplicative_generator().legendre(), crate::ff::LegendreSymbol::QuadraticNonResidue);
+
+        assert_eq!(Fr::from_str("18446744069414584320").unwrap(), {
+            let mut minus_one = Fr::one();
+            minus_one.negate();
+            minus_one
+        });
+        assert!(Fr::from_repr(FrRepr([MODULUS])).is_err());
+    }
+
+    #[test]
+    fn test_goldilocks_partial_reduction() {
+        let rng = &mut rng();
+
+        for _ in 0..1000 {
+            let a = Fr::rand(rng);
+            let b = Fr::rand(rng);
+            let c = Fr::rand(rng);
+
+            // same chain as in a butterfly: (a + b) * c - (a - b)
+            let mut unreduced = a;
+            unreduced.add_assign_unreduced(&b);
+            unreduced.mul_assign_unreduced(&c);
+            let mut tmp = a;
+            tmp.sub_assign_twice_unreduced(&b);
+            unreduced.sub_assign_unreduced(&tmp);
+            assert!(unreduced.overflow_factor() < 2);
+
+            let mut reduced = a;
+            reduced.add_assign(&b);
+            reduced.mul_assign(&c);
+            let mut tmp = a;
+            tmp.sub_assign(&b);
+            reduced.sub_assign(&tmp);
+
+            PartialReductionField::reduce_completely(&mut unreduced);
+            assert_eq!(unreduced.overflow_factor(), 0);
+            assert_eq!(unreduced.0, reduced.0);
+        }
+
+        // carries out of the limb are folded back
+        let mut a = Fr(u64::max_value());
+        a.add_assign_unreduced(&Fr(u64::max_value()));
+        PartialReductionField::reduce_completely(&mut a);
+        assert_eq!(a.0, ((2 * (u64::max_value() as u128)) % (MODULUS as u128)) as u64);
+    }
+
+    #[test]
+    fn test_goldilocks_extension() {
+        let rng = &mut rng();
+
+        for _ in 0..100 {
+            let a = Fr2::rand(rng);
+            let b = Fr2::rand(rng);
+            let c = Fr2::rand(rng);
+
+            // distributivity
+            let mut lhs = b;
+            lhs.add_assign(&c);
+            lhs.mul_assign(&a);
+            let mut ab = a;
+            ab.mul_assign(&b);
+            let mut ac = a;
+            ac.mul_assign(&c);
+            ab.add_assign(&ac);
+            assert_eq!(lhs, ab);
+
+            let mut tmp = a.inverse().unwrap();
+            tmp.mul_assign(&a);
+            assert_eq!(tmp, Fr2::one());
+
+            // frobenius is the p-th power
+            let mut frobenius = a;
+            frobenius.frobenius_map(1);
+            assert_eq!(frobenius, a.pow([MODULUS]));
+        }
+
+        let mut x = Fr2 {
+            c0: Fr::zero(),
+            c1: Fr::one(),
+        };
+        x.square();
+        assert_eq!(x, Fr2::from_base(Fr(NON_RESIDUE)));
+    }
+
+    #[test]
+    fn test_goldilocks_extension_challenges() {
+        use crate::plonk::commitments::transcript::{Blake2sTranscript, Prng, Transcript};
+
+        let mut transcript = Blake2sTranscript::<Fr>::new();
+        transcript.commit_field_element(&Fr(42));
+        let mut replay = transcript.clone();
+
+        let challenge = Fr2::get_challenge(&mut transcript);
+        assert_eq!(challenge.c0, replay.get_challenge());
+        assert_eq!(challenge.c1, replay.get_challenge());
+        assert!(challenge.c0 != challenge.c1);
+    }
+
+    #[test]
+    fn test_goldilocks_partial_reduction_ntt() {
+        use super::super::goldilocks_engine::Goldilocks64;
+        use crate::plonk::domains::Domain;
+        use crate::plonk::fft::cooley_tukey_ntt::partial_reduction::*;
+        use crate::plonk::fft::cooley_tukey_ntt::*;
+        use crate::plonk::fft::fft::serial_fft;
+        use crate::plonk::polynomials::*;
+        use crate::worker::Worker;
+
+        let worker = Worker::new();
+        let rng = &mut rng();
+
+        // a single butterfly is not reduced by the NTT, so start from four elements
+        for log_n in [2u32, 4, 10, 14].iter() {
+            let log_n = *log_n;
+            let poly_size = 1usize << log_n;
+            let precomp = BitReversedOmegas::<Fr>::new_for_domain_size(poly_size);
+            let domain = Domain::<Fr>::new_for_size(poly_size as u64).unwrap();
+
+            let coeffs = (0..poly_size).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+
+            let mut expected = coeffs.clone();
+            serial_fft(&mut expected, &domain.generator, log_n);
+
+            let mut serial = coeffs.clone();
+            serial_ct_ntt_partial_reduction(&mut serial, log_n, &precomp);
+
+            let mut parallel = coeffs.clone();
+            best_ct_ntt_partial_reduction(&mut parallel, &worker, log_n, None, &precomp);
+
+            for (i, expected) in expected.iter().enumerate() {
+                let j = bitreverse(i, log_n as usize);
+                assert_eq!(serial[j].overflow_factor(), 0);
+                assert_eq!(serial[j], *expected);
+                assert_eq!(parallel[j], *expected);
+            }
+
+            let poly = Polynomial::<Goldilocks64, _>::from_coeffs(coeffs).unwrap();
+            let lde = poly.clone().lde(&worker, 4).unwrap();
+            let mut bitreversed_lde = poly
+                .bitreversed_lde_using_bitreversed_ntt_with_partial_reduction(&worker, 4, &precomp, &Fr::one())
+                .unwrap();
+            bitreversed_lde.bitreverse_enumeration(&worker);
+            assert!(lde.as_ref() == bitreversed_lde.as_ref());
+        }
+    }
+}
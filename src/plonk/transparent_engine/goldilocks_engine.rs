@@ -0,0 +1,9 @@
+pub use super::goldilocks::{Fr, Fr2};
+
+use super::impl_macro::*;
+
+use super::TransparentEngine;
+
+transparent_engine_impl!{Goldilocks64, Fr}
+
+impl TransparentEngine for Goldilocks64 {}
@@ -55,6 +55,11 @@ pub use self::engine::Transparent252;
 pub(crate) mod proth;
 pub(crate) mod proth_engine;
 
+pub mod goldilocks;
+pub mod goldilocks_engine;
+
+pub use self::goldilocks_engine::Goldilocks64;
+
 #[cfg(test)]
 mod test {
     use super::proth_engine::Transparent252 as Transparent252Optimized;
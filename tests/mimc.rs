@@ -1,3 +1,6 @@
+// The prover is not part of the `verifier-only` build, see `verifier_only.rs`.
+#![cfg(not(feature = "verifier-only"))]
+
 // For benchmarking
 use std::time::{Duration, Instant};
 
@@ -327,3 +330,90 @@ fn transpile_and_prove_with_precomputations_mimc() {
 
     println!("Average proving time: {:?} ms", proving_avg);
 }
+
+/// Proves a MiMC image with fixed constants and writes the verification key and the proof
+/// to `tests/fixtures`, where `verifier_only.rs` reads them. Run it after a change to their
+/// serialization or to the prover:
+///
+/// ```text
+/// cargo test --test mimc -- --ignored --exact mimc_verifier_fixtures
+/// ```
+#[test]
+#[ignore]
+fn mimc_verifier_fixtures() {
+    use crate::Circuit;
+    use bellman_gpu::kate_commitment::*;
+    use bellman_gpu::plonk::better_cs::cs::Circuit as PlonkCircuit;
+    use bellman_gpu::plonk::better_cs::{
+        adaptor::{AdaptorCircuit, Transpiler},
+        cs::PlonkCsWidth4WithNextStepParams,
+        generator::*,
+        keys::*,
+        verifier::*,
+    };
+    use bellman_gpu::plonk::commitments::transcript::keccak_transcript::*;
+    use bellman_gpu::worker::Worker;
+    use pairing::bn256::{Bn256, Fr};
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use std::fs::File;
+    use std::path::Path;
+
+    type Transcr = RollingKeccakTranscript<Fr>;
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let constants = (0..MIMC_ROUNDS).map(|_| rng.gen()).collect::<Vec<_>>();
+
+    let c = MiMCDemo::<Bn256> {
+        xl: None,
+        xr: None,
+        constants: &constants,
+    };
+
+    let mut transpiler = Transpiler::<Bn256, PlonkCsWidth4WithNextStepParams>::new();
+    c.synthesize(&mut transpiler)
+        .expect("sythesize into traspilation must succeed");
+    let hints = transpiler.into_hints();
+
+    let c = MiMCDemo::<Bn256> {
+        xl: Some(rng.gen()),
+        xr: Some(rng.gen()),
+        constants: &constants,
+    };
+
+    let adapted_curcuit =
+        AdaptorCircuit::<Bn256, PlonkCsWidth4WithNextStepParams, _>::new(c.clone(), &hints);
+    let mut assembly = GeneratorAssembly4WithNextStep::<Bn256>::new();
+    adapted_curcuit
+        .synthesize(&mut assembly)
+        .expect("sythesize of transpiled into CS must succeed");
+    assembly.finalize();
+
+    let worker = Worker::new();
+
+    let setup = assembly.setup(&worker).unwrap();
+
+    let crs_mons =
+        Crs::<Bn256, CrsForMonomialForm>::crs_42(setup.permutation_polynomials[0].size(), &worker);
+
+    let verification_key = VerificationKey::from_setup(&setup, &worker, &crs_mons).unwrap();
+
+    let proof =
+        prove_by_steps::<_, _, Transcr>(c, &hints, &setup, None, &crs_mons, None).unwrap();
+
+    let is_valid = verify::<Bn256, PlonkCsWidth4WithNextStepParams, Transcr>(
+        &proof,
+        &verification_key,
+        None,
+    )
+    .unwrap();
+    assert!(is_valid, "proof verification failed");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    verification_key
+        .write(File::create(dir.join("mimc_vk.bin")).unwrap())
+        .unwrap();
+    proof.write(File::create(dir.join("mimc_proof.bin")).unwrap()).unwrap();
+}
@@ -0,0 +1,66 @@
+//! Runs with `cargo test --no-default-features --features verifier-only`.
+//!
+//! The prover is not compiled into this build, so the verification key and the proof are
+//! checked into `tests/fixtures`. They are written by the ignored `mimc_verifier_fixtures`
+//! test of `mimc.rs` in a prover build.
+#![cfg(feature = "verifier-only")]
+
+use bellman_gpu::pairing::bn256::{Bn256, Fr};
+use bellman_gpu::pairing::ff::Field;
+use bellman_gpu::plonk::better_cs::cs::PlonkCsWidth4WithNextStepParams;
+use bellman_gpu::plonk::better_cs::keys::{Proof, VerificationKey};
+use bellman_gpu::plonk::better_cs::verifier::verify;
+use bellman_gpu::plonk::commitments::transcript::keccak_transcript::RollingKeccakTranscript;
+
+use std::fs::File;
+use std::path::Path;
+
+type Transcr = RollingKeccakTranscript<Fr>;
+
+/// Regenerated with `cargo test --test mimc -- --ignored --exact mimc_verifier_fixtures`.
+fn fixture(name: &str) -> File {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+
+    File::open(&path).unwrap_or_else(|e| panic!("cannot open the fixture {:?}: {}", path, e))
+}
+
+fn read_fixtures() -> (
+    VerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
+    Proof<Bn256, PlonkCsWidth4WithNextStepParams>,
+) {
+    let verification_key = VerificationKey::read(fixture("mimc_vk.bin")).unwrap();
+    let proof = Proof::read(fixture("mimc_proof.bin")).unwrap();
+
+    (verification_key, proof)
+}
+
+#[test]
+fn verify_mimc_proof_without_prover() {
+    let (verification_key, proof) = read_fixtures();
+
+    let is_valid = verify::<Bn256, PlonkCsWidth4WithNextStepParams, Transcr>(
+        &proof,
+        &verification_key,
+        None,
+    )
+    .unwrap();
+    assert!(is_valid, "proof verification failed");
+}
+
+#[test]
+fn reject_mimc_proof_for_other_image() {
+    let (verification_key, mut proof) = read_fixtures();
+
+    proof.input_values[0].add_assign(&Fr::one());
+
+    let is_valid = verify::<Bn256, PlonkCsWidth4WithNextStepParams, Transcr>(
+        &proof,
+        &verification_key,
+        None,
+    )
+    .unwrap();
+    assert!(!is_valid, "proof for a wrong image must not verify");
+}